@@ -1,5 +1,5 @@
 use anyhow::Result;
-use audio::{get_microphones, SimpleRecorder};
+use audio::{get_microphones, get_speakers, SimpleRecorder};
 use clap::{Arg, Command};
 use std::{
     path::PathBuf,
@@ -17,7 +17,6 @@ use std::{
 /// 2. Proper device enumeration 
 /// 3. Peak monitoring during recording
 /// 4. Graceful shutdown with Ctrl+C
-
 fn main() -> Result<()> {
     let matches = Command::new("Simple Audio Recorder")
         .version("0.1.0")
@@ -91,7 +90,7 @@ fn main() -> Result<()> {
     let device_id = device_name.map(|s| s.as_str());
     recorder.start_recording(device_id, &output_path, |peak| {
         // Show audio level bar
-        let bar_length = (peak.abs() as usize) / 1640; // Scale for display
+        let bar_length = peak.unsigned_abs() as usize / 1640; // Scale for display
         let bar = "█".repeat(bar_length.min(20));
         print!("\r🔊 Level: [{:<20}] {:5}", bar, peak);
         std::io::Write::flush(&mut std::io::stdout()).unwrap();
@@ -136,6 +135,16 @@ fn list_audio_devices() -> Result<()> {
             );
         }
     }
+
+    println!("🔈 Available audio output devices:");
+    let speakers: Vec<serde_json::Value> = serde_json::from_str(&get_speakers()?)?;
+    if speakers.is_empty() {
+        println!("   No output devices found.");
+    } else {
+        for (i, speaker) in speakers.iter().enumerate() {
+            println!("   {}. {}", i + 1, speaker["name"].as_str().unwrap_or("Unknown"));
+        }
+    }
     
     Ok(())
 }
@@ -59,7 +59,7 @@ fn main() -> anyhow::Result<()> {
         return Err(anyhow::anyhow!("Channel count must be between 1 and 8"));
     }
 
-    if sample_rate < 8000 || sample_rate > 192000 {
+    if !(8000..=192000).contains(&sample_rate) {
         return Err(anyhow::anyhow!("Sample rate must be between 8000 and 192000 Hz"));
     }
 
@@ -0,0 +1,126 @@
+use anyhow::Result;
+use std::sync::mpsc::{self, Sender};
+use tokio::sync::{broadcast, oneshot, watch};
+
+use crate::{LevelMeter, RecordedAudio, RecorderEvent, SampleType, SimpleRecorder};
+
+/// Events a receiver may fall behind by before missing some, about 2.5s of levels
+const EVENTS: usize = 256;
+
+/// What the recorder thread is asked to do
+enum Command {
+    Start(Option<String>, oneshot::Sender<Result<()>>),
+    Stop(oneshot::Sender<Result<RecordedAudio>>),
+}
+
+/// A [`SimpleRecorder`] for async code, such as a Tauri or axum backend
+///
+/// The recorder lives on a thread of its own, as its input stream can't move between
+/// threads, so starting and stopping never block the executor. Recordings go to memory,
+/// as with [`SimpleRecorder::start_recording_buffered`]; their samples, events and
+/// levels come through tokio channels while they record.
+pub struct AsyncRecorder {
+    commands: Sender<Command>,
+    chunks: broadcast::Sender<Vec<SampleType>>,
+    events: broadcast::Sender<RecorderEvent>,
+    levels: watch::Receiver<LevelMeter>,
+}
+
+impl AsyncRecorder {
+    /// Run a recorder set up by `configure`, e.g. to keep the stream open, on its thread
+    pub fn new<F>(configure: F) -> Self
+    where
+        F: FnOnce(&mut SimpleRecorder) + Send + 'static,
+    {
+        let (commands, receiver) = mpsc::channel();
+        let (events, _) = broadcast::channel(EVENTS);
+        let (levels, level_receiver) = watch::channel(LevelMeter::measure(&[]));
+        let (chunks_reply, chunks) = mpsc::channel();
+
+        let on_event = events.clone();
+        std::thread::spawn(move || {
+            let mut recorder = SimpleRecorder::new();
+            configure(&mut recorder);
+            let _ = chunks_reply.send(recorder.chunk_sender());
+            for command in receiver {
+                match command {
+                    Command::Start(device_id, reply) => {
+                        let events = on_event.clone();
+                        let levels = levels.clone();
+                        let started = recorder.start_recording_buffered(
+                            device_id.as_deref(),
+                            move |event| {
+                                if let RecorderEvent::Level { meter, .. } = &event {
+                                    levels.send_replace(*meter);
+                                }
+                                let _ = events.send(event);
+                            },
+                        );
+                        let _ = reply.send(started);
+                    }
+                    Command::Stop(reply) => {
+                        let _ = reply.send(recorder.stop_recording_buffered());
+                    }
+                }
+            }
+        });
+
+        Self {
+            commands,
+            chunks: chunks.recv().expect("the recorder thread sends its chunks first"),
+            events,
+            levels: level_receiver,
+        }
+    }
+
+    /// Start recording from the device named `device_id`, or the default input
+    pub async fn start(&self, device_id: Option<&str>) -> Result<()> {
+        let (reply, started) = oneshot::channel();
+        self.send(Command::Start(device_id.map(str::to_string), reply))?;
+        started.await.map_err(|_| anyhow::anyhow!("The recorder thread has stopped"))?
+    }
+
+    /// Stop recording and return the samples, or the files they spilled to
+    pub async fn stop(&self) -> Result<RecordedAudio> {
+        let (reply, stopped) = oneshot::channel();
+        self.send(Command::Stop(reply))?;
+        stopped.await.map_err(|_| anyhow::anyhow!("The recorder thread has stopped"))?
+    }
+
+    /// Receive the mono samples of every recording in chunks while it records, as
+    /// with [`SimpleRecorder::subscribe`]
+    pub fn samples(&self) -> broadcast::Receiver<Vec<SampleType>> {
+        self.chunks.subscribe()
+    }
+
+    /// Receive the events of every recording, levels included, as they happen
+    ///
+    /// A receiver that falls more than about 2.5s behind misses events and gets
+    /// [`broadcast::error::RecvError::Lagged`].
+    pub fn events(&self) -> broadcast::Receiver<RecorderEvent> {
+        self.events.subscribe()
+    }
+
+    /// Watch the level of the latest samples recorded, e.g. to draw a meter
+    pub fn levels(&self) -> watch::Receiver<LevelMeter> {
+        self.levels.clone()
+    }
+
+    fn send(&self, command: Command) -> Result<()> {
+        self.commands
+            .send(command)
+            .map_err(|_| anyhow::anyhow!("The recorder thread has stopped"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stop_without_recording() {
+        let recorder = AsyncRecorder::new(|_| {});
+        let error = recorder.stop().await.unwrap_err();
+        assert_eq!(error.to_string(), "Not currently recording");
+    }
+}
@@ -0,0 +1,208 @@
+use anyhow::Result;
+use cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    FromSample, SizedSample,
+};
+use std::{
+    f32::consts::TAU,
+    path::Path,
+    sync::{mpsc, Arc},
+    time::Duration,
+};
+
+use crate::device::output_device_named;
+use crate::resample::resample_channel;
+
+/// Sample rate of generated tones
+const TONE_RATE: u32 = 48000;
+/// Fade in and out of generated tones, so they don't click
+const TONE_FADE: Duration = Duration::from_millis(5);
+/// Time the output stream is kept open after the cue, so the device can drain it
+const DRAIN_TIME: Duration = Duration::from_millis(100);
+
+/// A short sound played on an event, such as the start of a recording
+#[derive(Debug, Clone)]
+pub struct Cue {
+    /// Mono samples
+    samples: Arc<Vec<f32>>,
+    sample_rate: u32,
+    volume: f32,
+}
+
+impl Cue {
+    /// Load a cue from a WAV file of any sample rate and channel count
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let reader = hound::WavReader::open(path)
+            .map_err(|e| anyhow::anyhow!("Failed to open cue {}: {}", path.display(), e))?;
+        let spec = reader.spec();
+
+        let samples: Vec<f32> = match spec.sample_format {
+            hound::SampleFormat::Float => reader.into_samples::<f32>().collect::<Result<_, _>>(),
+            hound::SampleFormat::Int => {
+                let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+                reader
+                    .into_samples::<i32>()
+                    .map(|sample| sample.map(|sample| sample as f32 / scale))
+                    .collect::<Result<_, _>>()
+            }
+        }
+        .map_err(|e| anyhow::anyhow!("Failed to read cue {}: {}", path.display(), e))?;
+
+        // Mix down to mono
+        let channels = spec.channels.max(1) as usize;
+        let samples = samples
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect();
+
+        Ok(Self {
+            samples: Arc::new(samples),
+            sample_rate: spec.sample_rate,
+            volume: 1.0,
+        })
+    }
+
+    /// A sine beep of `frequency` Hz lasting `duration`
+    pub fn tone(frequency: f32, duration: Duration) -> Self {
+        let length = (duration.as_secs_f32() * TONE_RATE as f32) as usize;
+        let fade = (TONE_FADE.as_secs_f32() * TONE_RATE as f32) as usize;
+        let samples = (0..length)
+            .map(|i| {
+                let envelope = (i.min(length - i) as f32 / fade as f32).min(1.0);
+                let phase = TAU * frequency * i as f32 / TONE_RATE as f32;
+                0.5 * envelope * phase.sin()
+            })
+            .collect();
+
+        Self {
+            samples: Arc::new(samples),
+            sample_rate: TONE_RATE,
+            volume: 1.0,
+        }
+    }
+
+    /// Scale the cue's loudness; 1.0 plays it as recorded
+    pub fn volume(mut self, volume: f32) -> Self {
+        self.volume = volume.max(0.0);
+        self
+    }
+
+    /// How long the cue plays
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.samples.len() as f64 / self.sample_rate as f64)
+    }
+}
+
+/// Plays cues on one output device
+///
+/// With a device name, cues only ever play on that device (e.g. a headset);
+/// if it is missing they are not played at all rather than falling back to
+/// the speakers.
+#[derive(Debug, Clone, Default)]
+pub struct CuePlayer {
+    device: Option<String>,
+}
+
+impl CuePlayer {
+    /// Play on the device whose name is or contains `device`, or on the default
+    /// output device
+    pub fn new(device: Option<&str>) -> Self {
+        Self {
+            device: device.map(str::to_string),
+        }
+    }
+
+    /// Start playing `cue` in the background
+    ///
+    /// Returns once playback has started, or with the error that prevented it.
+    pub fn play(&self, cue: &Cue) -> Result<()> {
+        let (device, cue) = (self.device.clone(), cue.clone());
+        let (started, result) = mpsc::channel();
+        // Streams can't move between threads on every platform, so open it on the
+        // thread that keeps it alive until the cue has played
+        std::thread::spawn(move || match open_stream(device.as_deref(), &cue) {
+            Ok(stream) => {
+                let _ = started.send(Ok(()));
+                std::thread::sleep(cue.duration() + DRAIN_TIME);
+                drop(stream);
+            }
+            Err(e) => {
+                let _ = started.send(Err(e));
+            }
+        });
+        result
+            .recv()
+            .map_err(|_| anyhow::anyhow!("Cue playback thread exited"))?
+    }
+}
+
+fn open_stream(device_name: Option<&str>, cue: &Cue) -> Result<cpal::Stream> {
+    let host = cpal::default_host();
+    let device = match device_name {
+        Some(name) => output_device_named(&host, name)?,
+        None => host
+            .default_output_device()
+            .ok_or_else(|| anyhow::anyhow!("No default output device available"))?,
+    };
+    let config = device.default_output_config()?;
+    let sample_rate = config.sample_rate().0;
+
+    let samples: Vec<f32> = resample_channel(&cue.samples, cue.sample_rate, sample_rate)?
+        .into_iter()
+        .map(|sample| (sample * cue.volume).clamp(-1.0, 1.0))
+        .collect();
+
+    let stream = match config.sample_format() {
+        cpal::SampleFormat::I16 => build_output_stream::<i16>(&device, &config, samples)?,
+        cpal::SampleFormat::I32 => build_output_stream::<i32>(&device, &config, samples)?,
+        cpal::SampleFormat::U16 => build_output_stream::<u16>(&device, &config, samples)?,
+        cpal::SampleFormat::F32 => build_output_stream::<f32>(&device, &config, samples)?,
+        sample_format => {
+            return Err(anyhow::anyhow!("Unsupported sample format: {:?}", sample_format));
+        }
+    };
+    stream.play()?;
+    Ok(stream)
+}
+
+/// Stream `samples` to every channel of `device`, then silence
+fn build_output_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::SupportedStreamConfig,
+    samples: Vec<f32>,
+) -> Result<cpal::Stream>
+where
+    T: SizedSample + FromSample<f32> + Send + 'static,
+{
+    let channels = config.channels() as usize;
+    let mut position = 0;
+    let stream = device.build_output_stream(
+        &config.config(),
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+            for frame in data.chunks_mut(channels) {
+                let sample = samples.get(position).copied().unwrap_or(0.0);
+                position += 1;
+                frame.fill(T::from_sample(sample));
+            }
+        },
+        |err| eprintln!("Cue playback error: {}", err),
+        None,
+    )?;
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tone() {
+        let cue = Cue::tone(440.0, Duration::from_millis(100));
+        assert_eq!(cue.samples.len(), 4800);
+        assert_eq!(cue.duration(), Duration::from_millis(100));
+        // Faded in and out
+        assert_eq!(cue.samples[0], 0.0);
+        assert!(cue.samples.iter().all(|sample| sample.abs() <= 0.5));
+    }
+}
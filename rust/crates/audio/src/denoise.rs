@@ -0,0 +1,87 @@
+use nnnoiseless::DenoiseState;
+
+use crate::SampleType;
+
+/// The only sample rate RNNoise works at
+pub const DENOISE_SAMPLE_RATE: u32 = 48000;
+
+/// Removes background noise such as fans and keyboard clicks from mono 48kHz audio
+///
+/// Runs RNNoise (through nnnoiseless) on 10ms frames, so output lags the input by up
+/// to a frame until [`Denoiser::finish`] returns the rest. The first frame fades in.
+#[derive(Clone)]
+pub struct Denoiser {
+    state: Box<DenoiseState<'static>>,
+    /// Samples that don't make up a whole frame yet
+    pending: Vec<f32>,
+    output: Vec<f32>,
+}
+
+impl Denoiser {
+    pub fn new() -> Self {
+        Self {
+            state: DenoiseState::new(),
+            pending: Vec::with_capacity(DenoiseState::FRAME_SIZE),
+            output: vec![0.0; DenoiseState::FRAME_SIZE],
+        }
+    }
+
+    /// Denoise the next samples, returning those of every frame they completed
+    pub fn process(&mut self, samples: &[SampleType]) -> Vec<SampleType> {
+        let mut denoised = Vec::with_capacity(samples.len() + DenoiseState::FRAME_SIZE);
+        for &sample in samples {
+            self.pending.push(sample as f32);
+            if self.pending.len() == DenoiseState::FRAME_SIZE {
+                self.denoise_frame(&mut denoised);
+            }
+        }
+        denoised
+    }
+
+    /// Denoise the samples of the last, partial frame
+    pub fn finish(mut self) -> Vec<SampleType> {
+        let mut denoised = Vec::with_capacity(DenoiseState::FRAME_SIZE);
+        let len = self.pending.len();
+        if len > 0 {
+            self.pending.resize(DenoiseState::FRAME_SIZE, 0.0);
+            self.denoise_frame(&mut denoised);
+            denoised.truncate(len);
+        }
+        denoised
+    }
+
+    fn denoise_frame(&mut self, denoised: &mut Vec<SampleType>) {
+        self.state.process_frame(&mut self.output, &self.pending);
+        self.pending.clear();
+        // Already in the range of 16-bit samples
+        denoised.extend(self.output.iter().map(|&sample| {
+            sample.clamp(SampleType::MIN as f32, SampleType::MAX as f32) as SampleType
+        }));
+    }
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_denoiser_keeps_sample_count() {
+        let samples: Vec<SampleType> = (0..2000).map(|i| ((i * 37) % 200 - 100) as i16).collect();
+        let mut denoiser = Denoiser::new();
+
+        let mut denoised = Vec::new();
+        for chunk in samples.chunks(333) {
+            denoised.extend(denoiser.process(chunk));
+        }
+        // Whole frames of 480 samples come out right away
+        assert_eq!(denoised.len(), 1920);
+        denoised.extend(denoiser.finish());
+        assert_eq!(denoised.len(), samples.len());
+    }
+}
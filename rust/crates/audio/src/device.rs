@@ -1,5 +1,5 @@
 use anyhow::Result;
-use cpal::{traits::{DeviceTrait, HostTrait}, Device};
+use cpal::{traits::{DeviceTrait, HostTrait}, Device, Host};
 use serde::{Deserialize, Serialize};
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
@@ -34,6 +34,142 @@ pub fn get_microphones() -> Result<String> {
     Ok(serde_json::to_string(&devices_list)?)
 }
 
+/// Full name of the first input device whose name contains `name`, ignoring case
+///
+/// The name selects the device when passed to the recorder.
+pub fn find_input_device(name: &str) -> Result<String> {
+    let host = cpal::default_host();
+    let lowercase = name.to_lowercase();
+    host.input_devices()?
+        .filter_map(|device| device.name().ok())
+        .find(|device_name| device_name.to_lowercase().contains(&lowercase))
+        .ok_or_else(|| anyhow::anyhow!("No input device named like {:?}", name))
+}
+
+/// Full name of the first connected input device in `preferences`, each a name or part
+/// of one, or `None` to record from the system default
+///
+/// `"default"` in the list stands for the system's default input, which is always
+/// there, so devices after it are never picked. The default is also used when none of
+/// the preferred devices is connected.
+pub fn preferred_input_device(preferences: &[String]) -> Result<Option<String>> {
+    let host = cpal::default_host();
+    let connected: Vec<String> = host.input_devices()?
+        .filter_map(|device| device.name().ok())
+        .collect();
+    Ok(pick_device(preferences, &connected))
+}
+
+/// The first of `preferences` among the `connected` device names
+fn pick_device(preferences: &[String], connected: &[String]) -> Option<String> {
+    for preference in preferences {
+        if preference.eq_ignore_ascii_case("default") {
+            return None;
+        }
+        let lowercase = preference.to_lowercase();
+        let found = connected
+            .iter()
+            .find(|device_name| device_name.to_lowercase().contains(&lowercase));
+        if let Some(device_name) = found {
+            return Some(device_name.clone());
+        }
+    }
+    None
+}
+
+/// Get list of available speakers and headphones, like [`get_microphones`]
+pub fn get_speakers() -> Result<String> {
+    let host = cpal::default_host();
+    let devices = host.output_devices()?;
+
+    let devices_list: Vec<AudioDevice> = devices
+        .filter_map(|device| {
+            device.name().ok().map(|name| AudioDevice {
+                id: get_device_hash(&name),
+                name: name.clone(),
+            })
+        })
+        .collect();
+
+    Ok(serde_json::to_string(&devices_list)?)
+}
+
+/// Full name of the output device named `name`, or else the first whose name contains it,
+/// ignoring case
+///
+/// The name selects the device when passed to [`crate::CuePlayer::new`].
+pub fn find_output_device(name: &str) -> Result<String> {
+    let host = cpal::default_host();
+    let device = output_device_named(&host, name)?;
+    Ok(device.name()?)
+}
+
+/// The output device named `name`, or else the first whose name contains it (ignoring case)
+pub(crate) fn output_device_named(host: &cpal::Host, name: &str) -> Result<Device> {
+    let devices: Vec<_> = host.output_devices()?.collect();
+    let names: Vec<String> = devices
+        .iter()
+        .map(|device| device.name().unwrap_or_default())
+        .collect();
+
+    let exact = names.iter().position(|device_name| device_name == name);
+    let partial = || {
+        names
+            .iter()
+            .position(|device_name| device_name.to_lowercase().contains(&name.to_lowercase()))
+    };
+    exact
+        .or_else(partial)
+        .map(|index| devices[index].clone())
+        .ok_or_else(|| anyhow::anyhow!("Output device '{}' not found", name))
+}
+
+/// Names of the audio hosts this build can use, e.g. "ALSA" and "JACK"
+///
+/// JACK and ASIO are only available in builds with the `jack` or `asio` feature.
+pub fn get_hosts() -> Vec<String> {
+    cpal::available_hosts()
+        .into_iter()
+        .map(|id| id.name().to_string())
+        .collect()
+}
+
+/// The audio host named `name`, ignoring case, or the platform's default host without one
+pub fn get_host(name: Option<&str>) -> Result<Host> {
+    let Some(name) = name else {
+        return Ok(cpal::default_host());
+    };
+    let id = cpal::available_hosts()
+        .into_iter()
+        .find(|id| id.name().eq_ignore_ascii_case(name))
+        .ok_or_else(|| {
+            anyhow::anyhow!("No audio host {:?}; available: {}", name, get_hosts().join(", "))
+        })?;
+    cpal::host_from_id(id).map_err(|e| anyhow::anyhow!("Audio host {} unavailable: {}", name, e))
+}
+
+/// The input device on the audio host named `host` whose name is or contains `name`,
+/// ignoring case, or the host's default input without a name
+pub fn get_input_device_on_host(host: &str, name: Option<&str>) -> Result<Device> {
+    let host = get_host(Some(host))?;
+    let Some(name) = name else {
+        return host
+            .default_input_device()
+            .ok_or_else(|| anyhow::anyhow!("No default input device on {}", host.id().name()));
+    };
+    let devices: Vec<Device> = host.input_devices()?.collect();
+    let lowercase = name.to_lowercase();
+    let exact = devices.iter().find(|device| device.name().is_ok_and(|n| n == name));
+    let partial = || {
+        devices
+            .iter()
+            .find(|device| device.name().is_ok_and(|n| n.to_lowercase().contains(&lowercase)))
+    };
+    exact.or_else(partial).cloned().ok_or_else(|| {
+        anyhow::anyhow!("No input device named like {:?} on {}", name, host.id().name())
+    })
+}
+
 /// Get an input device by its identifier
 pub fn get_input_device(device_id: &str) -> Result<Device> {
     let host = cpal::default_host();
@@ -50,4 +186,23 @@ pub fn get_input_device(device_id: &str) -> Result<Device> {
     .ok_or_else(|| anyhow::anyhow!("Could not find input device"))?;
 
     Ok(device)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_device() {
+        let preferences = ["Shure MV7", "airpods", "default", "Webcam"].map(String::from);
+        let connected = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+
+        let devices: Vec<String> = connected(&["MacBook Pro Microphone", "Jo's AirPods Pro"]);
+        assert_eq!(pick_device(&preferences, &devices).as_deref(), Some("Jo's AirPods Pro"));
+        let devices: Vec<String> = connected(&["AirPods", "Shure MV7"]);
+        assert_eq!(pick_device(&preferences, &devices).as_deref(), Some("Shure MV7"));
+        // Nothing after the default is considered
+        let devices: Vec<String> = connected(&["USB Webcam"]);
+        assert_eq!(pick_device(&preferences, &devices), None);
+        assert_eq!(pick_device(&[], &devices), None);
+    }
+}
@@ -0,0 +1,261 @@
+use anyhow::Result;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// Number of volume steps in a fade
+const FADE_STEPS: u32 = 10;
+
+/// Lowers the system output volume while recording so playback doesn't bleed into the mic
+///
+/// Volume changes are faded in on a background thread. The original volume is
+/// restored by [`Ducker::restore`], or when the ducker is dropped.
+pub struct Ducker {
+    /// Fraction of the original volume to duck to (0.0 mutes)
+    level: f32,
+    fade: Duration,
+    /// Volume before ducking, while ducked
+    original: Option<f32>,
+    /// Incremented for every fade so a newer fade cancels an older one
+    generation: Arc<AtomicU64>,
+}
+
+impl Ducker {
+    /// Create a ducker that lowers the volume to `level` times the current volume
+    pub fn new(level: f32, fade: Duration) -> Self {
+        Self {
+            level: level.clamp(0.0, 1.0),
+            fade,
+            original: None,
+            generation: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Whether the volume is currently lowered
+    pub fn is_ducked(&self) -> bool {
+        self.original.is_some()
+    }
+
+    /// Fade the system volume down
+    pub fn duck(&mut self) -> Result<()> {
+        if self.original.is_some() {
+            return Ok(());
+        }
+
+        let volume = system_volume()?;
+        self.original = Some(volume);
+        self.fade_to(volume, volume * self.level);
+        Ok(())
+    }
+
+    /// Fade the system volume back to where it was before ducking
+    pub fn restore(&mut self) -> Result<()> {
+        let Some(original) = self.original else {
+            return Ok(());
+        };
+
+        // Stay ducked, and able to restore later, if the volume can't be read
+        let volume = system_volume()?;
+        self.original = None;
+        self.fade_to(volume, original);
+        Ok(())
+    }
+
+    /// Set the volume back to where it was before ducking right away, without fading,
+    /// e.g. before exiting on a signal
+    pub fn restore_now(&mut self) -> Result<()> {
+        // Stop any running fade
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        match self.original.take() {
+            Some(original) => set_system_volume(original),
+            None => Ok(()),
+        }
+    }
+
+    fn fade_to(&self, from: f32, to: f32) {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let current = Arc::clone(&self.generation);
+        let step_time = self.fade / FADE_STEPS;
+
+        std::thread::spawn(move || {
+            for step in 1..=FADE_STEPS {
+                if current.load(Ordering::SeqCst) != generation {
+                    return;
+                }
+                let volume = from + (to - from) * step as f32 / FADE_STEPS as f32;
+                if let Err(e) = set_system_volume(volume) {
+                    eprintln!("Failed to set system volume: {}", e);
+                    return;
+                }
+                std::thread::sleep(step_time);
+            }
+        });
+    }
+}
+
+impl Drop for Ducker {
+    fn drop(&mut self) {
+        // There is no time to fade on exit
+        let _ = self.restore_now();
+    }
+}
+
+/// Volume of the default output device, from 0.0 to 1.0
+pub fn system_volume() -> Result<f32> {
+    platform::volume()
+}
+
+/// Set the volume of the default output device, from 0.0 to 1.0
+pub fn set_system_volume(volume: f32) -> Result<()> {
+    platform::set_volume(volume.clamp(0.0, 1.0))
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use anyhow::Result;
+    use std::process::Command;
+
+    // PipeWire's wpctl first, PulseAudio's pactl as fallback
+    pub fn volume() -> Result<f32> {
+        if let Ok(output) = run("wpctl", &["get-volume", "@DEFAULT_AUDIO_SINK@"]) {
+            if let Some(volume) = super::parse_wpctl(&output) {
+                return Ok(volume);
+            }
+        }
+        let output = run("pactl", &["get-sink-volume", "@DEFAULT_SINK@"])?;
+        super::parse_pactl(&output)
+            .ok_or_else(|| anyhow::anyhow!("Unexpected pactl output: {}", output.trim()))
+    }
+
+    pub fn set_volume(volume: f32) -> Result<()> {
+        if run("wpctl", &["set-volume", "@DEFAULT_AUDIO_SINK@", &format!("{:.3}", volume)]).is_ok() {
+            return Ok(());
+        }
+        let percent = format!("{}%", (volume * 100.0).round() as u32);
+        run("pactl", &["set-sink-volume", "@DEFAULT_SINK@", &percent]).map(|_| ())
+    }
+
+    fn run(program: &str, args: &[&str]) -> Result<String> {
+        let output = Command::new(program)
+            .args(args)
+            .output()
+            .map_err(|e| anyhow::anyhow!("Failed to run {}: {}", program, e))?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("{} exited with {}", program, output.status));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use anyhow::Result;
+    use std::process::Command;
+
+    pub fn volume() -> Result<f32> {
+        let output = osascript("output volume of (get volume settings)")?;
+        let percent: f32 = output
+            .trim()
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Unexpected osascript output: {}", output.trim()))?;
+        Ok(percent / 100.0)
+    }
+
+    pub fn set_volume(volume: f32) -> Result<()> {
+        osascript(&format!("set volume output volume {}", (volume * 100.0).round() as u32))
+            .map(|_| ())
+    }
+
+    fn osascript(script: &str) -> Result<String> {
+        let output = Command::new("osascript")
+            .args(["-e", script])
+            .output()
+            .map_err(|e| anyhow::anyhow!("Failed to run osascript: {}", e))?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("osascript exited with {}", output.status));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use anyhow::Result;
+    use windows::Win32::{
+        Media::Audio::{
+            eConsole, eRender, Endpoints::IAudioEndpointVolume, IMMDeviceEnumerator,
+            MMDeviceEnumerator,
+        },
+        System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_ALL, COINIT_MULTITHREADED},
+    };
+
+    pub fn volume() -> Result<f32> {
+        let endpoint = endpoint_volume()?;
+        unsafe { endpoint.GetMasterVolumeLevelScalar() }
+            .map_err(|e| anyhow::anyhow!("Failed to get volume: {}", e))
+    }
+
+    pub fn set_volume(volume: f32) -> Result<()> {
+        let endpoint = endpoint_volume()?;
+        unsafe { endpoint.SetMasterVolumeLevelScalar(volume, std::ptr::null()) }
+            .map_err(|e| anyhow::anyhow!("Failed to set volume: {}", e))
+    }
+
+    fn endpoint_volume() -> Result<IAudioEndpointVolume> {
+        unsafe {
+            // Fails harmlessly if COM is already initialized on this thread
+            let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+            let enumerator: IMMDeviceEnumerator =
+                CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
+            let device = enumerator.GetDefaultAudioEndpoint(eRender, eConsole)?;
+            Ok(device.Activate(CLSCTX_ALL, None)?)
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod platform {
+    use anyhow::Result;
+
+    pub fn volume() -> Result<f32> {
+        Err(anyhow::anyhow!("Audio ducking is not supported on this platform"))
+    }
+
+    pub fn set_volume(_volume: f32) -> Result<()> {
+        Err(anyhow::anyhow!("Audio ducking is not supported on this platform"))
+    }
+}
+
+/// Parse `wpctl get-volume` output, e.g. "Volume: 0.40" or "Volume: 0.40 [MUTED]"
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_wpctl(output: &str) -> Option<f32> {
+    output.trim().strip_prefix("Volume:")?.split_whitespace().next()?.parse().ok()
+}
+
+/// Parse the first channel of `pactl get-sink-volume` output, e.g.
+/// "Volume: front-left: 26214 /  40% / -23.88 dB,   front-right: ..."
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_pactl(output: &str) -> Option<f32> {
+    let percent = output.split('/').nth(1)?.trim().strip_suffix('%')?;
+    percent.parse::<f32>().ok().map(|percent| percent / 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_volume() {
+        assert_eq!(parse_wpctl("Volume: 0.40\n"), Some(0.4));
+        assert_eq!(parse_wpctl("Volume: 0.25 [MUTED]"), Some(0.25));
+        assert_eq!(
+            parse_pactl("Volume: front-left: 26214 /  40% / -23.88 dB,   front-right: 26214 /  40% / -23.88 dB\n"),
+            Some(0.4)
+        );
+        assert_eq!(parse_pactl("garbage"), None);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::SampleType;
+
+/// Length of the blocks the gain is adjusted on
+const BLOCK_MS: u32 = 10;
+/// Blocks with a peak below this are left out, so silence isn't raised to the target
+const GATE_DB: f32 = -55.0;
+/// How fast the gain rises toward quieter input
+const RISE_DB_PER_SECOND: f32 = 6.0;
+
+/// Automatic gain control that brings the peaks of speech to a target level
+///
+/// Quiet microphones are raised by up to `max_gain_db` so Whisper doesn't hear near
+/// silence, and hot ones are lowered so they don't clip. The gain drops at once when a
+/// peak would go over the target and rises slowly, so speech keeps its dynamics.
+#[derive(Debug, Clone)]
+pub struct AutomaticGain {
+    block_len: usize,
+    target: f32,
+    max_gain: f32,
+    gate: f32,
+    /// Factor the gain may rise by per block
+    rise: f32,
+    gain: f32,
+}
+
+impl AutomaticGain {
+    /// Gain control for mono audio at `sample_rate`, aiming peaks at `target_db` dBFS
+    pub fn new(sample_rate: u32, target_db: f32) -> Self {
+        let block_len = (sample_rate * BLOCK_MS / 1000).max(1) as usize;
+        Self {
+            block_len,
+            target: db_to_linear(target_db.min(0.0)),
+            max_gain: db_to_linear(30.0),
+            gate: db_to_linear(GATE_DB),
+            rise: db_to_linear(RISE_DB_PER_SECOND * BLOCK_MS as f32 / 1000.0),
+            gain: 1.0,
+        }
+    }
+
+    /// Most the input is raised by, in dB; defaults to 30
+    pub fn max_gain_db(mut self, max_gain_db: f32) -> Self {
+        self.max_gain = db_to_linear(max_gain_db.max(0.0));
+        self.gain = self.gain.min(self.max_gain);
+        self
+    }
+
+    /// Current gain in dB
+    pub fn gain_db(&self) -> f32 {
+        20.0 * self.gain.log10()
+    }
+
+    /// Apply the gain to `samples` in place
+    pub fn process(&mut self, samples: &mut [SampleType]) {
+        for block in samples.chunks_mut(self.block_len) {
+            let peak = block
+                .iter()
+                .map(|sample| sample.unsigned_abs())
+                .max()
+                .unwrap_or(0);
+            let peak = peak as f32 / SampleType::MAX as f32;
+            if peak > self.gate {
+                let wanted = (self.target / peak).min(self.max_gain);
+                self.gain = if wanted < self.gain {
+                    wanted
+                } else {
+                    (self.gain * self.rise).min(wanted)
+                };
+            }
+
+            for sample in block.iter_mut() {
+                let amplified = *sample as f32 * self.gain;
+                *sample =
+                    amplified.clamp(SampleType::MIN as f32, SampleType::MAX as f32) as SampleType;
+            }
+        }
+    }
+}
+
+fn db_to_linear(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(samples: usize, amplitude: f32) -> Vec<SampleType> {
+        (0..samples)
+            .map(|i| ((i as f32 * 0.2).sin() * amplitude) as SampleType)
+            .collect()
+    }
+
+    fn peak(samples: &[SampleType]) -> SampleType {
+        samples.iter().map(|sample| sample.abs()).max().unwrap()
+    }
+
+    #[test]
+    fn test_quiet_input_rises_to_target() {
+        let mut gain = AutomaticGain::new(16000, -6.0);
+        // -40 dBFS, which needs 34 dB, more than the maximum
+        let mut quiet = tone(16000 * 6, 328.0);
+        gain.process(&mut quiet);
+
+        assert!((gain.gain_db() - 30.0).abs() < 0.01);
+        let end = &quiet[quiet.len() - 1600..];
+        assert!((peak(end) as f32 - 328.0 * db_to_linear(30.0)).abs() < 50.0);
+    }
+
+    #[test]
+    fn test_loud_input_is_lowered_at_once() {
+        let mut gain = AutomaticGain::new(16000, -6.0);
+        let mut loud = tone(1600, 32000.0);
+        gain.process(&mut loud);
+
+        // Within the first block already
+        assert!(peak(&loud) <= (db_to_linear(-6.0) * 32767.0) as SampleType + 1);
+        assert!(gain.gain_db() < -5.0);
+    }
+
+    #[test]
+    fn test_silence_is_not_raised() {
+        let mut gain = AutomaticGain::new(16000, -6.0);
+        let mut silence = tone(16000, 20.0);
+        gain.process(&mut silence);
+
+        assert_eq!(gain.gain_db(), 0.0);
+        assert!(peak(&silence) <= 20);
+    }
+}
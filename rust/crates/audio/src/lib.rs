@@ -1,11 +1,48 @@
+#[cfg(feature = "async")]
+pub mod async_recorder;
+pub mod cue;
+#[cfg(feature = "denoise")]
+pub mod denoise;
 pub mod device;
+pub mod ducking;
+pub mod filter;
+pub mod gain;
+pub mod meter;
+#[cfg(feature = "peaks")]
 pub mod peaks;
+pub mod permission;
 pub mod recorder;
+pub mod recovery;
 pub mod resample;
+pub mod rollover;
+#[cfg(feature = "silero")]
+pub mod silero;
+pub mod trim;
+pub mod vad;
 
 pub type SampleType = i16;
 
-pub use device::{get_input_device, get_microphones, AudioDevice};
-pub use peaks::send_peaks;
-pub use recorder::SimpleRecorder;
-pub use resample::resample_wav_file;
+#[cfg(feature = "async")]
+pub use async_recorder::AsyncRecorder;
+pub use cue::{Cue, CuePlayer};
+#[cfg(feature = "denoise")]
+pub use denoise::{Denoiser, DENOISE_SAMPLE_RATE};
+pub use device::{
+    find_input_device, find_output_device, get_host, get_hosts, get_input_device,
+    get_input_device_on_host, get_microphones, get_speakers, preferred_input_device, AudioDevice,
+};
+pub use ducking::Ducker;
+pub use filter::{DcBlocker, HighPassFilter};
+pub use gain::AutomaticGain;
+pub use meter::LevelMeter;
+#[cfg(feature = "peaks")]
+pub use peaks::{send_levels, send_peaks};
+pub use permission::{mic_permission, request_mic_permission, MicPermission};
+pub use recorder::{RecordedAudio, RecorderEvent, SimpleRecorder};
+pub use recovery::repair_wav_file;
+pub use resample::{resample_wav_file, StreamingResampler, STREAMING_OUTPUT_RATE};
+pub use rollover::{manifest_path, RecordingManifest, RollingWavWriter};
+#[cfg(feature = "silero")]
+pub use silero::{SileroModel, SileroVad};
+pub use trim::{speech_bounds, trim_silence};
+pub use vad::{SpeechDetector, VadBackend, VoiceActivityDetector};
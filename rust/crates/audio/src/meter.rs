@@ -0,0 +1,69 @@
+use crate::SampleType;
+
+/// Level reported for a block of digital silence
+pub const SILENCE_DBFS: f32 = -100.0;
+
+/// Peak and RMS level of a block of samples, in dBFS, for a level meter
+///
+/// 0 dBFS is full scale; silence is [`SILENCE_DBFS`]. Speech near the microphone
+/// usually peaks around -12 to -6 dBFS with an RMS level some 10 to 20 dB lower.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LevelMeter {
+    pub peak_dbfs: f32,
+    pub rms_dbfs: f32,
+}
+
+impl LevelMeter {
+    /// Measure the level of `samples`
+    pub fn measure(samples: &[SampleType]) -> Self {
+        Self {
+            peak_dbfs: peak_dbfs(samples),
+            rms_dbfs: rms_dbfs(samples),
+        }
+    }
+}
+
+/// Level of the loudest sample of `samples` in dBFS
+pub fn peak_dbfs(samples: &[SampleType]) -> f32 {
+    let peak = samples.iter().map(|sample| sample.unsigned_abs()).max().unwrap_or(0);
+    to_dbfs(peak as f64 / SampleType::MAX as f64)
+}
+
+/// RMS level of `samples` in dBFS
+pub fn rms_dbfs(samples: &[SampleType]) -> f32 {
+    let power = samples
+        .iter()
+        .map(|&sample| (sample as f64 / SampleType::MAX as f64).powi(2))
+        .sum::<f64>()
+        / samples.len().max(1) as f64;
+    to_dbfs(power.sqrt())
+}
+
+fn to_dbfs(amplitude: f64) -> f32 {
+    if amplitude > 0.0 {
+        (20.0 * amplitude.log10() as f32).clamp(SILENCE_DBFS, 0.0)
+    } else {
+        SILENCE_DBFS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_scale_sine() {
+        let sine: Vec<SampleType> = (0..1600)
+            .map(|i| ((i as f32 * 0.05).sin() * SampleType::MAX as f32) as SampleType)
+            .collect();
+        let meter = LevelMeter::measure(&sine);
+        assert!(meter.peak_dbfs > -0.01);
+        // The RMS of a sine is 3 dB below its peak
+        assert!((meter.rms_dbfs + 3.01).abs() < 0.05);
+
+        let silence = LevelMeter::measure(&[0; 160]);
+        assert_eq!(silence.peak_dbfs, SILENCE_DBFS);
+        assert_eq!(silence.rms_dbfs, SILENCE_DBFS);
+        assert_eq!(LevelMeter::measure(&[]).rms_dbfs, SILENCE_DBFS);
+    }
+}
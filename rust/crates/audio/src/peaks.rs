@@ -1,4 +1,4 @@
-use crate::SampleType;
+use crate::{LevelMeter, SampleType};
 use std::time::{Duration, Instant};
 use tokio::sync::broadcast;
 
@@ -13,9 +13,9 @@ where
     while let Ok(samples) = peaks_rx.recv().await {
         let current_peak = samples.iter().fold(0 as SampleType, |peak, &sample| {
             if sample > 0 {
-                peak.max(sample.min(SampleType::MAX))
+                peak.max(sample)
             } else {
-                peak.min(sample.max(SampleType::MIN))
+                peak.min(sample)
             }
         });
         if last_send_time.elapsed() >= THROTTLE_DURATION {
@@ -24,3 +24,18 @@ where
         }
     }
 }
+
+/// Like [`send_peaks`], but with the peak and RMS level in dBFS, for a proper level meter
+pub async fn send_levels<F>(mut peaks_rx: broadcast::Receiver<Vec<SampleType>>, mut callback: F)
+where
+    F: FnMut(LevelMeter) + Send + 'static,
+{
+    let mut last_send_time = Instant::now();
+
+    while let Ok(samples) = peaks_rx.recv().await {
+        if last_send_time.elapsed() >= THROTTLE_DURATION {
+            callback(LevelMeter::measure(&samples));
+            last_send_time = Instant::now();
+        }
+    }
+}
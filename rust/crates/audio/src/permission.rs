@@ -0,0 +1,180 @@
+use anyhow::Result;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use std::time::{Duration, Instant};
+
+/// How long to wait for the user to answer the system's permission prompt
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(60);
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Whether the system lets this program use the microphone
+///
+/// Without permission, macOS and Windows hand out silence instead of failing, so
+/// a denied permission otherwise shows up as recordings without speech.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MicPermission {
+    /// Allowed, or the platform has no microphone permission
+    Granted,
+    /// Turned off in the system's privacy settings
+    Denied,
+    /// Blocked by a device management policy (macOS)
+    Restricted,
+    /// Not asked for yet (macOS)
+    NotDetermined,
+}
+
+impl MicPermission {
+    /// Where the user can allow microphone access
+    pub fn settings_hint() -> &'static str {
+        platform::SETTINGS_HINT
+    }
+}
+
+/// The current microphone permission
+pub fn mic_permission() -> MicPermission {
+    platform::mic_permission()
+}
+
+/// Ask for microphone access if it wasn't asked for yet, and wait for the answer
+///
+/// On macOS the system prompt appears when a program first opens the microphone, so
+/// this opens it briefly; call it during setup instead of on the first recording.
+/// Elsewhere this only reports the current permission.
+pub fn request_mic_permission() -> Result<MicPermission> {
+    let permission = mic_permission();
+    if permission != MicPermission::NotDetermined {
+        return Ok(permission);
+    }
+
+    // Opening the microphone makes the system ask
+    let device = cpal::default_host()
+        .default_input_device()
+        .ok_or_else(|| anyhow::anyhow!("No default input device available"))?;
+    let config = device.default_input_config()?;
+    let stream = device.build_input_stream_raw(
+        &config.config(),
+        config.sample_format(),
+        |_, _| {},
+        |_| {},
+        None,
+    )?;
+    stream.play()?;
+
+    let deadline = Instant::now() + PROMPT_TIMEOUT;
+    loop {
+        let permission = mic_permission();
+        if permission != MicPermission::NotDetermined || Instant::now() >= deadline {
+            return Ok(permission);
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::MicPermission;
+    use std::{
+        ffi::{c_char, c_void},
+        os::raw::c_long,
+    };
+
+    pub const SETTINGS_HINT: &str =
+        "System Settings > Privacy & Security > Microphone, for your terminal or voicekb";
+
+    #[link(name = "AVFoundation", kind = "framework")]
+    extern "C" {
+        static AVMediaTypeAudio: *const c_void;
+    }
+
+    #[link(name = "objc")]
+    extern "C" {
+        fn objc_getClass(name: *const c_char) -> *const c_void;
+        fn sel_registerName(name: *const c_char) -> *const c_void;
+        fn objc_msgSend();
+    }
+
+    /// `[AVCaptureDevice authorizationStatusForMediaType:AVMediaTypeAudio]`
+    pub fn mic_permission() -> MicPermission {
+        type AuthorizationStatus =
+            unsafe extern "C" fn(*const c_void, *const c_void, *const c_void) -> c_long;
+
+        // SAFETY: objc_msgSend is called with the signature of the method it sends to
+        let status = unsafe {
+            let class = objc_getClass(c"AVCaptureDevice".as_ptr());
+            let selector = sel_registerName(c"authorizationStatusForMediaType:".as_ptr());
+            let send: AuthorizationStatus = std::mem::transmute(objc_msgSend as *const ());
+            send(class, selector, AVMediaTypeAudio)
+        };
+        match status {
+            0 => MicPermission::NotDetermined,
+            1 => MicPermission::Restricted,
+            2 => MicPermission::Denied,
+            _ => MicPermission::Granted,
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::MicPermission;
+    use windows::{
+        core::{w, PCWSTR},
+        Win32::System::Registry::{
+            RegGetValueW, HKEY, HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE, RRF_RT_REG_SZ,
+        },
+    };
+
+    pub const SETTINGS_HINT: &str = "Settings > Privacy & security > Microphone, \
+        including \"Let desktop apps access your microphone\"";
+
+    const CONSENT_STORE: PCWSTR = w!("Software\\Microsoft\\Windows\\CurrentVersion\\\
+        CapabilityAccessManager\\ConsentStore\\microphone");
+    const DESKTOP_APPS: PCWSTR = w!("Software\\Microsoft\\Windows\\CurrentVersion\\\
+        CapabilityAccessManager\\ConsentStore\\microphone\\NonPackaged");
+
+    /// The privacy switches for the whole device, for the user's apps and for desktop apps
+    pub fn mic_permission() -> MicPermission {
+        let switches = [
+            (HKEY_LOCAL_MACHINE, CONSENT_STORE),
+            (HKEY_CURRENT_USER, CONSENT_STORE),
+            (HKEY_CURRENT_USER, DESKTOP_APPS),
+        ];
+        if switches.iter().any(|&(key, path)| consent(key, path).as_deref() == Some("Deny")) {
+            MicPermission::Denied
+        } else {
+            MicPermission::Granted
+        }
+    }
+
+    /// The `Value` of a consent store key, e.g. "Allow" or "Deny"
+    fn consent(key: HKEY, path: PCWSTR) -> Option<String> {
+        let mut buffer = [0u16; 16];
+        let mut size = std::mem::size_of_val(&buffer) as u32;
+        // SAFETY: the buffer and its size in bytes describe the same memory
+        unsafe {
+            RegGetValueW(
+                key,
+                path,
+                w!("Value"),
+                RRF_RT_REG_SZ,
+                None,
+                Some(buffer.as_mut_ptr().cast()),
+                Some(&mut size),
+            )
+        }
+        .ok()
+        .ok()?;
+        let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+        Some(String::from_utf16_lossy(&buffer[..len]))
+    }
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+mod platform {
+    use super::MicPermission;
+
+    pub const SETTINGS_HINT: &str = "the sound settings of your system";
+
+    pub fn mic_permission() -> MicPermission {
+        MicPermission::Granted
+    }
+}
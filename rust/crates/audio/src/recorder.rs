@@ -1,28 +1,250 @@
 use anyhow::Result;
 use cpal::{
     traits::{DeviceTrait, HostTrait, StreamTrait},
-    FromSample, Sample, SizedSample,
+    FromSample, Sample, SizedSample, SupportedStreamConfig, SupportedStreamConfigRange,
 };
-use hound::{WavSpec, WavWriter};
+use hound::WavSpec;
+use rtrb::{Consumer, Producer, RingBuffer};
+#[cfg(feature = "stream")]
+use tokio::sync::broadcast;
 use std::{
-    fs::File,
-    io::BufWriter,
+    collections::VecDeque,
     path::PathBuf,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
         Arc, Mutex,
     },
+    time::Duration,
 };
 
+use crate::rollover::{RollingWavWriter, MAX_WAV_DATA_BYTES};
+use crate::filter::{DcBlocker, HighPassFilter};
+use crate::gain::AutomaticGain;
+use crate::meter::LevelMeter;
+use crate::vad::{SpeechDetector, VadBackend, VoiceActivityDetector};
+#[cfg(feature = "denoise")]
+use crate::denoise::{Denoiser, DENOISE_SAMPLE_RATE};
+
 pub type SampleType = i16;
 
+/// How often the WAV header is rewritten while recording, in seconds of audio.
+/// Keeps the file readable up to the last flush if the process dies.
+const HEADER_FLUSH_SECS: u32 = 1;
+
+/// Rate captured at when the device supports it: Whisper's, so recordings need no
+/// resampling
+const DEFAULT_SAMPLE_RATE: u32 = 16000;
+
+/// Gap between two input buffers, beyond their own length, reported as a dropout
+const DROPOUT_THRESHOLD: Duration = Duration::from_millis(50);
+
+/// Audio the ring buffer between the input callback and the writer thread holds,
+/// in seconds; more than this backlog is dropped and reported as a dropout
+const RING_SECS: u32 = 2;
+
+/// How often the writer thread takes samples off the ring buffer
+const WRITE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Chunks a subscriber may fall behind by before missing some, about 2.5s of audio
+#[cfg(feature = "stream")]
+const STREAM_CHUNKS: usize = 256;
+
+/// Diagnostics reported while recording
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecorderEvent {
+    /// Peak sample of the latest block of samples written, and its level for a meter
+    Level { peak: SampleType, meter: LevelMeter },
+    /// Samples in the latest block written that hit full scale
+    Clipping { samples: usize },
+    /// Audio went missing between two input buffers, or the writer fell behind
+    Dropout { missing: Duration },
+    /// Recording started on this input device, or `None` when the device went away
+    DeviceChanged { name: Option<String> },
+    /// The stream or the file writer failed
+    Error { message: String },
+    /// Speech was followed by `silence`, as set with
+    /// [`SimpleRecorder::set_stop_on_silence`]; reported once per recording, which goes
+    /// on until it is stopped
+    SpeechEnded { silence: Duration },
+    /// A recording into memory outgrew the limit set with
+    /// [`SimpleRecorder::set_spill_to_disk`] and goes on in `path`
+    Spilled { path: PathBuf },
+    /// The input stream delivers buffers of `frames`, or `latency` of audio each, as
+    /// granted for the size asked for with [`SimpleRecorder::set_buffer_frames`];
+    /// reported with the first samples of a stream and whenever the size changes
+    BufferSize { frames: usize, latency: Duration },
+    /// The recording reached the length or size set with
+    /// [`SimpleRecorder::set_recording_limit`] at `length`; reported once, after which its
+    /// audio is discarded until it is stopped
+    LimitReached { length: Duration },
+}
+
+/// A recording kept in memory, as returned by [`SimpleRecorder::stop_recording_buffered`]
+#[derive(Debug, Clone, Default)]
+pub struct RecordedAudio {
+    /// Mono samples; empty when the recording spilled to disk
+    pub samples: Vec<SampleType>,
+    pub sample_rate: u32,
+    /// Files the recording spilled to, in order, as set with
+    /// [`SimpleRecorder::set_spill_to_disk`]; they hold all of it
+    pub files: Vec<PathBuf>,
+}
+
+/// The event callback of the current recording, if any
+type EventCallback = Arc<Mutex<Option<Box<dyn FnMut(RecorderEvent) + Send>>>>;
+
 /// A simple, reliable audio recorder that uses CPAL directly
 /// Based on the working record.rs example
 pub struct SimpleRecorder {
     is_recording: Arc<AtomicBool>,
+    /// Set while a recording leaves out what the microphone hears
+    paused: Arc<AtomicBool>,
+    /// File of the current recording; `None` while recording to memory
     output_path: Option<PathBuf>,
-    writer: Option<Arc<Mutex<WavWriter<BufWriter<File>>>>>,
-    stream: Option<cpal::Stream>,
+    on_event: EventCallback,
+    stream: Option<InputStream>,
+    keep_open: bool,
+    /// Move a stream on the default input to whatever device is the default now
+    follow_default: bool,
+    /// Capture what the system plays instead of a microphone
+    loopback: bool,
+    /// Audio host to open devices on; the platform's default without one
+    host: Option<String>,
+    max_file_bytes: u64,
+    /// File that recordings into memory move to once they outgrow `max_memory_bytes`
+    spill_path: Option<PathBuf>,
+    max_memory_bytes: u64,
+    /// Rate to capture at when the device offers it, instead of its default
+    sample_rate: Option<u32>,
+    /// Frames per input buffer to ask the device for, instead of its default
+    buffer_frames: Option<u32>,
+    stop_on_silence: Option<Duration>,
+    /// Detector that tells when speech ended, for `stop_on_silence`
+    vad: VadBackend,
+    /// Longest recording to keep audio of
+    max_duration: Option<Duration>,
+    /// Most bytes of samples a recording keeps
+    max_bytes: Option<u64>,
+    /// Frequency below which recordings are cut, with their DC offset
+    high_pass_hz: Option<f32>,
+    /// Remove the DC offset of recordings when they aren't high-passed
+    remove_dc_offset: bool,
+    /// Level in dBFS that automatic gain control brings peaks to
+    auto_gain_db: Option<f32>,
+    /// Audio from before the start that recordings begin with
+    pre_roll: Duration,
+    /// Run recordings through RNNoise
+    #[cfg(feature = "denoise")]
+    denoise: bool,
+    recorded_files: Vec<PathBuf>,
+    /// Sends the samples of the current recording to subscribers as they come in
+    #[cfg(feature = "stream")]
+    chunks: broadcast::Sender<Vec<SampleType>>,
+}
+
+/// A running input stream, the device it captures from and its writer thread
+///
+/// The input callback converts samples straight into a lock-free ring buffer,
+/// without allocating or locking, so a slow disk or a busy lock never delays the
+/// audio thread. The writer thread takes them off and writes them to the file.
+struct InputStream {
+    _stream: cpal::Stream,
+    /// The device as it was asked for
+    device_id: Option<String>,
+    device_name: Option<String>,
+    spec: WavSpec,
+    /// Set when the stream reports an error, after which it is reopened
+    failed: Arc<AtomicBool>,
+    /// Set while the input callback runs
+    busy: Arc<AtomicBool>,
+    /// Commands for the writer thread, which exits when this is dropped
+    commands: Sender<WriterCommand>,
+    /// Samples kept from before a recording starts
+    pre_roll: Arc<AtomicUsize>,
+    /// Frames of the latest input buffer, 0 until the first one came in
+    buffer_frames: Arc<AtomicUsize>,
+}
+
+/// What the writer thread is asked to do
+enum WriterCommand {
+    /// Write samples here from now on, processed as asked
+    Start(Sink, Box<Processing>),
+    /// Write the samples left in the ring buffer, then finalize the recording
+    Stop(Sender<Result<Recording>>),
+}
+
+/// Where the writer thread puts the samples of a recording
+enum Sink {
+    File(RollingWavWriter),
+    Memory(Vec<SampleType>),
+    /// In memory until the samples outgrow the spill, then in its file
+    Spillable(Vec<SampleType>, Spill),
+}
+
+/// Where a recording into memory goes on once it grows too long
+struct Spill {
+    path: PathBuf,
+    spec: WavSpec,
+    max_file_bytes: u64,
+    max_samples: usize,
+}
+
+impl Spill {
+    /// Create the file with the `samples` recorded so far, to go on recording in
+    fn create(&self, samples: &[SampleType]) -> Result<RollingWavWriter> {
+        let mut writer = RollingWavWriter::create(&self.path, self.spec, self.max_file_bytes)?;
+        for &sample in samples {
+            writer.write_sample(sample)?;
+        }
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+/// What the writer thread does to the samples of a recording besides keeping them
+#[derive(Default)]
+struct Processing {
+    silence: Option<SilenceWatch>,
+    limit: Option<Limit>,
+    dc_blocker: Option<DcBlocker>,
+    high_pass: Option<HighPassFilter>,
+    gain: Option<AutomaticGain>,
+    #[cfg(feature = "denoise")]
+    denoiser: Option<Denoiser>,
+}
+
+/// Reports when speech is followed by a stretch of silence
+struct SilenceWatch {
+    detector: Box<dyn SpeechDetector>,
+    after: Duration,
+    reported: bool,
+}
+
+/// Cuts a recording off once it holds as many samples as allowed
+struct Limit {
+    /// Samples it may still take
+    remaining: usize,
+    /// Length of the recording once cut off
+    length: Duration,
+    reached: bool,
+}
+
+/// What a finished recording left behind
+enum Recording {
+    Files(Vec<PathBuf>),
+    Samples(Vec<SampleType>),
+}
+
+impl Sink {
+    fn finish(self) -> Result<Recording> {
+        match self {
+            Self::File(writer) => writer.finalize().map(Recording::Files),
+            Self::Memory(samples) | Self::Spillable(samples, _) => {
+                Ok(Recording::Samples(samples))
+            }
+        }
+    }
 }
 
 impl SimpleRecorder {
@@ -30,13 +252,265 @@ impl SimpleRecorder {
     pub fn new() -> Self {
         Self {
             is_recording: Arc::new(AtomicBool::new(false)),
+            paused: Arc::new(AtomicBool::new(false)),
             output_path: None,
-            writer: None,
+            on_event: Arc::new(Mutex::new(None)),
             stream: None,
+            keep_open: false,
+            follow_default: false,
+            loopback: false,
+            host: None,
+            max_file_bytes: MAX_WAV_DATA_BYTES,
+            spill_path: None,
+            max_memory_bytes: 0,
+            sample_rate: Some(DEFAULT_SAMPLE_RATE),
+            buffer_frames: None,
+            stop_on_silence: None,
+            vad: VadBackend::Energy,
+            max_duration: None,
+            max_bytes: None,
+            high_pass_hz: None,
+            remove_dc_offset: false,
+            auto_gain_db: None,
+            pre_roll: Duration::ZERO,
+            #[cfg(feature = "denoise")]
+            denoise: false,
+            recorded_files: Vec::new(),
+            #[cfg(feature = "stream")]
+            chunks: broadcast::channel(STREAM_CHUNKS).0,
         }
     }
 
-    /// Start recording to a file
+    /// Split recordings into several WAV files once a file holds `max_bytes` of audio
+    ///
+    /// Defaults to just under the 4 GiB WAV limit. When a recording rolls over,
+    /// a `.manifest.json` listing all parts is written next to the first file.
+    pub fn set_max_file_bytes(&mut self, max_bytes: u64) {
+        self.max_file_bytes = max_bytes.min(MAX_WAV_DATA_BYTES);
+    }
+
+    /// Move recordings into memory to a file at `path` once they hold more than
+    /// `max_memory_bytes` of samples, or keep them in memory however long with `None`,
+    /// the default
+    ///
+    /// Short recordings stay in memory, without touching the disk, while one that runs
+    /// for hours, such as a meeting, can't fill up memory. The file rolls over like
+    /// those of [`SimpleRecorder::start_recording`]; takes effect with the next recording.
+    pub fn set_spill_to_disk(&mut self, path: Option<PathBuf>, max_memory_bytes: u64) {
+        self.spill_path = path;
+        self.max_memory_bytes = max_memory_bytes;
+    }
+
+    /// Keep the input stream open between recordings
+    ///
+    /// The next recording then starts without waiting for the device, and samples
+    /// captured while idle are discarded. By default the stream is closed when a
+    /// recording stops, so the system only shows the microphone in use while recording.
+    pub fn set_keep_open(&mut self, keep_open: bool) {
+        self.keep_open = keep_open;
+        if !keep_open && !self.is_recording() {
+            self.stream = None;
+        }
+    }
+
+    /// Record from the system's current default input whenever a recording without a
+    /// device starts, also when the stream was kept open on an earlier default
+    ///
+    /// Switching from the laptop microphone to a headset between recordings then takes
+    /// effect with the next one. Off by default, when a kept-open stream stays on the
+    /// device that was the default when it opened.
+    pub fn set_follow_default(&mut self, follow_default: bool) {
+        self.follow_default = follow_default;
+    }
+
+    /// Open devices on the audio host named `host`, e.g. "JACK" or "ASIO", instead of the
+    /// platform's default host with `None`
+    ///
+    /// See [`crate::get_hosts`] for the hosts of this build. Takes effect with the next
+    /// recording, which fails if the host is unavailable.
+    pub fn set_host(&mut self, host: Option<String>) {
+        self.host = host;
+        if !self.is_recording() {
+            self.stream = None;
+        }
+    }
+
+    /// Record what the system plays, e.g. to caption a meeting, instead of a microphone
+    ///
+    /// On Windows this captures an output device in WASAPI loopback mode, the one whose
+    /// name contains the recording's device id or else the default speakers. Elsewhere
+    /// it records from the PulseAudio or PipeWire monitor source of an output, an input
+    /// named "Monitor of …". Takes effect with the next recording.
+    pub fn set_loopback(&mut self, loopback: bool) {
+        if loopback != self.loopback && !self.is_recording() {
+            self.stream = None;
+        }
+        self.loopback = loopback;
+    }
+
+    /// Capture at `sample_rate` when the device supports it, or at the device's default
+    /// rate with `None`
+    ///
+    /// Defaults to 16kHz, the rate Whisper takes, which saves resampling the recording.
+    /// Devices without it record at their default rate.
+    pub fn set_preferred_sample_rate(&mut self, sample_rate: Option<u32>) {
+        self.sample_rate = sample_rate;
+        if !self.is_recording() {
+            self.stream = None;
+        }
+    }
+
+    /// Ask the device for input buffers of `frames`, or leave their size to it with
+    /// `None`, the default
+    ///
+    /// Small buffers cut the latency of streaming transcription, large ones let the
+    /// audio thread wake up less often, which saves battery. The size is kept within what
+    /// the device allows; [`RecorderEvent::BufferSize`] and
+    /// [`SimpleRecorder::buffer_frames`] tell what it granted. Takes effect with the
+    /// next recording.
+    pub fn set_buffer_frames(&mut self, frames: Option<u32>) {
+        if frames != self.buffer_frames && !self.is_recording() {
+            self.stream = None;
+        }
+        self.buffer_frames = frames;
+    }
+
+    /// Report [`RecorderEvent::SpeechEnded`] once speech is followed by `silence`
+    ///
+    /// Lets hands-free dictation end a recording without a key release: stop it when the
+    /// event arrives. Silence before the first speech doesn't count, so there is time to
+    /// start talking. Off with `None`, the default; takes effect with the next recording.
+    pub fn set_stop_on_silence(&mut self, silence: Option<Duration>) {
+        self.stop_on_silence = silence;
+    }
+
+    /// Tell when speech ended for [`SimpleRecorder::set_stop_on_silence`] with this kind
+    /// of detector, by default [`VadBackend::Energy`]
+    ///
+    /// Takes effect with the next recording. One whose detector fails to start falls
+    /// back to the energy-based one, with an error event.
+    pub fn set_vad(&mut self, vad: VadBackend) {
+        self.vad = vad;
+    }
+
+    /// Stop keeping the audio of a recording once it is `max_duration` long or holds
+    /// `max_bytes` of 16-bit samples, whichever comes first; `None` leaves that unlimited,
+    /// the default
+    ///
+    /// Keeps a stuck key from recording until the disk or memory is full.
+    /// [`RecorderEvent::LimitReached`] reports a recording hitting the limit; stop it when
+    /// the event arrives, as nothing it records after is kept. Takes effect with the next
+    /// recording.
+    pub fn set_recording_limit(&mut self, max_duration: Option<Duration>, max_bytes: Option<u64>) {
+        self.max_duration = max_duration;
+        self.max_bytes = max_bytes;
+    }
+
+    /// Keep the last `pre_roll` of audio while idle and begin recordings with it, so
+    /// the first syllable isn't lost when speaking starts a moment before the key press
+    ///
+    /// Needs the stream kept open with [`SimpleRecorder::set_keep_open`]; the device
+    /// then captures all the time, though only the last `pre_roll` is kept, in memory.
+    /// Lowering it right before a recording starts shortens only that recording's
+    /// pre-roll, so raise it again afterwards to keep holding the longer one.
+    pub fn set_pre_roll(&mut self, pre_roll: Duration) {
+        self.pre_roll = pre_roll;
+        if let Some(stream) = &self.stream {
+            let samples = pre_roll_samples(pre_roll, stream.spec.sample_rate);
+            stream.pre_roll.store(samples, Ordering::Relaxed);
+        }
+    }
+
+    /// Cut the rumble below `cutoff_hz`, e.g. 80, from recordings, or leave it with
+    /// `None`, the default
+    ///
+    /// Handling noise and the low hum of cheap USB microphones hurt transcription, while
+    /// voices carry little below 100Hz. Removes the DC offset as well. Takes effect with
+    /// the next recording.
+    pub fn set_high_pass(&mut self, cutoff_hz: Option<f32>) {
+        self.high_pass_hz = cutoff_hz;
+    }
+
+    /// Remove the constant offset some microphones add to their samples, which skews
+    /// levels and gain, without cutting any frequencies of speech
+    ///
+    /// Not needed with [`SimpleRecorder::set_high_pass`]. Takes effect with the next
+    /// recording.
+    pub fn set_remove_dc_offset(&mut self, remove_dc_offset: bool) {
+        self.remove_dc_offset = remove_dc_offset;
+    }
+
+    /// Raise or lower recordings so the peaks of speech reach `target_db` dBFS, e.g.
+    /// -6, or leave them as recorded with `None`, the default
+    ///
+    /// Keeps quiet microphones from recording near silence, which Whisper takes for
+    /// `[BLANK_AUDIO]`, and hot ones from clipping. Silence is not raised. Takes effect
+    /// with the next recording.
+    pub fn set_auto_gain(&mut self, target_db: Option<f32>) {
+        self.auto_gain_db = target_db;
+    }
+
+    /// Remove background noise from recordings before they are written
+    ///
+    /// RNNoise only works at 48kHz, so this captures at that rate instead of the
+    /// preferred one. Devices without it record as they are, with an error event.
+    /// Switching it reopens a stream kept open.
+    #[cfg(feature = "denoise")]
+    pub fn set_denoise(&mut self, denoise: bool) {
+        let changed = self.denoise != denoise;
+        self.denoise = denoise;
+        if changed && !self.is_recording() {
+            self.stream = None;
+        }
+    }
+
+    /// Open the input stream ahead of the first recording, when it is kept open
+    pub fn open(&mut self, device_id: Option<&str>) -> Result<()> {
+        if self.keep_open {
+            self.ensure_stream(device_id)?;
+        }
+        Ok(())
+    }
+
+    /// Receive the mono samples of every recording in chunks while it records
+    ///
+    /// Chunks arrive about every 10ms, on top of recording to a file or to memory, so
+    /// a resampler, VAD or streaming transcriber can work during the recording. A
+    /// receiver that falls more than about 2.5s behind misses chunks and gets
+    /// [`broadcast::error::RecvError::Lagged`]. The sample rate is that of
+    /// [`SimpleRecorder::sample_rate`].
+    #[cfg(feature = "stream")]
+    pub fn subscribe(&self) -> broadcast::Receiver<Vec<SampleType>> {
+        self.chunks.subscribe()
+    }
+
+    /// The sender of the chunks [`SimpleRecorder::subscribe`] receives, to subscribe from
+    /// another thread
+    #[cfg(feature = "async")]
+    pub(crate) fn chunk_sender(&self) -> broadcast::Sender<Vec<SampleType>> {
+        self.chunks.clone()
+    }
+
+    /// Sample rate of the open input stream, if any
+    pub fn sample_rate(&self) -> Option<u32> {
+        self.stream.as_ref().map(|stream| stream.spec.sample_rate)
+    }
+
+    /// Frames per buffer the open input stream delivers, once its first buffer came in
+    pub fn buffer_frames(&self) -> Option<usize> {
+        let stream = self.stream.as_ref()?;
+        Some(stream.buffer_frames.load(Ordering::Relaxed)).filter(|&frames| frames > 0)
+    }
+
+    /// All files that make up the last finished recording, in order
+    pub fn recorded_files(&self) -> &[PathBuf] {
+        &self.recorded_files
+    }
+
+    /// Start recording to a file, calling `on_peak` with the peak of every input buffer
+    ///
+    /// Shorthand for [`SimpleRecorder::start_recording_with_events`] that only
+    /// forwards [`RecorderEvent::Level`] and prints errors.
     pub fn start_recording<P, F>(
         &mut self,
         device_id: Option<&str>,
@@ -47,71 +521,270 @@ impl SimpleRecorder {
         P: Into<PathBuf>,
         F: Fn(SampleType) + Send + 'static,
     {
+        self.start_recording_with_events(device_id, output_path, move |event| match event {
+            RecorderEvent::Level { peak, .. } => on_peak(peak),
+            RecorderEvent::Error { message } => eprintln!("❌ {}", message),
+            _ => {}
+        })
+    }
+
+    /// Start recording to a file, reporting levels and problems through `on_event`
+    ///
+    /// Events are delivered on the recorder's writer thread, never the audio thread.
+    pub fn start_recording_with_events<P, F>(
+        &mut self,
+        device_id: Option<&str>,
+        output_path: P,
+        on_event: F,
+    ) -> Result<()>
+    where
+        P: Into<PathBuf>,
+        F: FnMut(RecorderEvent) + Send + 'static,
+    {
+        self.start(device_id, Some(output_path.into()), Box::new(on_event))
+    }
+
+    /// Start recording into memory, reporting levels and problems through `on_event`
+    ///
+    /// Nothing is written to disk unless the recording outgrows the limit set with
+    /// [`SimpleRecorder::set_spill_to_disk`]; stop with
+    /// [`SimpleRecorder::stop_recording_buffered`] to get the samples, or the files they
+    /// spilled to. A crash loses what is in memory.
+    pub fn start_recording_buffered<F>(
+        &mut self,
+        device_id: Option<&str>,
+        on_event: F,
+    ) -> Result<()>
+    where
+        F: FnMut(RecorderEvent) + Send + 'static,
+    {
+        self.start(device_id, None, Box::new(on_event))
+    }
+
+    /// Start recording to `output_path`, or to memory without one
+    fn start(
+        &mut self,
+        device_id: Option<&str>,
+        output_path: Option<PathBuf>,
+        on_event: Box<dyn FnMut(RecorderEvent) + Send>,
+    ) -> Result<()> {
         if self.is_recording.load(Ordering::SeqCst) {
             return Err(anyhow::anyhow!("Already recording"));
         }
 
-        let output_path = output_path.into();
+        self.ensure_stream(device_id)?;
+        let stream = self.stream.as_ref().expect("stream was just opened");
 
-        // Get audio device
-        let host = cpal::default_host();
-        let device = if let Some(id) = device_id {
-            self.find_device_by_name(&host, id)?
+        let sink = match &output_path {
+            Some(path) => {
+                Sink::File(RollingWavWriter::create(path, stream.spec, self.max_file_bytes)?)
+            }
+            None => match &self.spill_path {
+                Some(path) => Sink::Spillable(Vec::new(), Spill {
+                    path: path.clone(),
+                    spec: stream.spec,
+                    max_file_bytes: self.max_file_bytes,
+                    max_samples: (self.max_memory_bytes / 2) as usize,
+                }),
+                None => Sink::Memory(Vec::new()),
+            },
+        };
+        let mut processing = Box::<Processing>::default();
+        let mut vad_error = None;
+        processing.silence = self.stop_on_silence.map(|after| {
+            let detector = self.vad.detector(stream.spec.sample_rate).unwrap_or_else(|e| {
+                vad_error = Some(e);
+                Box::new(VoiceActivityDetector::new(stream.spec.sample_rate))
+            });
+            SilenceWatch {
+                detector,
+                after,
+                reported: false,
+            }
+        });
+        let sample_rate = stream.spec.sample_rate;
+        let max_samples = [
+            self.max_duration.map(|max| (max.as_secs_f64() * sample_rate as f64) as usize),
+            self.max_bytes.map(|max| (max / 2) as usize),
+        ];
+        processing.limit = max_samples.into_iter().flatten().min().map(|max| Limit {
+            remaining: max,
+            length: Duration::from_secs_f64(max as f64 / sample_rate as f64),
+            reached: false,
+        });
+        processing.high_pass = self
+            .high_pass_hz
+            .map(|cutoff_hz| HighPassFilter::new(sample_rate, cutoff_hz));
+        if self.remove_dc_offset && processing.high_pass.is_none() {
+            processing.dc_blocker = Some(DcBlocker::new(sample_rate));
+        }
+        processing.gain = self
+            .auto_gain_db
+            .map(|target_db| AutomaticGain::new(stream.spec.sample_rate, target_db));
+        #[cfg(feature = "denoise")]
+        let denoise_rate = if !self.denoise {
+            None
+        } else if stream.spec.sample_rate == DENOISE_SAMPLE_RATE {
+            processing.denoiser = Some(Denoiser::new());
+            None
         } else {
-            host.default_input_device()
-                .ok_or_else(|| anyhow::anyhow!("No default input device available"))?
+            Some(stream.spec.sample_rate)
         };
+        stream
+            .commands
+            .send(WriterCommand::Start(sink, processing))
+            .map_err(|_| anyhow::anyhow!("The WAV writer thread has stopped"))?;
+        *lock(&self.on_event) = Some(on_event);
+        self.output_path = output_path;
 
-        // Get device configuration
-        let config = device.default_input_config()?;
+        self.paused.store(false, Ordering::SeqCst);
+        self.is_recording.store(true, Ordering::SeqCst);
+        let name = stream.device_name.clone();
+        emit(&self.on_event, RecorderEvent::DeviceChanged { name });
+        if let Some(e) = vad_error {
+            emit(&self.on_event, RecorderEvent::Error {
+                message: format!("{}, telling the end of speech by its level", e),
+            });
+        }
+        #[cfg(feature = "denoise")]
+        if let Some(rate) = denoise_rate {
+            emit(&self.on_event, RecorderEvent::Error {
+                message: format!(
+                    "Noise suppression needs {}Hz input but the microphone records at {}Hz",
+                    DENOISE_SAMPLE_RATE, rate
+                ),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Open an input stream on the device, unless a working one is already open on it
+    fn ensure_stream(&mut self, device_id: Option<&str>) -> Result<()> {
+        if let Some(stream) = &self.stream {
+            if stream.device_id.as_deref() == device_id
+                && !stream.failed.load(Ordering::SeqCst)
+                && !self.default_moved(stream)
+            {
+                return Ok(());
+            }
+        }
+        self.stream = None;
+
+        // Get audio device
+        let host = crate::device::get_host(self.host.as_deref())?;
+        let (device, config) = if self.loopback {
+            loopback_device(&host, device_id)?
+        } else {
+            let device = if let Some(id) = device_id {
+                self.find_device_by_name(&host, id)?
+            } else {
+                host.default_input_device()
+                    .ok_or_else(|| anyhow::anyhow!("No default input device available"))?
+            };
+
+            // Get device configuration, at the preferred rate if the device has it
+            #[cfg(feature = "denoise")]
+            let sample_rate =
+                if self.denoise { Some(DENOISE_SAMPLE_RATE) } else { self.sample_rate };
+            #[cfg(not(feature = "denoise"))]
+            let sample_rate = self.sample_rate;
+            let preferred = sample_rate.and_then(|rate| {
+                let configs = device.supported_input_configs().ok()?;
+                config_at_rate(configs, rate)
+            });
+            let config = match preferred {
+                Some(config) => config,
+                None => device.default_input_config()?,
+            };
+            (device, config)
+        };
         let sample_rate = config.sample_rate().0;
-        let channels = config.channels();
         let sample_format = config.sample_format();
 
         // Validate sample rate
-        if sample_rate < 8000 || sample_rate > 192000 {
+        if !(8000..=192000).contains(&sample_rate) {
             return Err(anyhow::anyhow!(
                 "Unusual sample rate: {} Hz. Expected range: 8000-192000 Hz",
                 sample_rate
             ));
         }
 
-        // Create WAV writer
-        let wav_spec = WavSpec {
+        let spec = WavSpec {
             channels: 1, // Always output mono
             sample_rate,
             bits_per_sample: 16,
             sample_format: hound::SampleFormat::Int,
         };
+        let capture = Capture {
+            failed: Arc::new(AtomicBool::new(false)),
+            busy: Arc::new(AtomicBool::new(false)),
+            missing_us: Arc::new(AtomicU64::new(0)),
+            pre_roll: Arc::new(AtomicUsize::new(pre_roll_samples(self.pre_roll, sample_rate))),
+            buffer_frames: Arc::new(AtomicUsize::new(0)),
+        };
 
-        let writer = WavWriter::create(&output_path, wav_spec)?;
-        let writer = Arc::new(Mutex::new(writer));
-        self.writer = Some(writer.clone());
-        self.output_path = Some(output_path);
+        // Ask for buffers of the requested size, within what the device allows
+        let mut stream_config = config.config();
+        if let Some(frames) = self.buffer_frames {
+            let frames = match *config.buffer_size() {
+                cpal::SupportedBufferSize::Range { min, max } => frames.clamp(min, max.max(min)),
+                cpal::SupportedBufferSize::Unknown => frames,
+            };
+            stream_config.buffer_size = cpal::BufferSize::Fixed(frames);
+        }
+        let (producer, consumer) = RingBuffer::new((sample_rate * RING_SECS) as usize);
 
         // Build and start stream
         let stream = match sample_format {
             cpal::SampleFormat::I8 => {
-                self.build_input_stream::<i8, _>(&device, &config, writer, channels, on_peak)?
+                self.build_input_stream::<i8>(&device, &stream_config, &capture, producer)?
             }
             cpal::SampleFormat::I16 => {
-                self.build_input_stream::<i16, _>(&device, &config, writer, channels, on_peak)?
+                self.build_input_stream::<i16>(&device, &stream_config, &capture, producer)?
             }
             cpal::SampleFormat::I32 => {
-                self.build_input_stream::<i32, _>(&device, &config, writer, channels, on_peak)?
+                self.build_input_stream::<i32>(&device, &stream_config, &capture, producer)?
             }
             cpal::SampleFormat::F32 => {
-                self.build_input_stream::<f32, _>(&device, &config, writer, channels, on_peak)?
+                self.build_input_stream::<f32>(&device, &stream_config, &capture, producer)?
             }
             _ => {
                 return Err(anyhow::anyhow!("Unsupported sample format: {:?}", sample_format));
             }
         };
 
-        stream.play()?;
-        self.stream = Some(stream);
-        self.is_recording.store(true, Ordering::SeqCst);
+        let (commands, receiver) = mpsc::channel();
+        let writer = WriterThread {
+            commands: receiver,
+            samples: consumer,
+            missing_us: capture.missing_us,
+            is_recording: self.is_recording.clone(),
+            on_event: self.on_event.clone(),
+            flush_interval: (sample_rate * HEADER_FLUSH_SECS) as usize,
+            processing: Box::default(),
+            pre_roll: capture.pre_roll.clone(),
+            held: VecDeque::new(),
+            sample_rate,
+            buffer_frames: capture.buffer_frames.clone(),
+            reported_frames: 0,
+            #[cfg(feature = "stream")]
+            chunks: self.chunks.clone(),
+        };
+        std::thread::spawn(move || writer.run());
 
+        stream.play()?;
+        self.stream = Some(InputStream {
+            _stream: stream,
+            device_id: device_id.map(str::to_string),
+            device_name: device.name().ok(),
+            spec,
+            failed: capture.failed,
+            busy: capture.busy,
+            commands,
+            pre_roll: capture.pre_roll,
+            buffer_frames: capture.buffer_frames,
+        });
         Ok(())
     }
 
@@ -120,28 +793,75 @@ impl SimpleRecorder {
         if !self.is_recording.load(Ordering::SeqCst) {
             return Err(anyhow::anyhow!("Not currently recording"));
         }
+        if self.output_path.is_none() {
+            return Err(anyhow::anyhow!(
+                "Recording to memory, stop it with stop_recording_buffered"
+            ));
+        }
 
-        // Stop recording
-        self.is_recording.store(false, Ordering::SeqCst);
+        if let Some(Recording::Files(files)) = self.finish()? {
+            self.recorded_files = files;
+        }
+        let output_path = self.output_path.take()
+            .ok_or_else(|| anyhow::anyhow!("No output path set"))?;
 
-        // Drop the stream
-        if let Some(stream) = self.stream.take() {
-            drop(stream);
+        Ok(output_path)
+    }
+
+    /// Stop recording into memory and return the samples, or the files they spilled to
+    pub fn stop_recording_buffered(&mut self) -> Result<RecordedAudio> {
+        if !self.is_recording.load(Ordering::SeqCst) {
+            return Err(anyhow::anyhow!("Not currently recording"));
+        }
+        if self.output_path.is_some() {
+            return Err(anyhow::anyhow!("Recording to a file, stop it with stop_recording"));
         }
 
-        // Finalize WAV file
-        if let Some(writer) = self.writer.take() {
-            if let Ok(writer) = Arc::try_unwrap(writer) {
-                if let Ok(writer) = writer.into_inner() {
-                    writer.finalize()?;
-                }
+        let sample_rate = self.sample_rate().unwrap_or(0);
+        let (samples, files) = match self.finish()? {
+            Some(Recording::Samples(samples)) => (samples, Vec::new()),
+            Some(Recording::Files(files)) => (Vec::new(), files),
+            None => (Vec::new(), Vec::new()),
+        };
+        self.recorded_files = files.clone();
+        Ok(RecordedAudio {
+            samples,
+            sample_rate,
+            files,
+        })
+    }
+
+    /// Stop the current recording once the writer has caught up, returning what it
+    /// recorded, or `None` if the stream was already gone
+    fn finish(&mut self) -> Result<Option<Recording>> {
+        // Stop recording, and let an input callback that still saw it running finish
+        self.is_recording.store(false, Ordering::SeqCst);
+        self.paused.store(false, Ordering::SeqCst);
+        let stream = self.stream.take();
+        if let Some(stream) = &stream {
+            while stream.busy.load(Ordering::SeqCst) {
+                std::thread::yield_now();
             }
         }
 
-        let output_path = self.output_path.take()
-            .ok_or_else(|| anyhow::anyhow!("No output path set"))?;
+        // Finalize the recording once the writer has caught up
+        let finished = stream.as_ref().map(|stream| {
+            let (reply, result) = mpsc::channel();
+            stream
+                .commands
+                .send(WriterCommand::Stop(reply))
+                .map_err(|_| anyhow::anyhow!("The WAV writer thread has stopped"))?;
+            result
+                .recv()
+                .map_err(|_| anyhow::anyhow!("The WAV writer thread has stopped"))?
+        });
+        *lock(&self.on_event) = None;
 
-        Ok(output_path)
+        // Close the stream, or keep it running for the next recording
+        if self.keep_open {
+            self.stream = stream;
+        }
+        finished.transpose()
     }
 
     /// Check if currently recording
@@ -149,67 +869,139 @@ impl SimpleRecorder {
         self.is_recording.load(Ordering::SeqCst)
     }
 
-    fn build_input_stream<T, F>(
+    /// Leave what the microphone hears out of the recording until
+    /// [`SimpleRecorder::resume`], e.g. during a phone call or a cough
+    ///
+    /// The stream stays open and the recording, its file included, goes on where it left
+    /// off when resumed, without a gap of silence.
+    pub fn pause(&mut self) -> Result<()> {
+        if !self.is_recording() {
+            return Err(anyhow::anyhow!("Not currently recording"));
+        }
+        self.paused.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Add what the microphone hears to the recording again after
+    /// [`SimpleRecorder::pause`]
+    pub fn resume(&mut self) -> Result<()> {
+        if !self.is_recording() {
+            return Err(anyhow::anyhow!("Not currently recording"));
+        }
+        self.paused.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Whether the current recording is paused
+    pub fn is_paused(&self) -> bool {
+        self.is_recording() && self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Whether `stream` records from the default input, which is followed and has
+    /// become another device since the stream opened
+    fn default_moved(&self, stream: &InputStream) -> bool {
+        if !self.follow_default || self.loopback || stream.device_id.is_some() {
+            return false;
+        }
+        let Ok(host) = crate::device::get_host(self.host.as_deref()) else {
+            return false;
+        };
+        let default = host.default_input_device();
+        match default.and_then(|device| device.name().ok()) {
+            Some(name) => stream.device_name.as_ref() != Some(&name),
+            None => false,
+        }
+    }
+
+    fn build_input_stream<T>(
         &self,
         device: &cpal::Device,
-        config: &cpal::SupportedStreamConfig,
-        writer: Arc<Mutex<WavWriter<BufWriter<File>>>>,
-        channels: u16,
-        on_peak: F,
+        config: &cpal::StreamConfig,
+        capture: &Capture,
+        mut samples: Producer<SampleType>,
     ) -> Result<cpal::Stream>
     where
         T: Sample + SizedSample + Send + 'static,
         SampleType: FromSample<T>,
-        F: Fn(SampleType) + Send + 'static,
     {
         let is_recording = self.is_recording.clone();
+        let paused = self.paused.clone();
+        let sample_rate = config.sample_rate.0;
+        let channels = config.channels;
+        let busy = capture.busy.clone();
+        let missing_us = capture.missing_us.clone();
+        let pre_roll = capture.pre_roll.clone();
+        let buffer_frames = capture.buffer_frames.clone();
+        let failed = capture.failed.clone();
+        // Capture time and length of the previous buffer, to detect dropouts
+        let mut previous: Option<(cpal::StreamInstant, Duration)> = None;
+        let on_error = self.on_event.clone();
 
         let stream = device.build_input_stream(
-            &config.config(),
-            move |data: &[T], _: &cpal::InputCallbackInfo| {
-                if !is_recording.load(Ordering::SeqCst) {
+            config,
+            move |data: &[T], info: &cpal::InputCallbackInfo| {
+                busy.store(true, Ordering::SeqCst);
+                buffer_frames.store(data.len() / channels as usize, Ordering::Relaxed);
+                let recording = is_recording.load(Ordering::SeqCst);
+                if recording && paused.load(Ordering::SeqCst) {
+                    // Resuming is not a dropout either
+                    previous = None;
+                    busy.store(false, Ordering::SeqCst);
                     return;
                 }
-
-                // Convert to i16 and handle multiple channels
-                let samples: Vec<SampleType> = if channels == 1 {
-                    // Mono: direct conversion
-                    data.iter()
-                        .map(|&sample| SampleType::from_sample(sample))
-                        .collect()
+                if !recording {
+                    // Samples of an open stream between recordings are not a dropout
+                    previous = None;
+                    if pre_roll.load(Ordering::Relaxed) == 0 {
+                        busy.store(false, Ordering::SeqCst);
+                        return;
+                    }
                 } else {
-                    // Multi-channel: convert to mono by averaging channels
-                    data.chunks_exact(channels as usize)
-                        .map(|frame| {
-                            // Convert to i16 first, then average
-                            let sum: i32 = frame
-                                .iter()
-                                .map(|&sample| SampleType::from_sample(sample) as i32)
-                                .sum();
-                            let avg = sum / channels as i32;
-                            avg.clamp(SampleType::MIN as i32, SampleType::MAX as i32) as SampleType
-                        })
-                        .collect()
-                };
-
-                // Find peak for callback
-                if let Some(&peak) = samples.iter().max_by_key(|&&x| x.abs()) {
-                    on_peak(peak);
-                }
-
-                // Write to WAV file
-                if let Ok(mut writer) = writer.lock() {
-                    for sample in samples {
-                        if let Err(e) = writer.write_sample(sample) {
-                            eprintln!("❌ Error writing sample: {}", e);
-                            is_recording.store(false, Ordering::SeqCst);
-                            break;
+                    let capture = info.timestamp().capture;
+                    let length = Duration::from_secs_f64(
+                        data.len() as f64 / channels as f64 / sample_rate as f64,
+                    );
+                    if let Some((previous_capture, previous_length)) = previous {
+                        let gap = capture.duration_since(&previous_capture).unwrap_or_default();
+                        if gap > previous_length + DROPOUT_THRESHOLD {
+                            let missing = gap - previous_length;
+                            missing_us.fetch_add(missing.as_micros() as u64, Ordering::Relaxed);
                         }
                     }
+                    previous = Some((capture, length));
+                }
+
+                // Convert to mono i16 right into the ring buffer, averaging the channels
+                let frames = data.len() / channels as usize;
+                let free = frames.min(samples.slots());
+                if let Ok(chunk) = samples.write_chunk_uninit(free) {
+                    chunk.fill_from_iter(data.chunks_exact(channels as usize).map(|frame| {
+                        let sum: i32 = frame
+                            .iter()
+                            .map(|&sample| SampleType::from_sample(sample) as i32)
+                            .sum();
+                        let avg = sum / channels as i32;
+                        avg.clamp(SampleType::MIN as i32, SampleType::MAX as i32) as SampleType
+                    }));
                 }
+                if free < frames && recording {
+                    // The writer fell behind and the rest of the buffer is lost
+                    let lost = (frames - free) as u64 * 1_000_000 / sample_rate as u64;
+                    missing_us.fetch_add(lost, Ordering::Relaxed);
+                }
+                busy.store(false, Ordering::SeqCst);
             },
-            |err| {
-                eprintln!("❌ Stream error: {}", err);
+            move |err| {
+                failed.store(true, Ordering::SeqCst);
+                let event = match err {
+                    cpal::StreamError::DeviceNotAvailable => {
+                        RecorderEvent::DeviceChanged { name: None }
+                    }
+                    err => RecorderEvent::Error {
+                        message: format!("Stream error: {}", err),
+                    },
+                };
+                emit(&on_error, event);
             },
             None,
         )?;
@@ -245,6 +1037,347 @@ impl SimpleRecorder {
     }
 }
 
+/// The output device to capture in loopback mode, the one named like `name` or the
+/// default, and the mix format it plays at, which loopback capture has to use
+#[cfg(windows)]
+fn loopback_device(
+    host: &cpal::Host,
+    name: Option<&str>,
+) -> Result<(cpal::Device, SupportedStreamConfig)> {
+    let device = match name {
+        Some(name) => crate::device::output_device_named(host, name)?,
+        None => host
+            .default_output_device()
+            .ok_or_else(|| anyhow::anyhow!("No default output device available"))?,
+    };
+    let config = device.default_output_config()?;
+    Ok((device, config))
+}
+
+/// The monitor source of an output, the first one whose name also contains `name` if
+/// given, and its default config
+#[cfg(not(windows))]
+fn loopback_device(
+    host: &cpal::Host,
+    name: Option<&str>,
+) -> Result<(cpal::Device, SupportedStreamConfig)> {
+    let name = name.map(str::to_lowercase);
+    let device = host
+        .input_devices()?
+        .find(|device| {
+            let device_name = device.name().unwrap_or_default().to_lowercase();
+            device_name.contains("monitor")
+                && name.as_ref().is_none_or(|name| device_name.contains(name))
+        })
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "No monitor source to capture system audio from; \
+                 it needs PulseAudio or PipeWire on Linux, or Windows"
+            )
+        })?;
+    let config = device.default_input_config()?;
+    Ok((device, config))
+}
+
+/// The config among `configs` that captures at `sample_rate` with the fewest channels,
+/// preferring the recorded i16 format over ones that need converting
+fn config_at_rate<I>(configs: I, sample_rate: u32) -> Option<SupportedStreamConfig>
+where
+    I: IntoIterator<Item = SupportedStreamConfigRange>,
+{
+    let format_rank = |format| match format {
+        cpal::SampleFormat::I16 => Some(0),
+        cpal::SampleFormat::F32 => Some(1),
+        cpal::SampleFormat::I32 => Some(2),
+        cpal::SampleFormat::I8 => Some(3),
+        _ => None,
+    };
+    let rate = cpal::SampleRate(sample_rate);
+    configs
+        .into_iter()
+        .filter(|config| config.min_sample_rate() <= rate && rate <= config.max_sample_rate())
+        .filter_map(|config| {
+            let rank = (config.channels(), format_rank(config.sample_format())?);
+            Some((rank, config))
+        })
+        .min_by_key(|(rank, _)| *rank)
+        .map(|(_, config)| config.with_sample_rate(rate))
+}
+
+/// State shared between an input callback and the recorder
+struct Capture {
+    failed: Arc<AtomicBool>,
+    busy: Arc<AtomicBool>,
+    /// Audio lost since the writer thread last reported it, in microseconds
+    missing_us: Arc<AtomicU64>,
+    /// Samples to keep while idle, captured only when there are any
+    pre_roll: Arc<AtomicUsize>,
+    /// Frames of the latest input buffer
+    buffer_frames: Arc<AtomicUsize>,
+}
+
+/// Takes samples off the ring buffer and adds them to the current recording
+struct WriterThread {
+    commands: Receiver<WriterCommand>,
+    samples: Consumer<SampleType>,
+    missing_us: Arc<AtomicU64>,
+    is_recording: Arc<AtomicBool>,
+    on_event: EventCallback,
+    flush_interval: usize,
+    processing: Box<Processing>,
+    /// Samples to hold while idle
+    pre_roll: Arc<AtomicUsize>,
+    /// The latest samples from while idle, which the next recording starts with
+    held: VecDeque<SampleType>,
+    sample_rate: u32,
+    /// Frames of the latest input buffer, and those last reported
+    buffer_frames: Arc<AtomicUsize>,
+    reported_frames: usize,
+    #[cfg(feature = "stream")]
+    chunks: broadcast::Sender<Vec<SampleType>>,
+}
+
+impl WriterThread {
+    /// Write recordings until the input stream is closed
+    fn run(mut self) {
+        let mut sink: Option<Sink> = None;
+        let mut samples_since_flush = 0usize;
+        loop {
+            match self.commands.recv_timeout(WRITE_INTERVAL) {
+                Ok(WriterCommand::Start(mut new_sink, processing)) => {
+                    self.processing = processing;
+                    samples_since_flush = 0;
+                    // Start with the pre-roll, shortened if it was lowered since it
+                    // was held; samples still in the ring buffer follow it
+                    let excess =
+                        self.held.len().saturating_sub(self.pre_roll.load(Ordering::Relaxed));
+                    let held: Vec<SampleType> = self.held.drain(..).skip(excess).collect();
+                    self.process_and_write(&mut new_sink, held, &mut samples_since_flush);
+                    sink = Some(new_sink);
+                }
+                Ok(WriterCommand::Stop(reply)) => {
+                    if let Some(mut sink) = sink.take() {
+                        self.write_available(&mut sink, &mut samples_since_flush);
+                        #[cfg(feature = "denoise")]
+                        if let Some(denoiser) = self.processing.denoiser.take() {
+                            let mut rest = denoiser.finish();
+                            if let Some(gain) = self.processing.gain.as_mut() {
+                                gain.process(&mut rest);
+                            }
+                            self.write_samples(&mut sink, &rest, &mut samples_since_flush);
+                        }
+                        let _ = reply.send(sink.finish());
+                    } else {
+                        let _ = reply.send(Ok(Recording::Files(Vec::new())));
+                    }
+                    self.missing_us.store(0, Ordering::Relaxed);
+                    continue;
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+            // Samples are only captured while recording, so they wait for their sink,
+            // unless they are held for the pre-roll
+            if let Some(sink) = sink.as_mut() {
+                self.write_available(sink, &mut samples_since_flush);
+            } else {
+                self.hold_available();
+            }
+        }
+    }
+
+    /// Move the samples in the ring buffer to the pre-roll, keeping the latest ones
+    fn hold_available(&mut self) {
+        let Ok(chunk) = self.samples.read_chunk(self.samples.slots()) else {
+            return;
+        };
+        let (first, second) = chunk.as_slices();
+        self.held.extend(first.iter().chain(second));
+        chunk.commit_all();
+        let excess = self.held.len().saturating_sub(self.pre_roll.load(Ordering::Relaxed));
+        self.held.drain(..excess);
+    }
+
+    /// Write the samples in the ring buffer and report their level and any dropouts
+    fn write_available(&mut self, sink: &mut Sink, samples_since_flush: &mut usize) {
+        let missing_us = self.missing_us.swap(0, Ordering::Relaxed);
+        if missing_us > 0 {
+            let missing = Duration::from_micros(missing_us);
+            emit(&self.on_event, RecorderEvent::Dropout { missing });
+        }
+
+        let Ok(chunk) = self.samples.read_chunk(self.samples.slots()) else {
+            return;
+        };
+        if chunk.is_empty() {
+            return;
+        }
+        let (first, second) = chunk.as_slices();
+        let samples = || first.iter().chain(second);
+
+        let frames = self.buffer_frames.load(Ordering::Relaxed);
+        if frames > 0 && frames != self.reported_frames {
+            self.reported_frames = frames;
+            let latency = Duration::from_secs_f64(frames as f64 / self.sample_rate as f64);
+            emit(&self.on_event, RecorderEvent::BufferSize { frames, latency });
+        }
+
+        let samples: Vec<SampleType> = samples().copied().collect();
+        chunk.commit_all();
+
+        // Report level and clipping
+        if let Some(&peak) = samples.iter().max_by_key(|x| x.unsigned_abs()) {
+            let meter = LevelMeter::measure(&samples);
+            emit(&self.on_event, RecorderEvent::Level { peak, meter });
+        }
+        let clipped = samples
+            .iter()
+            .filter(|&&x| x == SampleType::MAX || x == SampleType::MIN)
+            .count();
+        if clipped > 0 {
+            emit(&self.on_event, RecorderEvent::Clipping { samples: clipped });
+        }
+
+        self.process_and_write(sink, samples, samples_since_flush);
+    }
+
+    /// Filter, denoise and adjust the gain of `samples` as asked, then write them
+    fn process_and_write(
+        &mut self,
+        sink: &mut Sink,
+        mut samples: Vec<SampleType>,
+        samples_since_flush: &mut usize,
+    ) {
+        // Before the gain, which an offset or rumble would hold down
+        if let Some(blocker) = self.processing.dc_blocker.as_mut() {
+            blocker.process(&mut samples);
+        }
+        if let Some(filter) = self.processing.high_pass.as_mut() {
+            filter.process(&mut samples);
+        }
+        #[cfg(feature = "denoise")]
+        if let Some(denoiser) = self.processing.denoiser.as_mut() {
+            samples = denoiser.process(&samples);
+        }
+        if let Some(gain) = self.processing.gain.as_mut() {
+            gain.process(&mut samples);
+        }
+        self.write_samples(sink, &samples, samples_since_flush);
+    }
+
+    /// Keep `samples` within the limit, watch them for silence, send them to subscribers
+    /// and write them to `sink`
+    fn write_samples(
+        &mut self,
+        sink: &mut Sink,
+        mut samples: &[SampleType],
+        samples_since_flush: &mut usize,
+    ) {
+        if let Some(limit) = self.processing.limit.as_mut() {
+            let kept = samples.len().min(limit.remaining);
+            limit.remaining -= kept;
+            samples = &samples[..kept];
+            if limit.remaining == 0 && !limit.reached {
+                limit.reached = true;
+                emit(&self.on_event, RecorderEvent::LimitReached { length: limit.length });
+            }
+        }
+        if samples.is_empty() {
+            return;
+        }
+
+        if let Some(watch) = self.processing.silence.as_mut().filter(|watch| !watch.reported) {
+            if let Err(e) = watch.detector.process(samples) {
+                // Stop watching rather than report the same error for every block
+                watch.reported = true;
+                emit(&self.on_event, RecorderEvent::Error {
+                    message: format!("Voice activity detection failed: {}", e),
+                });
+            } else if watch.detector.heard_speech() && watch.detector.silence() >= watch.after {
+                watch.reported = true;
+                let silence = watch.detector.silence();
+                emit(&self.on_event, RecorderEvent::SpeechEnded { silence });
+            }
+        }
+
+        #[cfg(feature = "stream")]
+        if self.chunks.receiver_count() > 0 {
+            let _ = self.chunks.send(samples.to_vec());
+        }
+
+        if let Sink::Spillable(buffer, spill) = sink {
+            if buffer.len() + samples.len() > spill.max_samples {
+                *sink = match spill.create(buffer) {
+                    Ok(writer) => {
+                        let path = spill.path.clone();
+                        emit(&self.on_event, RecorderEvent::Spilled { path });
+                        Sink::File(writer)
+                    }
+                    Err(e) => {
+                        // Better to run out of memory later than to lose the recording
+                        emit(&self.on_event, RecorderEvent::Error {
+                            message: format!("Error spilling the recording to disk: {}", e),
+                        });
+                        Sink::Memory(std::mem::take(buffer))
+                    }
+                };
+            }
+        }
+
+        let writer = match sink {
+            Sink::File(writer) => writer,
+            Sink::Memory(buffer) | Sink::Spillable(buffer, _) => {
+                buffer.extend_from_slice(samples);
+                return;
+            }
+        };
+
+        // Write to WAV file
+        *samples_since_flush += samples.len();
+        for &sample in samples {
+            if let Err(e) = writer.write_sample(sample) {
+                emit(&self.on_event, RecorderEvent::Error {
+                    message: format!("Error writing sample: {}", e),
+                });
+                self.is_recording.store(false, Ordering::SeqCst);
+                break;
+            }
+        }
+
+        // Periodically update the header so a crash leaves a valid file
+        if *samples_since_flush >= self.flush_interval {
+            *samples_since_flush = 0;
+            if let Err(e) = writer.flush() {
+                emit(&self.on_event, RecorderEvent::Error {
+                    message: format!("Error flushing WAV header: {}", e),
+                });
+            }
+        }
+    }
+}
+
+/// Length of `pre_roll` in samples at `sample_rate`
+fn pre_roll_samples(pre_roll: Duration, sample_rate: u32) -> usize {
+    (pre_roll.as_secs_f64() * sample_rate as f64) as usize
+}
+
+fn emit(on_event: &EventCallback, event: RecorderEvent) {
+    if let Some(on_event) = lock(on_event).as_mut() {
+        on_event(event);
+    }
+}
+
+/// Lock `mutex`, recovering it when another thread panicked while holding it
+fn lock<T: ?Sized>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl Default for SimpleRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Drop for SimpleRecorder {
     fn drop(&mut self) {
         if self.is_recording() {
@@ -253,3 +1386,270 @@ impl Drop for SimpleRecorder {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hound::WavReader;
+
+    /// A writer thread fed by the returned producer
+    fn writer_thread() -> (
+        Producer<SampleType>,
+        Sender<WriterCommand>,
+        std::thread::JoinHandle<()>,
+    ) {
+        writer_thread_with_pre_roll(Arc::new(AtomicUsize::new(0)))
+    }
+
+    /// A writer thread that holds `pre_roll` samples while idle
+    fn writer_thread_with_pre_roll(
+        pre_roll: Arc<AtomicUsize>,
+    ) -> (
+        Producer<SampleType>,
+        Sender<WriterCommand>,
+        std::thread::JoinHandle<()>,
+    ) {
+        spawn_writer_thread(
+            pre_roll,
+            #[cfg(feature = "stream")]
+            broadcast::channel(STREAM_CHUNKS).0,
+        )
+    }
+
+    /// A writer thread that holds `pre_roll` samples while idle and streams what it
+    /// records to `chunks`
+    fn spawn_writer_thread(
+        pre_roll: Arc<AtomicUsize>,
+        #[cfg(feature = "stream")] chunks: broadcast::Sender<Vec<SampleType>>,
+    ) -> (
+        Producer<SampleType>,
+        Sender<WriterCommand>,
+        std::thread::JoinHandle<()>,
+    ) {
+        let (producer, consumer) = RingBuffer::new(1000);
+        let (commands, receiver) = mpsc::channel();
+        let writer = WriterThread {
+            commands: receiver,
+            samples: consumer,
+            missing_us: Arc::new(AtomicU64::new(0)),
+            is_recording: Arc::new(AtomicBool::new(true)),
+            on_event: Arc::new(Mutex::new(None)),
+            flush_interval: 100,
+            processing: Box::default(),
+            pre_roll,
+            held: VecDeque::new(),
+            sample_rate: 16000,
+            buffer_frames: Arc::new(AtomicUsize::new(0)),
+            reported_frames: 0,
+            #[cfg(feature = "stream")]
+            chunks,
+        };
+        (producer, commands, std::thread::spawn(move || writer.run()))
+    }
+
+    /// Stop the recording of the writer thread
+    fn stop(commands: &Sender<WriterCommand>) -> Recording {
+        let (reply, result) = mpsc::channel();
+        commands.send(WriterCommand::Stop(reply)).unwrap();
+        result.recv().unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_config_at_rate() {
+        let range = |channels, min, max, format| {
+            let rates = (cpal::SampleRate(min), cpal::SampleRate(max));
+            let buffer = cpal::SupportedBufferSize::Unknown;
+            SupportedStreamConfigRange::new(channels, rates.0, rates.1, buffer, format)
+        };
+        let configs = [
+            range(2, 44100, 48000, cpal::SampleFormat::I16),
+            range(2, 8000, 48000, cpal::SampleFormat::I16),
+            range(1, 8000, 48000, cpal::SampleFormat::F32),
+            range(1, 8000, 48000, cpal::SampleFormat::U16),
+        ];
+
+        let config = config_at_rate(configs, 16000).unwrap();
+        assert_eq!(config.channels(), 1);
+        assert_eq!(config.sample_rate().0, 16000);
+        assert_eq!(config.sample_format(), cpal::SampleFormat::F32);
+        assert!(config_at_rate(configs, 96000).is_none());
+    }
+
+    #[test]
+    fn test_writer_thread_drains_on_stop() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recording.wav");
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let (mut producer, commands, thread) = writer_thread();
+
+        let file = RollingWavWriter::create(&path, spec, MAX_WAV_DATA_BYTES).unwrap();
+        commands.send(WriterCommand::Start(Sink::File(file), Box::default())).unwrap();
+        for i in 0..500 {
+            producer.push(i as SampleType).unwrap();
+        }
+        // Samples still in the ring buffer when stopping end up in the file
+        let Recording::Files(files) = stop(&commands) else {
+            panic!("recorded to a file");
+        };
+
+        let samples: Vec<SampleType> =
+            WavReader::open(&files[0]).unwrap().samples().map(Result::unwrap).collect();
+        assert_eq!(samples, (0..500).collect::<Vec<SampleType>>());
+
+        drop(commands);
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_writer_thread_records_to_memory() {
+        let (mut producer, commands, thread) = writer_thread();
+
+        commands.send(WriterCommand::Start(Sink::Memory(Vec::new()), Box::default())).unwrap();
+        for i in 0..500 {
+            producer.push(i as SampleType).unwrap();
+        }
+        let Recording::Samples(samples) = stop(&commands) else {
+            panic!("recorded to memory");
+        };
+        assert_eq!(samples, (0..500).collect::<Vec<SampleType>>());
+
+        drop(commands);
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_writer_thread_spills_to_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let spill = Spill {
+            path: dir.path().join("recording.wav"),
+            spec: WavSpec {
+                channels: 1,
+                sample_rate: 16000,
+                bits_per_sample: 16,
+                sample_format: hound::SampleFormat::Int,
+            },
+            max_file_bytes: MAX_WAV_DATA_BYTES,
+            max_samples: 300,
+        };
+        let (mut producer, commands, thread) = writer_thread();
+
+        let sink = Sink::Spillable(Vec::new(), spill);
+        commands.send(WriterCommand::Start(sink, Box::default())).unwrap();
+        for i in 0..500 {
+            producer.push(i as SampleType).unwrap();
+        }
+        // Samples kept in memory before the limit was reached are in the file too
+        let Recording::Files(files) = stop(&commands) else {
+            panic!("spilled to a file");
+        };
+
+        let samples: Vec<SampleType> =
+            WavReader::open(&files[0]).unwrap().samples().map(Result::unwrap).collect();
+        assert_eq!(samples, (0..500).collect::<Vec<SampleType>>());
+
+        drop(commands);
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_writer_thread_starts_with_pre_roll() {
+        let pre_roll = Arc::new(AtomicUsize::new(100));
+        let (mut producer, commands, thread) = writer_thread_with_pre_roll(pre_roll);
+
+        // Captured while idle; only the last 100 samples are kept
+        for i in 0..300 {
+            producer.push(i as SampleType).unwrap();
+        }
+        while producer.slots() < 1000 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        commands.send(WriterCommand::Start(Sink::Memory(Vec::new()), Box::default())).unwrap();
+        for i in 300..350 {
+            producer.push(i as SampleType).unwrap();
+        }
+        let Recording::Samples(samples) = stop(&commands) else {
+            panic!("recorded to memory");
+        };
+        assert_eq!(samples, (200..350).collect::<Vec<SampleType>>());
+
+        drop(commands);
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_writer_thread_shortens_lowered_pre_roll() {
+        let pre_roll = Arc::new(AtomicUsize::new(100));
+        let (mut producer, commands, thread) = writer_thread_with_pre_roll(pre_roll.clone());
+
+        for i in 0..300 {
+            producer.push(i as SampleType).unwrap();
+        }
+        while producer.slots() < 1000 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        // Lowered for the next recording only, after the samples were held
+        pre_roll.store(40, Ordering::Relaxed);
+        commands.send(WriterCommand::Start(Sink::Memory(Vec::new()), Box::default())).unwrap();
+        let Recording::Samples(samples) = stop(&commands) else {
+            panic!("recorded to memory");
+        };
+        assert_eq!(samples, (260..300).collect::<Vec<SampleType>>());
+
+        drop(commands);
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_writer_thread_stops_at_limit() {
+        let (mut producer, commands, thread) = writer_thread();
+
+        let mut processing = Box::<Processing>::default();
+        processing.limit = Some(Limit {
+            remaining: 300,
+            length: Duration::from_secs_f64(300.0 / 16000.0),
+            reached: false,
+        });
+        commands.send(WriterCommand::Start(Sink::Memory(Vec::new()), processing)).unwrap();
+        for i in 0..500 {
+            producer.push(i as SampleType).unwrap();
+        }
+        // Samples past the limit are dropped
+        let Recording::Samples(samples) = stop(&commands) else {
+            panic!("recorded to memory");
+        };
+        assert_eq!(samples, (0..300).collect::<Vec<SampleType>>());
+
+        drop(commands);
+        thread.join().unwrap();
+    }
+
+    #[cfg(feature = "stream")]
+    #[test]
+    fn test_writer_thread_streams_chunks() {
+        let (chunks, mut subscriber) = broadcast::channel(STREAM_CHUNKS);
+        let (mut producer, commands, thread) =
+            spawn_writer_thread(Arc::new(AtomicUsize::new(0)), chunks);
+
+        commands.send(WriterCommand::Start(Sink::Memory(Vec::new()), Box::default())).unwrap();
+        for i in 0..500 {
+            producer.push(i as SampleType).unwrap();
+        }
+        let Recording::Samples(samples) = stop(&commands) else {
+            panic!("recorded to memory");
+        };
+
+        let mut streamed = Vec::new();
+        while let Ok(chunk) = subscriber.try_recv() {
+            streamed.extend(chunk);
+        }
+        assert_eq!(streamed, samples);
+
+        drop(commands);
+        thread.join().unwrap();
+    }
+}
@@ -0,0 +1,123 @@
+use anyhow::Result;
+use std::{
+    fs::OpenOptions,
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+/// Repair the header of a WAV file that was never finalized
+///
+/// When the process is killed mid-recording, the RIFF and data chunk sizes
+/// reflect the last header flush (or zero) instead of the bytes on disk.
+/// This rewrites both sizes from the actual file length, dropping a trailing
+/// partial frame if there is one.
+///
+/// Returns `true` if the header was changed, `false` if it was already valid.
+pub fn repair_wav_file<P: AsRef<Path>>(path: P) -> Result<bool> {
+    let mut file = OpenOptions::new().read(true).write(true).open(&path)?;
+    let file_len = file.metadata()?.len();
+
+    let mut riff = [0u8; 12];
+    file.read_exact(&mut riff)?;
+    if &riff[0..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+        return Err(anyhow::anyhow!("Not a RIFF/WAVE file"));
+    }
+
+    // Walk the chunks until the data chunk, remembering the frame size from fmt
+    let mut offset = 12u64;
+    let mut block_align: Option<u64> = None;
+    loop {
+        if offset + 8 > file_len {
+            return Err(anyhow::anyhow!("No data chunk found"));
+        }
+
+        let mut header = [0u8; 8];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut header)?;
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as u64;
+
+        match &header[0..4] {
+            b"fmt " => {
+                let mut fmt = [0u8; 14];
+                file.read_exact(&mut fmt)?;
+                block_align = Some(u16::from_le_bytes([fmt[12], fmt[13]]) as u64);
+            }
+            b"data" => break,
+            _ => {}
+        }
+
+        // Chunks are padded to an even size
+        offset += 8 + size + (size & 1);
+    }
+
+    let block_align = block_align
+        .filter(|&align| align > 0)
+        .ok_or_else(|| anyhow::anyhow!("Missing or invalid fmt chunk"))?;
+
+    let data_start = offset + 8;
+    let data_len = (file_len - data_start) / block_align * block_align;
+    let riff_len = data_start + data_len - 8;
+
+    let mut sizes = [0u8; 4];
+    file.seek(SeekFrom::Start(4))?;
+    file.read_exact(&mut sizes)?;
+    let old_riff_len = u32::from_le_bytes(sizes) as u64;
+    file.seek(SeekFrom::Start(offset + 4))?;
+    file.read_exact(&mut sizes)?;
+    let old_data_len = u32::from_le_bytes(sizes) as u64;
+
+    if old_riff_len == riff_len && old_data_len == data_len && data_start + data_len == file_len {
+        return Ok(false);
+    }
+
+    let riff_len = u32::try_from(riff_len)
+        .map_err(|_| anyhow::anyhow!("Recording exceeds the 4 GB WAV limit"))?;
+    file.seek(SeekFrom::Start(4))?;
+    file.write_all(&riff_len.to_le_bytes())?;
+    file.seek(SeekFrom::Start(offset + 4))?;
+    file.write_all(&(data_len as u32).to_le_bytes())?;
+    file.set_len(data_start + data_len)?;
+    file.sync_all()?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hound::{WavReader, WavSpec, WavWriter};
+
+    #[test]
+    fn test_repair_unfinalized_wav() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("crashed.wav");
+
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = WavWriter::create(&path, spec).unwrap();
+        for i in 0..1000 {
+            writer.write_sample(i as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        // Simulate a crash: zeroed sizes and a dangling half sample
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[4..8].copy_from_slice(&0u32.to_le_bytes());
+        let data_size_offset = bytes.len() - 2000 - 4;
+        bytes[data_size_offset..data_size_offset + 4].copy_from_slice(&0u32.to_le_bytes());
+        bytes.push(0x7f);
+        std::fs::write(&path, bytes).unwrap();
+
+        assert!(repair_wav_file(&path).unwrap());
+        assert!(!repair_wav_file(&path).unwrap());
+
+        let mut reader = WavReader::open(&path).unwrap();
+        let samples: Vec<i16> = reader.samples::<i16>().map(|s| s.unwrap()).collect();
+        assert_eq!(samples.len(), 1000);
+        assert_eq!(samples[999], 999);
+    }
+}
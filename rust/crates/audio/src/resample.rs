@@ -5,6 +5,106 @@ use rubato::{
 };
 use std::path::Path;
 
+/// Sample rate [`StreamingResampler`] converts to, the one Whisper expects
+pub const STREAMING_OUTPUT_RATE: u32 = 16000;
+
+/// Input frames the sinc resampler takes at a time
+const STREAMING_CHUNK_FRAMES: usize = 1024;
+
+/// Converts recorded audio to 16kHz mono chunk by chunk, while it is being recorded
+///
+/// Feed it input buffers of any size with [`StreamingResampler::process`] and call
+/// [`StreamingResampler::finish`] once the recording ends. The output lines up with
+/// the input and has as many samples as the input's length at 16kHz.
+pub struct StreamingResampler {
+    channels: usize,
+    /// `None` when the input already is 16kHz
+    resampler: Option<SincFixedIn<f32>>,
+    /// Mono input waiting for a full chunk
+    pending: Vec<f32>,
+    input_frames: usize,
+    output_frames: usize,
+    ratio: f64,
+}
+
+impl StreamingResampler {
+    /// Resample interleaved audio with `channels` channels at `input_rate`
+    pub fn new(input_rate: u32, channels: u16) -> Result<Self> {
+        if channels == 0 || input_rate == 0 {
+            return Err(anyhow::anyhow!(
+                "Invalid audio format: {} Hz, {} channels",
+                input_rate,
+                channels
+            ));
+        }
+
+        let ratio = STREAMING_OUTPUT_RATE as f64 / input_rate as f64;
+        let resampler = if input_rate == STREAMING_OUTPUT_RATE {
+            None
+        } else {
+            let params = SincInterpolationParameters {
+                sinc_len: 256,
+                f_cutoff: 0.95,
+                interpolation: SincInterpolationType::Linear,
+                oversampling_factor: 256,
+                window: WindowFunction::BlackmanHarris2,
+            };
+            Some(SincFixedIn::<f32>::new(ratio, 1.0, params, STREAMING_CHUNK_FRAMES, 1)?)
+        };
+
+        Ok(Self {
+            channels: channels as usize,
+            resampler,
+            pending: Vec::new(),
+            input_frames: 0,
+            output_frames: 0,
+            ratio,
+        })
+    }
+
+    /// Add interleaved samples, returning the 16kHz mono audio that is ready so far
+    pub fn process(&mut self, samples: &[i16]) -> Result<Vec<f32>> {
+        let channels = self.channels;
+        let mono = samples.chunks_exact(channels).map(|frame| {
+            frame.iter().map(|&s| s as f32 / 32768.0).sum::<f32>() / channels as f32
+        });
+        let Some(resampler) = self.resampler.as_mut() else {
+            return Ok(mono.collect());
+        };
+        self.pending.extend(mono);
+        self.input_frames += samples.len() / channels;
+
+        let mut output = Vec::new();
+        let mut start = 0;
+        while self.pending.len() - start >= resampler.input_frames_next() {
+            let end = start + resampler.input_frames_next();
+            let resampled = resampler.process(&[&self.pending[start..end]], None)?;
+            output.extend_from_slice(&resampled[0]);
+            start = end;
+        }
+        self.pending.drain(..start);
+        self.output_frames += output.len();
+        Ok(output)
+    }
+
+    /// Resample the input still buffered, returning the rest of the audio
+    pub fn finish(mut self) -> Result<Vec<f32>> {
+        let Some(mut resampler) = self.resampler.take() else {
+            return Ok(Vec::new());
+        };
+
+        // Pad the last chunk with silence and cut the output where the input ended
+        let expected = (self.input_frames as f64 * self.ratio).round() as usize;
+        let mut output = resampler.process_partial(Some(&[&self.pending]), None)?.remove(0);
+        while self.output_frames + output.len() < expected {
+            let flushed = resampler.process_partial(None::<&[&[f32]]>, None)?;
+            output.extend_from_slice(&flushed[0]);
+        }
+        output.truncate(expected.saturating_sub(self.output_frames));
+        Ok(output)
+    }
+}
+
 /// Resample a WAV file to a new sample rate and channel count
 pub fn resample_wav_file<P: AsRef<Path>>(
     input_path: P,
@@ -70,8 +170,8 @@ pub fn resample_wav_file<P: AsRef<Path>>(
     let mut output_samples = Vec::with_capacity(output_frames * target_channels as usize);
 
     for frame in 0..output_frames {
-        for channel in 0..target_channels as usize {
-            output_samples.push(final_channels[channel][frame]);
+        for channel in final_channels.iter().take(target_channels as usize) {
+            output_samples.push(channel[frame]);
         }
     }
 
@@ -99,7 +199,7 @@ pub fn resample_wav_file<P: AsRef<Path>>(
     Ok(())
 }
 
-fn resample_channel(input: &[f32], input_rate: u32, output_rate: u32) -> Result<Vec<f32>> {
+pub(crate) fn resample_channel(input: &[f32], input_rate: u32, output_rate: u32) -> Result<Vec<f32>> {
     if input_rate == output_rate {
         return Ok(input.to_vec());
     }
@@ -139,11 +239,11 @@ fn convert_channels(input_channels: Vec<Vec<f32>>, target_channels: usize) -> Ve
     match (input_count, target_channels) {
         // Stereo to mono: average left and right channels
         (2, 1) => {
-            let mut mono = Vec::with_capacity(frame_count);
-            for i in 0..frame_count {
-                let avg = (input_channels[0][i] + input_channels[1][i]) / 2.0;
-                mono.push(avg);
-            }
+            let mono = input_channels[0]
+                .iter()
+                .zip(&input_channels[1])
+                .map(|(left, right)| (left + right) / 2.0)
+                .collect();
             vec![mono]
         }
         // Mono to stereo: duplicate mono channel
@@ -169,6 +269,39 @@ fn convert_channels(input_channels: Vec<Vec<f32>>, target_channels: usize) -> Ve
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_streaming_resampler() {
+        let tone: Vec<i16> = (0..48000)
+            .map(|i| ((i as f32 * 0.01).sin() * 10000.0) as i16)
+            .collect();
+
+        // Chunks of any size add up to the same audio as a single buffer
+        let mut whole = StreamingResampler::new(48000, 1).unwrap();
+        let mut expected = whole.process(&tone).unwrap();
+        expected.extend(whole.finish().unwrap());
+        assert_eq!(expected.len(), 16000);
+
+        let mut chunked = StreamingResampler::new(48000, 1).unwrap();
+        let mut output = Vec::new();
+        for chunk in tone.chunks(441) {
+            output.extend(chunked.process(chunk).unwrap());
+        }
+        output.extend(chunked.finish().unwrap());
+        assert_eq!(output, expected);
+
+        // Lined up with the input, give or take a fraction of a sample: every third
+        // sample of the tone
+        let error = (100..15900)
+            .map(|i| (output[i] - tone[i * 3] as f32 / 32768.0).abs())
+            .fold(0.0, f32::max);
+        assert!(error < 0.01, "{}", error);
+
+        // 16kHz stereo is only downmixed
+        let mut stereo = StreamingResampler::new(16000, 2).unwrap();
+        assert_eq!(stereo.process(&[16384, -16384, 16384, 16384]).unwrap(), vec![0.0, 0.5]);
+        assert!(stereo.finish().unwrap().is_empty());
+    }
+
     #[test]
     fn test_channel_conversion() {
         // Test stereo to mono
@@ -0,0 +1,198 @@
+use anyhow::Result;
+use hound::{WavSpec, WavWriter};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use crate::SampleType;
+
+/// Largest data chunk we let a single WAV file grow to. The RIFF size fields
+/// are 32 bit, so anything past 4 GiB produces a broken file.
+pub const MAX_WAV_DATA_BYTES: u64 = u32::MAX as u64 - 1024;
+
+/// Describes a recording that was split across several WAV files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingManifest {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub bits_per_sample: u16,
+    /// File names, relative to the manifest, in playback order
+    pub files: Vec<String>,
+}
+
+impl RecordingManifest {
+    /// Read a manifest from disk
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+}
+
+/// Path of the manifest written next to a recording that rolled over
+pub fn manifest_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    path.as_ref().with_extension("manifest.json")
+}
+
+/// A WAV writer that continues in a new file once the current one is full
+///
+/// The first file is written to the requested path, later ones get a numeric
+/// suffix (`recording.001.wav`, `recording.002.wav`, ...). As soon as a second
+/// file is started a manifest listing all parts is written alongside.
+pub struct RollingWavWriter {
+    spec: WavSpec,
+    base_path: PathBuf,
+    max_data_bytes: u64,
+    writer: Option<WavWriter<BufWriter<File>>>,
+    bytes_written: u64,
+    files: Vec<PathBuf>,
+}
+
+impl RollingWavWriter {
+    /// Create a writer that starts a new file after `max_data_bytes` of sample data
+    pub fn create<P: Into<PathBuf>>(path: P, spec: WavSpec, max_data_bytes: u64) -> Result<Self> {
+        let base_path = path.into();
+        let writer = WavWriter::create(&base_path, spec)?;
+
+        Ok(Self {
+            spec,
+            max_data_bytes: max_data_bytes.min(MAX_WAV_DATA_BYTES),
+            writer: Some(writer),
+            bytes_written: 0,
+            files: vec![base_path.clone()],
+            base_path,
+        })
+    }
+
+    /// Write a single sample, rolling over to the next file when needed
+    pub fn write_sample(&mut self, sample: SampleType) -> Result<()> {
+        let frame_bytes = self.spec.bits_per_sample as u64 / 8 * self.spec.channels as u64;
+        let sample_bytes = self.spec.bits_per_sample as u64 / 8;
+
+        // Only split on frame boundaries
+        let at_frame_start = self.bytes_written.is_multiple_of(frame_bytes);
+        if at_frame_start && self.bytes_written + frame_bytes > self.max_data_bytes {
+            self.roll_over()?;
+        }
+
+        let writer = self
+            .writer
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Writer already finalized"))?;
+        writer.write_sample(sample)?;
+        self.bytes_written += sample_bytes;
+
+        Ok(())
+    }
+
+    /// Update the header of the current file without closing it
+    pub fn flush(&mut self) -> Result<()> {
+        if let Some(writer) = self.writer.as_mut() {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Files written so far, in order
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// Finalize the current file and return all files that make up the recording
+    pub fn finalize(mut self) -> Result<Vec<PathBuf>> {
+        if let Some(writer) = self.writer.take() {
+            writer.finalize()?;
+        }
+        if self.files.len() > 1 {
+            self.write_manifest()?;
+        }
+        Ok(self.files)
+    }
+
+    fn roll_over(&mut self) -> Result<()> {
+        if let Some(writer) = self.writer.take() {
+            writer.finalize()?;
+        }
+
+        let path = chunk_path(&self.base_path, self.files.len());
+        self.writer = Some(WavWriter::create(&path, self.spec)?);
+        self.files.push(path);
+        self.bytes_written = 0;
+
+        // Keep the manifest current in case the process dies before finalize
+        self.write_manifest()
+    }
+
+    fn write_manifest(&self) -> Result<()> {
+        let manifest = RecordingManifest {
+            sample_rate: self.spec.sample_rate,
+            channels: self.spec.channels,
+            bits_per_sample: self.spec.bits_per_sample,
+            files: self
+                .files
+                .iter()
+                .filter_map(|path| path.file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .collect(),
+        };
+
+        std::fs::write(
+            manifest_path(&self.base_path),
+            serde_json::to_string_pretty(&manifest)?,
+        )?;
+        Ok(())
+    }
+}
+
+/// Path of the `index`-th file of a recording; index 0 is the base path itself
+fn chunk_path(base: &Path, index: usize) -> PathBuf {
+    if index == 0 {
+        return base.to_path_buf();
+    }
+
+    let stem = base.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = base.extension().unwrap_or_default().to_string_lossy();
+    base.with_file_name(format!("{}.{:03}.{}", stem, index, extension))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hound::WavReader;
+
+    #[test]
+    fn test_rollover() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("meeting.wav");
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+
+        // 100 bytes = 50 samples per file
+        let mut writer = RollingWavWriter::create(&path, spec, 100).unwrap();
+        for i in 0..120 {
+            writer.write_sample(i as SampleType).unwrap();
+        }
+        let files = writer.finalize().unwrap();
+
+        assert_eq!(files.len(), 3);
+        assert_eq!(files[1], dir.path().join("meeting.001.wav"));
+
+        let lengths: Vec<u32> = files
+            .iter()
+            .map(|file| WavReader::open(file).unwrap().len())
+            .collect();
+        assert_eq!(lengths, vec![50, 50, 20]);
+
+        let manifest = RecordingManifest::load(manifest_path(&path)).unwrap();
+        assert_eq!(
+            manifest.files,
+            vec!["meeting.wav", "meeting.001.wav", "meeting.002.wav"]
+        );
+    }
+}
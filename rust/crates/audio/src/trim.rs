@@ -0,0 +1,47 @@
+use std::ops::Range;
+
+use crate::{meter::rms_dbfs, SampleType};
+
+/// Length of the blocks whose level is compared to the threshold, 10ms at 16kHz
+const BLOCK_LEN: usize = 160;
+
+/// `samples` without the quiet before the first and after the last block louder than
+/// `threshold_dbfs`, keeping `padding` samples of it on either side
+///
+/// Cuts the dead air between pressing the key and speaking, and between speaking and
+/// letting go, which Whisper would only take time over or hallucinate in. Empty when
+/// no block is loud enough.
+pub fn trim_silence(samples: &[SampleType], threshold_dbfs: f32, padding: usize) -> &[SampleType] {
+    &samples[speech_bounds(samples, threshold_dbfs, padding)]
+}
+
+/// The range of `samples` that [`trim_silence`] keeps
+pub fn speech_bounds(samples: &[SampleType], threshold_dbfs: f32, padding: usize) -> Range<usize> {
+    let loud = |block: &[SampleType]| rms_dbfs(block) > threshold_dbfs;
+    let Some(first) = samples.chunks(BLOCK_LEN).position(loud) else {
+        return 0..0;
+    };
+    let last = samples.chunks(BLOCK_LEN).rposition(loud).unwrap_or(first);
+    let start = (first * BLOCK_LEN).saturating_sub(padding);
+    let end = ((last + 1) * BLOCK_LEN).saturating_add(padding).min(samples.len());
+    start..end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trim_silence() {
+        let mut samples = vec![3; 4000];
+        samples.extend((0..8000).map(|i| ((i as f32 * 0.2).sin() * 8000.0) as SampleType));
+        samples.extend(vec![-3; 6000]);
+
+        let trimmed = trim_silence(&samples, -50.0, 800);
+        assert_eq!(speech_bounds(&samples, -50.0, 800), 3200..12800);
+        assert_eq!(trimmed.len(), 9600);
+        // Padding stops at the ends
+        assert_eq!(speech_bounds(&samples, -50.0, 10000), 0..18000);
+        assert!(trim_silence(&samples[..4000], -50.0, 800).is_empty());
+    }
+}
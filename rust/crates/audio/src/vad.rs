@@ -0,0 +1,255 @@
+use anyhow::Result;
+use std::time::Duration;
+
+#[cfg(feature = "silero")]
+use crate::silero::SileroModel;
+use crate::{meter::rms_dbfs, SampleType};
+
+/// Length of the frames the detector decides on
+const FRAME_MS: u64 = 30;
+/// How fast the noise floor estimate follows the room getting louder
+const NOISE_RISE_DB_PER_SECOND: f32 = 1.0;
+
+/// Tells speech from silence in audio as it is recorded, to find where speech ended
+///
+/// [`VoiceActivityDetector`] does so by level and [`crate::SileroVad`] with a model.
+pub trait SpeechDetector: Send {
+    /// Add the next samples of the recording
+    fn process(&mut self, samples: &[SampleType]) -> Result<()>;
+
+    /// Whether any speech was heard since the detector was created
+    fn heard_speech(&self) -> bool;
+
+    /// How long it has been quiet, since the last speech or since the start
+    fn silence(&self) -> Duration;
+}
+
+/// Which [`SpeechDetector`] tells when speech ended
+///
+/// The energy-based detector costs next to nothing but takes any loud noise for speech;
+/// Silero, with the `silero` feature, also hears speech over typing and fans but runs a
+/// neural network on the audio.
+#[derive(Debug, Clone, Default)]
+pub enum VadBackend {
+    /// [`VoiceActivityDetector`], by the level of the audio
+    #[default]
+    Energy,
+    /// [`crate::SileroVad`] running this model
+    #[cfg(feature = "silero")]
+    Silero(SileroModel),
+}
+
+impl VadBackend {
+    /// A detector of this kind for mono audio at `sample_rate`
+    pub fn detector(&self, sample_rate: u32) -> Result<Box<dyn SpeechDetector>> {
+        match self {
+            Self::Energy => Ok(Box::new(VoiceActivityDetector::new(sample_rate))),
+            #[cfg(feature = "silero")]
+            Self::Silero(model) => Ok(Box::new(model.detector(sample_rate)?)),
+        }
+    }
+}
+
+/// Energy-based voice activity detector for audio as it is recorded
+///
+/// Each 30ms frame counts as speech when it is `noise_margin_db` above a running
+/// estimate of the noise floor and louder than `min_level_db`. The floor drops to
+/// quieter frames at once but rises only slowly, so steady background noise is not
+/// taken for speech.
+#[derive(Debug, Clone)]
+pub struct VoiceActivityDetector {
+    frame_len: usize,
+    /// Samples that don't make up a whole frame yet
+    pending: Vec<SampleType>,
+    noise_margin_db: f32,
+    min_level_db: f32,
+    noise_floor_db: f32,
+    heard_speech: bool,
+    /// Frames since the last speech frame
+    silent_frames: u64,
+}
+
+impl VoiceActivityDetector {
+    /// A detector for mono audio at `sample_rate`
+    pub fn new(sample_rate: u32) -> Self {
+        let frame_len = (sample_rate as u64 * FRAME_MS / 1000).max(1) as usize;
+        let min_level_db = -50.0;
+        Self {
+            frame_len,
+            pending: Vec::with_capacity(frame_len),
+            noise_margin_db: 12.0,
+            min_level_db,
+            noise_floor_db: min_level_db,
+            heard_speech: false,
+            silent_frames: 0,
+        }
+    }
+
+    /// How far above the noise floor speech is, in dB; defaults to 12
+    pub fn noise_margin_db(mut self, margin_db: f32) -> Self {
+        self.noise_margin_db = margin_db;
+        self
+    }
+
+    /// Level in dBFS below which a frame is never speech; defaults to -50
+    pub fn min_level_db(mut self, level_db: f32) -> Self {
+        self.min_level_db = level_db;
+        self.noise_floor_db = level_db;
+        self
+    }
+
+    /// Add the next samples of the recording
+    pub fn process(&mut self, samples: &[SampleType]) {
+        let mut samples = samples;
+        while !samples.is_empty() {
+            let needed = self.frame_len - self.pending.len();
+            let (head, rest) = samples.split_at(needed.min(samples.len()));
+            self.pending.extend_from_slice(head);
+            samples = rest;
+            if self.pending.len() == self.frame_len {
+                let level = rms_dbfs(&self.pending);
+                self.pending.clear();
+                self.push_frame(level);
+            }
+        }
+    }
+
+    fn push_frame(&mut self, level: f32) {
+        let threshold = (self.noise_floor_db + self.noise_margin_db).max(self.min_level_db);
+        if level >= threshold {
+            self.heard_speech = true;
+            self.silent_frames = 0;
+            return;
+        }
+
+        self.silent_frames += 1;
+        if level < self.noise_floor_db {
+            self.noise_floor_db = level;
+        } else {
+            self.noise_floor_db += NOISE_RISE_DB_PER_SECOND * FRAME_MS as f32 / 1000.0;
+        }
+    }
+
+    /// Offsets into `samples` where speech resumes after a pause of at least
+    /// `min_pause`, halfway into the pause
+    ///
+    /// Splits an utterance into the stretches spoken in one go, such as its sentences.
+    /// Quiet before the first speech is not a pause. Starts over as with
+    /// [`VoiceActivityDetector::reset`], so quiet from earlier samples doesn't count.
+    pub fn split_at_pauses(&mut self, samples: &[SampleType], min_pause: Duration) -> Vec<usize> {
+        self.reset();
+        let mut splits = Vec::new();
+        let mut offset = 0;
+        for chunk in samples.chunks(self.frame_len) {
+            let (heard_speech, silent_frames, silence) =
+                (self.heard_speech, self.silent_frames, self.silence());
+            self.process(chunk);
+            if heard_speech && silence >= min_pause && self.silent_frames == 0 {
+                let pause = silent_frames as usize * self.frame_len;
+                splits.push(offset - pause / 2);
+            }
+            offset += chunk.len();
+        }
+        splits
+    }
+
+    /// Whether any speech was heard since the detector was created or reset
+    pub fn heard_speech(&self) -> bool {
+        self.heard_speech
+    }
+
+    /// How long it has been quiet, since the last speech or since the start
+    pub fn silence(&self) -> Duration {
+        Duration::from_millis(self.silent_frames * FRAME_MS)
+    }
+
+    /// Start over for a new recording, keeping the noise floor
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.heard_speech = false;
+        self.silent_frames = 0;
+    }
+}
+
+impl SpeechDetector for VoiceActivityDetector {
+    fn process(&mut self, samples: &[SampleType]) -> Result<()> {
+        VoiceActivityDetector::process(self, samples);
+        Ok(())
+    }
+
+    fn heard_speech(&self) -> bool {
+        VoiceActivityDetector::heard_speech(self)
+    }
+
+    fn silence(&self) -> Duration {
+        VoiceActivityDetector::silence(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(frames: usize, amplitude: f32) -> Vec<SampleType> {
+        (0..frames)
+            .map(|i| ((i as f32 * 0.2).sin() * amplitude) as SampleType)
+            .collect()
+    }
+
+    #[test]
+    fn test_speech_then_silence() {
+        let mut vad = VoiceActivityDetector::new(16000);
+
+        // Quiet room noise
+        vad.process(&tone(16000, 30.0));
+        assert!(!vad.heard_speech());
+        assert!(vad.silence() >= Duration::from_millis(960));
+
+        // Speech, fed in uneven chunks
+        for chunk in tone(16000, 8000.0).chunks(333) {
+            vad.process(chunk);
+        }
+        assert!(vad.heard_speech());
+        assert_eq!(vad.silence(), Duration::ZERO);
+
+        vad.process(&tone(8000, 30.0));
+        assert!(vad.heard_speech());
+        assert_eq!(vad.silence(), Duration::from_millis(480));
+
+        vad.reset();
+        assert!(!vad.heard_speech());
+        assert_eq!(vad.silence(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_energy_backend() {
+        let mut vad = VadBackend::Energy.detector(16000).unwrap();
+        vad.process(&tone(16000, 8000.0)).unwrap();
+        vad.process(&tone(8000, 30.0)).unwrap();
+        assert!(vad.heard_speech());
+        assert_eq!(vad.silence(), Duration::from_millis(480));
+    }
+
+    #[test]
+    fn test_split_at_pauses() {
+        let mut utterance = tone(8000, 30.0);
+        utterance.extend(tone(16000, 8000.0));
+        // Too short a pause to split at
+        utterance.extend(tone(3200, 30.0));
+        utterance.extend(tone(16000, 8000.0));
+        utterance.extend(tone(16000, 30.0));
+        utterance.extend(tone(8000, 8000.0));
+
+        let mut vad = VoiceActivityDetector::new(16000);
+        let splits = vad.split_at_pauses(&utterance, Duration::from_millis(500));
+        assert_eq!(splits.len(), 1);
+        // In the middle of the second second of quiet, to the frame
+        assert!(splits[0].abs_diff(43200 + 8000) <= 480);
+
+        // Quiet left over from before doesn't reach back past the start
+        vad.process(&tone(16000, 8000.0));
+        vad.process(&tone(32000, 30.0));
+        let splits = vad.split_at_pauses(&tone(8000, 8000.0), Duration::from_millis(500));
+        assert!(splits.is_empty());
+    }
+}
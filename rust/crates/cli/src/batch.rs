@@ -0,0 +1,172 @@
+use anyhow::Result;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use transcribe::{
+    load_wav_resampled, model::dtw_preset, Batch, DtwMode, DtwParameters, Transcriber,
+    TranscriptCache, WhisperContextParameters,
+};
+
+use crate::{bundled_paths, config::Config, resolve_model, transcribe_options, DEFAULT_MODEL};
+
+/// The `batch` subcommand
+pub fn command() -> Command {
+    Command::new("batch")
+        .about("Transcribe every WAV file in a directory into .txt and .json files")
+        .arg(Arg::new("dir").value_name("DIR").required(true))
+        .arg(
+            Arg::new("model")
+                .short('m')
+                .long("model")
+                .value_name("MODEL")
+                .help("Model name or path; defaults to the configured model"),
+        )
+        .arg(
+            Arg::new("jobs")
+                .short('j')
+                .long("jobs")
+                .value_name("N")
+                .help("Number of files to transcribe at the same time")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("format")
+                .short('f')
+                .long("format")
+                .value_name("FORMAT")
+                .value_parser(["txt", "json", "both"])
+                .default_value("both"),
+        )
+        .arg(
+            Arg::new("speakers")
+                .long("speakers")
+                .help("Format the text as a dialogue; needs a tdrz model such as small.en-tdrz")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("overwrite")
+                .long("overwrite")
+                .help("Transcribe files that already have outputs again")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-cache")
+                .long("no-cache")
+                .help("Decode every file instead of reusing earlier results")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-resume")
+                .long("no-resume")
+                .help("Start files from the beginning instead of their last checkpoint")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-recursive")
+                .long("no-recursive")
+                .help("Skip subdirectories")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+/// Run the `batch` subcommand
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let (model_dir, config_path) = bundled_paths()?;
+    let config = Config::load(&config_path)?;
+    let options = transcribe_options(&config.stages, &config_path)?
+        .tinydiarize(matches.get_flag("speakers"));
+
+    let model = matches
+        .get_one::<String>("model")
+        .cloned()
+        .or(config.model.clone())
+        .unwrap_or_else(|| DEFAULT_MODEL.to_string());
+    let model_path = if Path::new(&model).exists() {
+        PathBuf::from(&model)
+    } else {
+        resolve_model(&model_dir, &model)?
+    };
+    let use_gpu = config
+        .use_gpu
+        .unwrap_or(!transcribe::compiled_gpu_backends().is_empty());
+    println!("📚 Loading Whisper model {}...", model);
+    let mut params = WhisperContextParameters::default();
+    params.use_gpu(use_gpu).gpu_device(config.gpu_device);
+    if config.dtw_timestamps {
+        let name = model_path.file_name().unwrap_or_default().to_string_lossy();
+        match dtw_preset(&name) {
+            Some(model_preset) => {
+                params.dtw_parameters(DtwParameters {
+                    mode: DtwMode::ModelPreset { model_preset },
+                    ..Default::default()
+                });
+            }
+            None => eprintln!(
+                "⚠️  No DTW alignment heads known for {}, using whisper's word times",
+                name
+            ),
+        }
+    }
+    let transcriber = Transcriber::new_with_params(&model_path, params)?;
+
+    let format = matches.get_one::<String>("format").unwrap();
+    let mut batch = Batch::new()
+        .outputs(format != "json", format != "txt")
+        .overwrite(matches.get_flag("overwrite"))
+        .resume(!matches.get_flag("no-resume"))
+        .recursive(!matches.get_flag("no-recursive"));
+    if let Some(jobs) = matches.get_one::<usize>("jobs") {
+        batch = batch.workers(*jobs);
+    }
+    // Length of the progress line, to blank it before the next message
+    let status_len = Arc::new(AtomicUsize::new(0));
+    let root = PathBuf::from(matches.get_one::<String>("dir").unwrap());
+    let status = status_len.clone();
+    batch = batch.on_file_progress(move |input, percent| {
+        let name = input.strip_prefix(&root).unwrap_or(input);
+        let line = format!("⏳ {} ({}%)", name.display(), percent);
+        let previous = status.swap(line.chars().count(), Ordering::SeqCst);
+        print!("\r{:<width$}", line, width = previous);
+        std::io::Write::flush(&mut std::io::stdout()).unwrap();
+    });
+    if !matches.get_flag("no-cache") {
+        batch = batch.cache(TranscriptCache::with_default_dir()?);
+    }
+
+    let dir = matches.get_one::<String>("dir").unwrap();
+    let load = |path: &Path| load_wav_resampled(path);
+    let results = batch.run_with_loader(&transcriber, dir, &options, load, |result| {
+        let previous = status_len.swap(0, Ordering::SeqCst);
+        if previous > 0 {
+            print!("\r{}\r", " ".repeat(previous));
+        }
+        let name = result.input.strip_prefix(dir).unwrap_or(&result.input);
+        match &result.error {
+            _ if result.skipped => println!("⏭️  {} (already transcribed)", name.display()),
+            Some(e) => eprintln!("❌ {}: {}", name.display(), e),
+            None => println!(
+                "✅ {} ({:.1}s)",
+                name.display(),
+                result.elapsed.as_secs_f32()
+            ),
+        }
+    })?;
+
+    let failed = results.iter().filter(|result| result.error.is_some()).count();
+    let skipped = results.iter().filter(|result| result.skipped).count();
+    println!(
+        "📝 Transcribed {} files ({} skipped, {} failed)",
+        results.len() - failed - skipped,
+        skipped,
+        failed
+    );
+    if failed > 0 {
+        return Err(anyhow::anyhow!("{} files failed to transcribe", failed));
+    }
+    Ok(())
+}
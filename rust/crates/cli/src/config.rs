@@ -0,0 +1,579 @@
+use anyhow::Result;
+use keyctl::Key;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+use transcribe::{NumberWords, Postprocess, SpokenSymbols, Typography, Vad};
+
+pub const CONFIG_NAME: &str = "voicekb.json";
+
+/// User configuration, read from `voicekb.json`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Whisper model to use, e.g. "base.en"; downloaded into the model cache when missing
+    pub model: Option<String>,
+    /// Recording, decoding, post-processing and output settings; modes can override each
+    #[serde(flatten)]
+    pub stages: StageConfig,
+    /// Offload inference to the GPU; defaults to on when built with a GPU feature
+    pub use_gpu: Option<bool>,
+    /// Index of the GPU to use
+    pub gpu_device: i32,
+    /// Time words in `voicekb batch` JSON output with DTW alignment, which is more
+    /// accurate than whisper's timestamps
+    pub dtw_timestamps: bool,
+    /// Keep the microphone open between recordings so they start without delay,
+    /// instead of only opening it while a key is held
+    pub keep_mic_open: bool,
+    /// Move a microphone kept open to the system's new default input between recordings
+    pub follow_default_input: bool,
+    /// Audio host to record through, e.g. "JACK" or "ASIO"; the platform's default
+    /// without one
+    pub audio_host: Option<String>,
+    /// Frames per input buffer to ask the microphone for; small buffers lower the
+    /// latency, large ones save battery
+    pub buffer_frames: Option<u32>,
+    /// Write recordings to `temp_recording.wav` instead of keeping them in memory, so
+    /// one cut short by a crash can be recovered
+    pub record_to_file: bool,
+    /// Move recordings kept in memory to `temp_recording.wav` once they hold this many
+    /// MiB, so long ones such as meetings don't fill up memory
+    pub max_memory_mb: Option<u64>,
+    /// Stop recordings after this many seconds, in case a key is stuck
+    pub max_recording_secs: Option<u64>,
+    /// Stop recordings once they hold this many MiB of audio
+    pub max_recording_mb: Option<u64>,
+    /// Lower the system volume while recording
+    pub ducking: Option<DuckingConfig>,
+    /// Translate transcriptions before typing them
+    pub translation: Option<TranslationConfig>,
+    /// Hotkeys with their own model and language; defaults to a single Quote key mode
+    pub modes: Vec<ModeConfig>,
+    /// Record while a GPIO button is held instead of listening to the keyboard
+    pub gpio_trigger: Option<GpioConfig>,
+    /// Record with the call or mute button of a headset (Linux)
+    pub headset_trigger: Option<HeadsetConfig>,
+    /// Record with a Bluetooth camera-shutter remote (Linux)
+    pub remote_trigger: Option<RemoteConfig>,
+    /// Desktop notifications after each recording; modes can override them
+    pub notifications: Option<NotificationConfig>,
+    /// Keep recent transcriptions for `voicekb history`
+    pub history: Option<HistoryConfig>,
+    /// Say this, e.g. "retry last sentence", to record the last sentence typed again
+    pub retry_phrase: Option<String>,
+    /// Write how long each stage of every utterance takes to this Chrome trace file,
+    /// relative to the config file
+    pub trace_file: Option<PathBuf>,
+    /// Show a small dot on screen while recording and transcribing
+    pub indicator: Option<IndicatorConfig>,
+    /// Blink a keyboard LED (`scroll-lock`, `caps-lock` or `num-lock`) while recording
+    pub keyboard_led: Option<indicator::Led>,
+    /// Sounds played when recording starts and stops, on errors and once text is typed
+    pub cues: Option<CuesConfig>,
+}
+
+/// A push-to-talk hotkey and the settings used for recordings it starts
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModeConfig {
+    /// Key name as known to rdev, e.g. "F9" or "Quote"
+    pub key: Key,
+    /// Whisper model for this key; defaults to the top-level `model`
+    pub model: Option<String>,
+    /// Spoken language code, e.g. "nl", `"auto"` to detect it or `"layout"` for the
+    /// language of the keyboard layout; defaults to English
+    pub language: Option<String>,
+    /// Detect which of these languages is spoken, e.g. `["fr", "en"]`
+    #[serde(default)]
+    pub languages: Vec<String>,
+    /// Translate the speech into English with Whisper
+    #[serde(default)]
+    pub translate: bool,
+    /// Type with hardware scan codes (Windows), for games that ignore Unicode input
+    #[serde(default)]
+    pub scan_codes: bool,
+    /// Only recognize these phrases, e.g. "new line" or "select all"
+    #[serde(default)]
+    pub phrases: Vec<String>,
+    /// Accept a sequence of `phrases` instead of a single one
+    #[serde(default)]
+    pub repeat_phrases: bool,
+    /// Notifications for this key instead of the top-level `notifications`
+    pub notifications: Option<NotificationConfig>,
+    /// Settings for this key instead of the top-level ones
+    #[serde(flatten)]
+    pub stages: StageConfig,
+}
+
+/// Settings for each stage of turning a recording into typed text
+///
+/// Both the top level and each mode take these. A setting a mode leaves out falls
+/// back to the top-level one, and a setting left out of both to its default.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct StageConfig {
+    /// Microphone name, or part of it; the system's default input without one
+    pub input_device: Option<String>,
+    /// Microphones in order of preference, each a name or part of one, with `"default"`
+    /// for the system's default input; the first one connected records each utterance
+    pub input_devices: Option<Vec<String>>,
+    /// Record what the computer plays, e.g. a meeting, instead of the microphone;
+    /// `input_device` then names the output to capture
+    pub system_audio: Option<bool>,
+    /// Skip silence and split long pauses with a voice activity detector
+    pub vad: Option<VadConfig>,
+    /// Prompt passed to Whisper on every call to bias recognition toward domain vocabulary
+    pub initial_prompt: Option<String>,
+    /// Use beam search with this many beams instead of greedy decoding
+    pub beam_size: Option<i32>,
+    /// Initial sampling temperature
+    pub temperature: Option<f32>,
+    /// Temperature added on each retry of a repetitive or unlikely decode; 0 disables retries
+    pub temperature_increment: Option<f32>,
+    /// Decodes with less token entropy than this are retried as repetitive
+    pub entropy_threshold: Option<f32>,
+    /// Decodes with a lower average log probability than this are retried
+    pub logprob_threshold: Option<f32>,
+    /// Unlikely segments more probably silent than this are dropped as silence
+    pub no_speech_threshold: Option<f32>,
+    /// Names, codenames and jargon to favor during decoding and correct the output toward
+    pub vocabulary: Option<Vec<String>>,
+    /// Bias added to the first token of each word or phrase while decoding, e.g.
+    /// `{"Kubernetes": 3, "Thanks for watching": -5}`
+    pub token_bias: Option<BTreeMap<String, f32>>,
+    /// Tokens whose whole text matches this regex are never decoded
+    pub suppress_regex: Option<String>,
+    /// File of `spoken => written` pronunciation hints, relative to the config file
+    pub hints_file: Option<PathBuf>,
+    /// Control phrases and wake words that are never typed
+    pub control_phrases: Option<Vec<String>>,
+    /// Keep noise annotations such as `[laughter]` or `♪` out of the typed text
+    pub suppress_non_speech: Option<bool>,
+    /// Rewrites applied in order to the corrected text, e.g. `["numbers", "typography"]`
+    pub postprocess: Option<Vec<PostprocessStep>>,
+    /// Send transcriptions elsewhere instead of typing them
+    pub output: Option<OutputConfig>,
+    /// Paste texts longer than this many characters through the clipboard instead of
+    /// typing them
+    pub paste_above: Option<usize>,
+    /// Ask before typing transcriptions longer than this many characters, so a
+    /// hallucinated wall of repeated text doesn't end up in a chat window
+    pub max_chars: Option<usize>,
+    /// End a recording once speech is followed by this many milliseconds of silence,
+    /// without waiting for the key release
+    pub stop_on_silence_ms: Option<u64>,
+    /// Type each sentence as soon as it is decoded instead of after the whole recording;
+    /// ignored when translating
+    pub stream_typing: Option<bool>,
+    /// Leave fillers such as "um" and "you know" out of the typed text; the history keeps
+    /// them
+    pub remove_fillers: Option<bool>,
+    /// Start recordings with this many milliseconds of audio from before the key press;
+    /// keeps the microphone open
+    pub pre_roll_ms: Option<u64>,
+    /// Remove background noise from recordings with RNNoise
+    pub denoise: Option<bool>,
+    /// Cut rumble below this frequency in Hz from recordings, e.g. 80, with the DC offset
+    pub high_pass_hz: Option<f32>,
+    /// Remove the DC offset of recordings without cutting any frequencies
+    pub remove_dc_offset: Option<bool>,
+    /// Bring the peaks of speech in recordings to this level in dBFS, e.g. -6
+    pub auto_gain_db: Option<f32>,
+    /// Cut the quiet before and after speech quieter than this level in dBFS, e.g. -50,
+    /// before transcribing
+    pub trim_silence_db: Option<f32>,
+    /// Detector that tells when speech ended for `stop_on_silence_ms`
+    pub stop_on_silence_vad: Option<SilenceVadConfig>,
+}
+
+impl StageConfig {
+    /// These settings with the ones they leave out taken from `base`
+    pub fn over(&self, base: &StageConfig) -> StageConfig {
+        StageConfig {
+            input_device: self
+                .input_device
+                .clone()
+                .or_else(|| base.input_device.clone()),
+            input_devices: self
+                .input_devices
+                .clone()
+                .or_else(|| base.input_devices.clone()),
+            system_audio: self.system_audio.or(base.system_audio),
+            vad: self.vad.clone().or_else(|| base.vad.clone()),
+            initial_prompt: self
+                .initial_prompt
+                .clone()
+                .or_else(|| base.initial_prompt.clone()),
+            beam_size: self.beam_size.or(base.beam_size),
+            temperature: self.temperature.or(base.temperature),
+            temperature_increment: self.temperature_increment.or(base.temperature_increment),
+            entropy_threshold: self.entropy_threshold.or(base.entropy_threshold),
+            logprob_threshold: self.logprob_threshold.or(base.logprob_threshold),
+            no_speech_threshold: self.no_speech_threshold.or(base.no_speech_threshold),
+            vocabulary: self.vocabulary.clone().or_else(|| base.vocabulary.clone()),
+            token_bias: self.token_bias.clone().or_else(|| base.token_bias.clone()),
+            suppress_regex: self
+                .suppress_regex
+                .clone()
+                .or_else(|| base.suppress_regex.clone()),
+            hints_file: self.hints_file.clone().or_else(|| base.hints_file.clone()),
+            control_phrases: self
+                .control_phrases
+                .clone()
+                .or_else(|| base.control_phrases.clone()),
+            suppress_non_speech: self.suppress_non_speech.or(base.suppress_non_speech),
+            postprocess: self.postprocess.clone().or_else(|| base.postprocess.clone()),
+            output: self.output.clone().or_else(|| base.output.clone()),
+            paste_above: self.paste_above.or(base.paste_above),
+            max_chars: self.max_chars.or(base.max_chars),
+            stop_on_silence_ms: self.stop_on_silence_ms.or(base.stop_on_silence_ms),
+            stream_typing: self.stream_typing.or(base.stream_typing),
+            remove_fillers: self.remove_fillers.or(base.remove_fillers),
+            pre_roll_ms: self.pre_roll_ms.or(base.pre_roll_ms),
+            denoise: self.denoise.or(base.denoise),
+            high_pass_hz: self.high_pass_hz.or(base.high_pass_hz),
+            remove_dc_offset: self.remove_dc_offset.or(base.remove_dc_offset),
+            auto_gain_db: self.auto_gain_db.or(base.auto_gain_db),
+            trim_silence_db: self.trim_silence_db.or(base.trim_silence_db),
+            stop_on_silence_vad: self
+                .stop_on_silence_vad
+                .clone()
+                .or_else(|| base.stop_on_silence_vad.clone()),
+        }
+    }
+}
+
+/// A text rewrite in the `postprocess` list
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PostprocessStep {
+    /// Number words to digits, e.g. "twenty five" to "25"
+    Numbers,
+    /// Spoken symbol names to symbols, e.g. "at sign" to "@"
+    Symbols,
+    /// "..." to an ellipsis and spaced hyphens to em dashes
+    Typography,
+}
+
+impl PostprocessStep {
+    pub fn filter(self, postprocess: Postprocess) -> Postprocess {
+        match self {
+            Self::Numbers => postprocess.filter(NumberWords),
+            Self::Symbols => postprocess.filter(SpokenSymbols::default()),
+            Self::Typography => postprocess.filter(Typography),
+        }
+    }
+}
+
+/// Voice activity detector that ends recordings for `stop_on_silence_ms`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SilenceVadConfig {
+    /// By the level of the audio
+    #[default]
+    Energy,
+    /// With Silero VAD, running the ONNX model at this path relative to the config file
+    Silero(PathBuf),
+}
+
+/// Voice activity detection: `true` for the defaults, or the thresholds to change
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum VadConfig {
+    Enabled(bool),
+    Tuned(VadThresholds),
+}
+
+impl VadConfig {
+    /// The detector to use, if enabled
+    pub fn vad(&self) -> Option<Vad> {
+        let thresholds = match self {
+            Self::Enabled(false) => return None,
+            Self::Enabled(true) => return Some(Vad::new()),
+            Self::Tuned(thresholds) => thresholds,
+        };
+        let mut vad = Vad::new();
+        if let Some(noise_margin_db) = thresholds.noise_margin_db {
+            vad = vad.noise_margin_db(noise_margin_db);
+        }
+        if let Some(min_level_db) = thresholds.min_level_db {
+            vad = vad.min_level_db(min_level_db);
+        }
+        if let Some(padding_ms) = thresholds.padding_ms {
+            vad = vad.padding_ms(padding_ms);
+        }
+        if let Some(max_pause_ms) = thresholds.max_pause_ms {
+            vad = vad.max_pause_ms(max_pause_ms);
+        }
+        Some(vad)
+    }
+}
+
+/// Voice activity detector settings; those left out keep their defaults
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct VadThresholds {
+    /// How far above the noise floor, in dB, a frame must be to count as speech
+    pub noise_margin_db: Option<f32>,
+    /// Level in dBFS below which audio is always silence
+    pub min_level_db: Option<f32>,
+    /// Silence kept before and after speech
+    pub padding_ms: Option<usize>,
+    /// Longest pause that does not split the recording
+    pub max_pause_ms: Option<usize>,
+}
+
+/// Which outcomes of a recording show a desktop notification
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    /// Show the transcribed text, with buttons to copy or undo it
+    pub transcribed: bool,
+    /// Tell when a recording had no speech
+    pub no_speech: bool,
+    /// Tell when transcription failed
+    pub failed: bool,
+    /// Offer to transcribe the recording again with this (larger) model
+    pub retry_model: Option<String>,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            transcribed: true,
+            no_speech: false,
+            failed: true,
+            retry_model: None,
+        }
+    }
+}
+
+/// How much of the transcription history to keep
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// Entries kept besides pinned ones; older ones are dropped
+    pub max_entries: usize,
+    /// Keep the speaking rate, fillers and pauses of each transcription
+    pub analyze_speech: bool,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: 100,
+            analyze_speech: false,
+        }
+    }
+}
+
+/// Placement and size of the on-screen recording indicator
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct IndicatorConfig {
+    /// `cursor` or a screen corner such as `bottom-right`
+    pub position: indicator::Position,
+    /// Diameter in pixels
+    pub size: u32,
+}
+
+impl Default for IndicatorConfig {
+    fn default() -> Self {
+        Self {
+            position: indicator::Position::default(),
+            size: 16,
+        }
+    }
+}
+
+/// Sounds for recording events; events left out are silent
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct CuesConfig {
+    /// Output device name, or part of it; cues are never played on another device
+    pub device: Option<String>,
+    pub start: Option<CueConfig>,
+    pub stop: Option<CueConfig>,
+    pub error: Option<CueConfig>,
+    pub confirm: Option<CueConfig>,
+}
+
+/// The sound for one event
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct CueConfig {
+    /// WAV file, relative to the config file; a built-in beep without one
+    pub file: Option<PathBuf>,
+    /// Loudness relative to the file or beep, 1.0 plays it unchanged
+    pub volume: f32,
+}
+
+impl Default for CueConfig {
+    fn default() -> Self {
+        Self {
+            file: None,
+            volume: 1.0,
+        }
+    }
+}
+
+/// Settings for lowering the system volume while recording
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct DuckingConfig {
+    /// Fraction of the original volume to lower to, 0.0 mutes
+    pub level: f32,
+    /// Duration of the volume fade in milliseconds
+    pub fade_ms: u64,
+}
+
+impl Default for DuckingConfig {
+    fn default() -> Self {
+        Self {
+            level: 0.3,
+            fade_ms: 200,
+        }
+    }
+}
+
+/// Settings for the translation stage
+#[derive(Debug, Deserialize)]
+#[cfg_attr(not(feature = "translation"), allow(dead_code))]
+pub struct TranslationConfig {
+    /// Base URL of a LibreTranslate-compatible server
+    pub url: String,
+    /// Target language code, e.g. "nl"
+    pub target: String,
+    pub api_key: Option<String>,
+}
+
+/// A push button on a GPIO pin, e.g. on a Raspberry Pi
+#[derive(Debug, Deserialize)]
+pub struct GpioConfig {
+    /// GPIO line number as known to the kernel (BCM numbering on a Pi)
+    pub pin: u32,
+    /// The pin reads low while the button is pressed (button to ground with a pull-up)
+    #[serde(default)]
+    pub active_low: bool,
+}
+
+/// A headset button that starts and stops recording
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct HeadsetConfig {
+    /// Input device name, or part of it; any device with a headset button without one
+    pub device: Option<String>,
+    /// Start recording on one press and stop on the next, instead of recording while
+    /// the button is held; most headset buttons only report a short press
+    pub toggle: bool,
+    /// Also use the play/pause button, which Bluetooth headsets often send instead
+    pub play_pause: bool,
+}
+
+impl Default for HeadsetConfig {
+    fn default() -> Self {
+        Self {
+            device: None,
+            toggle: true,
+            play_pause: false,
+        }
+    }
+}
+
+/// A Bluetooth camera-shutter remote that starts and stops recording
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct RemoteConfig {
+    /// Name of the remote, or part of it, as shown by `voicekb remote pair`
+    pub device: String,
+    /// Start recording on one press and stop on the next, instead of recording while
+    /// the button is held
+    pub toggle: bool,
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        Self {
+            device: "Shutter".to_string(),
+            toggle: true,
+        }
+    }
+}
+
+/// Where transcribed text is sent
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputConfig {
+    /// Type into the focused window, the default
+    Keyboard,
+    /// Type through a virtual uinput keyboard (Linux), for Wayland and the console
+    Uinput,
+    /// Send each transcription as a line of UTF-8 text to `host:port`
+    Tcp(String),
+}
+
+impl Config {
+    /// Load the configuration file, falling back to defaults when it does not exist
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(path)?;
+        serde_json::from_str(&contents)
+            .map_err(|e| anyhow::anyhow!("Invalid config file {:?}: {}", path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_settings_over_top_level() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "vad": {"padding_ms": 100},
+                "beam_size": 5,
+                "vocabulary": ["Kubernetes"],
+                "max_chars": 500,
+                "high_pass_hz": 80,
+                "stop_on_silence_vad": {"silero": "silero_vad.onnx"},
+                "modes": [
+                    {"key": "F9", "vad": true, "beam_size": 2, "vocabulary": []},
+                    {"key": "F10", "max_chars": 100, "denoise": true, "high_pass_hz": 100}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        // The mode's settings win, lists and the whole VAD setting included
+        let dictation = config.modes[0].stages.over(&config.stages);
+        assert_eq!(dictation.beam_size, Some(2));
+        assert_eq!(dictation.vocabulary, Some(Vec::new()));
+        assert!(matches!(dictation.vad, Some(VadConfig::Enabled(true))));
+        assert_eq!(dictation.max_chars, Some(500));
+        assert_eq!(dictation.stream_typing, None);
+        assert_eq!(dictation.high_pass_hz, Some(80.0));
+        assert_eq!(dictation.denoise, None);
+
+        // Those it leaves out follow the top level, tuned VAD thresholds included
+        let chat = config.modes[1].stages.over(&config.stages);
+        assert_eq!(chat.max_chars, Some(100));
+        assert_eq!(chat.beam_size, Some(5));
+        assert_eq!(chat.vocabulary, Some(vec!["Kubernetes".to_string()]));
+        let Some(VadConfig::Tuned(thresholds)) = &chat.vad else {
+            panic!("expected the top-level VAD thresholds, got {:?}", chat.vad);
+        };
+        assert_eq!(thresholds.padding_ms, Some(100));
+        assert_eq!(thresholds.max_pause_ms, None);
+
+        // Preprocessing too, the silence detector included
+        assert_eq!(chat.denoise, Some(true));
+        assert_eq!(chat.high_pass_hz, Some(100.0));
+        assert!(matches!(chat.stop_on_silence_vad, Some(SilenceVadConfig::Silero(_))));
+    }
+}
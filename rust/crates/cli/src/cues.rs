@@ -0,0 +1,78 @@
+use crate::config::{CueConfig, CuesConfig};
+use anyhow::Result;
+use audio::{Cue, CuePlayer};
+use std::{path::Path, time::Duration};
+
+/// Something that happened to a recording, announced with a sound
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Start,
+    Stop,
+    Error,
+    /// The text was typed
+    Confirm,
+}
+
+impl Event {
+    /// Frequency and length of the built-in beep
+    fn beep(self) -> (f32, Duration) {
+        match self {
+            Self::Start => (880.0, Duration::from_millis(80)),
+            Self::Stop => (660.0, Duration::from_millis(80)),
+            Self::Error => (220.0, Duration::from_millis(250)),
+            Self::Confirm => (1320.0, Duration::from_millis(60)),
+        }
+    }
+}
+
+/// The configured sound for each event
+#[derive(Clone)]
+pub struct Cues {
+    player: CuePlayer,
+    start: Option<Cue>,
+    stop: Option<Cue>,
+    error: Option<Cue>,
+    confirm: Option<Cue>,
+}
+
+impl Cues {
+    /// Load the cue files, relative to `base_dir`
+    pub fn new(config: &CuesConfig, base_dir: &Path) -> Result<Self> {
+        let load = |cue: &Option<CueConfig>, event: Event| -> Result<Option<Cue>> {
+            let Some(cue) = cue else {
+                return Ok(None);
+            };
+            let sound = match &cue.file {
+                Some(file) => Cue::load(base_dir.join(file))?,
+                None => {
+                    let (frequency, duration) = event.beep();
+                    Cue::tone(frequency, duration)
+                }
+            };
+            Ok(Some(sound.volume(cue.volume)))
+        };
+
+        Ok(Self {
+            player: CuePlayer::new(config.device.as_deref()),
+            start: load(&config.start, Event::Start)?,
+            stop: load(&config.stop, Event::Stop)?,
+            error: load(&config.error, Event::Error)?,
+            confirm: load(&config.confirm, Event::Confirm)?,
+        })
+    }
+
+    /// Play the sound for `event`, if it has one
+    pub fn play(&self, event: Event) {
+        let cue = match event {
+            Event::Start => &self.start,
+            Event::Stop => &self.stop,
+            Event::Error => &self.error,
+            Event::Confirm => &self.confirm,
+        };
+        if let Some(cue) = cue {
+            if let Err(e) = self.player.play(cue) {
+                eprintln!("Failed to play sound: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use anyhow::Result;
+use std::{path::PathBuf, time::Duration};
+
+const GPIO_ROOT: &str = "/sys/class/gpio";
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// A level must hold this long before it counts, to ignore contact bounce
+const DEBOUNCE: Duration = Duration::from_millis(30);
+
+/// Watch a button on a GPIO pin through the sysfs interface, calling `callback`
+/// with `true` when it is pressed and `false` when it is released
+///
+/// Blocks for as long as the pin can be read.
+pub fn watch<F>(pin: u32, active_low: bool, mut callback: F) -> Result<()>
+where
+    F: FnMut(bool),
+{
+    let dir = PathBuf::from(GPIO_ROOT).join(format!("gpio{}", pin));
+    if !dir.exists() {
+        std::fs::write(PathBuf::from(GPIO_ROOT).join("export"), pin.to_string())
+            .map_err(|e| anyhow::anyhow!("Failed to export GPIO {}: {}", pin, e))?;
+        // udev needs a moment to make the new files accessible
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    std::fs::write(dir.join("direction"), "in")
+        .map_err(|e| anyhow::anyhow!("Failed to configure GPIO {} as input: {}", pin, e))?;
+
+    let value_path = dir.join("value");
+    let read = || -> Result<bool> {
+        let value = std::fs::read_to_string(&value_path)
+            .map_err(|e| anyhow::anyhow!("Failed to read GPIO {}: {}", pin, e))?;
+        Ok((value.trim() == "1") != active_low)
+    };
+
+    let mut pressed = read()?;
+    let mut stable_for = Duration::ZERO;
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+        if read()? == pressed {
+            stable_for = Duration::ZERO;
+            continue;
+        }
+        stable_for += POLL_INTERVAL;
+        if stable_for >= DEBOUNCE {
+            pressed = !pressed;
+            stable_for = Duration::ZERO;
+            callback(pressed);
+        }
+    }
+}
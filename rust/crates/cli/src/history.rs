@@ -0,0 +1,299 @@
+use anyhow::Result;
+use clap::{value_parser, Arg, ArgMatches, Command};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{bundled_paths, output, output::Output, speech::SpeechStats};
+
+/// Kept next to the config file
+pub const HISTORY_NAME: &str = "voicekb-history.json";
+
+/// A transcription that was typed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub id: u64,
+    /// Seconds since the Unix epoch
+    pub time: u64,
+    pub text: String,
+    /// The text as said, when fillers were left out of `text`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<String>,
+    /// Pinned entries are never pruned
+    #[serde(default)]
+    pub pinned: bool,
+    /// How it was spoken, when `analyze_speech` is on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speech: Option<SpeechStats>,
+}
+
+/// Typed transcriptions, oldest first, stored as a JSON file
+pub struct History {
+    path: PathBuf,
+    entries: Vec<Entry>,
+}
+
+impl History {
+    /// Read the history file, which is empty when it does not exist
+    pub fn load<P: Into<PathBuf>>(path: P) -> Result<Self> {
+        let path = path.into();
+        let entries = if path.exists() {
+            let contents = std::fs::read_to_string(&path)?;
+            serde_json::from_str(&contents)
+                .map_err(|e| anyhow::anyhow!("Invalid history file {:?}: {}", path, e))?
+        } else {
+            Vec::new()
+        };
+        Ok(Self { path, entries })
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// The entry with `id`, or the newest one without an id
+    pub fn get(&self, id: Option<u64>) -> Result<&Entry> {
+        match id {
+            Some(id) => self.entries.iter().find(|entry| entry.id == id),
+            None => self.entries.last(),
+        }
+        .ok_or_else(|| missing(id))
+    }
+
+    /// Add `text`, said as `raw` and spoken as `speech`, as the newest entry, dropping the
+    /// oldest unpinned entries beyond `max_entries`, and return its id
+    pub fn push(
+        &mut self,
+        text: &str,
+        raw: Option<String>,
+        speech: Option<SpeechStats>,
+        max_entries: usize,
+    ) -> u64 {
+        let id = self.entries.iter().map(|entry| entry.id + 1).max().unwrap_or(1);
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        self.entries.push(Entry {
+            id,
+            time,
+            text: text.to_string(),
+            raw,
+            pinned: false,
+            speech,
+        });
+
+        let mut excess = self.entries.len().saturating_sub(max_entries);
+        self.entries.retain(|entry| {
+            let drop = excess > 0 && !entry.pinned && entry.id != id;
+            excess -= drop as usize;
+            !drop
+        });
+        id
+    }
+
+    /// Remove the entry with `id`
+    pub fn remove(&mut self, id: u64) -> Result<Entry> {
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| entry.id == id)
+            .ok_or_else(|| missing(Some(id)))?;
+        Ok(self.entries.remove(index))
+    }
+
+    /// Pin or unpin the entry with `id`
+    pub fn set_pinned(&mut self, id: u64, pinned: bool) -> Result<()> {
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.id == id)
+            .ok_or_else(|| missing(Some(id)))?;
+        entry.pinned = pinned;
+        Ok(())
+    }
+
+    /// Write the history file
+    pub fn save(&self) -> Result<()> {
+        let contents = serde_json::to_string_pretty(&self.entries)?;
+        std::fs::write(&self.path, contents)
+            .map_err(|e| anyhow::anyhow!("Failed to write history file {:?}: {}", self.path, e))
+    }
+}
+
+fn missing(id: Option<u64>) -> anyhow::Error {
+    match id {
+        Some(id) => anyhow::anyhow!("No history entry {}", id),
+        None => anyhow::anyhow!("The history is empty"),
+    }
+}
+
+/// Add a typed transcription, what was said and how, to the history file at `path`
+pub fn record(
+    path: &Path,
+    text: &str,
+    raw: Option<String>,
+    speech: Option<SpeechStats>,
+    max_entries: usize,
+) -> Result<()> {
+    let mut history = History::load(path)?;
+    history.push(text, raw, speech, max_entries);
+    history.save()
+}
+
+/// The `history` subcommand
+pub fn command() -> Command {
+    let id = |required: bool| {
+        Arg::new("id")
+            .value_name("ID")
+            .help(if required { "Entry number" } else { "Entry number; the newest without one" })
+            .value_parser(value_parser!(u64))
+            .required(required)
+    };
+    Command::new("history")
+        .about("List and act on recent transcriptions (enable `history` in the config)")
+        .after_help(
+            "Entries keep only the text, not the recording, so they can't be transcribed \
+             again; the `retry_model` notification button does that right after recording.",
+        )
+        .subcommand_required(true)
+        .subcommand(Command::new("list").about("Show the history, newest last"))
+        .subcommand(
+            Command::new("stats")
+                .about("Sum up the speaking rate, fillers and pauses (with `analyze_speech`)"),
+        )
+        .subcommand(Command::new("copy").about("Copy an entry to the clipboard").arg(id(false)))
+        .subcommand(
+            Command::new("type")
+                .about("Type an entry into the focused window again")
+                .arg(id(false))
+                .arg(
+                    Arg::new("delay")
+                        .long("delay")
+                        .value_name("SECONDS")
+                        .help("Wait before typing, to switch to another window")
+                        .value_parser(value_parser!(u64))
+                        .default_value("0"),
+                ),
+        )
+        .subcommand(Command::new("delete").about("Remove an entry").arg(id(true)))
+        .subcommand(Command::new("pin").about("Keep an entry when pruning").arg(id(true)))
+        .subcommand(Command::new("unpin").about("Let an entry be pruned again").arg(id(true)))
+}
+
+/// The entry number given to a subcommand, if any
+fn id(args: &ArgMatches) -> Option<u64> {
+    args.get_one::<u64>("id").copied()
+}
+
+/// Run the `history` subcommand
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let (_, config_path) = bundled_paths()?;
+    let mut history = History::load(config_path.with_file_name(HISTORY_NAME))?;
+
+    match matches.subcommand() {
+        Some(("list", _)) => {
+            for entry in history.entries() {
+                let pin = if entry.pinned { "📌" } else { "  " };
+                println!("{:>4} {} {}", entry.id, pin, entry.text);
+                if let Some(raw) = &entry.raw {
+                    println!("        💬 {}", raw);
+                }
+                if let Some(speech) = &entry.speech {
+                    println!("        🗣️  {}", speech);
+                }
+            }
+        }
+        Some(("stats", _)) => {
+            let mut total = SpeechStats::default();
+            let mut utterances = 0;
+            for speech in history.entries().iter().filter_map(|entry| entry.speech.as_ref()) {
+                total.add(speech);
+                utterances += 1;
+            }
+            if utterances == 0 {
+                println!("No analyzed entries, turn on `analyze_speech` in `history`");
+                return Ok(());
+            }
+            let minutes = total.speaking_ms as f64 / 60_000.0;
+            println!(
+                "🗣️  {} utterances, {} words in {:.1} minutes",
+                utterances, total.words, minutes
+            );
+            println!("   {}", total);
+            println!(
+                "   {:.1} fillers per 100 words, {:.1} pauses per minute",
+                total.filler_count() as f64 * 100.0 / total.words.max(1) as f64,
+                total.pauses as f64 / minutes.max(1.0 / 60.0)
+            );
+        }
+        Some(("copy", args)) => {
+            output::copy_to_clipboard(&history.get(id(args))?.text)?;
+            println!("📋 Copied to the clipboard");
+        }
+        Some(("type", args)) => {
+            let text = history.get(id(args))?.text.clone();
+            let delay = *args.get_one::<u64>("delay").unwrap();
+            std::thread::sleep(Duration::from_secs(delay));
+            Output::new(None)?.send(&text)?;
+        }
+        Some(("delete", args)) => {
+            let entry = history.remove(id(args).unwrap())?;
+            history.save()?;
+            println!("🗑️  Deleted \"{}\"", entry.text);
+        }
+        Some((name @ ("pin" | "unpin"), args)) => {
+            history.set_pinned(id(args).unwrap(), name == "pin")?;
+            history.save()?;
+        }
+        _ => unreachable!("subcommand is required"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(history: &History) -> Vec<u64> {
+        history.entries().iter().map(|entry| entry.id).collect()
+    }
+
+    #[test]
+    fn test_pruning_keeps_pinned_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(HISTORY_NAME);
+        let mut history = History::load(&path).unwrap();
+        for text in ["one", "two", "three"] {
+            history.push(text, None, None, 3);
+        }
+        history.set_pinned(1, true).unwrap();
+
+        // The oldest unpinned entry goes once the limit is reached
+        assert_eq!(history.push("four", None, None, 3), 4);
+        assert_eq!(ids(&history), [1, 3, 4]);
+        assert_eq!(history.push("five", None, None, 3), 5);
+        assert_eq!(ids(&history), [1, 4, 5]);
+
+        // With only pinned entries left to drop, the newest stays beyond the limit
+        history.set_pinned(4, true).unwrap();
+        history.push("six", None, None, 2);
+        assert_eq!(ids(&history), [1, 4, 6]);
+
+        history.set_pinned(1, false).unwrap();
+        history.push("seven", None, None, 3);
+        assert_eq!(ids(&history), [4, 6, 7]);
+
+        assert_eq!(history.remove(6).unwrap().text, "six");
+        assert!(history.remove(6).is_err());
+        assert!(history.set_pinned(1, true).is_err());
+        assert_eq!(history.get(None).unwrap().text, "seven");
+
+        history.save().unwrap();
+        let saved = History::load(&path).unwrap();
+        assert_eq!(ids(&saved), [4, 7]);
+        assert!(saved.get(Some(4)).unwrap().pinned);
+    }
+}
@@ -1,69 +1,453 @@
+mod batch;
+mod config;
+mod cues;
+mod gpio;
+mod history;
+mod models;
+mod notify;
+mod output;
+#[cfg(feature = "ble")]
+mod remote;
+mod review;
+mod soak;
+mod speech;
+mod trace;
+
 use anyhow::Result;
-use audio::{resample::resample_wav_file, SimpleRecorder};
-use enigo::{Enigo, Keyboard, Settings};
-use keyctl::{listen, Key};
+use clap::{value_parser, Arg, ArgMatches, Command};
+use audio::{
+    manifest_path, mic_permission, repair_wav_file, request_mic_permission, Ducker,
+    MicPermission, RecorderEvent, SimpleRecorder, VadBackend,
+};
+use indicator::{Indicator, KeyboardLed, State};
+use keyctl::{listen_keys, Key, PassThroughStats};
 use std::{
-    env,
-    path::PathBuf,
+    collections::HashMap,
+    env, fmt,
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc, Mutex,
+        mpsc, Arc, Mutex,
     },
     time::{Duration, Instant},
 };
-use transcribe::{load_wav_as_float, Transcriber};
+use notify::Action;
+use output::Output;
+use review::{Rerecording, Review};
+use speech::SpeechStats;
+use trace::Tracer;
+use cues::{Cues, Event};
+use config::{
+    Config, HeadsetConfig, ModeConfig, NotificationConfig, RemoteConfig, SilenceVadConfig,
+    StageConfig, TranslationConfig, VadConfig, CONFIG_NAME,
+};
+#[cfg(feature = "translation")]
+use transcribe::LibreTranslate;
+use transcribe::{
+    load_wav_resampled, pcm_to_whisper, AbortHandle, Fillers, Grammar, Hints, ModelInfo,
+    ModelManager, NewSegment, Postprocess, Segment, Squelch, TextFilter, TranscribeOptions,
+    Transcriber, TranscriptionOutcome, Translator, Vocabulary,
+};
 
 // Configuration constants
-const MODEL_NAME: &str = "ggml-base.en.bin";
+const DEFAULT_MODEL: &str = "base.en";
+const MIN_SEGMENT_CONFIDENCE: f32 = 0.5;
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const TEMP_RECORDING: &str = "temp_recording.wav";
+const RECOVERED_RECORDING: &str = "recovered_recording.wav";
+/// Quiet kept around speech when trimming silence, 200ms at 16kHz
+const TRIM_PADDING: usize = 3200;
 
 fn main() -> Result<()> {
+    let command = Command::new("voicekb")
+        .about("Push-to-talk voice typing")
+        .subcommand(models::command())
+        .subcommand(batch::command())
+        .subcommand(history::command())
+        .subcommand(soak::command())
+        .subcommand(
+            Command::new("info")
+                .visible_alias("doctor")
+                .about("Show what this build was compiled with and whether it can record")
+                .arg(
+                    Arg::new("grab_latency")
+                        .long("grab-latency")
+                        .value_name("SECONDS")
+                        .help(
+                            "Grab the keyboard for this long and report the delay it adds \
+                             to keys typed meanwhile",
+                        )
+                        .value_parser(value_parser!(u64)),
+                ),
+        );
+    #[cfg(feature = "ble")]
+    let command = command.subcommand(remote::command());
+    let matches = command.get_matches();
+
+    match matches.subcommand() {
+        Some(("model", args)) => models::run(args),
+        Some(("batch", args)) => batch::run(args),
+        Some(("history", args)) => history::run(args),
+        Some(("soak", args)) => soak::run(args),
+        #[cfg(feature = "ble")]
+        Some(("remote", args)) => remote::run(args),
+        Some(("info", args)) => {
+            print_build_info();
+            measure_grab_latency(args)
+        }
+        _ => run(),
+    }
+}
+
+fn run() -> Result<()> {
     println!("🎤 Voice Keyboard CLI");
-    println!("Press and hold Quote key to record audio...");
 
-    // Model path based on build type
-    let model_path = if cfg!(debug_assertions) {
-        // Debug build: use repo models directory
-        PathBuf::from("../../models").join(MODEL_NAME)
+    let (model_dir, config_path) = bundled_paths()?;
+    let config = Config::load(&config_path)?;
+    let translation = Arc::new(config.translation.as_ref().and_then(translator));
+
+    recover_leftover_recording();
+    check_mic_permission();
+
+    let mode_configs = if config.modes.is_empty() {
+        vec![ModeConfig {
+            key: Key::Quote,
+            model: None,
+            language: None,
+            languages: Vec::new(),
+            translate: false,
+            scan_codes: false,
+            phrases: Vec::new(),
+            repeat_phrases: false,
+            notifications: None,
+            stages: StageConfig::default(),
+        }]
     } else {
-        // Release build: use executable directory
-        let exe_dir = env::current_exe()?.parent().unwrap().to_path_buf();
-        exe_dir.join("whisper-cpp").join(MODEL_NAME)
+        config.modes.clone()
     };
 
-    if !model_path.exists() {
-        return Err(anyhow::anyhow!(
-            "Model file not found: {:?}\n\
-            Please ensure the Whisper model is available.",
-            model_path
-        ));
+    // Initialize one transcriber per distinct model. Modes without a model of their
+    // own share the top-level model, which follows changes to the config file.
+    let default_model = config.model.clone().unwrap_or_else(|| DEFAULT_MODEL.to_string());
+    let use_gpu = config
+        .use_gpu
+        .unwrap_or(!transcribe::compiled_gpu_backends().is_empty());
+    let mut transcribers: HashMap<Option<String>, Transcriber> = HashMap::new();
+    // Modes telling the end of speech with the same Silero model share it
+    let mut silence_vads: HashMap<PathBuf, VadBackend> = HashMap::new();
+    // Modes without an output of their own share the top-level one
+    let output = Arc::new(Mutex::new(Output::new(config.stages.output.as_ref())?));
+    let mut modes = Vec::with_capacity(mode_configs.len());
+    for mode in mode_configs {
+        let model_name = mode.model.clone().unwrap_or_else(|| default_model.clone());
+
+        let transcriber = match transcribers.get(&mode.model) {
+            Some(transcriber) => transcriber.clone(),
+            None => {
+                let model_path = resolve_model(&model_dir, &model_name)?;
+                let info = ModelInfo::read(&model_path)?;
+                println!(
+                    "📚 Loading Whisper model {} ({}, {})...",
+                    model_name,
+                    info.size(),
+                    info.quantization()
+                );
+                let transcriber =
+                    Transcriber::new_with_gpu(&model_path, use_gpu, config.gpu_device)?;
+                println!("✅ Model loaded successfully");
+                println!("⚡ Backend: {}", transcriber.backend_info());
+                transcribers.insert(mode.model.clone(), transcriber.clone());
+                transcriber
+            }
+        };
+
+        // The mode's settings take precedence over the top-level ones
+        let stages = mode.stages.over(&config.stages);
+        let mut mode_options = transcribe_options(&stages, &config_path)?.translate(mode.translate);
+        // A layout whose language isn't known leaves it to detection
+        let language_from_layout = mode.language.as_deref() == Some("layout");
+        match mode.language.as_deref() {
+            Some("auto" | "layout") => mode_options = mode_options.detect_language(),
+            Some(language) => mode_options = mode_options.language(language),
+            None => {}
+        }
+        if !mode.languages.is_empty() {
+            mode_options = mode_options.detect_language().languages(mode.languages.clone());
+        }
+        let language = if mode.languages.is_empty() {
+            mode.language.clone().unwrap_or_else(|| "en".to_string())
+        } else {
+            mode.languages.join("/")
+        };
+        // The layout's language is only known once recording, so it counts as detected
+        let checked = !matches!(language.as_str(), "en" | "auto" | "layout");
+        if checked && !transcriber.is_multilingual() {
+            eprintln!(
+                "⚠️  {} is English-only and can't transcribe {}; use a multilingual model \
+                 such as base",
+                model_name, language
+            );
+        }
+        if !mode.phrases.is_empty() {
+            let grammar = Grammar::new(mode.phrases.clone()).repeat(mode.repeat_phrases);
+            mode_options = mode_options.grammar(grammar);
+        }
+
+        println!(
+            "Press and hold {:?} key to record audio ({}, {}{})...",
+            mode.key,
+            model_name,
+            language,
+            if mode.translate { " → en" } else { "" }
+        );
+        if mode.scan_codes && !cfg!(windows) {
+            eprintln!("⚠️  scan_codes is only supported on Windows, typing normally");
+        }
+        if !mode.phrases.is_empty() {
+            println!("🎯 Command mode: {}", mode.phrases.join(", "));
+        }
+        let input_devices = stages.input_devices.clone().unwrap_or_default();
+        let system_audio = stages.system_audio.unwrap_or(false);
+        if system_audio {
+            println!("🔊 Recording what the computer plays instead of the microphone");
+        }
+        let device_id = stages.input_device.as_deref().filter(|_| input_devices.is_empty());
+        // Outputs to capture and devices on another host are looked up by the recorder
+        let raw_device_id = system_audio || config.audio_host.is_some();
+        let device_id = device_id.filter(|_| !raw_device_id).and_then(|name| {
+            match audio::find_input_device(name) {
+                Ok(device_name) => {
+                    println!("🎙️  Recording from {}", device_name);
+                    Some(device_name)
+                }
+                Err(e) => {
+                    eprintln!("⚠️  {}, recording from the default microphone", e);
+                    None
+                }
+            }
+        });
+        let silence_vad = match &stages.stop_on_silence_vad {
+            Some(SilenceVadConfig::Silero(model)) => {
+                let path = config_path.parent().unwrap_or(Path::new(".")).join(model);
+                silence_vads.entry(path).or_insert_with_key(|path| silero_vad(path)).clone()
+            }
+            Some(SilenceVadConfig::Energy) | None => VadBackend::Energy,
+        };
+        let denoise = stages.denoise.unwrap_or(false);
+        if denoise {
+            if cfg!(feature = "denoise") {
+                println!("🔇 Suppressing background noise");
+            } else {
+                eprintln!(
+                    "⚠️  Built without the denoise feature, recording without noise suppression"
+                );
+            }
+        }
+        if let Some(cutoff_hz) = stages.high_pass_hz {
+            println!("🔉 Cutting rumble below {}Hz from recordings", cutoff_hz);
+        }
+        if let Some(target_db) = stages.auto_gain_db {
+            println!("🎚️  Adjusting the recording level to {} dBFS peaks", target_db);
+        }
+        if let Some(pre_roll_ms) = stages.pre_roll_ms {
+            println!("⏪ Starting recordings {}ms before the key press", pre_roll_ms);
+        }
+        let mode_output = match &mode.stages.output {
+            Some(config) => Arc::new(Mutex::new(Output::new(Some(config))?)),
+            None => Arc::clone(&output),
+        };
+        modes.push(Mode {
+            key: mode.key,
+            transcriber,
+            options: mode_options,
+            device_id: if raw_device_id { stages.input_device.clone() } else { device_id },
+            input_devices,
+            system_audio,
+            scan_codes: mode.scan_codes,
+            notifications: mode.notifications.clone().or_else(|| config.notifications.clone()),
+            output: mode_output,
+            paste_above: stages.paste_above,
+            max_chars: stages.max_chars,
+            stop_on_silence: stages.stop_on_silence_ms.map(Duration::from_millis),
+            language_from_layout,
+            // Typing as segments are decoded needs the text as is, not translated as a whole
+            stream_typing: stages.stream_typing.unwrap_or(false) && translation.is_none(),
+            remove_fillers: stages.remove_fillers.unwrap_or(false),
+            silence_vad,
+            denoise,
+            high_pass_hz: stages.high_pass_hz,
+            remove_dc_offset: stages.remove_dc_offset.unwrap_or(false),
+            auto_gain_db: stages.auto_gain_db,
+            trim_silence_db: stages.trim_silence_db,
+            pre_roll: Duration::from_millis(stages.pre_roll_ms.unwrap_or(0)),
+        });
     }
 
-    // Initialize transcriber
-    println!("📚 Loading Whisper model...");
-    let transcriber = Transcriber::new(&model_path)?;
-    println!("✅ Model loaded successfully");
+    if let Some(transcriber) = transcribers.remove(&None) {
+        watch_model_config(config_path.clone(), model_dir.clone(), default_model, transcriber);
+    }
+
+    let ducker = config.ducking.as_ref().map(|ducking| {
+        println!("🔉 Ducking system volume to {:.0}% while recording", ducking.level * 100.0);
+        Arc::new(Mutex::new(Ducker::new(ducking.level, Duration::from_millis(ducking.fade_ms))))
+    });
 
     // Create shared state
     let is_recording = Arc::new(AtomicBool::new(false));
-    let recorder = Arc::new(Mutex::new(SimpleRecorder::new()));
-    let enigo = Arc::new(Mutex::new(
-        Enigo::new(&Settings::default()).expect("Failed to create Enigo instance"),
-    ));
+    let mut recorder = SimpleRecorder::new();
+    recorder.set_follow_default(config.follow_default_input);
+    if let Some(host) = &config.audio_host {
+        match audio::get_host(Some(host)) {
+            Ok(_) => {
+                println!("🎛️  Recording through the {} audio host", host);
+                recorder.set_host(Some(host.clone()));
+            }
+            Err(e) => eprintln!("⚠️  {}, recording through the default audio host", e),
+        }
+    }
+    recorder.set_buffer_frames(config.buffer_frames);
+    if let Some(max_memory_mb) = config.max_memory_mb.filter(|_| !config.record_to_file) {
+        recorder.set_spill_to_disk(Some(TEMP_RECORDING.into()), max_memory_mb * 1024 * 1024);
+    }
+    if config.max_recording_secs.is_some() || config.max_recording_mb.is_some() {
+        recorder.set_recording_limit(
+            config.max_recording_secs.map(Duration::from_secs),
+            config.max_recording_mb.map(|max_mb| max_mb * 1024 * 1024),
+        );
+    }
+    // Hold the longest pre-roll of any mode while idle; each recording keeps its own
+    let idle_pre_roll = modes.iter().map(|mode| mode.pre_roll).max().unwrap_or_default();
+    if config.keep_mic_open || !idle_pre_roll.is_zero() {
+        recorder.set_keep_open(true);
+        modes[0].prepare(&mut recorder);
+        recorder.set_pre_roll(idle_pre_roll);
+        match recorder.open(modes[0].input_device().as_deref()) {
+            Ok(()) => println!("🎙️  Keeping the microphone open between recordings"),
+            Err(e) => eprintln!("⚠️  Failed to open the microphone: {}", e),
+        }
+    }
+    let recorder = Mutex::new(recorder);
+    let indicator = config.indicator.as_ref().and_then(|indicator| {
+        Indicator::new(indicator.position, indicator.size)
+            .map_err(|e| eprintln!("⚠️  Recording indicator unavailable: {}", e))
+            .ok()
+    });
+    let keyboard_led = config.keyboard_led.and_then(|led| {
+        KeyboardLed::new(led)
+            .map_err(|e| eprintln!("⚠️  Keyboard LED unavailable: {}", e))
+            .ok()
+    });
+    // Put the volume and the keyboard LED back on Ctrl-C or SIGTERM, which skip `Drop`
+    let exit_ducker = ducker.clone();
+    let exit_led = keyboard_led.clone();
+    let restored = ctrlc::set_handler(move || {
+        if let Some(Ok(mut ducker)) = exit_ducker.as_deref().map(Mutex::lock) {
+            if let Err(e) = ducker.restore_now() {
+                eprintln!("Failed to restore system volume: {}", e);
+            }
+        }
+        if let Some(led) = &exit_led {
+            led.restore();
+        }
+        std::process::exit(130);
+    });
+    if let Err(e) = restored {
+        eprintln!("⚠️  Failed to handle Ctrl-C, the volume and LED stay as they are: {}", e);
+    }
+    // Shows the state on the indicator and keyboard LED, if there are any
+    let show = move |state: State| {
+        if let Some(indicator) = &indicator {
+            indicator.set(state);
+        }
+        if let Some(led) = &keyboard_led {
+            led.set(state);
+        }
+    };
+    let cues = config
+        .cues
+        .as_ref()
+        .map(|cues| Cues::new(cues, config_path.parent().unwrap_or(Path::new("."))))
+        .transpose()?;
+    if let Some(device) = config.cues.as_ref().and_then(|cues| cues.device.as_deref()) {
+        match audio::find_output_device(device) {
+            Ok(device_name) => println!("🔈 Playing cues on {}", device_name),
+            Err(e) => eprintln!("⚠️  {}, cues are silent until it is connected", e),
+        }
+    }
+    // Plays the sound for an event, if there is one
+    let cue = move |event: Event| {
+        if let Some(cues) = &cues {
+            cues.play(event);
+        }
+    };
+
+    // Typed transcriptions are added to this file, keeping this many unpinned ones
+    let history_file = config.history.as_ref().map(|history| {
+        (config_path.with_file_name(history::HISTORY_NAME), history.max_entries)
+    });
+    let analyze_speech = config.history.as_ref().is_some_and(|history| history.analyze_speech);
+
+    // Holds the last utterance typed, so its last sentence can be recorded again
+    let review = config.retry_phrase.as_deref().map(|phrase| {
+        println!("🔁 Say \"{}\" to record the last sentence again", phrase);
+        Arc::new(Mutex::new(Review::new(phrase)))
+    });
+
+    // Times the stages of every utterance, if asked to
+    let tracer = config.trace_file.as_ref().and_then(|trace_file| {
+        let path = config_path.parent().unwrap_or(Path::new(".")).join(trace_file);
+        match Tracer::create(path.clone()) {
+            Ok(tracer) => {
+                println!("⏱️  Tracing utterances to {}", path.display());
+                Some(Arc::new(tracer))
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                None
+            }
+        }
+    });
 
+    let record_to_file = config.record_to_file;
     let recording_start_time = Arc::new(Mutex::new(None::<Instant>));
+    // Cancels the transcription running in the background, if any
+    let transcription: Mutex<Option<AbortHandle>> = Mutex::new(None);
 
     // Clone references for the callback
     let is_recording_clone = Arc::clone(&is_recording);
-    let recorder_clone = Arc::clone(&recorder);
-    let enigo_clone = Arc::clone(&enigo);
-    let transcriber = Arc::new(transcriber);
-    let transcriber_clone = Arc::clone(&transcriber);
     let recording_start_clone = Arc::clone(&recording_start_time);
 
-    if let Err(error) = listen(Key::Quote, true, move |is_pressed| {
+    let keys = modes.iter().map(|mode| mode.key).collect();
+    let first_key = modes[0].key;
+    // Mode of the key that started the current recording
+    let mut active_mode = None;
+    // Language of the keyboard layout when the current recording started
+    let mut layout_language = None;
+    // Stages of the current utterance so far, when tracing
+    let mut utterance_trace = None;
+    // Presses and releases of the keys or buttons, or `None` once they can't be read
+    let (inputs, input_receiver) = mpsc::channel::<Option<(Key, bool)>>();
+    // A recording that ends in silence is stopped like its key was released
+    let silence_inputs = inputs.clone();
+
+    let mut on_key = move |key: Key, is_pressed: bool| {
         if is_pressed {
             // Key pressed - start recording
             if !is_recording_clone.load(Ordering::SeqCst) {
+                // A new recording replaces the transcription still in progress
+                let running = transcription.lock().ok().and_then(|mut current| current.take());
+                if let Some(abort) = running {
+                    abort.abort();
+                }
+
+                utterance_trace = tracer.as_ref().map(Tracer::utterance);
+                active_mode = modes.iter().position(|mode| mode.key == key);
+                layout_language = active_mode
+                    .filter(|&index| modes[index].language_from_layout)
+                    .and_then(|_| keyctl::keyboard_language());
+                if let Some(language) = &layout_language {
+                    println!("⌨️  Keyboard layout language: {}", language);
+                }
                 println!("🔴 Recording started...");
                 is_recording_clone.store(true, Ordering::SeqCst);
 
@@ -73,17 +457,42 @@ fn main() -> Result<()> {
                 }
 
                 // Start recording
-                if let Ok(mut recorder) = recorder_clone.lock() {
-                    let temp_path = PathBuf::from("temp_recording.wav");
-                    if let Err(e) = recorder.start_recording(None, &temp_path, |peak| {
-                        // Optional: Show audio level during recording
-                        let bar_length = (peak.abs() as usize) / 3280; // Scale for display
-                        let bar = "█".repeat(bar_length.min(10));
-                        print!("\r🔊 [{:<10}]", bar);
-                        std::io::Write::flush(&mut std::io::stdout()).unwrap();
-                    }) {
+                if let Ok(mut recorder) = recorder.lock() {
+                    let mode = active_mode.map(|index| &modes[index]);
+                    let device_id = mode.and_then(Mode::input_device);
+                    let device_id = device_id.as_deref();
+                    if let Some(mode) = mode {
+                        mode.prepare(&mut recorder);
+                    }
+                    let silence_inputs = silence_inputs.clone();
+                    let on_event = move |event: RecorderEvent| {
+                        if let RecorderEvent::SpeechEnded { .. }
+                        | RecorderEvent::LimitReached { .. } = event
+                        {
+                            let _ = silence_inputs.send(Some((key, false)));
+                        }
+                        show_recorder_event(event);
+                    };
+                    let started = if record_to_file {
+                        recorder.start_recording_with_events(device_id, TEMP_RECORDING, on_event)
+                    } else {
+                        recorder.start_recording_buffered(device_id, on_event)
+                    };
+                    if let Err(e) = started {
                         eprintln!("Failed to start recording: {}", e);
                         is_recording_clone.store(false, Ordering::SeqCst);
+                        cue(Event::Error);
+                    } else {
+                        if let Some(trace) = utterance_trace.as_mut() {
+                            trace.stage("open microphone");
+                        }
+                        show(State::Recording);
+                        cue(Event::Start);
+                        if let Some(Ok(mut ducker)) = ducker.as_deref().map(Mutex::lock) {
+                            if let Err(e) = ducker.duck() {
+                                eprintln!("Failed to lower system volume: {}", e);
+                            }
+                        }
                     }
                 } else {
                     eprintln!("Failed to acquire recorder lock");
@@ -91,9 +500,17 @@ fn main() -> Result<()> {
                 }
             }
         } else {
-            // Key released - stop recording and transcribe
+            // Key released - stop recording and transcribe with the mode that started it
+            let mode = match active_mode {
+                Some(index) if modes[index].key == key => &modes[index],
+                _ => return,
+            };
             if is_recording_clone.load(Ordering::SeqCst) {
                 is_recording_clone.store(false, Ordering::SeqCst);
+                let mut trace = utterance_trace.take();
+                if let Some(trace) = trace.as_mut() {
+                    trace.stage("record");
+                }
 
                 // Check recording duration
                 let recording_duration = if let Ok(start_time) = recording_start_clone.lock() {
@@ -103,11 +520,31 @@ fn main() -> Result<()> {
                 };
 
                 println!("\n⏹️  Recording stopped");
+                cue(Event::Stop);
 
-                // Stop recording
-                let audio_path = if let Ok(mut recorder) = recorder_clone.lock() {
-                    match recorder.stop_recording() {
-                        Ok(path) => Some(path),
+                if let Some(Ok(mut ducker)) = ducker.as_deref().map(Mutex::lock) {
+                    if let Err(e) = ducker.restore() {
+                        eprintln!("Failed to restore system volume: {}", e);
+                    }
+                }
+
+                // Stop recording and convert to 16kHz mono for Whisper in memory
+                let audio = if let Ok(mut recorder) = recorder.lock() {
+                    let stopped = if record_to_file {
+                        // Long recordings roll over into several files
+                        recorder.stop_recording().map(|_| load_parts(recorder.recorded_files()))
+                    } else {
+                        recorder.stop_recording_buffered().map(|recorded| {
+                            if recorded.files.is_empty() {
+                                return pcm_to_whisper(&recorded.samples, recorded.sample_rate, 1);
+                            }
+                            // Spilled to disk, one file after the other
+                            load_parts(&recorded.files)
+                        })
+                    };
+                    recorder.set_pre_roll(idle_pre_roll);
+                    match stopped {
+                        Ok(audio) => Some(audio),
                         Err(e) => {
                             eprintln!("Failed to stop recording: {}", e);
                             None
@@ -118,107 +555,1156 @@ fn main() -> Result<()> {
                     None
                 };
 
-                if let Some(path) = audio_path {
+                // Set once the transcription runs in the background
+                let mut transcribing = false;
+                if let Some(audio) = audio {
                     // Check if recording is too short (minimum 100ms)
                     if let Some(duration) = recording_duration {
                         if duration < Duration::from_millis(100) {
                             println!("⚠️  Recording too short, skipping transcription");
-                            if let Err(e) = std::fs::remove_file(&path) {
-                                eprintln!("Failed to clean up temp file: {}", e);
-                            }
+                            show(State::Hidden);
                             return;
                         }
                     }
 
                     println!("🔍 Processing audio...");
+                    if let Some(trace) = trace.as_mut() {
+                        trace.stage("stop and resample");
+                    }
 
-                    // Create resampled file path
-                    let resampled_path = PathBuf::from("temp_recording_16khz.wav");
-
-                    // Resample to 16kHz mono for Whisper
-                    match resample_wav_file(&path, &resampled_path, 16000, 1) {
-                        Ok(_) => {
-                            println!("🔄 Audio resampled to 16kHz");
+                    match audio {
+                        Ok(mut audio) => {
+                            if let Some(threshold_db) = mode.trim_silence_db {
+                                trim_silence(&mut audio, threshold_db);
+                                if audio.is_empty() {
+                                    println!("🔇 No speech detected");
+                                    show(State::Hidden);
+                                    return;
+                                }
+                            }
+                            // Typing as segments are decoded would type the retry phrase
+                            let review = review.clone().filter(|_| !mode.stream_typing);
+                            // A recording after the retry phrase follows the sentences
+                            // before the last one
+                            let rerecording = review
+                                .as_ref()
+                                .and_then(|review| review.lock().ok()?.splice(&mut audio));
+                            if rerecording.is_some() {
+                                println!("🔁 Recording the last sentence again");
+                            }
+                            // Pad audio to at least 1.1 seconds (17600 samples at 16kHz) to ensure we exceed 1000ms
+                            let min_samples = 17600; // 1.1 seconds at 16kHz for safety margin
+                            if audio.len() < min_samples {
+                                println!(
+                                    "🔧 Padding audio to minimum length ({} -> {} samples)",
+                                    audio.len(),
+                                    min_samples
+                                );
+                                audio.resize(min_samples, 0.0);
+                            }
+                            let mut options = mode.options.clone();
+                            if let Some(language) = &layout_language {
+                                options = options.language(language.as_str());
+                            }
+                            let mut new_segments = None;
+                            if mode.stream_typing {
+                                let (sender, receiver) = mpsc::channel();
+                                options = options.on_segment(move |segment| {
+                                    let _ = sender.send(segment);
+                                });
+                                new_segments = Some(receiver);
+                            }
+                            // Kept for the notification's retry button
+                            let retry_audio = mode
+                                .notifications
+                                .as_ref()
+                                .and_then(|notifications| notifications.retry_model.as_ref())
+                                .map(|_| audio.clone());
+                            let review_audio = review.as_ref().map(|_| audio.clone());
+                            let (abort, join) =
+                                mode.transcriber.transcribe_cancellable(audio, &options);
+                            if let Ok(mut current) = transcription.lock() {
+                                *current = Some(abort.clone());
+                            }
 
-                            // Load and transcribe resampled audio
-                            match load_wav_as_float(&resampled_path) {
-                                Ok(mut audio) => {
-                                    // Pad audio to at least 1.1 seconds (17600 samples at 16kHz) to ensure we exceed 1000ms
-                                    let min_samples = 17600; // 1.1 seconds at 16kHz for safety margin
-                                    if audio.len() < min_samples {
+                            // Decode off the key listener so the hotkey can cancel it
+                            let mode = mode.clone();
+                            let translation = Arc::clone(&translation);
+                            let output = Arc::clone(&mode.output);
+                            let model_dir = model_dir.clone();
+                            let show = show.clone();
+                            let cue = cue.clone();
+                            let history_file = history_file.clone();
+                            show(State::Transcribing);
+                            std::thread::spawn(move || {
+                                // Ends when the transcription finishes and drops the sender
+                                let streamed = new_segments.map(|receiver| {
+                                    type_new_segments(receiver, &mode, &abort, &output)
+                                });
+                                let mut speech = None;
+                                // The text as said, when fillers were left out of it
+                                let mut raw = None;
+                                let typed = match join.join() {
+                                    Ok(Ok((segments, stats))) => {
+                                        if let Some(trace) = trace.as_mut() {
+                                            trace.stage("transcribe");
+                                        }
                                         println!(
-                                            "🔧 Padding audio to minimum length ({} -> {} samples)",
-                                            audio.len(),
-                                            min_samples
+                                            "⏱️  Decoded {:.1}s of audio in {}ms ({:.2}x real \
+                                             time, {} threads)",
+                                            stats.audio_seconds,
+                                            stats.decode_ms,
+                                            stats.rtf,
+                                            stats.threads
                                         );
-                                        audio.resize(min_samples, 0.0);
-                                    }
-                                    match transcriber_clone.transcribe(&audio) {
-                                        Ok(text) => {
-                                            let trimmed_text = text.trim();
-
-                                            // Check if transcription is empty, whitespace-only, or blank audio
-                                            if trimmed_text.is_empty()
-                                                || trimmed_text == "[BLANK_AUDIO]"
-                                            {
-                                                if trimmed_text == "[BLANK_AUDIO]" {
-                                                    println!("🔇 No speech detected");
-                                                } else {
-                                                    println!("⚠️  No text transcribed");
-                                                }
-                                            } else {
-                                                println!("📝 Transcribed: \"{}\"", trimmed_text);
-
-                                                // Wait a moment before typing
-                                                std::thread::sleep(Duration::from_millis(100));
-
-                                                // Type the transcribed text
-                                                if let Ok(mut enigo) = enigo_clone.lock() {
-                                                    if let Err(e) = enigo.text(trimmed_text) {
-                                                        eprintln!("Failed to type text: {}", e);
-                                                    } else {
-                                                        println!("✅ Text typed successfully");
-                                                    }
-                                                } else {
-                                                    eprintln!("Failed to acquire enigo lock");
-                                                }
+                                        let passed = keyctl::take_pass_through_stats();
+                                        if passed.events > 0 {
+                                            print_pass_through_stats(passed);
+                                        }
+                                        let mut outcome =
+                                            TranscriptionOutcome::from_segments(segments);
+                                        if analyze_speech {
+                                            speech = SpeechStats::measure(outcome.segments());
+                                            if let Some(speech) = &speech {
+                                                println!("🗣️  {}", speech);
                                             }
                                         }
-                                        Err(e) => {
-                                            eprintln!("Transcription failed: {}", e);
+                                        if let (true, TranscriptionOutcome::Speech(segments)) =
+                                            (mode.remove_fillers, &mut outcome)
+                                        {
+                                            raw = speech::remove_fillers(segments);
+                                        }
+                                        match streamed {
+                                            Some(typed) => {
+                                                report_untyped(&outcome, &typed);
+                                                Ok(typed)
+                                            }
+                                            None => match (&review, review_audio) {
+                                                (Some(review), Some(audio)) => type_reviewed(
+                                                    &outcome,
+                                                    &mode,
+                                                    &translation,
+                                                    &output,
+                                                    review,
+                                                    rerecording,
+                                                    audio,
+                                                ),
+                                                _ => type_segments(
+                                                    &outcome,
+                                                    &mode,
+                                                    &translation,
+                                                    &output,
+                                                ),
+                                            },
                                         }
                                     }
+                                    Ok(Err(_)) if abort.is_aborted() => {
+                                        // A new recording is already running
+                                        println!("🚫 Transcription cancelled");
+                                        return;
+                                    }
+                                    Ok(Err(e)) => {
+                                        Err(anyhow::anyhow!("Transcription failed: {}", e))
+                                    }
+                                    Err(_) => Err(anyhow::anyhow!("Transcription thread panicked")),
+                                };
+                                if let Some(mut trace) = trace {
+                                    trace.stage("type");
+                                    trace.finish();
                                 }
-                                Err(e) => {
-                                    eprintln!("Failed to load resampled audio: {}", e);
+                                show(State::Hidden);
+                                match &typed {
+                                    Ok(Some(text)) => {
+                                        cue(Event::Confirm);
+                                        if let Some((path, max_entries)) = &history_file {
+                                            if let Err(e) = history::record(
+                                                path,
+                                                text,
+                                                raw,
+                                                speech,
+                                                *max_entries,
+                                            ) {
+                                                eprintln!("Failed to save history: {}", e);
+                                            }
+                                        }
+                                    }
+                                    Ok(None) => {}
+                                    Err(e) => {
+                                        eprintln!("{}", e);
+                                        cue(Event::Error);
+                                    }
                                 }
-                            }
-
-                            // Clean up resampled file
-                            if let Err(e) = std::fs::remove_file(&resampled_path) {
-                                eprintln!("Failed to clean up resampled file: {}", e);
-                            }
+                                if let Some(notifications) = &mode.notifications {
+                                    let retry = RetryContext {
+                                        audio: retry_audio,
+                                        model_dir,
+                                        translation,
+                                    };
+                                    notify_result(&typed, notifications, &mode, retry, &output);
+                                }
+                                println!("🎤 Ready for next recording...");
+                            });
+                            transcribing = true;
                         }
                         Err(e) => {
-                            eprintln!("Failed to resample audio: {}", e);
+                            eprintln!("Failed to load recorded audio: {}", e);
+                            cue(Event::Error);
                         }
                     }
+                }
 
-                    // Clean up temporary file
-                    if let Err(e) = std::fs::remove_file(&path) {
-                        eprintln!("Failed to clean up temp file: {}", e);
-                    }
+                if !transcribing {
+                    show(State::Hidden);
+                    println!("🎤 Ready for next recording...");
                 }
+            }
+        }
+    };
 
-                println!("🎤 Ready for next recording...");
+    // The keys or buttons are read on a thread of their own, so that the recorder can end
+    // a recording on silence from its writer thread too. The recorder can't leave this one.
+    let (gpio_trigger, headset_trigger, remote_trigger) =
+        (config.gpio_trigger, config.headset_trigger, config.remote_trigger);
+    let listener = std::thread::spawn(move || {
+        let on_input = {
+            let inputs = inputs.clone();
+            move |key: Key, is_pressed: bool| {
+                let _ = inputs.send(Some((key, is_pressed)));
+            }
+        };
+        let result = if let Some(gpio) = &gpio_trigger {
+            // A GPIO button acts as the key of the first mode
+            println!("🔘 Press and hold the button on GPIO {} to record", gpio.pin);
+            gpio::watch(gpio.pin, gpio.active_low, |is_pressed| on_input(first_key, is_pressed))
+        } else if let Some(headset) = &headset_trigger {
+            watch_headset(headset, &is_recording, |is_pressed| on_input(first_key, is_pressed))
+        } else if let Some(remote) = &remote_trigger {
+            watch_remote(remote, &is_recording, |is_pressed| on_input(first_key, is_pressed))
+        } else {
+            listen_keys(keys, true, on_input)
+                .map_err(|error| anyhow::anyhow!("Error listening for key events: {:?}", error))
+        };
+        let _ = inputs.send(None);
+        result
+    });
+
+    while let Ok(Some((key, is_pressed))) = input_receiver.recv() {
+        on_key(key, is_pressed);
+    }
+    listener
+        .join()
+        .map_err(|_| anyhow::anyhow!("The key listener panicked"))?
+}
+
+/// Cut the quiet before and after the speech in 16kHz `audio`
+fn trim_silence(audio: &mut Vec<f32>, threshold_db: f32) {
+    let samples: Vec<i16> =
+        audio.iter().map(|&sample| (sample * i16::MAX as f32) as i16).collect();
+    let speech = audio::speech_bounds(&samples, threshold_db, TRIM_PADDING);
+    if speech.len() < audio.len() {
+        // 16 samples to the millisecond
+        println!("✂️  Trimmed {}ms of silence", (audio.len() - speech.len()) / 16);
+    }
+    audio.truncate(speech.end);
+    audio.drain(..speech.start);
+}
+
+/// Model directory and config file next to the binary (release) or in the repo (debug)
+fn bundled_paths() -> Result<(PathBuf, PathBuf)> {
+    if cfg!(debug_assertions) {
+        // Debug build: use repo models directory
+        Ok((PathBuf::from("../../models"), PathBuf::from("../..").join(CONFIG_NAME)))
+    } else {
+        // Release build: use executable directory
+        let exe_dir = env::current_exe()?.parent().unwrap().to_path_buf();
+        Ok((exe_dir.join("whisper-cpp"), exe_dir.join(CONFIG_NAME)))
+    }
+}
+
+/// Record with a headset button, using the first mode's settings
+///
+/// Blocks for as long as the headset can be read.
+#[cfg(target_os = "linux")]
+fn watch_headset<F>(
+    headset: &HeadsetConfig,
+    is_recording: &AtomicBool,
+    on_press: F,
+) -> Result<()>
+where
+    F: FnMut(bool),
+{
+    if headset.toggle {
+        println!("🎧 Press the headset button to start recording and again to stop");
+    } else {
+        println!("🎧 Press and hold the headset button to record");
+    }
+    keyctl::watch_headset(
+        headset.device.as_deref(),
+        headset.play_pause,
+        toggle_button(headset.toggle, is_recording, on_press),
+    )
+    .map_err(|e| anyhow::anyhow!("Failed to watch headset buttons: {}", e))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn watch_headset<F>(
+    _headset: &HeadsetConfig,
+    _is_recording: &AtomicBool,
+    _on_press: F,
+) -> Result<()>
+where
+    F: FnMut(bool),
+{
+    Err(anyhow::anyhow!("headset_trigger is only supported on Linux"))
+}
+
+/// Record with a Bluetooth shutter remote, using the first mode's settings
+///
+/// Blocks for as long as the remote can be read or waited for.
+#[cfg(target_os = "linux")]
+fn watch_remote<F>(remote: &RemoteConfig, is_recording: &AtomicBool, on_press: F) -> Result<()>
+where
+    F: FnMut(bool),
+{
+    if remote.toggle {
+        println!("📸 Press the remote's button to start recording and again to stop");
+    } else {
+        println!("📸 Press and hold the remote's button to record");
+    }
+    keyctl::watch_remote(&remote.device, toggle_button(remote.toggle, is_recording, on_press))
+        .map_err(|e| anyhow::anyhow!("Failed to watch the remote: {}", e))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn watch_remote<F>(_remote: &RemoteConfig, _is_recording: &AtomicBool, _on_press: F) -> Result<()>
+where
+    F: FnMut(bool),
+{
+    Err(anyhow::anyhow!("remote_trigger is only supported on Linux"))
+}
+
+/// Turn presses of a button into `on_press` calls, either as they are or, with `toggle`,
+/// flipping recording on or off with each press
+#[cfg(target_os = "linux")]
+fn toggle_button<'a, F>(
+    toggle: bool,
+    is_recording: &'a AtomicBool,
+    mut on_press: F,
+) -> impl FnMut(bool) + 'a
+where
+    F: FnMut(bool) + 'a,
+{
+    move |is_pressed| {
+        if !toggle {
+            on_press(is_pressed);
+        } else if is_pressed {
+            // Each press flips recording on or off
+            on_press(!is_recording.load(Ordering::SeqCst));
+        }
+    }
+}
+
+/// Decoding options from the stage settings of the config file or a mode
+fn transcribe_options(stages: &StageConfig, config_path: &Path) -> Result<TranscribeOptions> {
+    let mut options = TranscribeOptions::default();
+    if let Some(prompt) = &stages.initial_prompt {
+        println!("💬 Using initial prompt: \"{}\"", prompt);
+        options = options.initial_prompt(prompt.as_str());
+    }
+    if let Some(vocabulary) = stages.vocabulary.as_ref().filter(|words| !words.is_empty()) {
+        println!("📖 Using vocabulary of {} words", vocabulary.len());
+        options = options.vocabulary(Vocabulary::new(vocabulary.iter().cloned()));
+    }
+    for (text, bias) in stages.token_bias.iter().flatten() {
+        options = options.token_bias(text.as_str(), *bias);
+    }
+    if let Some(pattern) = &stages.suppress_regex {
+        println!("🚫 Never decoding tokens matching {}", pattern);
+        options = options.suppress_regex(pattern.as_str());
+    }
+    if let Some(hints_file) = &stages.hints_file {
+        let hints_path = config_path.parent().unwrap_or(Path::new(".")).join(hints_file);
+        let hints = Hints::load(&hints_path)?;
+        println!("🗣️  Using {} pronunciation hints", hints.entries().len());
+        options = options.hints(hints);
+    }
+    if let Some(phrases) = stages.control_phrases.as_ref().filter(|phrases| !phrases.is_empty()) {
+        options = options.squelch(Squelch::new(phrases));
+    }
+    if let Some(vad) = stages.vad.as_ref().and_then(VadConfig::vad) {
+        println!("🔇 Skipping silence with voice activity detection");
+        options = options.vad(vad);
+    }
+    if let Some(beam_size) = stages.beam_size {
+        println!("🔦 Using beam search with {} beams", beam_size);
+        options = options.beam_search(beam_size, -1.0);
+    }
+    if let Some(temperature) = stages.temperature {
+        options = options.temperature(temperature);
+    }
+    if let Some(increment) = stages.temperature_increment {
+        options = options.temperature_increment(increment);
+    }
+    if let Some(threshold) = stages.entropy_threshold {
+        options = options.entropy_threshold(threshold);
+    }
+    if let Some(threshold) = stages.logprob_threshold {
+        options = options.logprob_threshold(threshold);
+    }
+    if let Some(threshold) = stages.no_speech_threshold {
+        options = options.no_speech_threshold(threshold);
+    }
+    if stages.suppress_non_speech.unwrap_or(false) {
+        options = options.suppress_non_speech(true);
+    }
+    if let Some(steps) = stages.postprocess.as_ref().filter(|steps| !steps.is_empty()) {
+        let postprocess = steps
+            .iter()
+            .fold(Postprocess::new(), |postprocess, step| step.filter(postprocess));
+        options = options.postprocess(postprocess);
+    }
+    Ok(options)
+}
+
+/// A hotkey and the transcriber and options used for recordings it starts
+#[derive(Clone)]
+struct Mode {
+    key: Key,
+    transcriber: Transcriber,
+    options: TranscribeOptions,
+    /// Microphone to record from; the system default without one
+    device_id: Option<String>,
+    /// Microphones in order of preference, which replace `device_id` unless empty
+    input_devices: Vec<String>,
+    /// Record what the computer plays, from the output named like `device_id`
+    system_audio: bool,
+    /// Type with scan codes instead of the configured output
+    #[cfg_attr(not(windows), allow(dead_code))]
+    scan_codes: bool,
+    /// Outcomes that show a desktop notification
+    notifications: Option<NotificationConfig>,
+    /// Where the text goes
+    output: Arc<Mutex<Output>>,
+    /// Paste texts longer than this through the clipboard instead of typing them
+    paste_above: Option<usize>,
+    /// Type each segment as soon as it is decoded
+    stream_typing: bool,
+    /// Leave fillers out of the typed text
+    remove_fillers: bool,
+    /// Ask before typing texts longer than this
+    max_chars: Option<usize>,
+    /// Stop recording once speech is followed by this much silence
+    stop_on_silence: Option<Duration>,
+    /// Transcribe in the language of the keyboard layout at the start of a recording
+    language_from_layout: bool,
+    /// Detector that tells when speech ended for `stop_on_silence`
+    silence_vad: VadBackend,
+    /// Remove background noise from recordings
+    denoise: bool,
+    /// Cut rumble below this frequency in Hz from recordings
+    high_pass_hz: Option<f32>,
+    /// Remove the DC offset of recordings
+    remove_dc_offset: bool,
+    /// Bring the peaks of speech in recordings to this level in dBFS
+    auto_gain_db: Option<f32>,
+    /// Cut the quiet around speech quieter than this level in dBFS before transcribing
+    trim_silence_db: Option<f32>,
+    /// Audio from before the key press to start recordings with
+    pre_roll: Duration,
+}
+
+impl Mode {
+    /// Set up `recorder` for a recording of this mode
+    fn prepare(&self, recorder: &mut SimpleRecorder) {
+        recorder.set_stop_on_silence(self.stop_on_silence);
+        recorder.set_vad(self.silence_vad.clone());
+        recorder.set_loopback(self.system_audio);
+        set_denoise(recorder, self.denoise);
+        recorder.set_high_pass(self.high_pass_hz);
+        recorder.set_remove_dc_offset(self.remove_dc_offset);
+        recorder.set_auto_gain(self.auto_gain_db);
+        recorder.set_pre_roll(self.pre_roll);
+    }
+
+
+    /// Microphone to record the next utterance from, the first preferred one connected
+    fn input_device(&self) -> Option<String> {
+        if self.input_devices.is_empty() || self.system_audio {
+            return self.device_id.clone();
+        }
+        match audio::preferred_input_device(&self.input_devices) {
+            Ok(device_id) => device_id,
+            Err(e) => {
+                eprintln!("⚠️  {}, recording from the default microphone", e);
+                None
             }
         }
-    }) {
-        return Err(anyhow::anyhow!(
-            "Error listening for key events: {:?}",
-            error
-        ));
     }
+}
 
+/// A transcription longer than the mode's `max_chars`, held back until confirmed
+#[derive(Debug)]
+struct HeldBack {
+    chars: usize,
+    max_chars: usize,
+}
+
+impl fmt::Display for HeldBack {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "✋ Held back a transcription of {} characters, over max_chars ({})",
+            self.chars, self.max_chars
+        )
+    }
+}
+
+impl std::error::Error for HeldBack {}
+
+/// Type (or send) the confident part of a transcription, translated when configured
+///
+/// Returns the text sent, or `None` if nothing was transcribed. Text over the mode's
+/// `max_chars` is not typed but fails with [`HeldBack`] and waits for confirmation.
+fn type_segments(
+    outcome: &TranscriptionOutcome,
+    mode: &Mode,
+    translation: &Option<(Box<dyn Translator>, String)>,
+    output: &Arc<Mutex<Output>>,
+) -> Result<Option<String>> {
+    let segments = outcome.segments();
+    let unconfident = segments.iter().filter(|segment| {
+        !segment.text.is_empty() && !segment.is_confident(MIN_SEGMENT_CONFIDENCE)
+    });
+    for segment in unconfident {
+        println!(
+            "🤔 Skipping low-confidence segment ({:.0}%): \"{}\"",
+            segment.probability * 100.0,
+            segment.text
+        );
+    }
+    let text = confident_text(segments);
+    if text.is_empty() {
+        report_untyped(outcome, &None);
+        return Ok(None);
+    }
+    println!("📝 Transcribed: \"{}\"", text);
+    type_text(&text, mode, translation, output).map(Some)
+}
+
+/// Type (or send) `text`, translated when configured
+///
+/// Returns the text sent. Text over the mode's `max_chars` is not typed but fails with
+/// [`HeldBack`] and waits for confirmation.
+fn type_text(
+    text: &str,
+    mode: &Mode,
+    translation: &Option<(Box<dyn Translator>, String)>,
+    output: &Arc<Mutex<Output>>,
+) -> Result<String> {
+    let output_text = match translation {
+        Some((translator, target)) => {
+            let source = if mode.options.translate {
+                Some("en")
+            } else {
+                mode.options.language.as_deref()
+            };
+            translate_text(translator.as_ref(), source, target, text)
+        }
+        None => text.to_string(),
+    };
+
+    let chars = output_text.chars().count();
+    if let Some(max_chars) = mode.max_chars.filter(|&max_chars| chars > max_chars) {
+        hold_back(output_text, mode, output);
+        return Err(HeldBack { chars, max_chars }.into());
+    }
+
+    // Wait a moment before typing
+    std::thread::sleep(Duration::from_millis(100));
+    send_text(&output_text, mode, output);
+    Ok(output_text)
+}
+
+/// Type the transcription of a recording and hold it for the retry phrase, unless it is
+/// the phrase or records the last sentence again, which then replaces it
+fn type_reviewed(
+    outcome: &TranscriptionOutcome,
+    mode: &Mode,
+    translation: &Option<(Box<dyn Translator>, String)>,
+    output: &Arc<Mutex<Output>>,
+    review: &Mutex<Review>,
+    rerecording: Option<Rerecording>,
+    audio: Vec<f32>,
+) -> Result<Option<String>> {
+    let mut review = review
+        .lock()
+        .map_err(|_| anyhow::anyhow!("Failed to acquire review lock"))?;
+    if let Some(rerecording) = rerecording {
+        let Some((old, new)) = review.replace_last(&rerecording, &audio, outcome.segments())
+        else {
+            println!("⚠️  No text transcribed, keeping the last sentence");
+            return Ok(None);
+        };
+        println!("📝 Replacing \"{}\" with \"{}\"", old, new);
+        erase_text(&old, output)?;
+        return type_text(&new, mode, translation, output).map(Some);
+    }
+    if review.is_phrase(outcome.segments()) {
+        if review.arm() {
+            println!("🔁 Say the last sentence again");
+        } else {
+            println!("⚠️  No sentence to record again");
+        }
+        return Ok(None);
+    }
+
+    let typed = type_segments(outcome, mode, translation, output)?;
+    if let Some(text) = &typed {
+        review.hold(audio, outcome.segments(), text);
+    }
+    Ok(typed)
+}
+
+/// Ask with a notification whether to type `text` after all
+///
+/// Without notification buttons (macOS, Windows) the text is copied to the clipboard
+/// instead.
+fn hold_back(text: String, mode: &Mode, output: &Arc<Mutex<Output>>) {
+    const PREVIEW_CHARS: usize = 200;
+    let mut preview: String = text.chars().take(PREVIEW_CHARS).collect();
+    if preview.len() < text.len() {
+        preview.push('…');
+    }
+
+    if !cfg!(target_os = "linux") {
+        if let Err(e) = output::copy_to_clipboard(&text) {
+            eprintln!("{}", e);
+        }
+        notify::show("Long transcription copied instead of typed", &preview, &[], |_| {});
+        return;
+    }
+
+    let mode = mode.clone();
+    let output = Arc::clone(output);
+    let actions = [Action::Type, Action::Copy];
+    notify::show("Long transcription held back", &preview, &actions, move |action| {
+        match action {
+            Action::Type => send_text(&text, &mode, &output),
+            Action::Copy => {
+                if let Err(e) = output::copy_to_clipboard(&text) {
+                    eprintln!("{}", e);
+                }
+            }
+            Action::Undo | Action::Retry => {}
+        }
+    });
+}
+
+/// Type (or send) segments as the transcription reports them, until it finishes
+///
+/// Returns the text sent, or `None` if nothing was typed. Once the text would exceed
+/// the mode's `max_chars`, the remaining segments are held back for confirmation.
+fn type_new_segments(
+    segments: mpsc::Receiver<NewSegment>,
+    mode: &Mode,
+    abort: &AbortHandle,
+    output: &Arc<Mutex<Output>>,
+) -> Option<String> {
+    let mut typed = String::new();
+    let mut held_back = String::new();
+    for segment in segments {
+        if abort.is_aborted() {
+            continue;
+        }
+        if segment.probability < MIN_SEGMENT_CONFIDENCE {
+            println!(
+                "🤔 Skipping low-confidence segment ({:.0}%): \"{}\"",
+                segment.probability * 100.0,
+                segment.text
+            );
+            continue;
+        }
+
+        println!("📝 Transcribed: \"{}\"", segment.text);
+        let segment_text = if mode.remove_fillers {
+            Fillers.apply(&segment.text)
+        } else {
+            segment.text
+        };
+        if segment_text.is_empty() {
+            continue;
+        }
+        // Separate from the segment typed before
+        let text = if typed.is_empty() {
+            segment_text
+        } else {
+            format!(" {}", segment_text)
+        };
+        let chars = typed.chars().count() + held_back.chars().count() + text.chars().count();
+        if mode.max_chars.is_some_and(|max_chars| chars > max_chars) {
+            held_back.push_str(&text);
+            continue;
+        }
+        send_text(&text, mode, output);
+        typed.push_str(&text);
+    }
+
+    if let Some(max_chars) = mode.max_chars.filter(|_| !held_back.is_empty()) {
+        let chars = typed.chars().count() + held_back.chars().count();
+        println!("{}", HeldBack { chars, max_chars });
+        hold_back(held_back.trim_start().to_string(), mode, output);
+    }
+    (!typed.is_empty()).then_some(typed)
+}
+
+/// Say why nothing was typed for a recording, if nothing was
+fn report_untyped(outcome: &TranscriptionOutcome, typed: &Option<String>) {
+    match (outcome, typed) {
+        (TranscriptionOutcome::NoSpeech, _) => println!("🔇 No speech detected"),
+        (_, None) => println!("⚠️  No text transcribed"),
+        _ => {}
+    }
+}
+
+/// What the retry button of a notification needs to transcribe a recording again
+struct RetryContext {
+    /// The recording at 16kHz; only kept when a retry model is configured
+    audio: Option<Vec<f32>>,
+    model_dir: PathBuf,
+    translation: Arc<Option<(Box<dyn Translator>, String)>>,
+}
+
+/// Show a desktop notification for the outcome of a recording, if enabled for it
+fn notify_result(
+    typed: &Result<Option<String>>,
+    notifications: &NotificationConfig,
+    mode: &Mode,
+    retry: RetryContext,
+    output: &Arc<Mutex<Output>>,
+) {
+    let text = match typed {
+        Ok(Some(text)) if notifications.transcribed => text.clone(),
+        Ok(None) if notifications.no_speech => {
+            notify::show("No speech detected", "Nothing was typed", &[], |_| {});
+            return;
+        }
+        // Already asked about with a notification of its own
+        Err(e) if e.is::<HeldBack>() => return,
+        Err(e) if notifications.failed => {
+            notify::show("Transcription failed", &e.to_string(), &[], |_| {});
+            return;
+        }
+        _ => return,
+    };
+
+    let mut actions = vec![Action::Copy];
+    if output.lock().is_ok_and(|output| output.can_erase()) {
+        actions.push(Action::Undo);
+    }
+    let retry_model = notifications.retry_model.clone().filter(|_| retry.audio.is_some());
+    if retry_model.is_some() {
+        actions.push(Action::Retry);
+    }
+
+    let body = text.clone();
+    let mode = mode.clone();
+    let output = Arc::clone(output);
+    notify::show("Transcribed", &body, &actions, move |action| {
+        let result = match action {
+            Action::Copy => output::copy_to_clipboard(&text),
+            Action::Undo => erase_text(&text, &output),
+            Action::Retry => match retry_model {
+                Some(model) => retranscribe(&model, &text, &mode, &retry, &output),
+                None => Ok(()),
+            },
+            Action::Type => Ok(()),
+        };
+        if let Err(e) = result {
+            eprintln!("{}", e);
+        }
+    });
+}
+
+/// Take back `text` typed a moment ago
+fn erase_text(text: &str, output: &Mutex<Output>) -> Result<()> {
+    output
+        .lock()
+        .map_err(|_| anyhow::anyhow!("Failed to acquire output lock"))?
+        .erase(text.chars().count())
+}
+
+/// Transcribe a recording again with `model`, replacing the text typed for it
+fn retranscribe(
+    model: &str,
+    typed: &str,
+    mode: &Mode,
+    retry: &RetryContext,
+    output: &Arc<Mutex<Output>>,
+) -> Result<()> {
+    let audio = retry
+        .audio
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("The recording was not kept for a retry"))?;
+    println!("🔁 Transcribing again with {}...", model);
+    let transcriber = Transcriber::new(resolve_model(&retry.model_dir, model)?)?;
+    let outcome = transcriber.transcribe_outcome_with(audio, &mode.options)?;
+    if confident_text(outcome.segments()).is_empty() {
+        println!("⚠️  {} did not transcribe anything either, keeping the text", model);
+        return Ok(());
+    }
+
+    erase_text(typed, output)?;
+    type_segments(&outcome, mode, &retry.translation, output)?;
+    Ok(())
+}
+
+/// Type `text` into the focused window or send it to the configured output
+#[cfg_attr(not(windows), allow(unused_variables))]
+fn send_text(text: &str, mode: &Mode, output: &Mutex<Output>) {
+    #[cfg(windows)]
+    if mode.scan_codes {
+        match keyctl::type_scan_codes(text) {
+            Ok(()) => println!("✅ Text typed successfully"),
+            Err(e) => eprintln!("Failed to type text: {}", e),
+        }
+        return;
+    }
+
+    #[cfg(target_os = "macos")]
+    if secure_input_active(output) {
+        match output::copy_to_clipboard(text) {
+            Ok(()) => println!("📋 Copied to the clipboard instead, paste it with ⌘V"),
+            Err(e) => eprintln!("{}", e),
+        }
+        return;
+    }
+
+    // Type the transcribed text
+    if let Ok(mut output) = output.lock() {
+        match output.deliver(text, mode.paste_above) {
+            Ok(stats) if stats.pasted => {
+                println!("✅ Pasted {} characters in {}ms", stats.chars, stats.elapsed.as_millis())
+            }
+            Ok(stats) => println!(
+                "✅ Typed {} characters in {}ms ({:.0} characters/s)",
+                stats.chars,
+                stats.elapsed.as_millis(),
+                stats.chars_per_second()
+            ),
+            Err(e) => eprintln!("{}", e),
+        }
+    } else {
+        eprintln!("Failed to acquire output lock");
+    }
+}
+
+/// Whether macOS Secure Input would swallow typed output, warning when it turns on or off
+#[cfg(target_os = "macos")]
+fn secure_input_active(output: &Mutex<Output>) -> bool {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    static WAS_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+    let active = keyctl::secure_input_enabled()
+        && output.lock().is_ok_and(|output| output.is_keyboard());
+    if WAS_ACTIVE.swap(active, Ordering::SeqCst) != active {
+        if active {
+            eprintln!(
+                "🔒 macOS Secure Input is on, so typed text would be dropped. It is enabled by \
+                 focused password fields and by Secure Keyboard Entry in Terminal or iTerm; \
+                 text goes to the clipboard until it is turned off"
+            );
+        } else {
+            println!("🔓 Secure Input is off, typing text again");
+        }
+    }
+    active
+}
+
+/// Swap the top-level model when the `model` setting in the config file changes
+fn watch_model_config(
+    config_path: PathBuf,
+    model_dir: PathBuf,
+    mut current: String,
+    transcriber: Transcriber,
+) {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let mut last_modified = modified(&config_path);
+
+    std::thread::spawn(move || loop {
+        std::thread::sleep(CONFIG_POLL_INTERVAL);
+
+        let modified = modified(&config_path);
+        if modified == last_modified {
+            continue;
+        }
+        last_modified = modified;
+
+        let name = match Config::load(&config_path) {
+            Ok(config) => config.model.unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+            Err(e) => {
+                eprintln!("Failed to reload config: {}", e);
+                continue;
+            }
+        };
+        if name == current {
+            continue;
+        }
+
+        println!("\n🔁 Switching model to {}...", name);
+        match resolve_model(&model_dir, &name).and_then(|path| transcriber.swap_model(path)) {
+            Ok(()) => {
+                println!("✅ Now using model {}", name);
+                current = name;
+            }
+            Err(e) => eprintln!("Failed to switch model, keeping {}: {}", current, e),
+        }
+    });
+}
+
+/// Print the target and accelerators compiled in, to check a (cross-compiled) build
+fn print_build_info() {
+    let list = |backends: Vec<&str>| {
+        if backends.is_empty() {
+            "none".to_string()
+        } else {
+            backends.join(", ")
+        }
+    };
+    println!("Target: {}", transcribe::BUILD_TARGET);
+    println!("GPU backends: {}", list(transcribe::compiled_gpu_backends()));
+    println!("CPU acceleration: {}", list(transcribe::compiled_cpu_backends()));
+    println!("whisper.cpp: {}", transcribe::backend::system_info());
+    let access = match mic_permission() {
+        MicPermission::Granted => "allowed",
+        MicPermission::Denied => "denied",
+        MicPermission::Restricted => "blocked by policy",
+        MicPermission::NotDetermined => "not asked yet",
+    };
+    println!("Microphone access: {}", access);
+}
+
+/// Grab the keyboard for the `--grab-latency` seconds and report how long the grab
+/// held up the events typed meanwhile
+fn measure_grab_latency(args: &ArgMatches) -> Result<()> {
+    let Some(&seconds) = args.get_one::<u64>("grab_latency") else {
+        return Ok(());
+    };
+    println!("⌨️  Grabbing the keyboard for {}s, type something...", seconds);
+    // Never returns while grabbing; the process exits when the measurement is done
+    std::thread::spawn(|| {
+        if let Err(error) = listen_keys(Vec::new(), true, |_, _| {}) {
+            eprintln!("Error grabbing the keyboard: {:?}", error);
+        }
+    });
+    std::thread::sleep(Duration::from_secs(seconds));
+    print_pass_through_stats(keyctl::take_pass_through_stats());
     Ok(())
 }
+
+/// Load the parts of a recording, one after the other, as 16kHz mono audio and delete
+/// them along with their manifest
+fn load_parts(files: &[PathBuf]) -> Result<Vec<f32>> {
+    let audio = files
+        .iter()
+        .map(load_wav_resampled)
+        .collect::<Result<Vec<_>>>()
+        .map(|parts| parts.concat());
+    let manifest = files.first().map(manifest_path).filter(|path| path.exists());
+    for path in files.iter().chain(&manifest) {
+        if let Err(e) = std::fs::remove_file(path) {
+            eprintln!("Failed to clean up temp file: {}", e);
+        }
+    }
+    audio
+}
+
+/// Report the input events the key grab passed on to other programs
+fn print_pass_through_stats(stats: PassThroughStats) {
+    if stats.events == 0 {
+        println!("⌨️  No input events passed through the key grab");
+        return;
+    }
+    println!(
+        "⌨️  Passed {} input events through the key grab, holding them {:.2}ms on average \
+         and {:.2}ms at most",
+        stats.events,
+        stats.mean_latency.as_secs_f64() * 1000.0,
+        stats.max_latency.as_secs_f64() * 1000.0
+    );
+}
+
+/// Ask for microphone access now rather than on the first recording, and explain
+/// how to allow it when it was denied
+fn check_mic_permission() {
+    match request_mic_permission() {
+        Ok(MicPermission::Granted) => {}
+        Ok(MicPermission::NotDetermined) => {
+            eprintln!("⚠️  Microphone access was not granted yet; recordings will be silent")
+        }
+        Ok(_) => eprintln!(
+            "❌ Microphone access is denied, so recordings will be silent. Allow it in {}",
+            MicPermission::settings_hint()
+        ),
+        Err(e) => eprintln!("⚠️  Failed to check microphone access: {}", e),
+    }
+}
+
+/// Find a model next to the binary or in the model cache, downloading it when missing
+fn resolve_model(model_dir: &Path, name: &str) -> Result<PathBuf> {
+    let bundled = model_dir.join(format!("ggml-{}.bin", name));
+    if bundled.exists() {
+        return Ok(bundled);
+    }
+
+    let manager = ModelManager::with_default_dir()?;
+    if let Some(path) = manager.resolve(name)? {
+        return Ok(path);
+    }
+
+    println!("📦 Model {} not found locally", name);
+    models::pull(&manager, name).map_err(|e| {
+        anyhow::anyhow!(
+            "{}\nRun `voicekb model pull {}` to retry the download.",
+            e,
+            name
+        )
+    })
+}
+
+/// Salvage a recording left behind by a previous run that exited abnormally
+fn recover_leftover_recording() {
+    let leftover = PathBuf::from(TEMP_RECORDING);
+    if !leftover.exists() {
+        return;
+    }
+
+    match repair_wav_file(&leftover) {
+        Ok(_) => match std::fs::rename(&leftover, RECOVERED_RECORDING) {
+            Ok(()) => println!("🩹 Recovered unfinished recording to {}", RECOVERED_RECORDING),
+            Err(e) => eprintln!("Failed to move recovered recording: {}", e),
+        },
+        Err(e) => {
+            eprintln!("Failed to repair leftover recording: {}", e);
+            if let Err(e) = std::fs::remove_file(&leftover) {
+                eprintln!("Failed to clean up temp file: {}", e);
+            }
+        }
+    }
+}
+
+/// Remove background noise from recordings or not, for the `denoise` setting
+#[cfg(feature = "denoise")]
+fn set_denoise(recorder: &mut SimpleRecorder, denoise: bool) {
+    recorder.set_denoise(denoise);
+}
+
+#[cfg(not(feature = "denoise"))]
+fn set_denoise(_recorder: &mut SimpleRecorder, _denoise: bool) {}
+
+/// Tell the end of speech with Silero VAD, for the `stop_on_silence_vad` setting
+#[cfg(feature = "silero")]
+fn silero_vad(model: &Path) -> VadBackend {
+    match audio::SileroModel::load(model) {
+        Ok(model) => {
+            println!("🧠 Telling the end of speech with Silero VAD");
+            VadBackend::Silero(model)
+        }
+        Err(e) => {
+            eprintln!("⚠️  {}, telling the end of speech by its level", e);
+            VadBackend::Energy
+        }
+    }
+}
+
+#[cfg(not(feature = "silero"))]
+fn silero_vad(_model: &Path) -> VadBackend {
+    eprintln!("⚠️  Built without the silero feature, telling the end of speech by its level");
+    VadBackend::Energy
+}
+
+/// The translator and target language for the `translation` setting
+#[cfg(feature = "translation")]
+fn translator(translation: &TranslationConfig) -> Option<(Box<dyn Translator>, String)> {
+    println!("🌐 Translating to \"{}\" via {}", translation.target, translation.url);
+    let translator = LibreTranslate::new(&translation.url, translation.api_key.clone());
+    Some((Box::new(translator), translation.target.clone()))
+}
+
+#[cfg(not(feature = "translation"))]
+fn translator(_translation: &TranslationConfig) -> Option<(Box<dyn Translator>, String)> {
+    eprintln!("⚠️  Built without the translation feature, typing untranslated text");
+    None
+}
+
+/// Translate `text` from `source` (detected when `None`) into `target`,
+/// falling back to the original text on failure
+fn translate_text(
+    translator: &dyn Translator,
+    source: Option<&str>,
+    target: &str,
+    text: &str,
+) -> String {
+    match translator.translate(text, source, target) {
+        Ok(translated) => {
+            println!("🌐 Translated: \"{}\"", translated);
+            translated
+        }
+        Err(e) => {
+            eprintln!("Translation failed, typing original text: {}", e);
+            text.to_string()
+        }
+    }
+}
+
+/// Show the audio level during recording and warn about input problems
+fn show_recorder_event(event: RecorderEvent) {
+    match event {
+        RecorderEvent::Level { meter, .. } => {
+            // A block per 6 dB above -60 dBFS
+            let bar_length = ((meter.peak_dbfs + 60.0) / 6.0).clamp(0.0, 10.0) as usize;
+            let bar = "█".repeat(bar_length);
+            print!("\r🔊 [{:<10}] {:>4.0} dBFS", bar, meter.rms_dbfs);
+            std::io::Write::flush(&mut std::io::stdout()).unwrap();
+        }
+        RecorderEvent::Clipping { .. } => print!(" 📢 clipping, move away from the mic"),
+        RecorderEvent::Dropout { missing } => {
+            eprintln!("\n⚠️  Lost {}ms of audio", missing.as_millis())
+        }
+        RecorderEvent::DeviceChanged { name: Some(name) } => println!("🎙️  Using {}", name),
+        RecorderEvent::DeviceChanged { name: None } => eprintln!("\n❌ Microphone disconnected"),
+        RecorderEvent::Error { message } => eprintln!("\n❌ {}", message),
+        RecorderEvent::SpeechEnded { silence } => {
+            println!("\n🤫 {}ms of silence, stopping", silence.as_millis())
+        }
+        RecorderEvent::Spilled { path } => {
+            println!("\n💾 Long recording, going on in {}", path.display())
+        }
+        RecorderEvent::BufferSize { frames, latency } => println!(
+            "⏱️  Buffers of {} frames, {:.1}ms",
+            frames,
+            latency.as_secs_f64() * 1000.0
+        ),
+        RecorderEvent::LimitReached { length } => {
+            println!("\n⏲️  Recording limit of {}s reached, stopping", length.as_secs())
+        }
+    }
+}
+
+/// Segments whose text is typed: those meeting the confidence threshold, without the
+/// ones left empty by removing control phrases
+fn typed_segments(segments: &[Segment]) -> impl Iterator<Item = &Segment> {
+    segments
+        .iter()
+        .filter(|segment| !segment.text.is_empty() && segment.is_confident(MIN_SEGMENT_CONFIDENCE))
+}
+
+/// The text of `segments` as typed
+fn join_segments<'a>(segments: impl Iterator<Item = &'a Segment>) -> String {
+    let texts: Vec<&str> = segments.map(|segment| segment.text.trim()).collect();
+    texts.join(" ")
+}
+
+/// Join the text of all segments that meet the confidence threshold
+fn confident_text(segments: &[Segment]) -> String {
+    join_segments(typed_segments(segments))
+}
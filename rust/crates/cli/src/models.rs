@@ -0,0 +1,209 @@
+use anyhow::Result;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use std::path::PathBuf;
+use transcribe::{bench, model::KNOWN_MODELS, ModelManager, ModelRecord, TranscribeOptions};
+
+/// The `model` subcommand
+pub fn command() -> Command {
+    Command::new("model")
+        .about("Manage Whisper models")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("pull")
+                .about("Download a model into the model cache")
+                .arg(
+                    Arg::new("name")
+                        .value_name("NAME")
+                        .help("Model name, e.g. base.en")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("coreml")
+                        .long("coreml")
+                        .help("Also download the Core ML encoder, for coreml builds on macOS")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(Command::new("list").about("List available and downloaded models"))
+        .subcommand(
+            Command::new("licenses")
+                .about("Show the license and source of every downloaded model"),
+        )
+        .subcommand(
+            Command::new("bench")
+                .about("Compare speed and accuracy of models on a 16kHz WAV recording")
+                .arg(Arg::new("input").value_name("INPUT_FILE").required(true))
+                .arg(
+                    Arg::new("models")
+                        .value_name("MODEL")
+                        .help("Model names or paths; the first one is the accuracy baseline")
+                        .required(true)
+                        .num_args(1..),
+                )
+                .arg(
+                    Arg::new("reference")
+                        .short('r')
+                        .long("reference")
+                        .value_name("TEXT")
+                        .help("Known-correct transcript to compare against"),
+                ),
+        )
+        .subcommand(
+            Command::new("verify")
+                .about("Check a downloaded model against its recorded checksum")
+                .arg(Arg::new("name").value_name("NAME").required(true)),
+        )
+}
+
+/// Run the `model` subcommand
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let manager = ModelManager::with_default_dir()?;
+
+    match matches.subcommand() {
+        Some(("pull", args)) => {
+            let name = args.get_one::<String>("name").unwrap();
+            let path = pull(&manager, name)?;
+            println!("✅ Model saved to {:?}", path);
+            if args.get_flag("coreml") {
+                let encoder = pull_coreml_encoder(&manager, name)?;
+                println!("✅ Core ML encoder saved to {:?}", encoder);
+            }
+        }
+        Some(("list", _)) => {
+            let installed = manager.installed()?;
+            println!("📁 Model cache: {:?}", manager.cache_dir());
+            for name in KNOWN_MODELS {
+                match installed.iter().find(|record| record.name == *name) {
+                    Some(record) => print_installed(record),
+                    None => println!("  [ ] {}", name),
+                }
+            }
+            for record in installed
+                .iter()
+                .filter(|record| !KNOWN_MODELS.contains(&record.name.as_str()))
+            {
+                print_installed(record);
+            }
+        }
+        Some(("licenses", _)) => {
+            let installed = manager.installed()?;
+            if installed.is_empty() {
+                println!("No downloaded models, run `voicekb model pull <NAME>` first");
+            }
+            for record in &installed {
+                println!("{}: {}", record.name, record.license);
+                println!("   {}", record.source);
+            }
+        }
+        Some(("bench", args)) => {
+            let input = args.get_one::<String>("input").unwrap();
+            let models = args
+                .get_many::<String>("models")
+                .unwrap()
+                .map(|model| resolve(&manager, model))
+                .collect::<Result<Vec<_>>>()?;
+            let reference = args.get_one::<String>("reference").map(String::as_str);
+
+            let results = bench::run(input, &models, &TranscribeOptions::default(), reference)?;
+            for result in &results {
+                let name = result.model.file_name().unwrap_or_default().to_string_lossy();
+                let format = result.info.map_or("?", |info| info.quantization());
+                println!(
+                    "📊 {} ({}): load {:.2}s, RTF {:.2}, WER {:.1}%",
+                    name,
+                    format,
+                    result.load_time.as_secs_f32(),
+                    result.real_time_factor(),
+                    result.word_error_rate * 100.0
+                );
+                println!("   {}", result.diff);
+            }
+        }
+        Some(("verify", args)) => {
+            let name = args.get_one::<String>("name").unwrap();
+            if manager.verify(name)? {
+                println!("✅ Checksum of {} matches", name);
+            } else {
+                return Err(anyhow::anyhow!(
+                    "Checksum of {} does not match, run `voicekb model pull {}` to download it again",
+                    name,
+                    name
+                ));
+            }
+        }
+        _ => unreachable!("subcommand is required"),
+    }
+
+    Ok(())
+}
+
+/// List an installed model with where it came from and its license
+fn print_installed(record: &ModelRecord) {
+    println!("  [✓] {} ({}, {})", record.name, record.license, record.source);
+}
+
+/// A model given on the command line, either a file path or the name of a cached model
+fn resolve(manager: &ModelManager, model: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(model);
+    if path.exists() {
+        return Ok(path);
+    }
+    manager.resolve(model)?.ok_or_else(|| {
+        anyhow::anyhow!(
+            "Model {} not found, run `voicekb model pull {}` first",
+            model,
+            model
+        )
+    })
+}
+
+/// Download a model, printing progress to the terminal
+#[cfg(feature = "download")]
+pub fn pull(manager: &ModelManager, name: &str) -> Result<PathBuf> {
+    println!("⬇️  Downloading model {}...", name);
+    let path = manager.pull(name, |downloaded, total| {
+        let mb = downloaded as f64 / 1_000_000.0;
+        match total {
+            Some(total) => print!(
+                "\r⬇️  {:.1} / {:.1} MB ({:.0}%)",
+                mb,
+                total as f64 / 1_000_000.0,
+                downloaded as f64 / total as f64 * 100.0
+            ),
+            None => print!("\r⬇️  {:.1} MB", mb),
+        }
+        std::io::Write::flush(&mut std::io::stdout()).unwrap();
+    });
+    println!();
+    path
+}
+
+/// Download the Core ML encoder of a model, printing progress to the terminal
+#[cfg(feature = "download")]
+fn pull_coreml_encoder(manager: &ModelManager, name: &str) -> Result<PathBuf> {
+    println!("⬇️  Downloading the Core ML encoder of {}...", name);
+    let path = manager.pull_coreml_encoder(name, |downloaded, _| {
+        print!("\r⬇️  {:.1} MB", downloaded as f64 / 1_000_000.0);
+        std::io::Write::flush(&mut std::io::stdout()).unwrap();
+    });
+    println!();
+    path
+}
+
+#[cfg(not(feature = "download"))]
+pub fn pull(manager: &ModelManager, name: &str) -> Result<PathBuf> {
+    Err(anyhow::anyhow!(
+        "Built without the download feature, place ggml-{}.bin in {:?} manually",
+        name,
+        manager.cache_dir()
+    ))
+}
+
+#[cfg(not(feature = "download"))]
+fn pull_coreml_encoder(manager: &ModelManager, name: &str) -> Result<PathBuf> {
+    Err(anyhow::anyhow!(
+        "Built without the download feature, unpack ggml-{}-encoder.mlmodelc into {:?} manually",
+        name,
+        manager.cache_dir()
+    ))
+}
@@ -0,0 +1,135 @@
+use anyhow::Result;
+use std::process::Command;
+
+/// A button on a notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Copy the text to the clipboard
+    Copy,
+    /// Erase the typed text
+    Undo,
+    /// Transcribe the recording again with a larger model
+    Retry,
+    /// Type text that was held back
+    Type,
+}
+
+// Buttons are only shown on Linux
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+impl Action {
+    const ALL: [Self; 4] = [Self::Copy, Self::Undo, Self::Retry, Self::Type];
+
+    fn id(self) -> &'static str {
+        match self {
+            Self::Copy => "copy",
+            Self::Undo => "undo",
+            Self::Retry => "retry",
+            Self::Type => "type",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Copy => "Copy",
+            Self::Undo => "Undo",
+            Self::Retry => "Retry with better model",
+            Self::Type => "Type anyway",
+        }
+    }
+}
+
+/// Show a desktop notification in the background, calling `on_action` with the
+/// button the user clicks
+///
+/// Buttons need a `notify-send` with `--action` support (libnotify 0.7.9 or
+/// later) on Linux. On macOS and Windows the notification has no buttons.
+pub fn show<F>(title: &str, body: &str, actions: &[Action], on_action: F)
+where
+    F: FnOnce(Action) + Send + 'static,
+{
+    let (title, body, actions) = (title.to_string(), body.to_string(), actions.to_vec());
+    std::thread::spawn(move || match send(&title, &body, &actions) {
+        Ok(Some(action)) => on_action(action),
+        Ok(None) => {}
+        Err(e) => eprintln!("Failed to show notification: {}", e),
+    });
+}
+
+/// Show the notification and wait for the clicked button, if it has any
+#[cfg(target_os = "linux")]
+fn send(title: &str, body: &str, actions: &[Action]) -> Result<Option<Action>> {
+    let notify_send = |actions: &[Action]| {
+        let mut command = Command::new("notify-send");
+        command.arg("--app-name=voicekb");
+        for action in actions {
+            command.arg(format!("--action={}={}", action.id(), action.label()));
+        }
+        command
+            .arg("--")
+            .arg(title)
+            .arg(body)
+            .output()
+            .map_err(|e| anyhow::anyhow!("Failed to run notify-send: {}", e))
+    };
+
+    let mut output = notify_send(actions)?;
+    if !output.status.success() && !actions.is_empty() {
+        // Older notify-send without --action
+        output = notify_send(&[])?;
+    }
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "notify-send failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    // With actions, notify-send waits and prints the id of the clicked one
+    let clicked = String::from_utf8_lossy(&output.stdout);
+    Ok(Action::ALL
+        .into_iter()
+        .find(|action| action.id() == clicked.trim()))
+}
+
+#[cfg(target_os = "macos")]
+fn send(title: &str, body: &str, _actions: &[Action]) -> Result<Option<Action>> {
+    // Pass the text as arguments so it needs no AppleScript escaping
+    let status = Command::new("osascript")
+        .args(["-e", "on run argv"])
+        .args(["-e", "display notification (item 2 of argv) with title (item 1 of argv)"])
+        .args(["-e", "end run"])
+        .arg(title)
+        .arg(body)
+        .status()
+        .map_err(|e| anyhow::anyhow!("Failed to run osascript: {}", e))?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("osascript exited with {}", status));
+    }
+    Ok(None)
+}
+
+#[cfg(windows)]
+fn send(title: &str, body: &str, _actions: &[Action]) -> Result<Option<Action>> {
+    // Toasts need a registered app id; borrow PowerShell's. The text goes through
+    // the environment so it needs no escaping.
+    const SCRIPT: &str = r#"
+$manager = [Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime]
+$template = $manager::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02)
+$text = $template.GetElementsByTagName('text')
+$text.Item(0).AppendChild($template.CreateTextNode($env:VOICEKB_TITLE)) > $null
+$text.Item(1).AppendChild($template.CreateTextNode($env:VOICEKB_BODY)) > $null
+$toast = [Windows.UI.Notifications.ToastNotification]::new($template)
+$app = '{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\WindowsPowerShell\v1.0\powershell.exe'
+$manager::CreateToastNotifier($app).Show($toast)
+"#;
+    let status = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", SCRIPT])
+        .env("VOICEKB_TITLE", title)
+        .env("VOICEKB_BODY", body)
+        .status()
+        .map_err(|e| anyhow::anyhow!("Failed to run powershell: {}", e))?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("powershell exited with {}", status));
+    }
+    Ok(None)
+}
@@ -0,0 +1,230 @@
+use anyhow::Result;
+use enigo::{Direction, Enigo, Keyboard, Settings};
+use std::{
+    io::Write,
+    net::TcpStream,
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use crate::config::OutputConfig;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Time for the clipboard to take the text before the paste shortcut
+const PASTE_DELAY: Duration = Duration::from_millis(50);
+
+/// How long it took to get a text out
+#[derive(Debug, Clone, Copy)]
+pub struct TypingStats {
+    /// Characters in the text
+    pub chars: usize,
+    pub elapsed: Duration,
+    /// Pasted through the clipboard rather than typed
+    pub pasted: bool,
+}
+
+impl TypingStats {
+    pub fn chars_per_second(&self) -> f32 {
+        self.chars as f32 / self.elapsed.as_secs_f32().max(f32::EPSILON)
+    }
+}
+
+/// Where transcribed text ends up
+pub enum Output {
+    /// Typed into the focused window
+    Keyboard(Enigo),
+    /// Typed through a kernel-level virtual keyboard
+    #[cfg(target_os = "linux")]
+    Uinput(keyctl::VirtualKeyboard),
+    /// Sent as newline-terminated UTF-8 lines to a TCP server, reconnecting as needed
+    Tcp {
+        address: String,
+        stream: Option<TcpStream>,
+    },
+}
+
+impl Output {
+    pub fn new(config: Option<&OutputConfig>) -> Result<Self> {
+        match config {
+            None | Some(OutputConfig::Keyboard) => {
+                let enigo = Enigo::new(&Settings::default())
+                    .map_err(|e| anyhow::anyhow!("Failed to create Enigo instance: {}", e))?;
+                Ok(Self::Keyboard(enigo))
+            }
+            #[cfg(target_os = "linux")]
+            Some(OutputConfig::Uinput) => {
+                let keyboard = keyctl::VirtualKeyboard::new().map_err(|e| {
+                    anyhow::anyhow!("Failed to create virtual keyboard via /dev/uinput: {}", e)
+                })?;
+                Ok(Self::Uinput(keyboard))
+            }
+            #[cfg(not(target_os = "linux"))]
+            Some(OutputConfig::Uinput) => {
+                Err(anyhow::anyhow!("The uinput output is only available on Linux"))
+            }
+            Some(OutputConfig::Tcp(address)) => Ok(Self::Tcp {
+                address: address.clone(),
+                stream: None,
+            }),
+        }
+    }
+
+    /// Whether text is typed into the focused window with synthetic key events
+    #[cfg(target_os = "macos")]
+    pub fn is_keyboard(&self) -> bool {
+        matches!(self, Self::Keyboard(_))
+    }
+
+    /// Whether typed text can be taken back with [`Output::erase`]
+    pub fn can_erase(&self) -> bool {
+        !matches!(self, Self::Tcp { .. })
+    }
+
+    /// Delete the last `count` characters typed, with backspace
+    pub fn erase(&mut self, count: usize) -> Result<()> {
+        match self {
+            Self::Keyboard(enigo) => (0..count).try_for_each(|_| {
+                enigo
+                    .key(enigo::Key::Backspace, Direction::Click)
+                    .map_err(|e| anyhow::anyhow!("Failed to erase text: {}", e))
+            }),
+            #[cfg(target_os = "linux")]
+            Self::Uinput(keyboard) => keyboard
+                .backspace(count)
+                .map_err(|e| anyhow::anyhow!("Failed to erase text: {}", e)),
+            Self::Tcp { address, .. } => {
+                Err(anyhow::anyhow!("Text sent to {} can't be erased", address))
+            }
+        }
+    }
+
+    /// Send `text`, pasting it instead of typing it when it is longer than
+    /// `paste_above` characters and goes to the focused window, and time it
+    pub fn deliver(&mut self, text: &str, paste_above: Option<usize>) -> Result<TypingStats> {
+        let start = Instant::now();
+        let chars = text.chars().count();
+        let pasted = match self {
+            Self::Keyboard(enigo) if paste_above.is_some_and(|limit| chars > limit) => {
+                paste(enigo, text)?;
+                true
+            }
+            _ => {
+                self.send(text)?;
+                false
+            }
+        };
+        Ok(TypingStats {
+            chars,
+            elapsed: start.elapsed(),
+            pasted,
+        })
+    }
+
+    pub fn send(&mut self, text: &str) -> Result<()> {
+        match self {
+            Self::Keyboard(enigo) => enigo
+                .text(text)
+                .map_err(|e| anyhow::anyhow!("Failed to type text: {}", e)),
+            #[cfg(target_os = "linux")]
+            Self::Uinput(keyboard) => {
+                let skipped = keyboard
+                    .text(text)
+                    .map_err(|e| anyhow::anyhow!("Failed to type text: {}", e))?;
+                if !skipped.is_empty() {
+                    let skipped: String = skipped.into_iter().collect();
+                    eprintln!("⚠️  Could not type {:?} on the virtual keyboard", skipped);
+                }
+                Ok(())
+            }
+            Self::Tcp { address, stream } => {
+                let line = format!("{}\n", text.replace(['\r', '\n'], " "));
+                // A dropped connection only shows up on write, so reconnect and retry once
+                if let Some(connection) = stream.as_mut() {
+                    if connection.write_all(line.as_bytes()).is_ok() {
+                        return Ok(());
+                    }
+                }
+                *stream = None;
+
+                let mut connection = connect(address)?;
+                connection
+                    .write_all(line.as_bytes())
+                    .map_err(|e| anyhow::anyhow!("Failed to send text to {}: {}", address, e))?;
+                *stream = Some(connection);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Copy `text` to the clipboard
+///
+/// Uses `pbcopy` on macOS, `wl-copy` or `xclip` on Linux and PowerShell on Windows.
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    #[cfg(windows)]
+    let mut command = {
+        // Through the environment, which keeps the text UTF-16 all the way
+        let mut command = Command::new("powershell");
+        command
+            .args(["-NoProfile", "-NonInteractive", "-Command"])
+            .arg("Set-Clipboard -Value $env:VOICEKB_CLIPBOARD")
+            .env("VOICEKB_CLIPBOARD", text);
+        command
+    };
+    #[cfg(target_os = "macos")]
+    let mut command = Command::new("pbcopy");
+    #[cfg(all(unix, not(target_os = "macos")))]
+    let mut command = if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        Command::new("wl-copy")
+    } else {
+        let mut command = Command::new("xclip");
+        command.args(["-selection", "clipboard"]);
+        command
+    };
+
+    let program = command.get_program().to_string_lossy().into_owned();
+    let mut child = command
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Failed to run {}: {}", program, e))?;
+    // PowerShell already has the text; the others read it from stdin
+    if let Some(mut stdin) = child.stdin.take().filter(|_| !cfg!(windows)) {
+        stdin
+            .write_all(text.as_bytes())
+            .map_err(|e| anyhow::anyhow!("Failed to copy text to the clipboard: {}", e))?;
+    }
+    let status = child
+        .wait()
+        .map_err(|e| anyhow::anyhow!("Failed to copy text to the clipboard: {}", e))?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("{} exited with {}", program, status));
+    }
+    Ok(())
+}
+
+/// Put `text` on the clipboard and press the paste shortcut
+fn paste(enigo: &mut Enigo, text: &str) -> Result<()> {
+    copy_to_clipboard(text)?;
+    std::thread::sleep(PASTE_DELAY);
+
+    let modifier = if cfg!(target_os = "macos") {
+        enigo::Key::Meta
+    } else {
+        enigo::Key::Control
+    };
+    let error = |e| anyhow::anyhow!("Failed to paste text: {}", e);
+    enigo.key(modifier, Direction::Press).map_err(error)?;
+    let pressed = enigo.key(enigo::Key::Unicode('v'), Direction::Click);
+    // Release the modifier even if the click failed, so it doesn't stay held down
+    enigo.key(modifier, Direction::Release).map_err(error)?;
+    pressed.map_err(error)
+}
+
+fn connect(address: &str) -> Result<TcpStream> {
+    let addr = std::net::ToSocketAddrs::to_socket_addrs(address)
+        .map_err(|e| anyhow::anyhow!("Invalid output address {}: {}", address, e))?
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Output address {} did not resolve", address))?;
+    TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)
+        .map_err(|e| anyhow::anyhow!("Failed to connect to {}: {}", address, e))
+}
@@ -0,0 +1,125 @@
+use anyhow::Result;
+use btleplug::{
+    api::{bleuuid::uuid_from_u16, Central, Manager as _, Peripheral as _, ScanFilter},
+    platform::{Manager, Peripheral},
+};
+use clap::{Arg, ArgMatches, Command};
+use std::{
+    io::{self, BufRead, Write},
+    time::Duration,
+};
+
+/// The Bluetooth HID service, which shutter remotes advertise as keyboards
+const HID_SERVICE: u16 = 0x1812;
+const SCAN_TIME: Duration = Duration::from_secs(10);
+
+/// The `remote` subcommand
+pub fn command() -> Command {
+    Command::new("remote")
+        .about("Set up a Bluetooth camera-shutter remote as a dictation button")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("pair")
+                .about("Find a remote and connect to it")
+                .arg(
+                    Arg::new("name")
+                        .value_name("NAME")
+                        .help("Remote name, or part of it, e.g. \"AB Shutter\""),
+                ),
+        )
+}
+
+/// Run the `remote` subcommand
+pub fn run(args: &ArgMatches) -> Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    match args.subcommand() {
+        Some(("pair", args)) => runtime.block_on(pair(args.get_one::<String>("name"))),
+        _ => unreachable!("subcommand is required"),
+    }
+}
+
+/// Scan for remotes, let the user pick one and connect to it so the system pairs it
+async fn pair(name: Option<&String>) -> Result<()> {
+    let manager = Manager::new()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to access Bluetooth: {}", e))?;
+    let adapter = manager
+        .adapters()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to list Bluetooth adapters: {}", e))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("No Bluetooth adapter found"))?;
+
+    println!(
+        "🔍 Scanning for {} seconds; switch the remote on or press its button...",
+        SCAN_TIME.as_secs()
+    );
+    adapter
+        .start_scan(ScanFilter::default())
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to scan: {}", e))?;
+    tokio::time::sleep(SCAN_TIME).await;
+    let _ = adapter.stop_scan().await;
+
+    let name = name.map(|name| name.to_lowercase());
+    let mut remotes: Vec<(Peripheral, String)> = Vec::new();
+    for peripheral in adapter.peripherals().await? {
+        let Some(properties) = peripheral.properties().await? else {
+            continue;
+        };
+        let Some(local_name) = properties.local_name else {
+            continue;
+        };
+        // Without a name, anything advertising as a keyboard is a candidate
+        let matches = match &name {
+            Some(name) => local_name.to_lowercase().contains(name),
+            None => properties.services.contains(&uuid_from_u16(HID_SERVICE)),
+        };
+        if matches {
+            remotes.push((peripheral, local_name));
+        }
+    }
+
+    let (remote, remote_name) = match remotes.len() {
+        0 => return Err(anyhow::anyhow!("No remote found; is it switched on and in range?")),
+        1 => remotes.remove(0),
+        _ => {
+            for (i, (_, name)) in remotes.iter().enumerate() {
+                println!("  {}. {}", i + 1, name);
+            }
+            let choice = ask("Which remote? ")?;
+            let index = choice
+                .parse::<usize>()
+                .ok()
+                .filter(|index| (1..=remotes.len()).contains(index))
+                .ok_or_else(|| anyhow::anyhow!("Invalid choice: {}", choice))?;
+            remotes.remove(index - 1)
+        }
+    };
+
+    println!("🔗 Connecting to {}...", remote_name);
+    remote
+        .connect()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to connect to {}: {}", remote_name, e))?;
+
+    println!("✅ Connected to {}", remote_name);
+    println!("Add this to voicekb.json to record with it:");
+    println!("  \"remote_trigger\": {{ \"device\": \"{}\" }}", remote_name);
+    println!(
+        "If pressing the button does nothing, finish pairing it in the system Bluetooth settings."
+    );
+    Ok(())
+}
+
+/// Print `question` and read a line from stdin
+fn ask(question: &str) -> Result<String> {
+    print!("{}", question);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(answer.trim().to_string())
+}
@@ -0,0 +1,198 @@
+use transcribe::{Segment, Vad};
+
+use crate::{confident_text, join_segments, typed_segments};
+
+/// Rate of the audio given to Whisper
+const SAMPLES_PER_MS: usize = 16;
+/// Pauses longer than this end a sentence
+const SENTENCE_PAUSE_MS: usize = 500;
+
+/// A sentence of the last utterance and the text typed for it
+struct Sentence {
+    audio: Vec<f32>,
+    text: String,
+}
+
+/// The last utterance typed, held split into sentences at its pauses, so that its last
+/// sentence can be recorded again after saying the retry phrase
+pub struct Review {
+    /// The retry phrase, normalized
+    phrase: String,
+    sentences: Vec<Sentence>,
+    /// Set once the phrase was said; the next recording replaces the last sentence
+    armed: bool,
+}
+
+/// Where a recording of the last sentence again starts, after the sentences before it
+pub struct Rerecording {
+    start: usize,
+}
+
+impl Review {
+    pub fn new(phrase: &str) -> Self {
+        Self {
+            phrase: normalize(phrase),
+            sentences: Vec::new(),
+            armed: false,
+        }
+    }
+
+    /// Whether the confident text of `segments` is the retry phrase, ignoring case and
+    /// punctuation
+    pub fn is_phrase(&self, segments: &[Segment]) -> bool {
+        normalize(&confident_text(segments)) == self.phrase
+    }
+
+    /// Have the next recording replace the last sentence; false without one to replace
+    pub fn arm(&mut self) -> bool {
+        self.armed = !self.sentences.is_empty();
+        self.armed
+    }
+
+    /// Put the audio of the sentences before the last one in front of `audio`, if it
+    /// records the last sentence again, so that Whisper hears it in context
+    pub fn splice(&mut self, audio: &mut Vec<f32>) -> Option<Rerecording> {
+        if !std::mem::take(&mut self.armed) {
+            return None;
+        }
+        let before = &self.sentences[..self.sentences.len() - 1];
+        let context: Vec<f32> =
+            before.iter().flat_map(|sentence| &sentence.audio).copied().collect();
+        let start = context.len();
+        audio.splice(0..0, context);
+        Some(Rerecording { start })
+    }
+
+    /// Hold `audio` and its `segments`, which were typed as `typed`, split into sentences
+    ///
+    /// Nothing is held when the last sentence isn't what was typed last, e.g. once
+    /// translated.
+    pub fn hold(&mut self, audio: Vec<f32>, segments: &[Segment], typed: &str) {
+        // Sentences end halfway into the pauses between them
+        let speech = Vad::new().padding_ms(0).max_pause_ms(SENTENCE_PAUSE_MS).speech_ranges(&audio);
+        let mut ends: Vec<usize> =
+            speech.windows(2).map(|pair| (pair[0].end + pair[1].start) / 2).collect();
+        ends.push(audio.len());
+
+        self.sentences.clear();
+        let mut start = 0;
+        for end in ends {
+            let spoken = typed_segments(segments).filter(|segment| {
+                let middle = (segment.start_ms + segment.end_ms).max(0) as usize / 2;
+                (start..end).contains(&(middle * SAMPLES_PER_MS))
+            });
+            let text = join_segments(spoken);
+            // Quiet stretches belong to the sentence before
+            match self.sentences.last_mut() {
+                Some(last) if text.is_empty() || last.text.is_empty() => {
+                    last.audio.extend_from_slice(&audio[start..end]);
+                    if last.text.is_empty() {
+                        last.text = text;
+                    }
+                }
+                _ => self.sentences.push(Sentence {
+                    audio: audio[start..end].to_vec(),
+                    text,
+                }),
+            }
+            start = end;
+        }
+
+        let typed_last = self.sentences.last().is_some_and(|last| {
+            !last.text.is_empty() && typed.trim_end().ends_with(&last.text)
+        });
+        if !typed_last {
+            self.sentences.clear();
+        }
+    }
+
+    /// Replace the last sentence with what the `segments` of `audio`, spliced for
+    /// `rerecording`, have after the sentences before it
+    ///
+    /// Returns the text typed for the sentence so far and its new text, or `None` when
+    /// nothing new was said.
+    pub fn replace_last(
+        &mut self,
+        rerecording: &Rerecording,
+        audio: &[f32],
+        segments: &[Segment],
+    ) -> Option<(String, String)> {
+        let start_ms = (rerecording.start / SAMPLES_PER_MS) as i64;
+        let text = join_segments(
+            typed_segments(segments)
+                .filter(|segment| (segment.start_ms + segment.end_ms) / 2 >= start_ms),
+        );
+        let last = self.sentences.last_mut().filter(|_| !text.is_empty())?;
+        last.audio = audio[rerecording.start.min(audio.len())..].to_vec();
+        Some((std::mem::replace(&mut last.text, text.clone()), text))
+    }
+}
+
+/// Lowercase words of `text` without punctuation, separated by single spaces
+fn normalize(text: &str) -> String {
+    let words: String = text
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    words.to_lowercase().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(ms: usize, amplitude: f32) -> Vec<f32> {
+        (0..ms * SAMPLES_PER_MS).map(|i| amplitude * (i as f32 * 0.1).sin()).collect()
+    }
+
+    /// 1s of speech, 1s of quiet and 1s of speech
+    fn two_sentences() -> (Vec<f32>, Vec<Segment>) {
+        let mut audio = tone(1000, 0.5);
+        audio.extend(tone(1000, 0.001));
+        audio.extend(tone(1000, 0.5));
+        let segments = vec![
+            Segment::new("Hello there.", 0, 1000),
+            Segment::new("How are you?", 2000, 3000),
+        ];
+        (audio, segments)
+    }
+
+    #[test]
+    fn test_hold_splits_sentences_at_pauses() {
+        let (audio, segments) = two_sentences();
+        let mut review = Review::new("Scratch that!");
+        review.hold(audio, &segments, "Hello there. How are you?");
+
+        let texts: Vec<&str> =
+            review.sentences.iter().map(|sentence| sentence.text.as_str()).collect();
+        assert_eq!(texts, ["Hello there.", "How are you?"]);
+        // Split halfway into the pause, to the frame
+        assert!(review.sentences[0].audio.len().abs_diff(1500 * SAMPLES_PER_MS) <= 480);
+
+        assert!(review.is_phrase(&[Segment::new(" scratch that.", 0, 500)]));
+        assert!(review.arm());
+        let mut rerecorded = tone(1000, 0.5);
+        let rerecording = review.splice(&mut rerecorded).unwrap();
+        assert_eq!(rerecording.start, review.sentences[0].audio.len());
+
+        let segments = [
+            Segment::new("Hello there.", 0, 1000),
+            Segment::new("How is it going?", 1600, 2400),
+        ];
+        let replaced = review.replace_last(&rerecording, &rerecorded, &segments);
+        assert_eq!(
+            replaced,
+            Some(("How are you?".to_string(), "How is it going?".to_string()))
+        );
+        assert_eq!(review.sentences[1].audio.len(), 1000 * SAMPLES_PER_MS);
+    }
+
+    #[test]
+    fn test_hold_nothing_unless_last_sentence_was_typed() {
+        let (audio, segments) = two_sentences();
+        let mut review = Review::new("scratch that");
+        review.hold(audio, &segments, "Bonjour. Comment ça va ?");
+        assert!(!review.arm());
+        assert!(review.splice(&mut Vec::new()).is_none());
+    }
+}
@@ -0,0 +1,227 @@
+use anyhow::Result;
+use audio::SimpleRecorder;
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use std::{
+    fmt,
+    net::TcpListener,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use transcribe::{pcm_to_whisper, Transcriber};
+
+use crate::{
+    bundled_paths,
+    config::{Config, OutputConfig},
+    output::Output,
+    resolve_model, transcribe_options, DEFAULT_MODEL,
+};
+
+/// Cycles run before the baseline is taken, while caches and pools fill up
+const WARMUP_CYCLES: u64 = 5;
+/// Growth of the resident memory past the baseline that counts as a leak
+const MAX_RSS_GROWTH: f64 = 0.2;
+/// Rate of the synthetic recordings, as a typical microphone records
+const SYNTHETIC_RATE: u32 = 48000;
+
+/// The hidden `soak` subcommand
+pub fn command() -> Command {
+    Command::new("soak")
+        .about("Loop record, transcribe and output cycles to check long-running stability")
+        .hide(true)
+        .arg(
+            Arg::new("hours")
+                .long("hours")
+                .value_name("HOURS")
+                .help("How long to keep going")
+                .value_parser(value_parser!(f64))
+                .default_value("1"),
+        )
+        .arg(
+            Arg::new("model")
+                .short('m')
+                .long("model")
+                .value_name("MODEL")
+                .help("Model name or path; defaults to the configured model"),
+        )
+        .arg(
+            Arg::new("mic")
+                .long("mic")
+                .help("Record a second from the default microphone instead of synthetic audio")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("report-every")
+                .long("report-every")
+                .value_name("CYCLES")
+                .help("Print the resources in use after this many cycles")
+                .value_parser(value_parser!(u64))
+                .default_value("50"),
+        )
+}
+
+/// Resources of this process at one moment, where the platform tells them
+#[derive(Debug, Clone, Copy, Default)]
+struct Usage {
+    rss_kib: Option<u64>,
+    threads: Option<u64>,
+    handles: Option<u64>,
+}
+
+impl Usage {
+    #[cfg(target_os = "linux")]
+    fn now() -> Self {
+        let status = std::fs::read_to_string("/proc/self/status").unwrap_or_default();
+        let field = |name: &str| {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix(name)?.split_whitespace().next()?.parse().ok())
+        };
+        Self {
+            rss_kib: field("VmRSS:"),
+            threads: field("Threads:"),
+            handles: std::fs::read_dir("/proc/self/fd").ok().map(|fds| fds.count() as u64),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn now() -> Self {
+        Self::default()
+    }
+}
+
+impl fmt::Display for Usage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let show = |value: Option<u64>| value.map_or("?".to_string(), |value| value.to_string());
+        write!(
+            f,
+            "RSS {} MiB, {} threads, {} handles",
+            show(self.rss_kib.map(|kib| kib / 1024)),
+            show(self.threads),
+            show(self.handles)
+        )
+    }
+}
+
+/// Run the `soak` subcommand
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let (model_dir, config_path) = bundled_paths()?;
+    let config = Config::load(&config_path)?;
+    let options = transcribe_options(&config.stages, &config_path)?;
+
+    let model = matches
+        .get_one::<String>("model")
+        .cloned()
+        .or(config.model.clone())
+        .unwrap_or_else(|| DEFAULT_MODEL.to_string());
+    let model_path = if Path::new(&model).exists() {
+        PathBuf::from(&model)
+    } else {
+        resolve_model(&model_dir, &model)?
+    };
+    let use_gpu = config
+        .use_gpu
+        .unwrap_or(!transcribe::compiled_gpu_backends().is_empty());
+    println!("📚 Loading Whisper model {}...", model);
+    let transcriber = Transcriber::new_with_gpu(&model_path, use_gpu, config.gpu_device)?;
+
+    // Transcriptions go to a local server that discards them, not the focused window
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let address = listener.local_addr()?.to_string();
+    std::thread::spawn(move || {
+        for mut connection in listener.incoming().flatten() {
+            let _ = std::io::copy(&mut connection, &mut std::io::sink());
+        }
+    });
+    let mut output = Output::new(Some(&OutputConfig::Tcp(address)))?;
+
+    let mut recorder = matches.get_flag("mic").then(SimpleRecorder::new);
+    let hours = *matches.get_one::<f64>("hours").unwrap();
+    let report_every = (*matches.get_one::<u64>("report-every").unwrap()).max(1);
+    let start = Instant::now();
+    let duration = Duration::from_secs_f64(hours.max(0.0) * 3600.0);
+    println!("🔁 Soaking for {:.1}h, {}", hours, Usage::now());
+
+    let mut baseline = None;
+    let mut peak_rss = 0;
+    let mut cycles = 0u64;
+    while start.elapsed() < duration {
+        let audio = match recorder.as_mut() {
+            Some(recorder) => record_second(recorder)?,
+            None => pcm_to_whisper(&synthetic_recording(cycles), SYNTHETIC_RATE, 1)?,
+        };
+        // Transcribed on a thread of its own, as the daemon does
+        let (_abort, join) = transcriber.transcribe_cancellable(audio, &options);
+        let (segments, _) = join
+            .join()
+            .map_err(|_| anyhow::anyhow!("Transcription thread panicked"))??;
+        let text: Vec<&str> = segments.iter().map(|segment| segment.text.as_str()).collect();
+        output.deliver(&format!("{}: {}", cycles, text.join(" ")), None)?;
+
+        cycles += 1;
+        let usage = Usage::now();
+        peak_rss = peak_rss.max(usage.rss_kib.unwrap_or(0));
+        if cycles == WARMUP_CYCLES {
+            baseline = Some(usage);
+        }
+        if cycles.is_multiple_of(report_every) {
+            let elapsed = start.elapsed().as_secs();
+            println!(
+                "⏱️  {} cycles, {}h{:02}m: {}",
+                cycles,
+                elapsed / 3600,
+                elapsed / 60 % 60,
+                usage
+            );
+        }
+    }
+
+    let end = Usage::now();
+    println!("✅ {} cycles, peak RSS {} MiB, {}", cycles, peak_rss / 1024, end);
+    let Some(baseline) = baseline else {
+        println!("⚠️  Too few cycles to compare to a baseline");
+        return Ok(());
+    };
+    let mut leaks = Vec::new();
+    if let (Some(before), Some(after)) = (baseline.rss_kib, end.rss_kib) {
+        if after as f64 > before as f64 * (1.0 + MAX_RSS_GROWTH) {
+            leaks.push(format!("RSS grew from {} to {} MiB", before / 1024, after / 1024));
+        }
+    }
+    if let (Some(before), Some(after)) = (baseline.threads, end.threads) {
+        if after > before {
+            leaks.push(format!("threads went from {} to {}", before, after));
+        }
+    }
+    if let (Some(before), Some(after)) = (baseline.handles, end.handles) {
+        if after > before {
+            leaks.push(format!("handles went from {} to {}", before, after));
+        }
+    }
+    if !leaks.is_empty() {
+        return Err(anyhow::anyhow!("Resources grew during the soak: {}", leaks.join(", ")));
+    }
+    println!("🧘 No growth since cycle {}", WARMUP_CYCLES);
+    Ok(())
+}
+
+/// Record a second from the default microphone, opening and closing it like a key press
+fn record_second(recorder: &mut SimpleRecorder) -> Result<Vec<f32>> {
+    recorder.start_recording_buffered(None, |_| {})?;
+    std::thread::sleep(Duration::from_secs(1));
+    let recorded = recorder.stop_recording_buffered()?;
+    pcm_to_whisper(&recorded.samples, recorded.sample_rate, 1)
+}
+
+/// Two to four seconds of a gliding tone in noise, different every cycle
+fn synthetic_recording(cycle: u64) -> Vec<i16> {
+    let len = SYNTHETIC_RATE as u64 * (2 + cycle % 3);
+    let mut noise = cycle.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    (0..len)
+        .map(|i| {
+            noise = noise.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            let t = i as f32 / SYNTHETIC_RATE as f32;
+            let tone = (2.0 * std::f32::consts::PI * (200.0 + 100.0 * t) * t).sin() * 6000.0;
+            tone as i16 + (noise >> 54) as i16 - 512
+        })
+        .collect()
+}
@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt};
+use transcribe::{find_fillers, Fillers, Segment, TextFilter};
+
+use crate::{confident_text, typed_segments};
+
+/// Gaps between segments at least this long count as pauses
+const PAUSE_MS: i64 = 700;
+
+/// How an utterance was spoken, kept in the history for practicing talks
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpeechStats {
+    pub words: usize,
+    /// From the first word to the last, in milliseconds
+    pub speaking_ms: u64,
+    /// How often each filler was said
+    pub fillers: BTreeMap<String, usize>,
+    /// Gaps of at least 700ms between sentences
+    pub pauses: usize,
+    pub longest_pause_ms: u64,
+}
+
+impl SpeechStats {
+    /// Measure the confident `segments` of an utterance, if anything was said in them
+    pub fn measure(segments: &[Segment]) -> Option<Self> {
+        let segments: Vec<&Segment> = typed_segments(segments).collect();
+        let (first, last) = (segments.first()?, segments.last()?);
+
+        let mut stats = Self {
+            speaking_ms: (last.end_ms - first.start_ms).max(0) as u64,
+            ..Self::default()
+        };
+        for segment in &segments {
+            stats.words += segment.text.split_whitespace().filter(|word| is_word(word)).count();
+            for filler in find_fillers(&segment.text) {
+                *stats.fillers.entry(filler).or_default() += 1;
+            }
+        }
+        for pair in segments.windows(2) {
+            let gap = pair[1].start_ms - pair[0].end_ms;
+            if gap >= PAUSE_MS {
+                stats.pauses += 1;
+                stats.longest_pause_ms = stats.longest_pause_ms.max(gap as u64);
+            }
+        }
+        Some(stats)
+    }
+
+    /// Words per minute while speaking
+    pub fn words_per_minute(&self) -> f64 {
+        if self.speaking_ms == 0 {
+            return 0.0;
+        }
+        self.words as f64 * 60_000.0 / self.speaking_ms as f64
+    }
+
+    /// Fillers said in all
+    pub fn filler_count(&self) -> usize {
+        self.fillers.values().sum()
+    }
+
+    /// Add the stats of another utterance to these
+    pub fn add(&mut self, other: &Self) {
+        self.words += other.words;
+        self.speaking_ms += other.speaking_ms;
+        for (filler, count) in &other.fillers {
+            *self.fillers.entry(filler.clone()).or_default() += count;
+        }
+        self.pauses += other.pauses;
+        self.longest_pause_ms = self.longest_pause_ms.max(other.longest_pause_ms);
+    }
+}
+
+impl fmt::Display for SpeechStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.0} wpm, {} fillers", self.words_per_minute(), self.filler_count())?;
+        if !self.fillers.is_empty() {
+            let fillers: Vec<String> = self
+                .fillers
+                .iter()
+                .map(|(filler, count)| format!("\"{}\" {}", filler, count))
+                .collect();
+            write!(f, " ({})", fillers.join(", "))?;
+        }
+        write!(f, ", {} pauses", self.pauses)?;
+        if self.pauses > 0 {
+            write!(f, " (longest {:.1}s)", self.longest_pause_ms as f64 / 1000.0)?;
+        }
+        Ok(())
+    }
+}
+
+/// Leave the fillers out of the text of `segments`, returning their confident text as
+/// said if there were any and something is left to type
+pub fn remove_fillers(segments: &mut [Segment]) -> Option<String> {
+    let said = confident_text(segments);
+    let mut removed = false;
+    for segment in segments.iter_mut() {
+        if !find_fillers(&segment.text).is_empty() {
+            segment.text = Fillers.apply(&segment.text);
+            removed = true;
+        }
+    }
+    (removed && !confident_text(segments).is_empty()).then_some(said)
+}
+
+/// Whether `token` is a word rather than punctuation on its own, such as a dash
+fn is_word(token: &str) -> bool {
+    token.chars().any(char::is_alphanumeric)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(text: &str, start_ms: i64, end_ms: i64, probability: f32) -> Segment {
+        Segment {
+            probability,
+            ..Segment::new(text, start_ms, end_ms)
+        }
+    }
+
+    #[test]
+    fn test_measure() {
+        let segments = [
+            segment("Um, so I think it works.", 0, 2000, 0.9),
+            segment("", 2000, 2500, 0.9),
+            segment("Uh, right - um.", 3000, 4000, 0.8),
+            // Not typed, so not counted
+            segment("Hmm.", 4500, 5000, 0.1),
+        ];
+        let stats = SpeechStats::measure(&segments).unwrap();
+        assert_eq!(stats.words, 9);
+        assert_eq!(stats.speaking_ms, 4000);
+        assert_eq!(stats.words_per_minute(), 135.0);
+        assert_eq!(stats.filler_count(), 3);
+        assert_eq!(stats.fillers.get("um"), Some(&2));
+        assert_eq!(stats.fillers.get("uh"), Some(&1));
+        assert_eq!((stats.pauses, stats.longest_pause_ms), (1, 1000));
+        assert_eq!(
+            stats.to_string(),
+            "135 wpm, 3 fillers (\"uh\" 1, \"um\" 2), 1 pauses (longest 1.0s)"
+        );
+
+        let mut total = stats.clone();
+        total.add(&SpeechStats::measure(&[segment("Fine.", 0, 500, 1.0)]).unwrap());
+        assert_eq!((total.words, total.speaking_ms), (10, 4500));
+        assert_eq!((total.filler_count(), total.pauses, total.longest_pause_ms), (3, 1, 1000));
+
+        assert!(SpeechStats::measure(&[]).is_none());
+        assert!(SpeechStats::measure(&[segment("", 0, 1000, 1.0)]).is_none());
+        assert!(SpeechStats::measure(&[segment("Hi.", 0, 1000, 0.1)]).is_none());
+    }
+
+    #[test]
+    fn test_remove_fillers() {
+        let mut segments = [
+            segment(" Um, so I think, you know, it works.", 0, 2000, 0.9),
+            segment(" Uh, right.", 2000, 3000, 0.9),
+            segment(" Do you know why?", 3000, 4000, 0.9),
+        ];
+        let raw = remove_fillers(&mut segments);
+        assert_eq!(
+            raw.as_deref(),
+            Some("Um, so I think, you know, it works. Uh, right. Do you know why?")
+        );
+        let texts: Vec<&str> = segments.iter().map(|segment| segment.text.as_str()).collect();
+        assert_eq!(texts, ["So I think, it works.", "Right.", " Do you know why?"]);
+
+        // Nothing to keep the raw text of
+        let mut unchanged = [segment(" Do you know why?", 0, 1000, 0.9)];
+        assert_eq!(remove_fillers(&mut unchanged), None);
+        assert_eq!(unchanged[0].text, " Do you know why?");
+
+        // Nothing is typed, so there is no entry to keep it with
+        let mut all_fillers = [segment(" Um.", 0, 500, 0.9), segment(" Uh, hmm.", 500, 1000, 0.9)];
+        assert_eq!(remove_fillers(&mut all_fillers), None);
+        assert!(all_fillers.iter().all(|segment| segment.text.is_empty()));
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::{
+    fs::File,
+    io::Write,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+
+/// A stage of an utterance, as a complete event of the Chrome trace event format
+#[derive(Serialize)]
+struct TraceEvent {
+    name: String,
+    ph: &'static str,
+    /// Start in microseconds since the tracer was created
+    ts: u64,
+    /// Length in microseconds
+    dur: u64,
+    pid: u32,
+    /// The utterance, so that each gets a track of its own
+    tid: u64,
+}
+
+/// Records how long each pipeline stage of every utterance takes, and writes them to a
+/// file that chrome://tracing and ui.perfetto.dev open
+///
+/// Each utterance's events are appended to the file as it finishes, leaving the JSON
+/// array open as the trace event format allows, so neither memory nor the time to write
+/// grows over a long session.
+pub struct Tracer {
+    epoch: Instant,
+    utterances: AtomicU64,
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl Tracer {
+    /// Start a new trace at `path`, replacing the one of an earlier run
+    pub fn create(path: PathBuf) -> Result<Self> {
+        let file = File::create(&path)
+            .and_then(|mut file| file.write_all(b"[\n").map(|_| file))
+            .map_err(|e| anyhow::anyhow!("Failed to create trace {:?}: {}", path, e))?;
+        Ok(Self {
+            epoch: Instant::now(),
+            utterances: AtomicU64::new(0),
+            path,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Start tracing the next utterance, now
+    pub fn utterance(self: &Arc<Self>) -> UtteranceTrace {
+        let now = Instant::now();
+        UtteranceTrace {
+            tracer: Arc::clone(self),
+            id: self.utterances.fetch_add(1, Ordering::Relaxed) + 1,
+            start: now,
+            last: now,
+            events: Vec::new(),
+        }
+    }
+
+    fn event(&self, utterance: u64, name: &str, start: Instant, end: Instant) -> TraceEvent {
+        TraceEvent {
+            name: name.to_string(),
+            ph: "X",
+            ts: start.duration_since(self.epoch).as_micros() as u64,
+            dur: end.duration_since(start).as_micros() as u64,
+            pid: std::process::id(),
+            tid: utterance,
+        }
+    }
+
+    /// Append the stages of an utterance
+    fn write(&self, events: &[TraceEvent]) -> Result<()> {
+        let mut json = String::new();
+        for event in events {
+            json.push_str(&serde_json::to_string(event)?);
+            json.push_str(",\n");
+        }
+        let mut file = self
+            .file
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Failed to acquire trace lock"))?;
+        file.write_all(json.as_bytes())
+            .map_err(|e| anyhow::anyhow!("Failed to write trace {:?}: {}", self.path, e))
+    }
+}
+
+/// The stages of one utterance, each from the end of the one before
+pub struct UtteranceTrace {
+    tracer: Arc<Tracer>,
+    id: u64,
+    start: Instant,
+    last: Instant,
+    events: Vec<TraceEvent>,
+}
+
+impl UtteranceTrace {
+    /// End the stage `name`, which started when the one before ended
+    pub fn stage(&mut self, name: &str) {
+        let now = Instant::now();
+        self.events.push(self.tracer.event(self.id, name, self.last, now));
+        self.last = now;
+    }
+
+    /// End the utterance, spanning all its stages, and add it to the trace file
+    pub fn finish(mut self) {
+        let utterance = self.tracer.event(self.id, "utterance", self.start, Instant::now());
+        self.events.push(utterance);
+        if let Err(e) = self.tracer.write(&self.events) {
+            eprintln!("{}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utterances_are_appended() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trace.json");
+        let tracer = Arc::new(Tracer::create(path.clone()).unwrap());
+        for _ in 0..2 {
+            let mut trace = tracer.utterance();
+            trace.stage("record");
+            trace.stage("transcribe");
+            trace.finish();
+        }
+
+        // Valid JSON once the array is closed, as trace viewers do
+        let written = std::fs::read_to_string(&path).unwrap();
+        let closed = format!("{}]", written.trim_end().trim_end_matches(','));
+        let events: Vec<serde_json::Value> = serde_json::from_str(&closed).unwrap();
+        let names: Vec<&str> = events.iter().map(|event| event["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["record", "transcribe", "utterance"].repeat(2));
+        assert_eq!(events[3]["tid"], 2);
+    }
+}
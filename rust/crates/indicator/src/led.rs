@@ -0,0 +1,254 @@
+use crate::State;
+use anyhow::Result;
+use serde::Deserialize;
+use std::{
+    sync::mpsc::{self, RecvTimeoutError},
+    time::Duration,
+};
+
+/// Time the LED stays on or off while blinking
+const BLINK_TIME: Duration = Duration::from_millis(500);
+
+/// What the blinking thread is told
+enum Message {
+    State(State),
+    /// Put the LED back now and reply once it is
+    Restore(mpsc::Sender<()>),
+}
+
+/// A keyboard lock LED
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Led {
+    #[default]
+    ScrollLock,
+    CapsLock,
+    NumLock,
+}
+
+/// Blinks a keyboard LED while recording, for feedback without anything on screen
+///
+/// On Linux the LED is switched through `/sys/class/leds`, which leaves the
+/// lock itself alone but needs write access to the LED's `brightness` file
+/// (root or a udev rule). On Windows the lock key itself is toggled, so Scroll
+/// Lock is the one to pick. Other platforms are not supported. The LED goes
+/// back to its original state when recording stops.
+#[derive(Clone)]
+pub struct KeyboardLed {
+    messages: mpsc::Sender<Message>,
+}
+
+impl KeyboardLed {
+    pub fn new(led: Led) -> Result<Self> {
+        let mut handle = platform::Handle::open(led)?;
+        let (messages, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut recording = false;
+            let mut lit = None;
+            loop {
+                match receiver.recv_timeout(BLINK_TIME) {
+                    Ok(Message::State(state)) => recording = state == State::Recording,
+                    Ok(Message::Restore(done)) => {
+                        if lit.take().is_some() {
+                            let _ = handle.restore();
+                        }
+                        recording = false;
+                        let _ = done.send(());
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+
+                let result = if recording {
+                    let on = !lit.unwrap_or(false);
+                    lit = Some(on);
+                    handle.set(on)
+                } else if lit.take().is_some() {
+                    handle.restore()
+                } else {
+                    Ok(())
+                };
+                if let Err(e) = result {
+                    eprintln!("Failed to switch keyboard LED: {}", e);
+                }
+            }
+            if lit.is_some() {
+                let _ = handle.restore();
+            }
+        });
+        Ok(Self { messages })
+    }
+
+    /// Blink while `state` is [`State::Recording`], otherwise leave the LED alone
+    pub fn set(&self, state: State) {
+        // The thread only exits after all senders are gone
+        let _ = self.messages.send(Message::State(state));
+    }
+
+    /// Stop blinking and put the LED back as it was, returning once it is, e.g. before
+    /// exiting on a signal
+    pub fn restore(&self) {
+        let (done, restored) = mpsc::channel();
+        if self.messages.send(Message::Restore(done)).is_ok() {
+            let _ = restored.recv_timeout(BLINK_TIME);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::Led;
+    use anyhow::Result;
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    /// The matching LEDs of every keyboard, with their original brightness
+    pub struct Handle {
+        leds: Vec<(PathBuf, String)>,
+    }
+
+    impl Handle {
+        pub fn open(led: Led) -> Result<Self> {
+            let name = match led {
+                Led::ScrollLock => "scrolllock",
+                Led::CapsLock => "capslock",
+                Led::NumLock => "numlock",
+            };
+            let suffix = format!("::{}", name);
+
+            let entries = fs::read_dir("/sys/class/leds")
+                .map_err(|e| anyhow::anyhow!("Failed to list /sys/class/leds: {}", e))?;
+            let mut leds = Vec::new();
+            for entry in entries.flatten() {
+                if !entry.file_name().to_string_lossy().ends_with(&suffix) {
+                    continue;
+                }
+                let path = entry.path().join("brightness");
+                let original = fs::read_to_string(&path)
+                    .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+                fs::OpenOptions::new().write(true).open(&path).map_err(|e| {
+                    anyhow::anyhow!(
+                        "Can't write {} ({}); run as root or allow it with a udev rule",
+                        path.display(),
+                        e
+                    )
+                })?;
+                leds.push((path, original.trim().to_string()));
+            }
+
+            if leds.is_empty() {
+                return Err(anyhow::anyhow!("No {} LED found in /sys/class/leds", name));
+            }
+            Ok(Self { leds })
+        }
+
+        pub fn set(&mut self, on: bool) -> Result<()> {
+            for (path, _) in &self.leds {
+                write(path, if on { "1" } else { "0" })?;
+            }
+            Ok(())
+        }
+
+        pub fn restore(&mut self) -> Result<()> {
+            for (path, original) in &self.leds {
+                write(path, original)?;
+            }
+            Ok(())
+        }
+    }
+
+    fn write(path: &Path, brightness: &str) -> Result<()> {
+        fs::write(path, brightness)
+            .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::Led;
+    use anyhow::Result;
+    use windows::Win32::UI::Input::KeyboardAndMouse::{
+        GetKeyState, SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYBD_EVENT_FLAGS,
+        KEYEVENTF_KEYUP, VIRTUAL_KEY, VK_CAPITAL, VK_NUMLOCK, VK_SCROLL,
+    };
+
+    /// The lock key, with whether it was on at the start
+    pub struct Handle {
+        key: VIRTUAL_KEY,
+        original: bool,
+    }
+
+    impl Handle {
+        pub fn open(led: Led) -> Result<Self> {
+            let key = match led {
+                Led::ScrollLock => VK_SCROLL,
+                Led::CapsLock => VK_CAPITAL,
+                Led::NumLock => VK_NUMLOCK,
+            };
+            Ok(Self {
+                key,
+                original: is_on(key),
+            })
+        }
+
+        pub fn set(&mut self, on: bool) -> Result<()> {
+            if is_on(self.key) == on {
+                return Ok(());
+            }
+            // Tap the lock key; its LED follows
+            let input = |flags| INPUT {
+                r#type: INPUT_KEYBOARD,
+                Anonymous: INPUT_0 {
+                    ki: KEYBDINPUT {
+                        wVk: self.key,
+                        dwFlags: flags,
+                        ..Default::default()
+                    },
+                },
+            };
+            let inputs = [input(KEYBD_EVENT_FLAGS(0)), input(KEYEVENTF_KEYUP)];
+            let sent = unsafe { SendInput(&inputs, std::mem::size_of::<INPUT>() as i32) };
+            if sent as usize != inputs.len() {
+                return Err(anyhow::anyhow!(
+                    "Failed to toggle lock key: {}",
+                    windows::core::Error::from_win32()
+                ));
+            }
+            Ok(())
+        }
+
+        pub fn restore(&mut self) -> Result<()> {
+            self.set(self.original)
+        }
+    }
+
+    /// Whether the lock is toggled on; the low bit of the key state
+    fn is_on(key: VIRTUAL_KEY) -> bool {
+        unsafe { GetKeyState(key.0 as i32) & 1 != 0 }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod platform {
+    use super::Led;
+    use anyhow::Result;
+
+    pub enum Handle {}
+
+    impl Handle {
+        pub fn open(_led: Led) -> Result<Self> {
+            Err(anyhow::anyhow!("Keyboard LED feedback is not supported on this platform"))
+        }
+
+        pub fn set(&mut self, _on: bool) -> Result<()> {
+            match *self {}
+        }
+
+        pub fn restore(&mut self) -> Result<()> {
+            match *self {}
+        }
+    }
+}
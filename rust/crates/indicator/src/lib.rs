@@ -0,0 +1,166 @@
+use anyhow::Result;
+use serde::Deserialize;
+use std::{
+    sync::mpsc::{self, RecvTimeoutError},
+    time::Duration,
+};
+
+mod led;
+#[cfg(not(any(target_os = "linux", windows)))]
+mod unsupported;
+#[cfg(windows)]
+mod win32;
+#[cfg(target_os = "linux")]
+mod xwindow;
+
+#[cfg(not(any(target_os = "linux", windows)))]
+use unsupported::Surface;
+#[cfg(windows)]
+use win32::Surface;
+#[cfg(target_os = "linux")]
+use xwindow::Surface;
+
+pub use led::{KeyboardLed, Led};
+
+/// Time between spinner frames
+const FRAME_TIME: Duration = Duration::from_millis(80);
+/// Degrees the spinner turns per frame
+const SPINNER_STEP: i32 = 30;
+/// Gap between the indicator and the cursor or the screen edge, in pixels
+const MARGIN: i32 = 16;
+
+/// What the indicator shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    Hidden,
+    /// A red dot: the microphone is live
+    Recording,
+    /// A spinning ring: the recording is being transcribed
+    Transcribing,
+}
+
+/// Where the indicator appears
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Position {
+    /// Next to the mouse cursor, where it is when the indicator appears
+    Cursor,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+/// A small always-on-top dot showing whether the microphone is live
+///
+/// The dot is a borderless window that ignores clicks, drawn on a background
+/// thread. It is removed when the last clone is dropped. Uses X11 on Linux
+/// (XWayland on Wayland desktops) and Win32 on Windows; other platforms are
+/// not supported yet.
+#[derive(Clone)]
+pub struct Indicator {
+    states: mpsc::Sender<State>,
+}
+
+impl Indicator {
+    /// Create a hidden indicator of `size` pixels at `position`
+    pub fn new(position: Position, size: u32) -> Result<Self> {
+        let (states, receiver) = mpsc::channel();
+        let (created, result) = mpsc::channel();
+        std::thread::spawn(move || run(position, size, receiver, created));
+        result
+            .recv()
+            .map_err(|_| anyhow::anyhow!("Indicator thread exited"))??;
+        Ok(Self { states })
+    }
+
+    /// Change what the indicator shows
+    pub fn set(&self, state: State) {
+        // The thread only exits after all senders are gone
+        let _ = self.states.send(state);
+    }
+}
+
+fn run(
+    position: Position,
+    size: u32,
+    states: mpsc::Receiver<State>,
+    created: mpsc::Sender<Result<()>>,
+) {
+    let mut surface = match Surface::new(size) {
+        Ok(surface) => {
+            let _ = created.send(Ok(()));
+            surface
+        }
+        Err(e) => {
+            let _ = created.send(Err(e));
+            return;
+        }
+    };
+
+    let mut state = State::Hidden;
+    let mut frame = 0;
+    loop {
+        match states.recv_timeout(FRAME_TIME) {
+            Ok(new_state) if new_state != state => {
+                if state == State::Hidden {
+                    let (x, y) = place(position, size, surface.screen_size(), surface.cursor());
+                    surface.show(x, y);
+                } else if new_state == State::Hidden {
+                    surface.hide();
+                }
+                state = new_state;
+            }
+            Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+
+        surface.pump();
+        match state {
+            State::Hidden => {}
+            State::Recording => surface.fill_circle(0, RED),
+            State::Transcribing => {
+                let start = (frame * SPINNER_STEP) % 360;
+                surface.fill_circle(0, RING);
+                surface.fill_wedge(start, 90, SPINNER);
+                surface.fill_circle(size / 4, RING);
+                frame = (frame + 1) % (360 / SPINNER_STEP);
+            }
+        }
+        surface.flush();
+    }
+}
+
+/// Top-left corner of an indicator of `size` pixels on a screen of `screen` pixels
+fn place(position: Position, size: u32, screen: (i32, i32), cursor: (i32, i32)) -> (i32, i32) {
+    let size = size as i32;
+    let (x, y) = match position {
+        Position::Cursor => (cursor.0 + MARGIN, cursor.1 + MARGIN),
+        Position::TopLeft => (MARGIN, MARGIN),
+        Position::TopRight => (screen.0 - size - MARGIN, MARGIN),
+        Position::BottomLeft => (MARGIN, screen.1 - size - MARGIN),
+        Position::BottomRight => (screen.0 - size - MARGIN, screen.1 - size - MARGIN),
+    };
+    // Keep a dot near the cursor on screen at the right and bottom edges
+    (x.clamp(0, (screen.0 - size).max(0)), y.clamp(0, (screen.1 - size).max(0)))
+}
+
+// Colors as 0xRRGGBB
+const RED: u32 = 0xe53935;
+const RING: u32 = 0x424242;
+const SPINNER: u32 = 0x42a5f5;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_place() {
+        let screen = (1920, 1080);
+        assert_eq!(place(Position::TopLeft, 20, screen, (0, 0)), (16, 16));
+        assert_eq!(place(Position::BottomRight, 20, screen, (0, 0)), (1884, 1044));
+        assert_eq!(place(Position::Cursor, 20, screen, (100, 200)), (116, 216));
+        assert_eq!(place(Position::Cursor, 20, screen, (1910, 1075)), (1900, 1060));
+    }
+}
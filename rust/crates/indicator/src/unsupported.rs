@@ -0,0 +1,42 @@
+use anyhow::Result;
+
+/// Stands in for the indicator window on platforms without an implementation
+pub(crate) enum Surface {}
+
+impl Surface {
+    pub fn new(_size: u32) -> Result<Self> {
+        Err(anyhow::anyhow!("The recording indicator is not supported on this platform"))
+    }
+
+    pub fn screen_size(&self) -> (i32, i32) {
+        match *self {}
+    }
+
+    pub fn cursor(&self) -> (i32, i32) {
+        match *self {}
+    }
+
+    pub fn show(&mut self, _x: i32, _y: i32) {
+        match *self {}
+    }
+
+    pub fn hide(&mut self) {
+        match *self {}
+    }
+
+    pub fn fill_circle(&mut self, _inset: u32, _color: u32) {
+        match *self {}
+    }
+
+    pub fn fill_wedge(&mut self, _start: i32, _span: i32, _color: u32) {
+        match *self {}
+    }
+
+    pub fn pump(&mut self) {
+        match *self {}
+    }
+
+    pub fn flush(&mut self) {
+        match *self {}
+    }
+}
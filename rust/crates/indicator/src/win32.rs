@@ -0,0 +1,169 @@
+use anyhow::Result;
+use windows::{
+    core::w,
+    Win32::{
+        Foundation::{COLORREF, HWND, LPARAM, LRESULT, POINT, WPARAM},
+        Graphics::Gdi::{
+            CreateEllipticRgn, CreateSolidBrush, DeleteObject, Ellipse, GetDC, GetStockObject,
+            Pie, ReleaseDC, SelectObject, SetWindowRgn, NULL_PEN,
+        },
+        System::LibraryLoader::GetModuleHandleW,
+        UI::WindowsAndMessaging::{
+            CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, GetCursorPos,
+            GetSystemMetrics, PeekMessageW, RegisterClassW, SetLayeredWindowAttributes,
+            SetWindowPos, ShowWindow, TranslateMessage, HWND_TOPMOST, LWA_ALPHA, MSG, PM_REMOVE,
+            SM_CXSCREEN, SM_CYSCREEN, SWP_NOACTIVATE, SWP_NOSIZE, SWP_SHOWWINDOW, SW_HIDE,
+            WNDCLASSW, WS_EX_LAYERED, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_EX_TOPMOST,
+            WS_EX_TRANSPARENT, WS_POPUP,
+        },
+    },
+};
+
+/// A round, click-through popup window that stays above other windows
+pub(crate) struct Surface {
+    window: HWND,
+    size: u32,
+}
+
+impl Surface {
+    pub fn new(size: u32) -> Result<Self> {
+        let class = w!("voicekb-indicator");
+        // SAFETY: the window is only used from this thread and destroyed in `drop`
+        unsafe {
+            let instance = GetModuleHandleW(None)
+                .map_err(|e| anyhow::anyhow!("Failed to get module handle: {}", e))?;
+            let window_class = WNDCLASSW {
+                lpfnWndProc: Some(window_proc),
+                hInstance: instance.into(),
+                lpszClassName: class,
+                ..Default::default()
+            };
+            // Fails harmlessly when an earlier indicator registered the class
+            RegisterClassW(&window_class);
+
+            // Tool windows stay out of the taskbar; layered + transparent lets clicks through
+            let window = CreateWindowExW(
+                WS_EX_TOPMOST | WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE | WS_EX_LAYERED
+                    | WS_EX_TRANSPARENT,
+                class,
+                w!("voicekb"),
+                WS_POPUP,
+                0,
+                0,
+                size as i32,
+                size as i32,
+                None,
+                None,
+                instance,
+                None,
+            );
+            if window.0 == 0 {
+                return Err(anyhow::anyhow!(
+                    "Failed to create indicator window: {}",
+                    windows::core::Error::from_win32()
+                ));
+            }
+
+            SetLayeredWindowAttributes(window, COLORREF(0), 255, LWA_ALPHA)
+                .map_err(|e| anyhow::anyhow!("Failed to set up indicator window: {}", e))?;
+            let shape = CreateEllipticRgn(0, 0, size as i32 + 1, size as i32 + 1);
+            // The window owns the region from here on
+            SetWindowRgn(window, shape, true);
+            Ok(Self { window, size })
+        }
+    }
+
+    pub fn screen_size(&self) -> (i32, i32) {
+        unsafe { (GetSystemMetrics(SM_CXSCREEN), GetSystemMetrics(SM_CYSCREEN)) }
+    }
+
+    pub fn cursor(&self) -> (i32, i32) {
+        let mut point = POINT::default();
+        let _ = unsafe { GetCursorPos(&mut point) };
+        (point.x, point.y)
+    }
+
+    pub fn show(&mut self, x: i32, y: i32) {
+        let flags = SWP_NOSIZE | SWP_NOACTIVATE | SWP_SHOWWINDOW;
+        let _ = unsafe { SetWindowPos(self.window, HWND_TOPMOST, x, y, 0, 0, flags) };
+    }
+
+    pub fn hide(&mut self) {
+        unsafe {
+            ShowWindow(self.window, SW_HIDE);
+        }
+    }
+
+    /// Fill the circle `inset` pixels inside the window edge
+    pub fn fill_circle(&mut self, inset: u32, color: u32) {
+        let (near, far) = (inset as i32, (self.size - inset) as i32);
+        self.paint(color, |dc| unsafe {
+            Ellipse(dc, near, near, far, far);
+        });
+    }
+
+    /// Fill a pie slice starting at `start` degrees (counterclockwise from three
+    /// o'clock) and spanning `span` degrees
+    pub fn fill_wedge(&mut self, start: i32, span: i32, color: u32) {
+        let size = self.size as i32;
+        let center = size as f32 / 2.0;
+        // Pie takes the slice as two points on the rays; y grows downward
+        let point = |degrees: i32| {
+            let radians = (degrees as f32).to_radians();
+            (
+                (center + center * radians.cos()) as i32,
+                (center - center * radians.sin()) as i32,
+            )
+        };
+        let ((x1, y1), (x2, y2)) = (point(start), point(start + span));
+        self.paint(color, |dc| unsafe {
+            Pie(dc, 0, 0, size, size, x1, y1, x2, y2);
+        });
+    }
+
+    /// Handle pending window messages
+    pub fn pump(&mut self) {
+        let mut message = MSG::default();
+        unsafe {
+            while PeekMessageW(&mut message, self.window, 0, 0, PM_REMOVE).as_bool() {
+                TranslateMessage(&message);
+                DispatchMessageW(&message);
+            }
+        }
+    }
+
+    /// GDI draws straight to the window
+    pub fn flush(&mut self) {}
+
+    /// Draw with a solid brush of `color` (0xRRGGBB) and no outline
+    fn paint<F: FnOnce(windows::Win32::Graphics::Gdi::HDC)>(&self, color: u32, draw: F) {
+        // COLORREF is 0x00BBGGRR
+        let bgr = ((color & 0xff) << 16) | (color & 0xff00) | ((color >> 16) & 0xff);
+        unsafe {
+            let dc = GetDC(self.window);
+            let brush = CreateSolidBrush(COLORREF(bgr));
+            let old_brush = SelectObject(dc, brush);
+            let old_pen = SelectObject(dc, GetStockObject(NULL_PEN));
+            draw(dc);
+            SelectObject(dc, old_pen);
+            SelectObject(dc, old_brush);
+            DeleteObject(brush);
+            ReleaseDC(self.window, dc);
+        }
+    }
+}
+
+impl Drop for Surface {
+    fn drop(&mut self) {
+        let _ = unsafe { DestroyWindow(self.window) };
+    }
+}
+
+unsafe extern "system" fn window_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    DefWindowProcW(window, message, wparam, lparam)
+}
@@ -0,0 +1,194 @@
+use anyhow::Result;
+use std::{mem, os::raw::c_int, ptr};
+use x11::{xfixes, xlib};
+
+// Shape kinds from the X Shape extension, used through XFixes regions
+const SHAPE_BOUNDING: c_int = 0;
+const SHAPE_INPUT: c_int = 2;
+
+/// A round, click-through X11 window that stays above other windows
+pub(crate) struct Surface {
+    display: *mut xlib::Display,
+    window: xlib::Window,
+    gc: xlib::GC,
+    size: u32,
+}
+
+impl Surface {
+    pub fn new(size: u32) -> Result<Self> {
+        // SAFETY: the display connection and everything created on it are only
+        // used from this thread and released in `drop`
+        unsafe {
+            let display = xlib::XOpenDisplay(ptr::null());
+            if display.is_null() {
+                return Err(anyhow::anyhow!("Failed to open the X display; is DISPLAY set?"));
+            }
+            let screen = xlib::XDefaultScreen(display);
+            let root = xlib::XRootWindow(display, screen);
+
+            // Override-redirect keeps the window manager from decorating or focusing it
+            let mut attributes: xlib::XSetWindowAttributes = mem::zeroed();
+            attributes.override_redirect = xlib::True;
+            attributes.background_pixel = xlib::XBlackPixel(display, screen);
+            let window = xlib::XCreateWindow(
+                display,
+                root,
+                0,
+                0,
+                size,
+                size,
+                0,
+                xlib::CopyFromParent,
+                xlib::InputOutput as u32,
+                ptr::null_mut(),
+                xlib::CWOverrideRedirect | xlib::CWBackPixel,
+                &mut attributes,
+            );
+
+            // Cut the window to a circle, one rectangle per row, and let clicks through
+            let mut rows = circle_rows(size);
+            let shape =
+                xfixes::XFixesCreateRegion(display, rows.as_mut_ptr(), rows.len() as c_int);
+            xfixes::XFixesSetWindowShapeRegion(display, window, SHAPE_BOUNDING, 0, 0, shape);
+            xfixes::XFixesDestroyRegion(display, shape);
+            let empty = xfixes::XFixesCreateRegion(display, ptr::null_mut(), 0);
+            xfixes::XFixesSetWindowShapeRegion(display, window, SHAPE_INPUT, 0, 0, empty);
+            xfixes::XFixesDestroyRegion(display, empty);
+
+            let gc = xlib::XCreateGC(display, window, 0, ptr::null_mut());
+            xlib::XFlush(display);
+            Ok(Self {
+                display,
+                window,
+                gc,
+                size,
+            })
+        }
+    }
+
+    pub fn screen_size(&self) -> (i32, i32) {
+        unsafe {
+            let screen = xlib::XDefaultScreen(self.display);
+            (
+                xlib::XDisplayWidth(self.display, screen),
+                xlib::XDisplayHeight(self.display, screen),
+            )
+        }
+    }
+
+    pub fn cursor(&self) -> (i32, i32) {
+        let (mut root, mut child) = (0, 0);
+        let (mut x, mut y, mut window_x, mut window_y, mut mask) = (0, 0, 0, 0, 0);
+        unsafe {
+            let screen = xlib::XDefaultScreen(self.display);
+            xlib::XQueryPointer(
+                self.display,
+                xlib::XRootWindow(self.display, screen),
+                &mut root,
+                &mut child,
+                &mut x,
+                &mut y,
+                &mut window_x,
+                &mut window_y,
+                &mut mask,
+            );
+        }
+        (x, y)
+    }
+
+    pub fn show(&mut self, x: i32, y: i32) {
+        unsafe {
+            xlib::XMoveWindow(self.display, self.window, x, y);
+            xlib::XMapRaised(self.display, self.window);
+        }
+    }
+
+    pub fn hide(&mut self) {
+        unsafe {
+            xlib::XUnmapWindow(self.display, self.window);
+        }
+        self.flush();
+    }
+
+    /// Fill the circle `inset` pixels inside the window edge
+    pub fn fill_circle(&mut self, inset: u32, color: u32) {
+        let diameter = self.size.saturating_sub(2 * inset);
+        let offset = inset as c_int;
+        unsafe {
+            xlib::XSetForeground(self.display, self.gc, color.into());
+            xlib::XFillArc(
+                self.display,
+                self.window,
+                self.gc,
+                offset,
+                offset,
+                diameter,
+                diameter,
+                0,
+                360 * 64,
+            );
+        }
+    }
+
+    /// Fill a pie slice starting at `start` degrees (counterclockwise from three
+    /// o'clock) and spanning `span` degrees
+    pub fn fill_wedge(&mut self, start: i32, span: i32, color: u32) {
+        unsafe {
+            xlib::XSetForeground(self.display, self.gc, color.into());
+            xlib::XFillArc(
+                self.display,
+                self.window,
+                self.gc,
+                0,
+                0,
+                self.size,
+                self.size,
+                start * 64,
+                span * 64,
+            );
+        }
+    }
+
+    /// Handle pending window events; the window asks for none, so drop whatever arrives
+    pub fn pump(&mut self) {
+        unsafe {
+            while xlib::XPending(self.display) > 0 {
+                let mut event: xlib::XEvent = mem::zeroed();
+                xlib::XNextEvent(self.display, &mut event);
+            }
+        }
+    }
+
+    pub fn flush(&mut self) {
+        unsafe {
+            xlib::XFlush(self.display);
+        }
+    }
+}
+
+impl Drop for Surface {
+    fn drop(&mut self) {
+        unsafe {
+            xlib::XFreeGC(self.display, self.gc);
+            xlib::XDestroyWindow(self.display, self.window);
+            xlib::XCloseDisplay(self.display);
+        }
+    }
+}
+
+/// The rows of a circle of `size` pixels as one rectangle each
+fn circle_rows(size: u32) -> Vec<xlib::XRectangle> {
+    let radius = size as f32 / 2.0;
+    (0..size)
+        .map(|row| {
+            let dy = row as f32 + 0.5 - radius;
+            let half_width = (radius * radius - dy * dy).max(0.0).sqrt();
+            xlib::XRectangle {
+                x: (radius - half_width).round() as i16,
+                y: row as i16,
+                width: (2.0 * half_width).round() as u16,
+                height: 1,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,126 @@
+use evdev_rs::{
+    enums::{EventCode, EV_KEY},
+    Device, GrabMode, ReadFlag, ReadStatus,
+};
+use std::{
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+
+const INPUT_DIR: &str = "/dev/input";
+
+/// Watch the call and mute buttons of headsets, calling `callback` with `true`
+/// when one is pressed and `false` when it is released
+///
+/// Headsets report the buttons of the HID telephony page as `KEY_MICMUTE` and
+/// `KEY_PHONE`; with `play_pause`, the play/pause button that Bluetooth headsets
+/// often have counts too. Only input devices whose name contains `device`
+/// (ignoring case) are watched, or every device with such a button without one.
+/// Needs read access to `/dev/input/event*`, usually through the `input` group.
+///
+/// Blocks for as long as one of the devices can be read.
+pub fn watch_headset<F>(device: Option<&str>, play_pause: bool, mut callback: F) -> io::Result<()>
+where
+    F: FnMut(bool),
+{
+    let mut buttons = vec![EV_KEY::KEY_MICMUTE, EV_KEY::KEY_PHONE];
+    if play_pause {
+        buttons.push(EV_KEY::KEY_PLAYPAUSE);
+    }
+
+    let paths = find_devices(device, &buttons)?;
+    if paths.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            match device {
+                Some(name) => format!("No input device named '{}' with a headset button", name),
+                None => "No input device with a headset button".to_string(),
+            },
+        ));
+    }
+
+    // Devices hold raw libevdev handles, so each is opened on its own reader thread
+    let (events, received) = mpsc::channel();
+    for path in paths {
+        let (events, buttons) = (events.clone(), buttons.clone());
+        std::thread::spawn(move || {
+            if let Err(e) = read_buttons(&path, &buttons, false, |pressed| {
+                let _ = events.send(pressed);
+            }) {
+                eprintln!("Stopped reading {}: {}", path.display(), e);
+            }
+        });
+    }
+    drop(events);
+
+    for pressed in received {
+        callback(pressed);
+    }
+    Err(io::Error::other("All headset devices were disconnected"))
+}
+
+/// Event devices named like `name` that have one of `buttons`
+pub(crate) fn find_devices(name: Option<&str>, buttons: &[EV_KEY]) -> io::Result<Vec<PathBuf>> {
+    let name = name.map(str::to_lowercase);
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(INPUT_DIR)?.flatten() {
+        let path = entry.path();
+        let is_event = path
+            .file_name()
+            .is_some_and(|file| file.to_string_lossy().starts_with("event"));
+        if !is_event {
+            continue;
+        }
+        // Devices we may not read are skipped, like the ones without buttons
+        let Ok(device) = File::open(&path).and_then(Device::new_from_fd) else {
+            continue;
+        };
+
+        let device_name = device.name().unwrap_or_default().to_lowercase();
+        let named = name.as_ref().is_none_or(|name| device_name.contains(name));
+        let has_button = buttons
+            .iter()
+            .any(|button| device.has_event_code(&EventCode::EV_KEY(button.clone())));
+        if named && has_button {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Report presses and releases of `buttons` on the device at `path` until it fails
+///
+/// With `grab`, no other program receives the device's events while it is read.
+pub(crate) fn read_buttons<F>(
+    path: &Path,
+    buttons: &[EV_KEY],
+    grab: bool,
+    mut report: F,
+) -> io::Result<()>
+where
+    F: FnMut(bool),
+{
+    let mut device = Device::new_from_fd(File::open(path)?)?;
+    if grab {
+        device.grab(GrabMode::Grab)?;
+    }
+    loop {
+        let (status, event) = match device.next_event(ReadFlag::NORMAL | ReadFlag::BLOCKING) {
+            Ok(next) => next,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+            Err(e) => return Err(e),
+        };
+        if status == ReadStatus::Sync {
+            continue;
+        }
+        if let EventCode::EV_KEY(key) = &event.event_code {
+            // 0 is a release, 1 a press and 2 an auto-repeat
+            if buttons.contains(key) && event.value < 2 {
+                report(event.value == 1);
+            }
+        }
+    }
+}
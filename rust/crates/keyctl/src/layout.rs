@@ -0,0 +1,239 @@
+/// Language of the active keyboard layout as an ISO 639-1 code such as "nl", or
+/// `None` when it can't be told
+///
+/// Bilingual typists switch layouts with the language they write in, so this tells
+/// which language they are about to dictate. On Windows it is the layout of the
+/// focused window, on macOS the current input source and on Linux the most recently
+/// used input source of GNOME. Call it from the main thread on macOS.
+pub fn keyboard_language() -> Option<String> {
+    platform::keyboard_language()
+}
+
+#[cfg(windows)]
+mod platform {
+    use windows::Win32::UI::Input::KeyboardAndMouse::GetKeyboardLayout;
+    use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
+
+    pub fn keyboard_language() -> Option<String> {
+        // SAFETY: both calls only read state of the focused window's thread
+        let layout = unsafe {
+            let thread = GetWindowThreadProcessId(GetForegroundWindow(), None);
+            GetKeyboardLayout(thread)
+        };
+        // The low word is the language identifier, whose low ten bits are the language
+        let primary = (layout.0 as usize & 0x3ff) as u16;
+        primary_language(primary).map(str::to_string)
+    }
+
+    /// ISO 639-1 code of a Windows primary language identifier
+    fn primary_language(primary: u16) -> Option<&'static str> {
+        let code = match primary {
+            0x01 => "ar",
+            0x02 => "bg",
+            0x03 => "ca",
+            0x04 => "zh",
+            0x05 => "cs",
+            0x06 => "da",
+            0x07 => "de",
+            0x08 => "el",
+            0x09 => "en",
+            0x0a => "es",
+            0x0b => "fi",
+            0x0c => "fr",
+            0x0d => "he",
+            0x0e => "hu",
+            0x0f => "is",
+            0x10 => "it",
+            0x11 => "ja",
+            0x12 => "ko",
+            0x13 => "nl",
+            0x14 => "no",
+            0x15 => "pl",
+            0x16 => "pt",
+            0x18 => "ro",
+            0x19 => "ru",
+            0x1a => "hr",
+            0x1b => "sk",
+            0x1d => "sv",
+            0x1e => "th",
+            0x1f => "tr",
+            0x21 => "id",
+            0x22 => "uk",
+            0x24 => "sl",
+            0x25 => "et",
+            0x26 => "lv",
+            0x27 => "lt",
+            0x29 => "fa",
+            0x2a => "vi",
+            0x39 => "hi",
+            _ => return None,
+        };
+        Some(code)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::{c_char, c_void, CStr};
+
+    type CFTypeRef = *const c_void;
+
+    const CF_STRING_ENCODING_UTF8: u32 = 0x0800_0100;
+
+    #[link(name = "Carbon", kind = "framework")]
+    extern "C" {
+        #[allow(non_upper_case_globals)]
+        static kTISPropertyInputSourceLanguages: CFTypeRef;
+        fn TISCopyCurrentKeyboardInputSource() -> CFTypeRef;
+        fn TISGetInputSourceProperty(source: CFTypeRef, key: CFTypeRef) -> CFTypeRef;
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFArrayGetCount(array: CFTypeRef) -> isize;
+        fn CFArrayGetValueAtIndex(array: CFTypeRef, index: isize) -> CFTypeRef;
+        fn CFStringGetCString(
+            string: CFTypeRef,
+            buffer: *mut c_char,
+            size: isize,
+            encoding: u32,
+        ) -> u8;
+        fn CFRelease(value: CFTypeRef);
+    }
+
+    pub fn keyboard_language() -> Option<String> {
+        // SAFETY: the property and its array belong to the copied source, which is
+        // released only after the language has been copied out of them
+        unsafe {
+            let source = TISCopyCurrentKeyboardInputSource();
+            if source.is_null() {
+                return None;
+            }
+            let languages = TISGetInputSourceProperty(source, kTISPropertyInputSourceLanguages);
+            let mut language = None;
+            if !languages.is_null() && CFArrayGetCount(languages) > 0 {
+                // The most relevant language comes first, e.g. "nl" or "zh-Hans"
+                let first = CFArrayGetValueAtIndex(languages, 0);
+                let mut buffer = [0 as c_char; 64];
+                let size = buffer.len() as isize;
+                if CFStringGetCString(first, buffer.as_mut_ptr(), size, CF_STRING_ENCODING_UTF8)
+                    != 0
+                {
+                    let tag = CStr::from_ptr(buffer.as_ptr()).to_string_lossy();
+                    language = tag.split('-').next().map(str::to_string);
+                }
+            }
+            CFRelease(source);
+            language
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::process::Command;
+
+    pub fn keyboard_language() -> Option<String> {
+        let output = Command::new("gsettings")
+            .args(["get", "org.gnome.desktop.input-sources", "mru-sources"])
+            .output()
+            .ok()?;
+        let sources = String::from_utf8_lossy(&output.stdout);
+        first_xkb_layout(&sources)
+            .and_then(layout_language)
+            .map(str::to_string)
+    }
+
+    /// The first XKB layout in a GVariant list such as `[('xkb', 'nl'), ('xkb', 'us')]`
+    pub(super) fn first_xkb_layout(sources: &str) -> Option<&str> {
+        let start = sources.find("('xkb', '")? + "('xkb', '".len();
+        let end = sources[start..].find('\'')?;
+        Some(&sources[start..start + end])
+    }
+
+    /// ISO 639-1 code of the language an XKB layout such as `de` or `us+intl` is for
+    ///
+    /// Layouts used for more than one language, such as Belgian (Dutch and French) or
+    /// Indian (many scripts), leave it to detection unless their variant tells.
+    pub(super) fn layout_language(layout: &str) -> Option<&'static str> {
+        let (country, variant) = layout.split_once('+').unwrap_or((layout, ""));
+        let code = match (country, variant) {
+            ("ca" | "in", "eng") => "en",
+            ("ch", variant) if variant.starts_with("fr") => "fr",
+            ("be" | "in", _) => return None,
+            _ => country_language(country)?,
+        };
+        Some(code)
+    }
+
+    /// Language of the default layout of `country`, an XKB layout name such as `de`
+    fn country_language(country: &str) -> Option<&'static str> {
+        let code = match country {
+            "us" | "gb" | "au" | "ie" | "nz" | "za" => "en",
+            "ara" | "eg" | "ma" | "sy" | "iq" => "ar",
+            "nl" => "nl",
+            "br" | "pt" => "pt",
+            "ch" | "de" | "at" => "de",
+            "cn" | "tw" => "zh",
+            "cz" => "cs",
+            "dk" => "da",
+            "ee" => "et",
+            "es" | "latam" | "mx" => "es",
+            "fi" => "fi",
+            "ca" | "fr" => "fr",
+            "gr" => "el",
+            "hr" => "hr",
+            "hu" => "hu",
+            "il" => "he",
+            "ir" => "fa",
+            "is" => "is",
+            "it" => "it",
+            "jp" => "ja",
+            "kr" => "ko",
+            "lt" => "lt",
+            "lv" => "lv",
+            "no" => "no",
+            "pl" => "pl",
+            "ro" => "ro",
+            "ru" => "ru",
+            "se" => "sv",
+            "si" => "sl",
+            "sk" => "sk",
+            "th" => "th",
+            "tr" => "tr",
+            "ua" => "uk",
+            "vn" => "vi",
+            _ => return None,
+        };
+        Some(code)
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+mod platform {
+    pub fn keyboard_language() -> Option<String> {
+        None
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::platform::*;
+
+    #[test]
+    fn test_gnome_layout_language() {
+        let sources = "[('xkb', 'us+intl'), ('ibus', 'anthy'), ('xkb', 'de')]\n";
+        assert_eq!(first_xkb_layout(sources), Some("us+intl"));
+        assert_eq!(first_xkb_layout("@a(ss) []"), None);
+
+        assert_eq!(layout_language("us+intl"), Some("en"));
+        assert_eq!(layout_language("be"), None);
+        assert_eq!(layout_language("ca"), Some("fr"));
+        assert_eq!(layout_language("ca+eng"), Some("en"));
+        assert_eq!(layout_language("in"), None);
+        assert_eq!(layout_language("in+eng"), Some("en"));
+        assert_eq!(layout_language("ch+fr"), Some("fr"));
+        assert_eq!(layout_language("ua"), Some("uk"));
+        assert_eq!(layout_language("epo"), None);
+    }
+}
@@ -1,7 +1,37 @@
 use rdev::{grab as rdev_grab, listen as rdev_listen, Event, EventType, GrabError, ListenError};
-use std::sync::{Arc, Mutex};
+use std::{
+    sync::{Arc, Mutex},
+    time::Instant,
+};
 
+#[cfg(target_os = "linux")]
+pub mod headset;
+pub mod layout;
+pub mod passthrough;
+#[cfg(target_os = "linux")]
+pub mod remote;
+#[cfg(windows)]
+pub mod scancode;
+#[cfg(target_os = "macos")]
+pub mod secure_input;
+pub mod tracker;
+#[cfg(target_os = "linux")]
+pub mod uinput;
+
+#[cfg(target_os = "linux")]
+pub use headset::watch_headset;
+#[cfg(target_os = "linux")]
+pub use remote::watch_remote;
+pub use layout::keyboard_language;
+pub use passthrough::{take_pass_through_stats, PassThroughStats};
 pub use rdev::Key;
+#[cfg(windows)]
+pub use scancode::type_scan_codes;
+#[cfg(target_os = "macos")]
+pub use secure_input::secure_input_enabled;
+pub use tracker::KeyStateTracker;
+#[cfg(target_os = "linux")]
+pub use uinput::VirtualKeyboard;
 
 #[derive(Debug)]
 pub enum InputError {
@@ -21,67 +51,65 @@ pub fn listen<T>(hotkey: Key, grab: bool, mut callback: T) -> Result<(), InputEr
 where
     T: FnMut(bool) + 'static,
 {
-    let is_pressed = Arc::new(Mutex::new(false));
+    listen_keys(vec![hotkey], grab, move |_, is_pressed| callback(is_pressed))
+}
+
+/// Listen for several hotkeys and call the callback with the key and its state
+/// (true = pressed, false = released)
+/// De-duplicates repeated key press events when a key is held down
+///
+/// # Arguments
+///
+/// * `hotkeys` - The keys to listen for
+/// * `grab` - If true, uses grab mode (exclusive input capture), if false uses listen mode.
+///   Grab mode passes other events on and counts them in [`take_pass_through_stats`]
+/// * `callback` - Callback function called with the key and a boolean indicating its state
+pub fn listen_keys<T>(hotkeys: Vec<Key>, grab: bool, callback: T) -> Result<(), InputError>
+where
+    T: FnMut(Key, bool) + 'static,
+{
+    let tracker = Arc::new(Mutex::new(KeyStateTracker::new(hotkeys)));
+    let callback = Arc::new(Mutex::new(callback));
+
+    // Returns true if the event belonged to one of the hotkeys
+    let handle = move |event_type: &EventType| -> bool {
+        let (key, is_pressed) = match *event_type {
+            EventType::KeyPress(key) => (key, true),
+            EventType::KeyRelease(key) => (key, false),
+            _ => return false,
+        };
+        let change = {
+            let mut tracker = tracker.lock().unwrap();
+            if !tracker.tracks(&key) {
+                return false;
+            }
+            // Only report the first press of a held key
+            tracker.update(key, is_pressed)
+        };
+        if let Some(is_pressed) = change {
+            if let Ok(mut cb) = callback.lock() {
+                cb(key, is_pressed);
+            }
+        }
+        true
+    };
 
     if grab {
-        let callback = Arc::new(Mutex::new(callback));
         let grab_handler = move |event: Event| -> Option<Event> {
-            match event.event_type {
-                EventType::KeyPress(key) => {
-                    // println!("Key pressed: {:?}", key);
-                    if key == hotkey {
-                        let mut pressed = is_pressed.lock().unwrap();
-                        if !*pressed {
-                            *pressed = true;
-                            if let Ok(mut cb) = callback.lock() {
-                                cb(true);
-                            }
-                        }
-                        None // Block the event
-                    } else {
-                        Some(event) // Pass through other events
-                    }
-                }
-                EventType::KeyRelease(key) => {
-                    if key == hotkey {
-                        let mut pressed = is_pressed.lock().unwrap();
-                        if *pressed {
-                            *pressed = false;
-                            if let Ok(mut cb) = callback.lock() {
-                                cb(false);
-                            }
-                        }
-                        None // Block the event
-                    } else {
-                        Some(event) // Pass through other events
-                    }
-                }
-                _ => Some(event), // Pass through all other events
+            // rdev stamps events just before calling us, so only the handler can be timed
+            let entered = Instant::now();
+            if handle(&event.event_type) {
+                None // Block the event
+            } else {
+                // Pass through all other events, counting the time they were held up
+                passthrough::record(entered.elapsed());
+                Some(event)
             }
         };
         rdev_grab(grab_handler).map_err(InputError::Grab)
     } else {
-        let listen_handler = move |event: Event| match event.event_type {
-            EventType::KeyPress(key) => {
-                // println!("Key pressed: {:?}", key);
-                if key == hotkey {
-                    let mut pressed = is_pressed.lock().unwrap();
-                    if !*pressed {
-                        *pressed = true;
-                        callback(true);
-                    }
-                }
-            }
-            EventType::KeyRelease(key) => {
-                if key == hotkey {
-                    let mut pressed = is_pressed.lock().unwrap();
-                    if *pressed {
-                        *pressed = false;
-                        callback(false);
-                    }
-                }
-            }
-            _ => {}
+        let listen_handler = move |event: Event| {
+            handle(&event.event_type);
         };
         rdev_listen(listen_handler).map_err(InputError::Listen)
     }
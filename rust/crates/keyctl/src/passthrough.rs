@@ -0,0 +1,60 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+static EVENTS: AtomicU64 = AtomicU64::new(0);
+static TOTAL_LATENCY_US: AtomicU64 = AtomicU64::new(0);
+static MAX_LATENCY_US: AtomicU64 = AtomicU64::new(0);
+
+/// Input events that grab mode passed on to other programs, and how long the hotkey
+/// handler held them up
+///
+/// Shows whether grabbing the keyboard for the hotkeys makes typing feel slower
+/// system-wide; the latency should stay well below a millisecond. It covers the time
+/// from entering the handler to returning the event: rdev stamps events right before
+/// the handler rather than when the OS saw them, and re-injects them after it returns,
+/// so the time spent around the handler can't be measured.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PassThroughStats {
+    pub events: u64,
+    pub mean_latency: Duration,
+    pub max_latency: Duration,
+}
+
+/// Count an event that the handler held up for `latency` before passing it through
+pub(crate) fn record(latency: Duration) {
+    let latency_us = latency.as_micros() as u64;
+    EVENTS.fetch_add(1, Ordering::Relaxed);
+    TOTAL_LATENCY_US.fetch_add(latency_us, Ordering::Relaxed);
+    MAX_LATENCY_US.fetch_max(latency_us, Ordering::Relaxed);
+}
+
+/// Events passed through in grab mode since the last call
+pub fn take_pass_through_stats() -> PassThroughStats {
+    let events = EVENTS.swap(0, Ordering::Relaxed);
+    let total_us = TOTAL_LATENCY_US.swap(0, Ordering::Relaxed);
+    let max_us = MAX_LATENCY_US.swap(0, Ordering::Relaxed);
+    PassThroughStats {
+        events,
+        mean_latency: Duration::from_micros(total_us.checked_div(events).unwrap_or(0)),
+        max_latency: Duration::from_micros(max_us),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_pass_through_stats() {
+        record(Duration::from_micros(100));
+        record(Duration::from_micros(300));
+
+        let stats = take_pass_through_stats();
+        assert_eq!(stats.events, 2);
+        assert_eq!(stats.mean_latency, Duration::from_micros(200));
+        assert_eq!(stats.max_latency, Duration::from_micros(300));
+        assert_eq!(take_pass_through_stats(), PassThroughStats::default());
+    }
+}
@@ -0,0 +1,46 @@
+use crate::headset::{find_devices, read_buttons};
+use evdev_rs::enums::EV_KEY;
+use std::{io, time::Duration};
+
+/// How often to look for the remote while it is disconnected
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+/// Returned when reading a device that was removed
+const ENODEV: i32 = 19;
+
+/// Watch the button of a Bluetooth camera-shutter remote, calling `callback` with
+/// `true` when it is pressed and `false` when it is released
+///
+/// Once paired, these remotes are keyboards that send volume up (the iOS button)
+/// or enter (the Android button). The input device whose name contains `device`
+/// (ignoring case) is grabbed so that its keys reach no other program. Remotes
+/// disconnect when idle and reconnect on the next press, so a missing remote is
+/// waited for rather than an error. Needs read access to `/dev/input/event*`.
+///
+/// Blocks for as long as the remote can be read or waited for.
+pub fn watch_remote<F>(device: &str, mut callback: F) -> io::Result<()>
+where
+    F: FnMut(bool),
+{
+    let buttons = [EV_KEY::KEY_VOLUMEUP, EV_KEY::KEY_VOLUMEDOWN, EV_KEY::KEY_ENTER];
+
+    let mut waiting = false;
+    loop {
+        let Some(path) = find_devices(Some(device), &buttons)?.into_iter().next() else {
+            if !waiting {
+                eprintln!("Waiting for the remote '{}' to connect...", device);
+                waiting = true;
+            }
+            std::thread::sleep(RECONNECT_INTERVAL);
+            continue;
+        };
+
+        waiting = false;
+        let error = read_buttons(&path, &buttons, true, &mut callback).unwrap_err();
+        // The device node disappears when the remote goes to sleep
+        if error.kind() != io::ErrorKind::NotFound && error.raw_os_error() != Some(ENODEV) {
+            return Err(error);
+        }
+        // Release the button in case the remote went away while it was held
+        callback(false);
+    }
+}
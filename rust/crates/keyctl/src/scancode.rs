@@ -0,0 +1,102 @@
+use std::{io, thread, time::Duration};
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    MapVirtualKeyW, SendInput, VkKeyScanW, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT,
+    KEYBD_EVENT_FLAGS, KEYEVENTF_KEYUP, KEYEVENTF_SCANCODE, KEYEVENTF_UNICODE, MAPVK_VK_TO_VSC,
+    VIRTUAL_KEY, VK_CONTROL, VK_MENU, VK_SHIFT,
+};
+
+/// Pause between characters; games poll input once per frame and miss faster strokes
+const KEY_DELAY: Duration = Duration::from_millis(10);
+
+/// Type `text` as hardware scan codes with `SendInput`
+///
+/// Games reading raw input ignore the Unicode events enigo sends, but see scan
+/// codes like real key presses. Each character is looked up in the active
+/// keyboard layout, including the Shift/Ctrl/Alt it needs. Characters the
+/// layout can't produce fall back to Unicode events.
+pub fn type_scan_codes(text: &str) -> io::Result<()> {
+    for unit in text.encode_utf16() {
+        let inputs = match key_for(unit) {
+            Some((vk, modifiers)) => stroke(vk, &modifiers),
+            None => vec![
+                unicode_input(unit, KEYBD_EVENT_FLAGS(0)),
+                unicode_input(unit, KEYEVENTF_KEYUP),
+            ],
+        };
+        send(&inputs)?;
+        thread::sleep(KEY_DELAY);
+    }
+    Ok(())
+}
+
+/// Virtual key and modifiers that produce `unit` in the active layout
+fn key_for(unit: u16) -> Option<(VIRTUAL_KEY, Vec<VIRTUAL_KEY>)> {
+    // Low byte is the virtual key, high byte the shift state; -1 if there is no key
+    let scan = unsafe { VkKeyScanW(unit) };
+    if scan == -1 {
+        return None;
+    }
+
+    let vk = VIRTUAL_KEY((scan & 0xff) as u16);
+    let state = (scan >> 8) & 0xff;
+    let modifiers = [(1, VK_SHIFT), (2, VK_CONTROL), (4, VK_MENU)]
+        .into_iter()
+        .filter(|(bit, _)| state & bit != 0)
+        .map(|(_, key)| key)
+        .collect();
+    Some((vk, modifiers))
+}
+
+/// Press the modifiers, tap the key and release the modifiers
+fn stroke(vk: VIRTUAL_KEY, modifiers: &[VIRTUAL_KEY]) -> Vec<INPUT> {
+    let mut inputs: Vec<INPUT> = modifiers
+        .iter()
+        .map(|modifier| scan_input(*modifier, KEYBD_EVENT_FLAGS(0)))
+        .collect();
+    inputs.push(scan_input(vk, KEYBD_EVENT_FLAGS(0)));
+    inputs.push(scan_input(vk, KEYEVENTF_KEYUP));
+    inputs.extend(
+        modifiers
+            .iter()
+            .rev()
+            .map(|modifier| scan_input(*modifier, KEYEVENTF_KEYUP)),
+    );
+    inputs
+}
+
+fn scan_input(vk: VIRTUAL_KEY, flags: KEYBD_EVENT_FLAGS) -> INPUT {
+    let scan_code = unsafe { MapVirtualKeyW(vk.0 as u32, MAPVK_VK_TO_VSC) } as u16;
+    keyboard_input(KEYBDINPUT {
+        wVk: VIRTUAL_KEY(0),
+        wScan: scan_code,
+        dwFlags: flags | KEYEVENTF_SCANCODE,
+        time: 0,
+        dwExtraInfo: 0,
+    })
+}
+
+fn unicode_input(unit: u16, flags: KEYBD_EVENT_FLAGS) -> INPUT {
+    keyboard_input(KEYBDINPUT {
+        wVk: VIRTUAL_KEY(0),
+        wScan: unit,
+        dwFlags: flags | KEYEVENTF_UNICODE,
+        time: 0,
+        dwExtraInfo: 0,
+    })
+}
+
+fn keyboard_input(ki: KEYBDINPUT) -> INPUT {
+    INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 { ki },
+    }
+}
+
+fn send(inputs: &[INPUT]) -> io::Result<()> {
+    let sent = unsafe { SendInput(inputs, std::mem::size_of::<INPUT>() as i32) };
+    if sent as usize != inputs.len() {
+        // Blocked by UIPI when the target runs elevated and we don't
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -0,0 +1,13 @@
+#[link(name = "Carbon", kind = "framework")]
+extern "C" {
+    fn IsSecureEventInputEnabled() -> u8;
+}
+
+/// Whether macOS Secure Input is active
+///
+/// While a password field or a terminal with Secure Keyboard Entry is focused,
+/// the system drops synthetic key events without reporting an error, so typed
+/// output silently goes nowhere.
+pub fn secure_input_enabled() -> bool {
+    unsafe { IsSecureEventInputEnabled() != 0 }
+}
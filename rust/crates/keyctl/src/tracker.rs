@@ -0,0 +1,130 @@
+use std::{collections::HashSet, hash::Hash};
+
+/// Turns raw key events into exactly one press and one release per hold
+///
+/// Keyboards repeat press events while a key is held, and a release can arrive for a
+/// key whose press was never seen, e.g. one held down before listening started. The
+/// tracker reports neither, so a trigger can rely on presses and releases alternating.
+/// It works on any key type, so other trigger backends (mouse, HID, gamepad buttons)
+/// can use it with their own button codes.
+#[derive(Debug, Clone)]
+pub struct KeyStateTracker<K> {
+    tracked: HashSet<K>,
+    pressed: HashSet<K>,
+}
+
+impl<K: Eq + Hash + Clone> KeyStateTracker<K> {
+    /// Track the state of `keys`; events of other keys are ignored
+    pub fn new<I: IntoIterator<Item = K>>(keys: I) -> Self {
+        Self {
+            tracked: keys.into_iter().collect(),
+            pressed: HashSet::new(),
+        }
+    }
+
+    /// Whether events of `key` are tracked
+    pub fn tracks(&self, key: &K) -> bool {
+        self.tracked.contains(key)
+    }
+
+    /// Record a press or release of `key`
+    ///
+    /// Returns `Some(true)` for the first press of a hold and `Some(false)` for the
+    /// release that ends it, or `None` for repeats, stray releases and keys that are not
+    /// tracked.
+    pub fn update(&mut self, key: K, is_pressed: bool) -> Option<bool> {
+        if !self.tracks(&key) {
+            return None;
+        }
+        let changed = if is_pressed {
+            self.pressed.insert(key)
+        } else {
+            self.pressed.remove(&key)
+        };
+        changed.then_some(is_pressed)
+    }
+
+    pub fn is_pressed(&self, key: &K) -> bool {
+        self.pressed.contains(key)
+    }
+
+    /// Whether all of `keys` are held at the same time, e.g. for a chord
+    pub fn all_pressed(&self, keys: &[K]) -> bool {
+        keys.iter().all(|key| self.pressed.contains(key))
+    }
+
+    /// Keys that are held down, in no particular order
+    pub fn pressed(&self) -> impl Iterator<Item = &K> {
+        self.pressed.iter()
+    }
+
+    /// Forget which keys are held, e.g. after the input device was reconnected
+    pub fn reset(&mut self) {
+        self.pressed.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_press_and_release() {
+        let mut tracker = KeyStateTracker::new(['a']);
+
+        assert_eq!(tracker.update('a', true), Some(true));
+        assert!(tracker.is_pressed(&'a'));
+        assert_eq!(tracker.update('a', false), Some(false));
+        assert!(!tracker.is_pressed(&'a'));
+        assert_eq!(tracker.update('a', true), Some(true));
+    }
+
+    #[test]
+    fn test_repeats_and_stray_releases() {
+        let mut tracker = KeyStateTracker::new(['a']);
+
+        // A release without a press, e.g. of a key held before listening started
+        assert_eq!(tracker.update('a', false), None);
+        assert_eq!(tracker.update('a', true), Some(true));
+        // Auto-repeat while held
+        assert_eq!(tracker.update('a', true), None);
+        assert_eq!(tracker.update('a', true), None);
+        assert_eq!(tracker.update('a', false), Some(false));
+        assert_eq!(tracker.update('a', false), None);
+    }
+
+    #[test]
+    fn test_untracked_keys() {
+        let mut tracker = KeyStateTracker::new(['a']);
+
+        assert!(!tracker.tracks(&'b'));
+        assert_eq!(tracker.update('b', true), None);
+        assert_eq!(tracker.update('b', false), None);
+        assert!(!tracker.is_pressed(&'b'));
+        assert_eq!(tracker.pressed().count(), 0);
+    }
+
+    #[test]
+    fn test_keys_are_independent() {
+        let mut tracker = KeyStateTracker::new(['a', 'b']);
+
+        assert_eq!(tracker.update('a', true), Some(true));
+        assert_eq!(tracker.update('b', true), Some(true));
+        assert!(tracker.all_pressed(&['a', 'b']));
+        assert_eq!(tracker.update('a', false), Some(false));
+        assert!(!tracker.all_pressed(&['a', 'b']));
+        assert_eq!(tracker.pressed().collect::<Vec<_>>(), [&'b']);
+        assert_eq!(tracker.update('b', false), Some(false));
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut tracker = KeyStateTracker::new(['a']);
+
+        assert_eq!(tracker.update('a', true), Some(true));
+        tracker.reset();
+        // The release of a hold that was forgotten is a stray release
+        assert_eq!(tracker.update('a', false), None);
+        assert_eq!(tracker.update('a', true), Some(true));
+    }
+}
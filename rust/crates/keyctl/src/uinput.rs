@@ -0,0 +1,167 @@
+use evdev_rs::{
+    enums::{EventCode, EventType, EV_KEY, EV_SYN},
+    Device, InputEvent, TimeVal, UInputDevice,
+};
+use std::{io, thread, time::Duration};
+
+const DEVICE_NAME: &str = "voicekb virtual keyboard";
+/// Time for the compositor to pick up a newly created device
+const SETTLE_TIME: Duration = Duration::from_millis(200);
+/// Pause between key strokes so no event is dropped
+const KEY_DELAY: Duration = Duration::from_millis(2);
+
+const LETTERS: [EV_KEY; 26] = [
+    EV_KEY::KEY_A, EV_KEY::KEY_B, EV_KEY::KEY_C, EV_KEY::KEY_D, EV_KEY::KEY_E,
+    EV_KEY::KEY_F, EV_KEY::KEY_G, EV_KEY::KEY_H, EV_KEY::KEY_I, EV_KEY::KEY_J,
+    EV_KEY::KEY_K, EV_KEY::KEY_L, EV_KEY::KEY_M, EV_KEY::KEY_N, EV_KEY::KEY_O,
+    EV_KEY::KEY_P, EV_KEY::KEY_Q, EV_KEY::KEY_R, EV_KEY::KEY_S, EV_KEY::KEY_T,
+    EV_KEY::KEY_U, EV_KEY::KEY_V, EV_KEY::KEY_W, EV_KEY::KEY_X, EV_KEY::KEY_Y,
+    EV_KEY::KEY_Z,
+];
+
+/// Digits 0-9, and the symbols on the same keys with shift
+const DIGITS: [(EV_KEY, char); 10] = [
+    (EV_KEY::KEY_0, ')'), (EV_KEY::KEY_1, '!'), (EV_KEY::KEY_2, '@'), (EV_KEY::KEY_3, '#'),
+    (EV_KEY::KEY_4, '$'), (EV_KEY::KEY_5, '%'), (EV_KEY::KEY_6, '^'), (EV_KEY::KEY_7, '&'),
+    (EV_KEY::KEY_8, '*'), (EV_KEY::KEY_9, '('),
+];
+
+/// Punctuation keys: unshifted and shifted character
+const PUNCTUATION: [(EV_KEY, char, char); 11] = [
+    (EV_KEY::KEY_MINUS, '-', '_'),
+    (EV_KEY::KEY_EQUAL, '=', '+'),
+    (EV_KEY::KEY_LEFTBRACE, '[', '{'),
+    (EV_KEY::KEY_RIGHTBRACE, ']', '}'),
+    (EV_KEY::KEY_BACKSLASH, '\\', '|'),
+    (EV_KEY::KEY_SEMICOLON, ';', ':'),
+    (EV_KEY::KEY_APOSTROPHE, '\'', '"'),
+    (EV_KEY::KEY_GRAVE, '`', '~'),
+    (EV_KEY::KEY_COMMA, ',', '<'),
+    (EV_KEY::KEY_DOT, '.', '>'),
+    (EV_KEY::KEY_SLASH, '/', '?'),
+];
+
+/// Types text through a virtual keyboard created with Linux uinput
+///
+/// Events are injected at the kernel level, so they reach Wayland compositors
+/// and the console like a real keyboard. Key codes assume a US layout is
+/// active, and only characters on a US keyboard can be typed. Needs write
+/// access to `/dev/uinput`.
+pub struct VirtualKeyboard {
+    device: UInputDevice,
+}
+
+// SAFETY: the uinput handle only wraps a file descriptor that is used from one
+// thread at a time through `&mut self`
+unsafe impl Send for VirtualKeyboard {}
+
+impl VirtualKeyboard {
+    /// Create the virtual keyboard device
+    pub fn new() -> io::Result<Self> {
+        let template = Device::new()
+            .ok_or_else(|| io::Error::other("Failed to allocate evdev device"))?;
+        template.set_name(DEVICE_NAME);
+        template.enable(&EventType::EV_KEY)?;
+        template.enable(&EventType::EV_SYN)?;
+        let special = [
+            EV_KEY::KEY_SPACE,
+            EV_KEY::KEY_ENTER,
+            EV_KEY::KEY_TAB,
+            EV_KEY::KEY_BACKSPACE,
+            EV_KEY::KEY_LEFTSHIFT,
+        ];
+        let keys = LETTERS
+            .iter()
+            .chain(DIGITS.iter().map(|(key, _)| key))
+            .chain(PUNCTUATION.iter().map(|(key, _, _)| key))
+            .chain(&special);
+        for key in keys {
+            template.enable(&EventCode::EV_KEY(key.clone()))?;
+        }
+
+        let device = UInputDevice::create_from_device(&template)?;
+        thread::sleep(SETTLE_TIME);
+        Ok(Self { device })
+    }
+
+    /// Type `text`, returning the characters that have no key on a US keyboard
+    pub fn text(&mut self, text: &str) -> io::Result<Vec<char>> {
+        let mut skipped = Vec::new();
+        for c in text.chars() {
+            match key_for(c) {
+                Some((key, shift)) => self.stroke(key, shift)?,
+                None => skipped.push(c),
+            }
+        }
+        Ok(skipped)
+    }
+
+    /// Press backspace `count` times
+    pub fn backspace(&mut self, count: usize) -> io::Result<()> {
+        for _ in 0..count {
+            self.stroke(EV_KEY::KEY_BACKSPACE, false)?;
+        }
+        Ok(())
+    }
+
+    fn stroke(&self, key: EV_KEY, shift: bool) -> io::Result<()> {
+        if shift {
+            self.emit(EV_KEY::KEY_LEFTSHIFT, 1)?;
+        }
+        self.emit(key.clone(), 1)?;
+        self.emit(key, 0)?;
+        if shift {
+            self.emit(EV_KEY::KEY_LEFTSHIFT, 0)?;
+        }
+        thread::sleep(KEY_DELAY);
+        Ok(())
+    }
+
+    fn emit(&self, key: EV_KEY, value: i32) -> io::Result<()> {
+        let time = TimeVal::new(0, 0);
+        self.device
+            .write_event(&InputEvent::new(&time, &EventCode::EV_KEY(key), value))?;
+        self.device
+            .write_event(&InputEvent::new(&time, &EventCode::EV_SYN(EV_SYN::SYN_REPORT), 0))
+    }
+}
+
+/// Key and shift state that produce `c` on a US layout
+fn key_for(c: char) -> Option<(EV_KEY, bool)> {
+    match c {
+        'a'..='z' => Some((LETTERS[c as usize - 'a' as usize].clone(), false)),
+        'A'..='Z' => Some((LETTERS[c as usize - 'A' as usize].clone(), true)),
+        '0'..='9' => Some((DIGITS[c as usize - '0' as usize].0.clone(), false)),
+        ' ' => Some((EV_KEY::KEY_SPACE, false)),
+        '\n' => Some((EV_KEY::KEY_ENTER, false)),
+        '\t' => Some((EV_KEY::KEY_TAB, false)),
+        // Whisper likes typographic quotes and dashes; type their ASCII versions
+        '‘' | '’' => key_for('\''),
+        '“' | '”' => key_for('"'),
+        '–' | '—' => key_for('-'),
+        _ => DIGITS
+            .iter()
+            .find(|(_, shifted)| *shifted == c)
+            .map(|(key, _)| (key.clone(), true))
+            .or_else(|| {
+                PUNCTUATION.iter().find_map(|(key, plain, shifted)| {
+                    (*plain == c || *shifted == c).then(|| (key.clone(), *shifted == c))
+                })
+            }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_for() {
+        assert_eq!(key_for('k'), Some((EV_KEY::KEY_K, false)));
+        assert_eq!(key_for('Q'), Some((EV_KEY::KEY_Q, true)));
+        assert_eq!(key_for('0'), Some((EV_KEY::KEY_0, false)));
+        assert_eq!(key_for('?'), Some((EV_KEY::KEY_SLASH, true)));
+        assert_eq!(key_for('’'), Some((EV_KEY::KEY_APOSTROPHE, false)));
+        assert_eq!(key_for('é'), None);
+    }
+}
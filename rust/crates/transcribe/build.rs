@@ -0,0 +1,24 @@
+use std::env;
+
+// Accelerators are picked with Cargo features, not detected here: a build script can't
+// turn on the whisper-rs-sys features that set whisper.cpp's CMake options. OpenVINO
+// has no such feature in whisper-rs 0.14, which also keeps the whisper context private,
+// so its encoder can't be enabled through the bindings at all.
+fn main() {
+    let target = env::var("TARGET").unwrap();
+    let host = env::var("HOST").unwrap();
+
+    // Reported at runtime so it's clear what a (cross-compiled) binary was built for
+    println!("cargo:rustc-env=TRANSCRIBE_TARGET={}", target);
+    println!("cargo:rerun-if-env-changed=OPENBLAS_PATH");
+
+    // whisper-rs-sys links against the system OpenBLAS, which is the host's one
+    // when cross-compiling unless told otherwise
+    let openblas = env::var_os("CARGO_FEATURE_OPENBLAS").is_some();
+    if openblas && host != target && env::var_os("OPENBLAS_PATH").is_none() {
+        println!(
+            "cargo:warning=Cross-compiling for {} with openblas, set OPENBLAS_PATH to the target's OpenBLAS",
+            target
+        );
+    }
+}
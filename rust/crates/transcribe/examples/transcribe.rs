@@ -1,4 +1,7 @@
-use transcribe::{Transcriber, load_wav_as_float};
+use transcribe::{
+    load_wav_as_float, model::dtw_preset, DtwMode, DtwParameters, Format, TranscribeOptions,
+    Transcriber, WhisperContextParameters,
+};
 use clap::{Arg, Command};
 use std::path::PathBuf;
 
@@ -23,6 +26,29 @@ async fn main() -> anyhow::Result<()> {
                 .help("Path to Whisper model file (.bin)")
                 .default_value("../../../models/ggml-tiny.en.bin"),
         )
+        .arg(
+            Arg::new("language")
+                .short('l')
+                .long("language")
+                .value_name("LANGUAGE")
+                .help("Spoken language code, or \"auto\" to detect it")
+                .default_value("en"),
+        )
+        .arg(
+            Arg::new("format")
+                .short('f')
+                .long("format")
+                .value_name("FORMAT")
+                .help("Output format")
+                .value_parser(["text", "srt", "vtt", "json"])
+                .default_value("text"),
+        )
+        .arg(
+            Arg::new("dtw")
+                .long("dtw")
+                .help("Time words with DTW alignment, for word-accurate JSON output")
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("verbose")
                 .short('v')
@@ -34,6 +60,8 @@ async fn main() -> anyhow::Result<()> {
 
     let input_path = PathBuf::from(matches.get_one::<String>("input").unwrap());
     let model_path = PathBuf::from(matches.get_one::<String>("model").unwrap());
+    let language = matches.get_one::<String>("language").unwrap();
+    let format: Format = matches.get_one::<String>("format").unwrap().parse()?;
     let verbose = matches.get_flag("verbose");
 
     if !input_path.exists() {
@@ -53,7 +81,17 @@ async fn main() -> anyhow::Result<()> {
     }
 
     // Create transcriber
-    let transcriber = Transcriber::new(&model_path)?;
+    let mut params = WhisperContextParameters::default();
+    if matches.get_flag("dtw") {
+        let name = model_path.file_name().unwrap_or_default().to_string_lossy();
+        let model_preset = dtw_preset(&name)
+            .ok_or_else(|| anyhow::anyhow!("No DTW alignment heads known for {}", name))?;
+        params.dtw_parameters(DtwParameters {
+            mode: DtwMode::ModelPreset { model_preset },
+            ..Default::default()
+        });
+    }
+    let transcriber = Transcriber::new_with_params(&model_path, params)?;
 
     if verbose {
         println!("Loading audio from: {:?}", input_path);
@@ -71,7 +109,11 @@ async fn main() -> anyhow::Result<()> {
 
     // Transcribe
     let start = std::time::Instant::now();
-    let text = transcriber.transcribe(&audio)?;
+    let options = match language.as_str() {
+        "auto" => TranscribeOptions::default().detect_language(),
+        language => TranscribeOptions::default().language(language),
+    };
+    let segments = transcriber.transcribe_segments_with(&audio, &options.print_progress(verbose))?;
     let duration = start.elapsed();
 
     if verbose {
@@ -79,8 +121,8 @@ async fn main() -> anyhow::Result<()> {
         println!("---");
     }
 
-    // Output the transcribed text
-    println!("{}", text);
+    // Output the transcription
+    println!("{}", format.render(&segments)?);
 
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,52 @@
+use std::{
+    ffi::c_void,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use whisper_rs::FullParams;
+
+/// Cancels a running transcription
+///
+/// Clones share the same flag, so a handle can be handed to another thread
+/// (e.g. the key listener) while the decode runs.
+#[derive(Debug, Clone, Default)]
+pub struct AbortHandle {
+    aborted: Arc<AtomicBool>,
+}
+
+impl AbortHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the transcription to stop at the next opportunity
+    pub fn abort(&self) {
+        self.aborted.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_aborted(&self) -> bool {
+        self.aborted.load(Ordering::SeqCst)
+    }
+
+    /// Install the handle as the abort callback of `params`
+    ///
+    /// The handle must outlive every use of `params`.
+    pub(crate) fn install(&self, params: &mut FullParams) {
+        // SAFETY: the callback only reads the flag through the user data pointer.
+        // Callers keep `self` alive until decoding with `params` has finished.
+        unsafe {
+            params.set_abort_callback(Some(check_abort));
+            params.set_abort_callback_user_data(Arc::as_ptr(&self.aborted) as *mut c_void);
+        }
+    }
+}
+
+unsafe extern "C" fn check_abort(user_data: *mut c_void) -> bool {
+    if user_data.is_null() {
+        return false;
+    }
+    let aborted = &*(user_data as *const AtomicBool);
+    aborted.load(Ordering::SeqCst)
+}
@@ -0,0 +1,110 @@
+use std::fmt;
+
+/// Target triple this crate was compiled for
+pub const BUILD_TARGET: &str = env!("TRANSCRIBE_TARGET");
+
+/// GPU backends this build of whisper.cpp was compiled with
+pub fn compiled_gpu_backends() -> Vec<&'static str> {
+    let mut backends = Vec::new();
+    if cfg!(feature = "cuda") {
+        backends.push("CUDA");
+    }
+    if cfg!(feature = "hipblas") {
+        backends.push("ROCm");
+    }
+    if cfg!(feature = "metal") {
+        backends.push("Metal");
+    }
+    if cfg!(feature = "vulkan") {
+        backends.push("Vulkan");
+    }
+    backends
+}
+
+/// CPU acceleration this build of whisper.cpp was compiled with
+pub fn compiled_cpu_backends() -> Vec<&'static str> {
+    let mut backends = Vec::new();
+    // whisper.cpp always uses Accelerate on Apple platforms
+    if cfg!(target_vendor = "apple") {
+        backends.push("Accelerate");
+    }
+    if cfg!(feature = "openblas") {
+        backends.push("OpenBLAS");
+    }
+    if cfg!(feature = "openmp") {
+        backends.push("OpenMP");
+    }
+    if cfg!(feature = "coreml") {
+        backends.push("Core ML");
+    }
+    backends
+}
+
+/// whisper.cpp system info: the CPU features it was built with and the loaded backends
+pub fn system_info() -> String {
+    whisper_rs::print_system_info().trim().to_string()
+}
+
+/// Describes the compute backend a model was loaded on
+#[derive(Debug, Clone)]
+pub struct BackendInfo {
+    /// GPU backends compiled into this build
+    pub gpu_backends: Vec<&'static str>,
+    /// CPU acceleration compiled into this build
+    pub cpu_backends: Vec<&'static str>,
+    /// Target triple of this build
+    pub target: &'static str,
+    /// Whether GPU offload was requested when loading the model
+    pub use_gpu: bool,
+    /// Index of the GPU the model was loaded on
+    pub gpu_device: i32,
+    /// whisper.cpp system info (CPU features and loaded backends)
+    pub system_info: String,
+    /// Whether a Core ML build found the model's Core ML encoder; without it the
+    /// encoder runs on the regular backend
+    pub coreml_encoder: bool,
+}
+
+impl BackendInfo {
+    pub(crate) fn new(use_gpu: bool, gpu_device: i32) -> Self {
+        Self {
+            gpu_backends: compiled_gpu_backends(),
+            cpu_backends: compiled_cpu_backends(),
+            target: BUILD_TARGET,
+            use_gpu,
+            gpu_device,
+            system_info: system_info(),
+            coreml_encoder: false,
+        }
+    }
+
+    /// Whether inference runs on a GPU
+    pub fn is_gpu(&self) -> bool {
+        self.use_gpu && !self.gpu_backends.is_empty()
+    }
+}
+
+impl fmt::Display for BackendInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_gpu() {
+            write!(
+                f,
+                "{} (device {})",
+                self.gpu_backends.join("/"),
+                self.gpu_device
+            )?;
+        } else {
+            write!(f, "CPU")?;
+            if !self.cpu_backends.is_empty() {
+                write!(f, " with {}", self.cpu_backends.join("/"))?;
+            }
+            if !self.gpu_backends.is_empty() {
+                write!(f, " ({} available, GPU disabled)", self.gpu_backends.join("/"))?;
+            }
+        }
+        if cfg!(feature = "coreml") && !self.coreml_encoder {
+            write!(f, ", without a Core ML encoder for this model")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,379 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    format_dialogue, load_wav_as_float, subtitle, Priority, Segment, TranscribeOptions,
+    Transcriber, TranscriptCache,
+};
+
+/// Transcribes every audio file in a directory with a bounded pool of workers
+///
+/// All workers share the transcriber's model; each decodes with its own state.
+/// Decodes run at [`Priority::Background`], so dictation on a clone of the same
+/// transcriber goes first.
+/// Results are written next to the input as `<name>.txt` and `<name>.json`.
+#[derive(Debug, Clone)]
+pub struct Batch {
+    workers: usize,
+    extensions: Vec<String>,
+    recursive: bool,
+    write_text: bool,
+    write_json: bool,
+    overwrite: bool,
+    cache: Option<TranscriptCache>,
+    resume: bool,
+    file_progress: Option<FileProgress>,
+}
+
+type FileProgressFn = dyn Fn(&Path, u8) + Send + Sync;
+
+/// Receives the progress of each file, see [`Batch::on_file_progress`]
+#[derive(Clone)]
+struct FileProgress(Arc<FileProgressFn>);
+
+impl fmt::Debug for FileProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("FileProgress")
+    }
+}
+
+/// Outcome of transcribing one file
+#[derive(Debug)]
+pub struct BatchResult {
+    pub input: PathBuf,
+    /// Files written for the input, empty when it was skipped or failed
+    pub outputs: Vec<PathBuf>,
+    /// The input already had all its outputs
+    pub skipped: bool,
+    pub elapsed: Duration,
+    pub error: Option<anyhow::Error>,
+}
+
+/// Contents of the `.json` output
+#[derive(Serialize)]
+struct Transcript<'a> {
+    file: &'a Path,
+    text: &'a str,
+    segments: &'a [Segment],
+}
+
+impl Default for Batch {
+    fn default() -> Self {
+        Self {
+            // whisper.cpp scales well up to about four threads per decode
+            workers: (num_cpus::get() / 4).max(1),
+            extensions: vec!["wav".to_string()],
+            recursive: true,
+            write_text: true,
+            write_json: true,
+            overwrite: false,
+            cache: None,
+            resume: true,
+            file_progress: None,
+        }
+    }
+}
+
+impl Batch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of files transcribed at the same time
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Set the file extensions (without dot, case-insensitive) to transcribe
+    pub fn extensions<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.extensions = extensions
+            .into_iter()
+            .map(|extension| extension.into().to_lowercase())
+            .collect();
+        self
+    }
+
+    /// Set whether subdirectories are searched too
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Choose which outputs to write
+    pub fn outputs(mut self, text: bool, json: bool) -> Self {
+        self.write_text = text;
+        self.write_json = json;
+        self
+    }
+
+    /// Transcribe files again even if their outputs exist
+    pub fn overwrite(mut self, overwrite: bool) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+
+    /// Reuse results for audio that was transcribed before with the same model and options
+    pub fn cache(mut self, cache: TranscriptCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Receive how much of each file is transcribed, in percent, as it is decoded
+    ///
+    /// The callback runs on the worker threads, for several files at a time when
+    /// there is more than one worker.
+    pub fn on_file_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&Path, u8) + Send + Sync + 'static,
+    {
+        self.file_progress = Some(FileProgress(Arc::new(callback)));
+        self
+    }
+
+    /// Save decoded speech chunks next to each input as `<name>.checkpoint.json`
+    ///
+    /// On by default, so a batch that was killed continues inside the file it
+    /// was working on when run again. Checkpoints need a VAD in the options to
+    /// split recordings into chunks.
+    pub fn resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    /// Audio files in `dir` that match the configured extensions, sorted by path
+    pub fn files<P: AsRef<Path>>(&self, dir: P) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        let mut pending = vec![dir.as_ref().to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            let entries = std::fs::read_dir(&dir)
+                .map_err(|e| anyhow::anyhow!("Failed to read directory {:?}: {}", dir, e))?;
+            for entry in entries {
+                let path = entry?.path();
+                if path.is_dir() {
+                    if self.recursive {
+                        pending.push(path);
+                    }
+                } else if self.matches(&path) {
+                    files.push(path);
+                }
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    /// Transcribe all 16kHz WAV files in `dir`
+    pub fn run<P, F>(
+        &self,
+        transcriber: &Transcriber,
+        dir: P,
+        options: &TranscribeOptions,
+        progress: F,
+    ) -> Result<Vec<BatchResult>>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&BatchResult),
+    {
+        self.run_with_loader(transcriber, dir, options, |path| load_wav_as_float(path), progress)
+    }
+
+    /// Transcribe all matching files in `dir`, decoding them to 16kHz mono with `load`
+    ///
+    /// `progress` is called on the calling thread as each file finishes.
+    pub fn run_with_loader<P, L, F>(
+        &self,
+        transcriber: &Transcriber,
+        dir: P,
+        options: &TranscribeOptions,
+        load: L,
+        mut progress: F,
+    ) -> Result<Vec<BatchResult>>
+    where
+        P: AsRef<Path>,
+        L: Fn(&Path) -> Result<Vec<f32>> + Sync,
+        F: FnMut(&BatchResult),
+    {
+        let files = self.files(dir)?;
+        let workers = self.workers.min(files.len()).max(1);
+        // Split the decoding threads between the workers instead of oversubscribing
+        let threads = (options.threads / workers as i32).max(1);
+        let parallel_chunks = (options.parallel_chunks / workers).max(1);
+        let options = options
+            .clone()
+            .threads(threads)
+            .parallel_chunks(parallel_chunks)
+            .priority(Priority::Background);
+
+        let queue = Mutex::new(files.into_iter());
+        let (sender, receiver) = mpsc::channel();
+        let mut results = Vec::new();
+
+        std::thread::scope(|scope| {
+            for _ in 0..workers {
+                let sender = sender.clone();
+                let (queue, load, options) = (&queue, &load, &options);
+                scope.spawn(move || loop {
+                    let next = queue.lock().ok().and_then(|mut queue| queue.next());
+                    let Some(input) = next else {
+                        break;
+                    };
+                    let result = self.transcribe_file(transcriber, input, options, load);
+                    if sender.send(result).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(sender);
+
+            for result in receiver {
+                progress(&result);
+                results.push(result);
+            }
+        });
+
+        results.sort_by(|a, b| a.input.cmp(&b.input));
+        Ok(results)
+    }
+
+    fn transcribe_file<L>(
+        &self,
+        transcriber: &Transcriber,
+        input: PathBuf,
+        options: &TranscribeOptions,
+        load: &L,
+    ) -> BatchResult
+    where
+        L: Fn(&Path) -> Result<Vec<f32>>,
+    {
+        let start = Instant::now();
+        let outputs = self.output_paths(&input);
+        let mut result = BatchResult {
+            input,
+            outputs: Vec::new(),
+            skipped: false,
+            elapsed: Duration::ZERO,
+            error: None,
+        };
+
+        if !self.overwrite && !outputs.is_empty() && outputs.iter().all(|path| path.exists()) {
+            result.skipped = true;
+            return result;
+        }
+
+        let with_progress;
+        let options = match &self.file_progress {
+            Some(FileProgress(callback)) => {
+                let (callback, input) = (callback.clone(), result.input.clone());
+                with_progress =
+                    options.clone().on_progress(move |percent| callback(&input, percent));
+                &with_progress
+            }
+            None => options,
+        };
+        let written = load(&result.input)
+            .and_then(|audio| {
+                let checkpoint = result.input.with_extension("checkpoint.json");
+                let transcribe = || {
+                    if self.resume {
+                        transcriber.transcribe_segments_resumable(&audio, options, &checkpoint)
+                    } else {
+                        transcriber.transcribe_segments_with(&audio, options)
+                    }
+                };
+                match &self.cache {
+                    Some(cache) => {
+                        cache.get_or_transcribe(transcriber, &audio, options, transcribe)
+                    }
+                    None => transcribe(),
+                }
+            })
+            .and_then(|segments| self.write(&result.input, &segments, options.tinydiarize));
+        match written {
+            Ok(paths) => result.outputs = paths,
+            Err(e) => result.error = Some(e),
+        }
+        result.elapsed = start.elapsed();
+        result
+    }
+
+    /// Write the outputs for `input`, as a dialogue when speakers were detected
+    fn write(&self, input: &Path, segments: &[Segment], dialogue: bool) -> Result<Vec<PathBuf>> {
+        let text = if dialogue {
+            format_dialogue(segments)
+        } else {
+            subtitle::to_text(segments)
+        };
+
+        let mut written = Vec::new();
+        if self.write_text {
+            let path = input.with_extension("txt");
+            std::fs::write(&path, format!("{}\n", text))?;
+            written.push(path);
+        }
+        if self.write_json {
+            let path = input.with_extension("json");
+            let transcript = Transcript {
+                file: input,
+                text: &text,
+                segments,
+            };
+            std::fs::write(&path, serde_json::to_string_pretty(&transcript)?)?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+
+    fn output_paths(&self, input: &Path) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        if self.write_text {
+            paths.push(input.with_extension("txt"));
+        }
+        if self.write_json {
+            paths.push(input.with_extension("json"));
+        }
+        paths
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| self.extensions.contains(&extension.to_lowercase()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        for name in ["b.wav", "a.WAV", "notes.txt", "nested/c.wav"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+
+        let names = |batch: Batch| -> Vec<String> {
+            batch
+                .files(dir)
+                .unwrap()
+                .iter()
+                .map(|path| path.strip_prefix(dir).unwrap().to_string_lossy().replace('\\', "/"))
+                .collect()
+        };
+        assert_eq!(names(Batch::new()), ["a.WAV", "b.wav", "nested/c.wav"]);
+        assert_eq!(names(Batch::new().recursive(false)), ["a.WAV", "b.wav"]);
+    }
+}
@@ -0,0 +1,142 @@
+use anyhow::Result;
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    fuzzy::normalize, load_wav_as_float, model::ModelInfo, TranscribeOptions, Transcriber,
+};
+
+/// Whisper expects 16kHz audio
+const SAMPLE_RATE: f32 = 16000.0;
+
+/// Outcome of running the reference recording through one model
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    pub model: PathBuf,
+    /// Header information, if the model is a ggml file we can read
+    pub info: Option<ModelInfo>,
+    pub load_time: Duration,
+    pub transcribe_time: Duration,
+    pub audio_duration: Duration,
+    pub text: String,
+    /// Word error rate against the reference transcript (0.0 = identical)
+    pub word_error_rate: f32,
+    /// Word diff against the reference: `[-removed-]` and `{+added+}`
+    pub diff: String,
+}
+
+impl BenchResult {
+    /// Transcription time divided by audio duration; below 1.0 is faster than real time
+    pub fn real_time_factor(&self) -> f32 {
+        self.transcribe_time.as_secs_f32() / self.audio_duration.as_secs_f32().max(f32::EPSILON)
+    }
+}
+
+/// Transcribe a 16kHz WAV file with each model and compare speed and output
+///
+/// The output is compared against `reference` when given, otherwise against
+/// the output of the first model, so list the most accurate model first.
+pub fn run<P: AsRef<Path>>(
+    wav_path: P,
+    models: &[PathBuf],
+    options: &TranscribeOptions,
+    reference: Option<&str>,
+) -> Result<Vec<BenchResult>> {
+    let audio = load_wav_as_float(wav_path)?;
+    let audio_duration = Duration::from_secs_f32(audio.len() as f32 / SAMPLE_RATE);
+
+    let mut reference = reference.map(str::to_string);
+    let mut results = Vec::with_capacity(models.len());
+    for model in models {
+        let start = Instant::now();
+        let transcriber = Transcriber::new(model)?;
+        let load_time = start.elapsed();
+
+        let start = Instant::now();
+        let text = transcriber.transcribe_with(&audio, options)?;
+        let transcribe_time = start.elapsed();
+
+        let reference = reference.get_or_insert_with(|| text.clone());
+        let (errors, diff) = word_diff(reference, &text);
+        let reference_words = reference.split_whitespace().count().max(1);
+
+        results.push(BenchResult {
+            model: model.clone(),
+            info: ModelInfo::read(model).ok(),
+            load_time,
+            transcribe_time,
+            audio_duration,
+            word_error_rate: errors as f32 / reference_words as f32,
+            diff,
+            text,
+        });
+    }
+
+    Ok(results)
+}
+
+/// Word-level edit distance between two transcripts, and a readable diff
+///
+/// Words are compared ignoring case and punctuation.
+pub fn word_diff(reference: &str, hypothesis: &str) -> (usize, String) {
+    let a: Vec<&str> = reference.split_whitespace().collect();
+    let b: Vec<&str> = hypothesis.split_whitespace().collect();
+    let a_norm: Vec<String> = a.iter().map(|w| normalize(w)).collect();
+    let b_norm: Vec<String> = b.iter().map(|w| normalize(w)).collect();
+
+    // distance[i][j] = edits to turn a[i..] into b[j..]
+    let mut distance = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..=a.len()).rev() {
+        for j in (0..=b.len()).rev() {
+            distance[i][j] = if i == a.len() {
+                b.len() - j
+            } else if j == b.len() {
+                a.len() - i
+            } else if a_norm[i] == b_norm[j] {
+                distance[i + 1][j + 1]
+            } else {
+                1 + distance[i + 1][j + 1]
+                    .min(distance[i + 1][j])
+                    .min(distance[i][j + 1])
+            };
+        }
+    }
+
+    let mut parts = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a_norm[i] == b_norm[j] {
+            parts.push(b[j].to_string());
+            i += 1;
+            j += 1;
+        } else if i < a.len() && (j == b.len() || distance[i][j] == 1 + distance[i + 1][j]) {
+            parts.push(format!("[-{}-]", a[i]));
+            i += 1;
+        } else if i < a.len() && distance[i][j] == 1 + distance[i + 1][j + 1] {
+            parts.push(format!("[-{}-]{{+{}+}}", a[i], b[j]));
+            i += 1;
+            j += 1;
+        } else {
+            parts.push(format!("{{+{}+}}", b[j]));
+            j += 1;
+        }
+    }
+
+    (distance[0][0], parts.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_diff() {
+        let (errors, diff) = word_diff("The quick brown fox jumps", "the quick fox jumped high");
+        assert_eq!(errors, 3);
+        assert_eq!(diff, "the quick [-brown-] fox [-jumps-]{+jumped+} {+high+}");
+
+        assert_eq!(word_diff("Same text.", "same text").0, 0);
+    }
+}
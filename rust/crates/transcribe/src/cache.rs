@@ -0,0 +1,144 @@
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+use crate::{model::app_cache_dir, Segment, TranscribeOptions, Transcriber};
+
+/// Transcription results on disk, keyed by a hash of the audio, model and options
+///
+/// Transcribing the same 16kHz audio again with the same model and settings
+/// returns the stored segments instead of decoding. The key covers everything
+/// that changes the text (language, prompt, vocabulary, sampling, ...) but not
+/// the thread count or printing. A model file that is replaced (different size
+/// or modification time) gets new keys.
+#[derive(Debug, Clone)]
+pub struct TranscriptCache {
+    dir: PathBuf,
+}
+
+impl TranscriptCache {
+    /// Create a cache that stores results in `dir`
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Create a cache in the platform cache directory, e.g. `~/.cache/voicekb/transcripts`
+    pub fn with_default_dir() -> Result<Self> {
+        app_cache_dir()
+            .map(|base| Self::new(base.join("transcripts")))
+            .ok_or_else(|| anyhow::anyhow!("Could not determine a cache directory for transcripts"))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Transcribe into segments, returning stored results when available
+    pub fn transcribe(
+        &self,
+        transcriber: &Transcriber,
+        audio: &[f32],
+        options: &TranscribeOptions,
+    ) -> Result<Vec<Segment>> {
+        self.get_or_transcribe(transcriber, audio, options, || {
+            transcriber.transcribe_segments_with(audio, options)
+        })
+    }
+
+    /// Return stored results for the audio, or store what `transcribe` returns
+    ///
+    /// Lets callers choose how to decode on a miss, e.g. with
+    /// [`Transcriber::transcribe_segments_resumable`].
+    pub fn get_or_transcribe<F>(
+        &self,
+        transcriber: &Transcriber,
+        audio: &[f32],
+        options: &TranscribeOptions,
+        transcribe: F,
+    ) -> Result<Vec<Segment>>
+    where
+        F: FnOnce() -> Result<Vec<Segment>>,
+    {
+        let key = cache_key(audio, &transcriber.model_cache_id(), options);
+        if let Some(segments) = self.get(&key) {
+            return Ok(segments);
+        }
+
+        let segments = transcribe()?;
+        // A cache that can't be written only costs speed next time
+        let _ = self.put(&key, &segments);
+        Ok(segments)
+    }
+
+    /// Stored segments for `key`; unreadable entries count as missing
+    fn get(&self, key: &str) -> Option<Vec<Segment>> {
+        let json = std::fs::read_to_string(self.path(key)).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    fn put(&self, key: &str, segments: &[Segment]) -> Result<()> {
+        std::fs::create_dir_all(&self.dir).map_err(|e| {
+            anyhow::anyhow!("Failed to create cache directory {:?}: {}", self.dir, e)
+        })?;
+        // Write to a temporary file first so concurrent readers never see half an entry
+        let path = self.path(key);
+        let partial = path.with_extension(format!("{}.part", std::process::id()));
+        std::fs::write(&partial, serde_json::to_string(segments)?)?;
+        std::fs::rename(&partial, &path)?;
+        Ok(())
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+/// SHA-256 of the audio, the model and the options that affect the text
+///
+/// `model` identifies the model's contents, see `Transcriber::model_cache_id`.
+pub(crate) fn cache_key(audio: &[f32], model: &str, options: &TranscribeOptions) -> String {
+    let mut hasher = Sha256::new();
+    for sample in audio {
+        hasher.update(sample.to_le_bytes());
+    }
+    hasher.update(model);
+
+    // Leave out settings that only change speed or logging
+    let mut options = options.clone();
+    options.abort = None;
+    options.on_segment = None;
+    options.on_progress = None;
+    options.priority = Default::default();
+    options.threads = 0;
+    options.parallel_chunks = 0;
+    options.print_special = false;
+    options.print_progress = false;
+    options.print_realtime = false;
+    options.print_timestamps = false;
+    hasher.update(format!("{:?}", options));
+
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_key() {
+        let audio = vec![0.0, 0.5, -0.5];
+        let model = "ggml-base.en.bin";
+        let options = TranscribeOptions::default();
+
+        let key = cache_key(&audio, model, &options);
+        assert_eq!(key.len(), 64);
+        assert_eq!(key, cache_key(&audio, model, &options.clone().threads(1)));
+        assert_ne!(key, cache_key(&audio[1..], model, &options));
+        assert_ne!(key, cache_key(&audio, "ggml-small.bin", &options));
+        assert_ne!(key, cache_key(&audio, model, &options.clone().language("nl")));
+    }
+}
@@ -0,0 +1,83 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use crate::Segment;
+
+/// Decoded speech chunks of one recording, saved to disk after every chunk
+///
+/// A checkpoint belongs to one combination of audio, model and options (see
+/// [`crate::cache`]); a file saved for another combination is ignored.
+pub(crate) struct Checkpoint {
+    path: PathBuf,
+    saved: Saved,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Saved {
+    key: String,
+    /// Segments by chunk offset in samples
+    chunks: BTreeMap<usize, Vec<Segment>>,
+}
+
+impl Checkpoint {
+    /// Load the checkpoint at `path` if it was saved for `key`, or start an empty one
+    pub fn open(path: &Path, key: String) -> Self {
+        let saved = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str::<Saved>(&json).ok())
+            .filter(|saved| saved.key == key)
+            .unwrap_or(Saved {
+                key,
+                chunks: BTreeMap::new(),
+            });
+        Self {
+            path: path.to_path_buf(),
+            saved,
+        }
+    }
+
+    /// Segments of the chunk at `offset`, if it was decoded before
+    pub fn get(&self, offset: usize) -> Option<&Vec<Segment>> {
+        self.saved.chunks.get(&offset)
+    }
+
+    /// Add the segments of a decoded chunk and save the checkpoint
+    pub fn record(&mut self, offset: usize, segments: Vec<Segment>) -> Result<()> {
+        self.saved.chunks.insert(offset, segments);
+        // Replace the file in one step so a crash mid-write keeps the previous checkpoint
+        let partial = self.path.with_extension("part");
+        std::fs::write(&partial, serde_json::to_string(&self.saved)?)
+            .and_then(|_| std::fs::rename(&partial, &self.path))
+            .map_err(|e| anyhow::anyhow!("Failed to save checkpoint {:?}: {}", self.path, e))
+    }
+
+    /// Delete the checkpoint once the recording is fully transcribed
+    pub fn remove(self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("checkpoint.json");
+        let segment = Segment::new("Hello", 0, 500);
+
+        let mut checkpoint = Checkpoint::open(&path, "a".to_string());
+        checkpoint.record(16000, vec![segment.clone()]).unwrap();
+
+        assert_eq!(Checkpoint::open(&path, "a".to_string()).get(16000), Some(&vec![segment]));
+        assert_eq!(Checkpoint::open(&path, "b".to_string()).get(16000), None);
+
+        checkpoint.remove();
+        assert!(!path.exists());
+    }
+}
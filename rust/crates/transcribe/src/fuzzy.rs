@@ -0,0 +1,104 @@
+/// Lowercase a phrase and drop everything but letters and digits
+pub(crate) fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// Replace runs of up to `max_words` words in `text` that resemble one of `targets`
+///
+/// Each target is a `(normalized, replacement)` pair. At every position the
+/// longest-scoring run wins; punctuation around the run is kept.
+pub(crate) fn replace_similar(
+    text: &str,
+    targets: &[(String, &str)],
+    max_words: usize,
+    min_similarity: f32,
+) -> String {
+    let targets: Vec<&(String, &str)> = targets
+        .iter()
+        .filter(|(norm, _)| !norm.is_empty())
+        .collect();
+    if targets.is_empty() {
+        return text.to_string();
+    }
+
+    let input: Vec<&str> = text.split_whitespace().collect();
+    let mut output: Vec<String> = Vec::with_capacity(input.len());
+
+    let mut i = 0;
+    while i < input.len() {
+        let mut best: Option<(usize, &str, f32)> = None;
+
+        for len in 1..=max_words.min(input.len() - i) {
+            let candidate: String = input[i..i + len].iter().map(|w| normalize(w)).collect();
+            if candidate.is_empty() {
+                continue;
+            }
+
+            for (norm, replacement) in &targets {
+                let score = similarity(&candidate, norm);
+                if score >= min_similarity && best.is_none_or(|(_, _, s)| score > s) {
+                    best = Some((len, replacement, score));
+                }
+            }
+        }
+
+        match best {
+            Some((len, replacement, _)) => {
+                let (leading, _, _) = split_punctuation(input[i]);
+                let (_, _, trailing) = split_punctuation(input[i + len - 1]);
+                output.push(format!("{}{}{}", leading, replacement, trailing));
+                i += len;
+            }
+            None => {
+                output.push(input[i].to_string());
+                i += 1;
+            }
+        }
+    }
+
+    output.join(" ")
+}
+
+/// Similarity of two strings in the range 0.0 (unrelated) to 1.0 (identical),
+/// based on the Levenshtein distance relative to the longer string
+pub(crate) fn similarity(a: &str, b: &str) -> f32 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+
+    1.0 - levenshtein(&a, &b) as f32 / longest as f32
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+/// Split a whitespace-delimited word into leading punctuation, core and trailing punctuation
+pub(crate) fn split_punctuation(word: &str) -> (&str, &str, &str) {
+    let start = word
+        .find(|c: char| c.is_alphanumeric())
+        .unwrap_or(word.len());
+    let end = word
+        .rfind(|c: char| c.is_alphanumeric())
+        .map(|i| i + word[i..].chars().next().map_or(1, char::len_utf8))
+        .unwrap_or(start);
+    (&word[..start], &word[start..end], &word[end..])
+}
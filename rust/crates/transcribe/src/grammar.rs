@@ -0,0 +1,188 @@
+use whisper_rs::WhisperToken;
+
+/// A fixed set of phrases that recognition is limited to
+///
+/// Meant for command modes: with a grammar set, whisper can only produce one of
+/// the phrases (or, with [`Grammar::repeat`], a sequence of them), so short
+/// commands like "new line" or "select all" are recognized far more reliably
+/// than in free dictation. Each phrase is accepted with or without a leading
+/// space and capital, the way whisper tends to write them, but without
+/// punctuation.
+///
+/// whisper.cpp's own GBNF grammars can't be used through whisper-rs, which
+/// passes the rules in the wrong layout, so the phrases are enforced with a
+/// logits filter instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grammar {
+    phrases: Vec<String>,
+    repeat: bool,
+}
+
+impl Grammar {
+    /// Create a grammar that accepts exactly one of `phrases`
+    pub fn new<I, S>(phrases: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            phrases: phrases
+                .into_iter()
+                .map(Into::into)
+                .map(|phrase: String| phrase.trim().to_string())
+                .filter(|phrase| !phrase.is_empty())
+                .collect(),
+            repeat: false,
+        }
+    }
+
+    /// A grammar for digit strings such as "4 2" or "42"
+    pub fn digits() -> Self {
+        Self::new((0..10).map(|digit| digit.to_string())).repeat(true)
+    }
+
+    /// Accept a sequence of phrases instead of a single one
+    pub fn repeat(mut self, repeat: bool) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    /// The accepted phrases
+    pub fn phrases(&self) -> &[String] {
+        &self.phrases
+    }
+
+    /// Whether a sequence of phrases is accepted
+    pub fn is_repeated(&self) -> bool {
+        self.repeat
+    }
+
+    /// Spellings of each phrase that whisper may produce
+    pub(crate) fn variants(&self) -> Vec<String> {
+        let mut variants = Vec::new();
+        for phrase in &self.phrases {
+            for text in [phrase.clone(), capitalize(phrase)] {
+                for variant in [format!(" {}", text), text] {
+                    if !variants.contains(&variant) {
+                        variants.push(variant);
+                    }
+                }
+            }
+        }
+        variants
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// The token sequences of a grammar, to look up which tokens may come next
+#[derive(Debug)]
+pub(crate) struct PhraseTrie {
+    nodes: Vec<Node>,
+    repeat: bool,
+}
+
+#[derive(Debug, Default)]
+struct Node {
+    children: Vec<(WhisperToken, usize)>,
+    /// A phrase ends here
+    end: bool,
+}
+
+impl PhraseTrie {
+    pub fn new<I>(sequences: I, repeat: bool) -> Self
+    where
+        I: IntoIterator<Item = Vec<WhisperToken>>,
+    {
+        let mut nodes = vec![Node::default()];
+        for sequence in sequences {
+            if sequence.is_empty() {
+                continue;
+            }
+            let mut node = 0;
+            for token in sequence {
+                node = match nodes[node].child(token) {
+                    Some(child) => child,
+                    None => {
+                        nodes.push(Node::default());
+                        let child = nodes.len() - 1;
+                        nodes[node].children.push((token, child));
+                        child
+                    }
+                };
+            }
+            nodes[node].end = true;
+        }
+        Self { nodes, repeat }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes[0].children.is_empty()
+    }
+
+    /// The tokens that may follow the text tokens decoded so far, and whether
+    /// decoding may stop here
+    pub fn allowed(&self, decoded: &[WhisperToken]) -> (Vec<WhisperToken>, bool) {
+        let mut node = 0;
+        for &token in decoded {
+            let next = self.nodes[node].child(token).or_else(|| {
+                // Start the next phrase once one is complete
+                (self.repeat && self.nodes[node].end)
+                    .then(|| self.nodes[0].child(token))
+                    .flatten()
+            });
+            match next {
+                Some(next) => node = next,
+                // Only reachable if the filter wasn't applied; let decoding end
+                None => return (Vec::new(), true),
+            }
+        }
+
+        let current = &self.nodes[node];
+        let mut tokens: Vec<WhisperToken> = current.children.iter().map(|&(t, _)| t).collect();
+        if self.repeat && current.end {
+            tokens.extend(self.nodes[0].children.iter().map(|&(t, _)| t));
+        }
+        (tokens, current.end)
+    }
+}
+
+impl Node {
+    fn child(&self, token: WhisperToken) -> Option<usize> {
+        self.children
+            .iter()
+            .find(|&&(t, _)| t == token)
+            .map(|&(_, child)| child)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grammar() {
+        let grammar = Grammar::new(["new line", " ", "Undo"]);
+        assert_eq!(grammar.phrases(), ["new line", "Undo"]);
+        assert_eq!(
+            grammar.variants(),
+            [" new line", "new line", " New line", "New line", " Undo", "Undo"]
+        );
+
+        // "new line" = [1, 2], "new tab" = [1, 3], "undo" = [4]
+        let trie = PhraseTrie::new(vec![vec![1, 2], vec![1, 3], vec![4]], false);
+        assert_eq!(trie.allowed(&[]), (vec![1, 4], false));
+        assert_eq!(trie.allowed(&[1]), (vec![2, 3], false));
+        assert_eq!(trie.allowed(&[1, 2]), (vec![], true));
+
+        let trie = PhraseTrie::new(vec![vec![1, 2], vec![4]], true);
+        assert_eq!(trie.allowed(&[4]), (vec![1, 4], true));
+        assert_eq!(trie.allowed(&[4, 1]), (vec![2], false));
+    }
+}
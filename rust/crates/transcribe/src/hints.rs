@@ -0,0 +1,125 @@
+use anyhow::Result;
+use std::path::Path;
+
+use crate::fuzzy::{normalize, replace_similar};
+
+/// Default minimum similarity between a transcribed phrase and a hint
+const DEFAULT_MIN_SIMILARITY: f32 = 0.8;
+
+/// Pronunciation hints: phonetic spellings mapped to the word that was meant
+///
+/// Hints are read from a plain text file with one mapping per line:
+///
+/// ```text
+/// # spoken => written
+/// jay son => JSON
+/// kube cuttle => kubectl
+/// ```
+///
+/// Transcribed phrases that closely resemble the spoken side are replaced by
+/// the written side. Matching ignores case, spacing and punctuation, so
+/// "Jayson," becomes "JSON,".
+#[derive(Debug, Clone, Default)]
+pub struct Hints {
+    entries: Vec<(String, String)>,
+    min_similarity: f32,
+}
+
+impl Hints {
+    /// Create hints from `(spoken, written)` pairs
+    pub fn new<I, S, W>(entries: I) -> Self
+    where
+        I: IntoIterator<Item = (S, W)>,
+        S: Into<String>,
+        W: Into<String>,
+    {
+        Self {
+            entries: entries
+                .into_iter()
+                .map(|(spoken, written)| (spoken.into(), written.into()))
+                .filter(|(spoken, written)| !normalize(spoken).is_empty() && !written.is_empty())
+                .collect(),
+            min_similarity: DEFAULT_MIN_SIMILARITY,
+        }
+    }
+
+    /// Parse hints in the `spoken => written` line format
+    pub fn parse(contents: &str) -> Result<Self> {
+        let mut entries = Vec::new();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (spoken, written) = line.split_once("=>").ok_or_else(|| {
+                anyhow::anyhow!("Line {}: expected \"spoken => written\"", number + 1)
+            })?;
+            entries.push((spoken.trim().to_string(), written.trim().to_string()));
+        }
+        Ok(Self::new(entries))
+    }
+
+    /// Load hints from a file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+        Self::parse(&contents).map_err(|e| anyhow::anyhow!("Invalid hints file {:?}: {}", path, e))
+    }
+
+    /// Set the minimum similarity (0.0 - 1.0) required to apply a hint
+    pub fn min_similarity(mut self, min_similarity: f32) -> Self {
+        self.min_similarity = min_similarity.clamp(0.0, 1.0);
+        self
+    }
+
+    /// The `(spoken, written)` pairs
+    pub fn entries(&self) -> &[(String, String)] {
+        &self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Replace phrases in `text` that sound like one of the hints
+    pub fn apply(&self, text: &str) -> String {
+        // Allow one extra word in case whisper split the phrase differently
+        let max_words = self
+            .entries
+            .iter()
+            .map(|(spoken, _)| spoken.split_whitespace().count() + 1)
+            .max()
+            .unwrap_or(0);
+        let targets: Vec<(String, &str)> = self
+            .entries
+            .iter()
+            .map(|(spoken, written)| (normalize(spoken), written.as_str()))
+            .collect();
+        replace_similar(text, &targets, max_words, self.min_similarity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_apply() {
+        let hints = Hints::parse(
+            "# spoken => written\n\
+             jay son => JSON\n\
+             \n\
+             kube cuttle => kubectl\n",
+        )
+        .unwrap();
+        assert_eq!(hints.entries().len(), 2);
+
+        assert_eq!(
+            hints.apply("Parse the Jayson, then run kube cuddle apply."),
+            "Parse the JSON, then run kubectl apply."
+        );
+        assert_eq!(hints.apply("nothing to see here"), "nothing to see here");
+        assert!(Hints::parse("jay son JSON").is_err());
+    }
+}
@@ -0,0 +1,67 @@
+use anyhow::Result;
+use whisper_rs::WhisperState;
+
+/// A spoken language and how likely it is, as detected by whisper
+#[derive(Debug, Clone, PartialEq)]
+pub struct LanguageProbability {
+    /// Language code, e.g. "fr"
+    pub language: &'static str,
+    pub probability: f32,
+}
+
+/// The probability of every language in the audio loaded into `state`, most likely first
+///
+/// Only the first 30 seconds are considered.
+pub(crate) fn detect(
+    state: &mut WhisperState,
+    audio: &[f32],
+    threads: usize,
+) -> Result<Vec<LanguageProbability>> {
+    state
+        .pcm_to_mel(audio, threads)
+        .map_err(|e| anyhow::anyhow!("Failed to compute spectrogram: {}", e))?;
+    let (_, probabilities) = state
+        .lang_detect(0, threads)
+        .map_err(|e| anyhow::anyhow!("Failed to detect language: {}", e))?;
+
+    let mut languages: Vec<LanguageProbability> = probabilities
+        .into_iter()
+        .enumerate()
+        .filter_map(|(id, probability)| {
+            whisper_rs::get_lang_str(id as i32).map(|language| LanguageProbability {
+                language,
+                probability,
+            })
+        })
+        .collect();
+    languages.sort_by(|a, b| b.probability.total_cmp(&a.probability));
+    Ok(languages)
+}
+
+/// The most likely of `candidates` among the detected languages
+pub(crate) fn best_candidate(
+    detected: &[LanguageProbability],
+    candidates: &[String],
+) -> Option<&'static str> {
+    detected
+        .iter()
+        .find(|detected| candidates.iter().any(|candidate| candidate == detected.language))
+        .map(|detected| detected.language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_best_candidate() {
+        let detected = [
+            LanguageProbability { language: "de", probability: 0.5 },
+            LanguageProbability { language: "fr", probability: 0.3 },
+            LanguageProbability { language: "en", probability: 0.2 },
+        ];
+        let candidates = ["en".to_string(), "fr".to_string()];
+        assert_eq!(best_candidate(&detected, &candidates), Some("fr"));
+        assert_eq!(best_candidate(&detected, &["nl".to_string()]), None);
+    }
+}
@@ -1,70 +1,888 @@
+mod abort;
+pub mod backend;
+pub mod batch;
+pub mod bench;
+pub mod cache;
+mod checkpoint;
+mod fuzzy;
+pub mod grammar;
+pub mod hints;
+pub mod language;
+#[cfg(feature = "translation")]
+mod libretranslate;
+mod logit_bias;
+pub mod model;
+pub mod options;
+pub mod outcome;
+pub mod pcm;
+pub mod postprocess;
+pub mod progress;
+pub mod segment;
+mod segment_callback;
+pub mod scheduler;
+pub mod squelch;
+mod state_pool;
+pub mod stats;
+pub mod subtitle;
+pub mod translate;
+pub mod vad;
+pub mod vocabulary;
+
 use anyhow::Result;
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
-use std::path::Path;
+use whisper_rs::{FullParams, WhisperContext, WhisperState, WhisperToken};
+use cache::cache_key;
+use checkpoint::Checkpoint;
+use scheduler::{Scheduler, Slot};
+use state_pool::{PooledState, StatePool};
+use sha2::{Digest, Sha256};
+use std::{
+    borrow::Cow,
+    io::Read,
+    ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    thread::JoinHandle,
+    time::Instant,
+};
+
+pub use abort::AbortHandle;
+pub use batch::{Batch, BatchResult};
+pub use cache::TranscriptCache;
+pub use backend::{compiled_cpu_backends, compiled_gpu_backends, BackendInfo, BUILD_TARGET};
+pub use grammar::Grammar;
+pub use hints::Hints;
+pub use language::LanguageProbability;
+pub use model::{ModelInfo, ModelManager, ModelRecord};
+pub use options::{Sampling, TranscribeOptions};
+pub use outcome::TranscriptionOutcome;
+pub use scheduler::Priority;
+pub use pcm::{load_wav_resampled, pcm_to_whisper, read_wav_resampled};
+pub use postprocess::{
+    find_fillers, Attach, Fillers, NumberWords, Postprocess, SpokenSymbols, TextFilter, Typography,
+    FILLERS,
+};
+pub use progress::ProgressCallback;
+pub use segment::{format_dialogue, Segment, Word};
+pub use segment_callback::{NewSegment, SegmentCallback, SegmentId};
+pub use squelch::Squelch;
+pub use stats::TranscriptionStats;
+pub use subtitle::Format;
+#[cfg(feature = "translation")]
+pub use translate::LibreTranslate;
+pub use translate::Translator;
+pub use vad::Vad;
+pub use vocabulary::Vocabulary;
+pub use whisper_rs::{DtwMode, DtwModelPreset, DtwParameters, WhisperContextParameters};
+use grammar::PhraseTrie;
+use logit_bias::LogitBias;
+use progress::{ChunkProgress, ProgressTracker};
+use segment::{align_tokens, group_words, label_speakers, to_ms, token_confidence, Token};
+use segment_callback::SegmentSink;
+
+/// A transcription running on its own thread, see [`Transcriber::transcribe_cancellable`]
+pub type TranscriptionThread = JoinHandle<Result<(Vec<Segment>, TranscriptionStats)>>;
 
+/// Speech-to-text using a Whisper model
+///
+/// Clones share the loaded model, so a model swapped in through one clone is
+/// used by all of them. They also share a scheduler that holds back
+/// [`Priority::Background`] decodes while interactive ones run.
+///
+/// Cloning is cheap, and clones can transcribe from any number of threads at
+/// once: the weights are loaded once and only read while decoding, and every
+/// decode runs on a whisper state of its own, taken from a pool of warm states.
+/// Each state holds its own KV caches, so limit how many decode at the same time
+/// with [`Transcriber::max_concurrent`] when serving many requests.
+#[derive(Clone)]
 pub struct Transcriber {
+    model: Arc<RwLock<Arc<LoadedModel>>>,
+    scheduler: Arc<Scheduler>,
+}
+
+// Frontends share transcribers between threads
+const _: fn() = assert_send_sync::<Transcriber>;
+fn assert_send_sync<T: Send + Sync>() {}
+
+/// A Whisper context, where and on which backend it was loaded and its warm decoding states
+struct LoadedModel {
     context: WhisperContext,
+    source: ModelSource,
+    backend: BackendInfo,
+    /// Tokens are aligned with DTW, see [`Transcriber::new_with_params`]
+    dtw: bool,
+    states: StatePool,
+}
+
+impl LoadedModel {
+    fn load(model_path: &Path, params: WhisperContextParameters) -> Result<Self> {
+        let model_path_str = model_path.to_str()
+            .ok_or_else(|| anyhow::anyhow!("Model path contains invalid UTF-8"))?;
+
+        let mut backend = BackendInfo::new(params.use_gpu, params.gpu_device);
+        backend.coreml_encoder =
+            cfg!(feature = "coreml") && model::coreml_encoder_path(model_path).exists();
+        let dtw = uses_dtw(&params);
+        let context = WhisperContext::new_with_params(model_path_str, params)
+            .map_err(|e| anyhow::anyhow!("Failed to load Whisper model with params: {}", e))?;
+        let states = StatePool::warm(&context)?;
+
+        Ok(Self {
+            context,
+            source: ModelSource::File(model_path.to_path_buf()),
+            backend,
+            dtw,
+            states,
+        })
+    }
+
+    fn load_buffer(buffer: &[u8], params: WhisperContextParameters) -> Result<Self> {
+        let backend = BackendInfo::new(params.use_gpu, params.gpu_device);
+        let dtw = uses_dtw(&params);
+        let context = WhisperContext::new_from_buffer_with_params(buffer, params)
+            .map_err(|e| anyhow::anyhow!("Failed to load Whisper model from memory: {}", e))?;
+        let states = StatePool::warm(&context)?;
+
+        Ok(Self {
+            context,
+            source: ModelSource::Buffer(hex_digest(buffer)),
+            backend,
+            dtw,
+            states,
+        })
+    }
+
+    /// Identifies the model's contents and how its words are timed in cache keys
+    fn cache_id(&self) -> String {
+        if self.dtw {
+            format!("{} dtw", self.source.cache_id())
+        } else {
+            self.source.cache_id()
+        }
+    }
+}
+
+/// A state with the results of a run and the scheduler slot it was decoded in
+struct ScheduledState<'a> {
+    // Declared first to be dropped first: back in the pool before the slot frees up
+    state: PooledState<'a>,
+    _slot: Slot<'a>,
+}
+
+impl Deref for ScheduledState<'_> {
+    type Target = WhisperState;
+
+    fn deref(&self) -> &WhisperState {
+        &self.state
+    }
+}
+
+impl DerefMut for ScheduledState<'_> {
+    fn deref_mut(&mut self) -> &mut WhisperState {
+        &mut self.state
+    }
+}
+
+/// Whether whisper computes DTW token timestamps with these parameters; flash
+/// attention turns them off
+fn uses_dtw(params: &WhisperContextParameters) -> bool {
+    !params.flash_attn && !matches!(params.dtw_parameters.mode, DtwMode::None)
+}
+
+/// Where a model was loaded from
+enum ModelSource {
+    File(PathBuf),
+    /// Memory, identified by the SHA-256 of the model
+    Buffer(String),
+}
+
+impl ModelSource {
+    /// Identifies the model's contents in cache keys
+    ///
+    /// Files are identified by path, size and modification time rather than
+    /// hashed on every lookup.
+    fn cache_id(&self) -> String {
+        match self {
+            Self::File(path) => {
+                let metadata = std::fs::metadata(path).ok();
+                let modified = metadata.as_ref().and_then(|m| m.modified().ok());
+                format!("{:?} {:?} {:?}", path, metadata.map(|m| m.len()), modified)
+            }
+            Self::Buffer(digest) => digest.clone(),
+        }
+    }
+}
+
+impl ModelSource {
+    /// The model file, or the SHA-256 of a model in memory
+    fn name(&self) -> String {
+        match self {
+            Self::File(path) => path.display().to_string(),
+            Self::Buffer(digest) => format!("sha256:{}", digest),
+        }
+    }
+}
+
+impl std::fmt::Display for ModelSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::Buffer(_) => write!(f, "The in-memory model"),
+        }
+    }
+}
+
+/// Lowercase hex SHA-256 of `bytes`
+fn hex_digest(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
 }
 
 impl Transcriber {
     /// Create a new transcriber with the specified model path
     pub fn new<P: AsRef<Path>>(model_path: P) -> Result<Self> {
-        let model_path_str = model_path.as_ref().to_str()
-            .ok_or_else(|| anyhow::anyhow!("Model path contains invalid UTF-8"))?;
-        
-        let params = WhisperContextParameters::default();
-        let ctx = WhisperContext::new_with_params(model_path_str, params)
-            .map_err(|e| anyhow::anyhow!("Failed to load Whisper model: {}", e))?;
-        
-        Ok(Self { context: ctx })
+        Self::new_with_params(model_path, WhisperContextParameters::default())
+    }
+
+    /// Create a new transcriber, choosing whether to offload to the GPU and which device to use
+    pub fn new_with_gpu<P: AsRef<Path>>(model_path: P, use_gpu: bool, gpu_device: i32) -> Result<Self> {
+        let mut params = WhisperContextParameters::default();
+        params.use_gpu(use_gpu).gpu_device(gpu_device);
+        Self::new_with_params(model_path, params)
+    }
+
+    /// Create a new transcriber from a model file's contents, e.g. one bundled as a
+    /// resource or downloaded into memory
+    ///
+    /// The buffer is only read while loading and can be dropped afterwards.
+    pub fn from_buffer(buffer: &[u8]) -> Result<Self> {
+        Self::from_buffer_with_params(buffer, WhisperContextParameters::default())
+    }
+
+    /// Create a new transcriber from a model file's contents with custom parameters
+    pub fn from_buffer_with_params(
+        buffer: &[u8],
+        params: WhisperContextParameters,
+    ) -> Result<Self> {
+        Ok(Self::from_model(LoadedModel::load_buffer(buffer, params)?))
     }
 
     /// Create a new transcriber with custom parameters
+    ///
+    /// For word timings accurate enough to highlight words as they are spoken, turn on
+    /// DTW token alignment with `params.dtw_parameters`, using
+    /// [`DtwMode::ModelPreset`] with the model's heads from [`model::dtw_preset`].
+    /// [`Word`] timings then come from the alignment instead of whisper's timestamp
+    /// tokens. The heads are model-specific, so [`Transcriber::swap_model`] loads the
+    /// new model without DTW.
     pub fn new_with_params<P: AsRef<Path>>(
         model_path: P,
         params: WhisperContextParameters,
     ) -> Result<Self> {
-        let model_path_str = model_path.as_ref().to_str()
-            .ok_or_else(|| anyhow::anyhow!("Model path contains invalid UTF-8"))?;
-        
-        let ctx = WhisperContext::new_with_params(model_path_str, params)
-            .map_err(|e| anyhow::anyhow!("Failed to load Whisper model with params: {}", e))?;
-        
-        Ok(Self { context: ctx })
+        Ok(Self::from_model(LoadedModel::load(model_path.as_ref(), params)?))
+    }
+
+    fn from_model(model: LoadedModel) -> Self {
+        Self {
+            model: Arc::new(RwLock::new(Arc::new(model))),
+            scheduler: Arc::new(Scheduler::default()),
+        }
+    }
+
+    /// Decode at most `limit` speech chunks at the same time, across all clones
+    ///
+    /// Further decodes wait for a running one to finish, interactive ones first.
+    /// This bounds the number of whisper states, and so the memory used, when
+    /// many recordings are transcribed in parallel. There is no limit by default.
+    pub fn max_concurrent(self, limit: usize) -> Self {
+        self.scheduler.set_limit(limit);
+        self
+    }
+
+    /// The compute backend the model was loaded on
+    pub fn backend_info(&self) -> BackendInfo {
+        self.model().backend.clone()
+    }
+
+    /// File the current model was loaded from, or `None` for a model loaded from memory
+    pub fn model_path(&self) -> Option<PathBuf> {
+        match &self.model().source {
+            ModelSource::File(path) => Some(path.clone()),
+            ModelSource::Buffer(_) => None,
+        }
+    }
+
+    /// Identifies the current model's contents in cache keys
+    pub(crate) fn model_cache_id(&self) -> String {
+        self.model().cache_id()
+    }
+
+    /// Replace the loaded model with the one at `model_path`
+    ///
+    /// The new model is loaded on the same backend as the current one. Calls
+    /// already in progress finish on the old model, later calls use the new
+    /// one; if loading fails the current model stays in place.
+    pub fn swap_model<P: AsRef<Path>>(&self, model_path: P) -> Result<()> {
+        let backend = self.backend_info();
+        let mut params = WhisperContextParameters::default();
+        params.use_gpu(backend.use_gpu).gpu_device(backend.gpu_device);
+
+        let model = Arc::new(LoadedModel::load(model_path.as_ref(), params)?);
+        *self.model.write().unwrap_or_else(|e| e.into_inner()) = model;
+        Ok(())
+    }
+
+    /// Load a new model on a background thread and switch to it once loaded
+    pub fn swap_model_in_background<P: Into<PathBuf>>(
+        &self,
+        model_path: P,
+    ) -> JoinHandle<Result<()>> {
+        let transcriber = self.clone();
+        let model_path = model_path.into();
+        std::thread::spawn(move || transcriber.swap_model(model_path))
+    }
+
+    /// Whether the model knows languages other than English (not a `.en` model)
+    pub fn is_multilingual(&self) -> bool {
+        self.model().context.is_multilingual()
+    }
+
+    /// The probability of each language being spoken in `audio` (f32, 16kHz), most
+    /// likely first
+    ///
+    /// Only the first 30 seconds are considered. English-only models always
+    /// detect English.
+    pub fn detect_language(&self, audio: &[f32]) -> Result<Vec<LanguageProbability>> {
+        let model = self.model();
+        let _slot = self.scheduler.acquire(Priority::Interactive);
+        let mut state = model.states.checkout(&model.context)?;
+        language::detect(&mut state, audio, num_cpus::get())
+    }
+
+    /// The currently loaded model
+    fn model(&self) -> Arc<LoadedModel> {
+        Arc::clone(&self.model.read().unwrap_or_else(|e| e.into_inner()))
     }
 
     /// Transcribe audio samples (f32, 16kHz)
     pub fn transcribe(&self, audio: &[f32]) -> Result<String> {
-        let mut state = self.context.create_state()
-            .map_err(|e| anyhow::anyhow!("Failed to create model state: {}", e))?;
+        self.transcribe_with(audio, &TranscribeOptions::default())
+    }
 
-        // Configure transcription parameters
-        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 0 });
-        params.set_n_threads(num_cpus::get() as i32);
-        params.set_translate(false); // Don't translate, just transcribe
-        params.set_language(Some("en"));
-        params.set_token_timestamps(false);
-        params.set_print_special(false);
-        params.set_print_progress(false);
-        params.set_print_realtime(false);
-        params.set_print_timestamps(false);
+    /// Transcribe audio samples (f32, 16kHz) with custom options
+    pub fn transcribe_with(&self, audio: &[f32], options: &TranscribeOptions) -> Result<String> {
+        self.transcribe_with_stats(audio, options).map(|(text, _)| text)
+    }
 
-        // Run transcription
-        state.full(params, audio)
-            .map_err(|e| anyhow::anyhow!("Failed to run transcription: {}", e))?;
+    /// Transcribe audio samples (f32, 16kHz) with custom options, timing the decode
+    pub fn transcribe_with_stats(
+        &self,
+        audio: &[f32],
+        options: &TranscribeOptions,
+    ) -> Result<(String, TranscriptionStats)> {
+        let start = Instant::now();
+        let model = self.model();
+
+        let parts = self.decode_chunks(&model, audio, options, |_| None, |state, _| {
+            // Extract transcribed text
+            let num_segments = state.full_n_segments()
+                .map_err(|e| anyhow::anyhow!("Failed to get segment count: {}", e))?;
+
+            let mut result = String::new();
+            for i in 0..num_segments {
+                let segment = state.full_get_segment_text(i)
+                    .map_err(|e| anyhow::anyhow!("Failed to get segment {}: {}", i, e))?;
+                result.push_str(&segment);
+            }
+            Ok(result.trim().to_string())
+        })?;
+
+        let stats = Self::stats(&model, audio, options, start);
+        Ok((options.correct(&parts.join(" ")), stats))
+    }
+
+    /// Transcribe interleaved 16-bit PCM of any sample rate and channel count
+    pub fn transcribe_pcm(
+        &self,
+        samples: &[i16],
+        sample_rate: u32,
+        channels: u16,
+    ) -> Result<String> {
+        self.transcribe_pcm_with(samples, sample_rate, channels, &TranscribeOptions::default())
+    }
+
+    /// Transcribe interleaved 16-bit PCM of any sample rate and channel count with custom options
+    pub fn transcribe_pcm_with(
+        &self,
+        samples: &[i16],
+        sample_rate: u32,
+        channels: u16,
+        options: &TranscribeOptions,
+    ) -> Result<String> {
+        let audio = pcm_to_whisper(samples, sample_rate, channels)?;
+        self.transcribe_with(&audio, options)
+    }
+
+    /// Transcribe audio samples (f32, 16kHz) into timestamped segments with word timings
+    pub fn transcribe_segments(&self, audio: &[f32]) -> Result<Vec<Segment>> {
+        self.transcribe_segments_with(audio, &TranscribeOptions::default())
+    }
+
+    /// Transcribe audio samples (f32, 16kHz) into timestamped segments with custom options
+    pub fn transcribe_segments_with(
+        &self,
+        audio: &[f32],
+        options: &TranscribeOptions,
+    ) -> Result<Vec<Segment>> {
+        self.transcribe_segments_with_stats(audio, options).map(|(segments, _)| segments)
+    }
+
+    /// Transcribe audio samples (f32, 16kHz) into timestamped segments, timing the decode
+    pub fn transcribe_segments_with_stats(
+        &self,
+        audio: &[f32],
+        options: &TranscribeOptions,
+    ) -> Result<(Vec<Segment>, TranscriptionStats)> {
+        let start = Instant::now();
+        let model = self.model();
+        let options = &options.clone().token_timestamps(true);
+
+        let chunks = self.decode_chunks(&model, audio, options, |_| None, |state, offset| {
+            let offset_ms = (offset * 1000 / SAMPLE_RATE) as i64;
+            Self::segments(&model, state, options, offset_ms)
+        })?;
+        let mut segments: Vec<Segment> = chunks.into_iter().flatten().collect();
+        label_speakers(&mut segments);
+
+        Ok((segments, Self::stats(&model, audio, options, start)))
+    }
+
+    /// Transcribe audio samples (f32, 16kHz) into segments, telling silence apart
+    ///
+    /// Markers whisper writes for audio without speech, such as `[BLANK_AUDIO]`,
+    /// are left out; a recording with nothing else is [`TranscriptionOutcome::NoSpeech`].
+    pub fn transcribe_outcome_with(
+        &self,
+        audio: &[f32],
+        options: &TranscribeOptions,
+    ) -> Result<TranscriptionOutcome> {
+        let segments = self.transcribe_segments_with(audio, options)?;
+        Ok(TranscriptionOutcome::from_segments(segments))
+    }
+
+    /// Transcribe into segments, saving every decoded speech chunk to `checkpoint_path`
+    ///
+    /// When a transcription is interrupted (e.g. the process is killed), calling
+    /// this again with the same audio, model and options continues after the last
+    /// saved chunk. The checkpoint is deleted once all chunks are done. Chunks are
+    /// decoded in parallel as with [`TranscribeOptions::parallel_chunks`], each saved
+    /// as soon as it is done; without a VAD the whole recording is a single chunk.
+    pub fn transcribe_segments_resumable(
+        &self,
+        audio: &[f32],
+        options: &TranscribeOptions,
+        checkpoint_path: &Path,
+    ) -> Result<Vec<Segment>> {
+        let model = self.model();
+        let options = &options.clone().token_timestamps(true);
+        let key = cache_key(audio, &model.cache_id(), options);
+        let checkpoint = Mutex::new(Checkpoint::open(checkpoint_path, key));
+        let checkpoint_lock = || checkpoint.lock().unwrap_or_else(|e| e.into_inner());
+
+        let chunks = self.decode_chunks(
+            &model,
+            audio,
+            options,
+            |offset| checkpoint_lock().get(offset).cloned(),
+            |state, offset| {
+                let offset_ms = (offset * 1000 / SAMPLE_RATE) as i64;
+                let decoded = Self::segments(&model, state, options, offset_ms)?;
+                checkpoint_lock().record(offset, decoded.clone())?;
+                Ok(decoded)
+            },
+        )?;
+        checkpoint.into_inner().unwrap_or_else(|e| e.into_inner()).remove();
+        let mut segments: Vec<Segment> = chunks.into_iter().flatten().collect();
+        label_speakers(&mut segments);
+
+        Ok(segments)
+    }
+
+    /// Transcribe into segments on a background thread, returning a handle to cancel it
+    ///
+    /// An aborted transcription finishes with an error.
+    pub fn transcribe_cancellable(
+        &self,
+        audio: Vec<f32>,
+        options: &TranscribeOptions,
+    ) -> (AbortHandle, TranscriptionThread) {
+        let handle = AbortHandle::new();
+        let options = options.clone().abort_handle(handle.clone());
+        let transcriber = self.clone();
+        let join = std::thread::spawn(move || {
+            transcriber.transcribe_segments_with_stats(&audio, &options)
+        });
+        (handle, join)
+    }
+
+    /// Transcribe on tokio's blocking thread pool
+    ///
+    /// The decode is queued right away, so this must be called from within a
+    /// runtime. The returned future owns everything it needs and can be spawned
+    /// or awaited later.
+    #[cfg(feature = "async")]
+    pub fn transcribe_async(
+        &self,
+        audio: Vec<f32>,
+        options: &TranscribeOptions,
+    ) -> impl std::future::Future<Output = Result<String>> + Send + 'static {
+        let transcriber = self.clone();
+        let options = options.clone();
+        let task =
+            tokio::task::spawn_blocking(move || transcriber.transcribe_with(&audio, &options));
+        async move {
+            task.await
+                .map_err(|e| anyhow::anyhow!("Transcription task failed: {}", e))?
+        }
+    }
+
+    /// Run whisper on each speech chunk of `audio` and extract a result per chunk, in order
+    ///
+    /// Up to `options.parallel_chunks` chunks are decoded at the same time, each on
+    /// its own pooled state with an equal share of the decoding threads. Chunks that
+    /// `saved` has a result for, by offset in samples, are skipped. `extract` gets the
+    /// finished state and the chunk's offset in samples.
+    fn decode_chunks<T, S, F>(
+        &self,
+        model: &LoadedModel,
+        audio: &[f32],
+        options: &TranscribeOptions,
+        saved: S,
+        extract: F,
+    ) -> Result<Vec<T>>
+    where
+        T: Send,
+        S: Fn(usize) -> Option<T> + Sync,
+        F: Fn(&mut WhisperState, usize) -> Result<T> + Sync,
+    {
+        let chunks = speech_chunks(audio, options);
+        let tracker = progress_tracker(options, &chunks);
+        let decode = |offset: usize, chunk: &[f32], options: &TranscribeOptions| {
+            let progress = tracker.as_ref().map(|tracker| tracker.chunk(offset, chunk.len()));
+            if let Some(result) = saved(offset) {
+                if let Some(progress) = &progress {
+                    progress.finish();
+                }
+                return Ok(result);
+            }
+            let mut state = self.run_scheduled(model, chunk, offset, options, progress.as_ref())?;
+            extract(&mut state, offset)
+        };
+        // Report new segments in order
+        let parallel = if options.on_segment.is_some() { 1 } else { options.parallel_chunks };
+        let workers = parallel.min(chunks.len()).max(1);
+        if workers == 1 {
+            return chunks
+                .iter()
+                .map(|(offset, chunk)| decode(*offset, chunk, options))
+                .collect();
+        }
+
+        let options = &options.clone().threads(options.threads / workers as i32);
+        let next = AtomicUsize::new(0);
+        let results: Vec<Mutex<Option<Result<T>>>> =
+            chunks.iter().map(|_| Mutex::new(None)).collect();
+        std::thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let Some((offset, chunk)) = chunks.get(index) else {
+                        break;
+                    };
+                    let result = decode(*offset, chunk, options);
+                    if result.is_err() {
+                        // Don't start chunks after a failure; earlier ones are all claimed
+                        next.store(chunks.len(), Ordering::SeqCst);
+                    }
+                    *results[index].lock().unwrap_or_else(|e| e.into_inner()) = Some(result);
+                });
+            }
+        });
+
+        results
+            .into_iter()
+            .filter_map(|slot| slot.into_inner().unwrap_or_else(|e| e.into_inner()))
+            .collect()
+    }
+
+    /// Stats of a transcription of `audio` with `model` that started at `start`
+    fn stats(
+        model: &LoadedModel,
+        audio: &[f32],
+        options: &TranscribeOptions,
+        start: Instant,
+    ) -> TranscriptionStats {
+        TranscriptionStats::new(audio.len(), start.elapsed(), model.source.name(), options.threads)
+    }
+
+    /// Run whisper once the scheduler lets a decode with the options' priority start
+    ///
+    /// The slot is held until the state goes back to the pool, so the scheduler's
+    /// limit also bounds the number of states.
+    fn run_scheduled<'a>(
+        &'a self,
+        model: &'a LoadedModel,
+        audio: &[f32],
+        offset: usize,
+        options: &TranscribeOptions,
+        progress: Option<&ChunkProgress>,
+    ) -> Result<ScheduledState<'a>> {
+        let slot = self.scheduler.acquire(options.priority);
+        Ok(ScheduledState {
+            state: Self::run(model, audio, offset, options, progress)?,
+            _slot: slot,
+        })
+    }
+
+    /// Collect the segments of a finished run, shifting timestamps by `offset_ms`
+    fn segments(
+        model: &LoadedModel,
+        state: &mut WhisperState,
+        options: &TranscribeOptions,
+        offset_ms: i64,
+    ) -> Result<Vec<Segment>> {
+        let eot = model.context.token_eot();
 
-        // Extract transcribed text
         let num_segments = state.full_n_segments()
             .map_err(|e| anyhow::anyhow!("Failed to get segment count: {}", e))?;
 
-        let mut result = String::new();
+        let mut segments = Vec::with_capacity(num_segments as usize);
         for i in 0..num_segments {
-            let segment = state.full_get_segment_text(i)
+            let text = state.full_get_segment_text_lossy(i)
                 .map_err(|e| anyhow::anyhow!("Failed to get segment {}: {}", i, e))?;
-            result.push_str(&segment);
+            let t0 = state.full_get_segment_t0(i)
+                .map_err(|e| anyhow::anyhow!("Failed to get segment {} start: {}", i, e))?;
+            let t1 = state.full_get_segment_t1(i)
+                .map_err(|e| anyhow::anyhow!("Failed to get segment {} end: {}", i, e))?;
+
+            let num_tokens = state.full_n_tokens(i)
+                .map_err(|e| anyhow::anyhow!("Failed to get token count of segment {}: {}", i, e))?;
+
+            let mut tokens = Vec::with_capacity(num_tokens as usize);
+            let mut dtw_ms = Vec::with_capacity(num_tokens as usize);
+            for j in 0..num_tokens {
+                let data = state.full_get_token_data(i, j)
+                    .map_err(|e| anyhow::anyhow!("Failed to get token {} of segment {}: {}", j, i, e))?;
+
+                // Skip special tokens ([_BEG_], timestamps, ...)
+                if data.id >= eot {
+                    continue;
+                }
+
+                let bytes = state.full_get_token_bytes(i, j)
+                    .map_err(|e| anyhow::anyhow!("Failed to get token {} of segment {}: {}", j, i, e))?;
+                dtw_ms.push(if data.t_dtw >= 0 { to_ms(data.t_dtw) + offset_ms } else { -1 });
+                tokens.push(Token {
+                    bytes,
+                    start_ms: to_ms(data.t0) + offset_ms,
+                    end_ms: to_ms(data.t1) + offset_ms,
+                    probability: data.p,
+                    logprob: data.plog,
+                });
+            }
+
+            if model.dtw {
+                align_tokens(&mut tokens, &dtw_ms, to_ms(t1) + offset_ms);
+            }
+
+            let (probability, avg_logprob) = token_confidence(&tokens);
+            segments.push(Segment {
+                text: options.correct(text.trim()),
+                start_ms: to_ms(t0) + offset_ms,
+                end_ms: to_ms(t1) + offset_ms,
+                words: group_words(&tokens),
+                probability,
+                avg_logprob,
+                speaker_turn: state.full_get_segment_speaker_turn_next(i),
+                speaker: 0,
+            });
+        }
+
+        Ok(segments)
+    }
+
+    /// Run the full whisper pipeline on a pooled state and return it with the results
+    ///
+    /// `offset` is the position of `audio` in the recording, in samples.
+    fn run<'a>(
+        model: &'a LoadedModel,
+        audio: &[f32],
+        offset: usize,
+        options: &TranscribeOptions,
+        progress: Option<&ChunkProgress>,
+    ) -> Result<PooledState<'a>> {
+        Self::check_language(model, options)?;
+        let mut state = model.states.checkout(&model.context)?;
+        // Pick among the allowed languages before whisper picks any
+        let detected_language = match &options.language {
+            None if !options.languages.is_empty() => {
+                let threads = options.threads.max(1) as usize;
+                let detected = language::detect(&mut state, audio, threads)?;
+                language::best_candidate(&detected, &options.languages)
+            }
+            _ => None,
+        };
+
+        // Configure transcription parameters
+        let mut params = FullParams::new(options.sampling.into());
+        params.set_n_threads(options.threads);
+        params.set_translate(options.translate);
+        if options.language.as_deref().is_some_and(|language| language.contains('\0')) {
+            return Err(anyhow::anyhow!("Language contains a null byte"));
+        }
+        params.set_language(detected_language.or(options.language.as_deref()));
+        params.set_token_timestamps(options.token_timestamps);
+        params.set_tdrz_enable(options.tinydiarize);
+        params.set_suppress_nst(options.suppress_non_speech);
+        params.set_print_special(options.print_special);
+        params.set_print_progress(options.print_progress);
+        params.set_print_realtime(options.print_realtime);
+        params.set_print_timestamps(options.print_timestamps);
+        if let Some(temperature) = options.temperature {
+            params.set_temperature(temperature);
+        }
+        if let Some(increment) = options.temperature_increment {
+            params.set_temperature_inc(increment);
+        }
+        if let Some(threshold) = options.entropy_threshold {
+            params.set_entropy_thold(threshold);
+        }
+        if let Some(threshold) = options.logprob_threshold {
+            params.set_logprob_thold(threshold);
+        }
+        if let Some(threshold) = options.no_speech_threshold {
+            params.set_no_speech_thold(threshold);
+        }
+        if let Some(prompt) = options.prompt() {
+            if prompt.contains('\0') {
+                return Err(anyhow::anyhow!("Initial prompt contains a null byte"));
+            }
+            params.set_initial_prompt(&prompt);
         }
 
-        Ok(result.trim().to_string())
+        if options.grammar.is_some() {
+            // Commands are short; timestamps would only compete with the phrase tokens
+            params.set_no_timestamps(true);
+            params.set_single_segment(true);
+        }
+
+        // Boost vocabulary words and enforce the grammar; must outlive `state.full`
+        let bias = Self::logit_bias(model, options)?;
+        bias.install(&mut params);
+        // Reports segments as they are decoded; must outlive `state.full`
+        let offset_ms = (offset * 1000 / SAMPLE_RATE) as i64;
+        let end_ms = ((offset + audio.len()) * 1000 / SAMPLE_RATE) as i64;
+        let sink = options
+            .on_segment
+            .as_ref()
+            .map(|callback| SegmentSink::new(callback, options, offset_ms, end_ms));
+        if let Some(sink) = &sink {
+            sink.install(&mut params);
+        }
+        // Must outlive `state.full` too
+        if let Some(progress) = progress {
+            progress.install(&mut params);
+        }
+        if let Some(abort) = &options.abort {
+            if abort.is_aborted() {
+                return Err(anyhow::anyhow!("Transcription cancelled"));
+            }
+            abort.install(&mut params);
+        }
+
+        // Run transcription
+        let result = state.full(params, audio);
+        let cancelled = options.abort.as_ref().is_some_and(AbortHandle::is_aborted);
+        if cancelled || result.is_err() {
+            // Don't reuse a state that an interrupted or failed decode left behind
+            state.discard();
+            return Err(match result {
+                Err(e) if !cancelled => anyhow::anyhow!("Failed to run transcription: {}", e),
+                _ => anyhow::anyhow!("Transcription cancelled"),
+            });
+        }
+        if let Some(progress) = progress {
+            progress.finish();
+        }
+
+        Ok(state)
+    }
+
+    /// Fail early when an English-only model is asked for another language
+    fn check_language(model: &LoadedModel, options: &TranscribeOptions) -> Result<()> {
+        if model.context.is_multilingual() {
+            return Ok(());
+        }
+        let other = options
+            .language
+            .iter()
+            .chain(&options.languages)
+            .find(|language| language.as_str() != "en");
+        match other {
+            Some(language) => Err(anyhow::anyhow!(
+                "{} is an English-only model and can't transcribe \"{}\"; use a multilingual \
+                 model such as base",
+                model.source,
+                language
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Logit bias boosting the first token of every vocabulary word, applying the
+    /// token biases and suppressions and limiting the output to the grammar
+    fn logit_bias(model: &LoadedModel, options: &TranscribeOptions) -> Result<LogitBias> {
+        let mut bias = LogitBias::new(model.context.n_vocab());
+        let mut boosts: Vec<(&String, f32)> = Vec::new();
+        if let Some(vocabulary) = &options.vocabulary {
+            boosts.extend(vocabulary.words().iter().map(|word| (word, vocabulary.token_boost())));
+        }
+        boosts.extend(options.token_bias.iter().map(|(text, value)| (text, *value)));
+        for (word, value) in boosts {
+            // Words usually appear mid-sentence, i.e. with a leading space
+            for text in [format!(" {}", word), word.clone()] {
+                let tokens = model.context.tokenize(&text, 64).unwrap_or_default();
+                if let Some(&token) = tokens.first() {
+                    bias.add(token, value);
+                }
+            }
+        }
+        if let Some(pattern) = &options.suppress_regex {
+            // Text tokens only; the special ones after them steer the decoder
+            let texts: Vec<(WhisperToken, String)> = (0..model.context.token_eot())
+                .filter_map(|token| {
+                    let text = model.context.token_to_cstr(token).ok()?;
+                    Some((token, text.to_string_lossy().into_owned()))
+                })
+                .collect();
+            let texts = texts.iter().map(|(token, text)| (*token, text.as_str()));
+            for token in logit_bias::matching_tokens(pattern, texts)? {
+                bias.suppress(token);
+            }
+        }
+        if let Some(grammar) = &options.grammar {
+            let sequences = grammar
+                .variants()
+                .into_iter()
+                .filter_map(|text| model.context.tokenize(&text, 64).ok());
+            let trie = PhraseTrie::new(sequences, grammar.is_repeated());
+            bias.restrict(trie, model.context.token_eot());
+        }
+        Ok(bias)
     }
 
     /// Transcribe from a WAV file
@@ -74,11 +892,63 @@ impl Transcriber {
     }
 }
 
+/// Whisper expects 16kHz audio
+const SAMPLE_RATE: usize = 16000;
+/// Whisper rejects input shorter than a second; pad with some margin
+const MIN_CHUNK_SAMPLES: usize = SAMPLE_RATE * 11 / 10;
+
+/// Split audio into the chunks to run whisper on, with their offset in samples
+///
+/// Without a VAD this is the whole recording. With one, only the speech is
+/// kept, split at long pauses; a recording without speech yields no chunks.
+fn speech_chunks<'a>(
+    audio: &'a [f32],
+    options: &TranscribeOptions,
+) -> Vec<(usize, Cow<'a, [f32]>)> {
+    let Some(vad) = &options.vad else {
+        return vec![(0, Cow::Borrowed(audio))];
+    };
+
+    vad.speech_ranges(audio)
+        .into_iter()
+        .map(|range| {
+            let start = range.start;
+            let mut chunk = audio[range].to_vec();
+            if chunk.len() < MIN_CHUNK_SAMPLES {
+                chunk.resize(MIN_CHUNK_SAMPLES, 0.0);
+            }
+            (start, Cow::Owned(chunk))
+        })
+        .collect()
+}
+
+/// Tracks the progress across `chunks` when the options ask for it
+fn progress_tracker<'a>(
+    options: &'a TranscribeOptions,
+    chunks: &[(usize, Cow<[f32]>)],
+) -> Option<ProgressTracker<'a>> {
+    let chunks = chunks.iter().map(|(offset, chunk)| (*offset, chunk.len()));
+    options
+        .on_progress
+        .as_ref()
+        .map(|callback| ProgressTracker::new(callback, chunks))
+}
+
 /// Load a WAV file and convert to f32 audio samples
 pub fn load_wav_as_float<P: AsRef<Path>>(path: P) -> Result<Vec<f32>> {
     let reader = hound::WavReader::open(&path)
         .map_err(|e| anyhow::anyhow!("Failed to open WAV file: {}", e))?;
-    
+    wav_as_float(reader)
+}
+
+/// Read WAV data, e.g. from memory, and convert it to f32 audio samples
+pub fn read_wav_as_float<R: Read>(reader: R) -> Result<Vec<f32>> {
+    let reader = hound::WavReader::new(reader)
+        .map_err(|e| anyhow::anyhow!("Failed to read WAV data: {}", e))?;
+    wav_as_float(reader)
+}
+
+fn wav_as_float<R: Read>(reader: hound::WavReader<R>) -> Result<Vec<f32>> {
     let spec = reader.spec();
     
     // Ensure it's 16kHz for Whisper
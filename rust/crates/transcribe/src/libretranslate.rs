@@ -0,0 +1,68 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::Translator;
+
+/// Translator backed by a LibreTranslate-compatible HTTP API
+///
+/// LibreTranslate runs Argos/CTranslate2 models locally, so pointing this at
+/// `http://localhost:5000` keeps everything on the machine.
+pub struct LibreTranslate {
+    url: String,
+    api_key: Option<String>,
+    agent: ureq::Agent,
+}
+
+#[derive(Serialize)]
+struct TranslateRequest<'a> {
+    q: &'a str,
+    source: &'a str,
+    target: &'a str,
+    format: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_key: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct TranslateResponse {
+    #[serde(rename = "translatedText")]
+    translated_text: String,
+}
+
+impl LibreTranslate {
+    /// Create a translator for the server at `url`
+    pub fn new(url: impl Into<String>, api_key: Option<String>) -> Self {
+        let agent = ureq::AgentBuilder::new()
+            .timeout(Duration::from_secs(30))
+            .build();
+
+        Self {
+            url: url.into().trim_end_matches('/').to_string(),
+            api_key,
+            agent,
+        }
+    }
+}
+
+impl Translator for LibreTranslate {
+    fn translate(&self, text: &str, source: Option<&str>, target: &str) -> Result<String> {
+        let request = TranslateRequest {
+            q: text,
+            source: source.unwrap_or("auto"),
+            target,
+            format: "text",
+            api_key: self.api_key.as_deref(),
+        };
+
+        let response: TranslateResponse = self
+            .agent
+            .post(&format!("{}/translate", self.url))
+            .send_json(&request)
+            .map_err(|e| anyhow::anyhow!("Translation request failed: {}", e))?
+            .into_json()
+            .map_err(|e| anyhow::anyhow!("Invalid translation response: {}", e))?;
+
+        Ok(response.translated_text)
+    }
+}
@@ -0,0 +1,154 @@
+use regex::Regex;
+use std::ffi::c_void;
+use std::os::raw::c_int;
+use whisper_rs::{FullParams, WhisperSysContext, WhisperSysState, WhisperToken, WhisperTokenData};
+
+use crate::grammar::PhraseTrie;
+
+/// Logit adjustments applied at every decoding step
+///
+/// Adds a bias to specific tokens, rules out suppressed ones and, with a grammar,
+/// every token that can't continue one of its phrases.
+#[derive(Debug, Default)]
+pub(crate) struct LogitBias {
+    biases: Vec<(WhisperToken, f32)>,
+    /// Tokens that are never decoded
+    suppressed: Vec<WhisperToken>,
+    n_vocab: usize,
+    /// Phrases the output is limited to, with the end-of-text token
+    grammar: Option<(PhraseTrie, WhisperToken)>,
+}
+
+impl LogitBias {
+    pub fn new(n_vocab: c_int) -> Self {
+        Self {
+            biases: Vec::new(),
+            suppressed: Vec::new(),
+            n_vocab: n_vocab.max(0) as usize,
+            grammar: None,
+        }
+    }
+
+    /// Only allow text that follows `trie`, ending with `eot`
+    pub fn restrict(&mut self, trie: PhraseTrie, eot: WhisperToken) {
+        if !trie.is_empty() {
+            self.grammar = Some((trie, eot));
+        }
+    }
+
+    /// Add `bias` to the logit of `token`
+    pub fn add(&mut self, token: WhisperToken, bias: f32) {
+        if token >= 0 && (token as usize) < self.n_vocab && bias != 0.0 {
+            self.biases.push((token, bias));
+        }
+    }
+
+    /// Never decode `token`
+    pub fn suppress(&mut self, token: WhisperToken) {
+        if token >= 0 && (token as usize) < self.n_vocab {
+            self.suppressed.push(token);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.biases.is_empty() && self.suppressed.is_empty() && self.grammar.is_none()
+    }
+
+    /// Install the bias as the logits filter of `params`
+    ///
+    /// The bias must outlive every use of `params`.
+    pub fn install(&self, params: &mut FullParams) {
+        if self.is_empty() {
+            return;
+        }
+
+        // SAFETY: the callback only reads `self` through the user data pointer and
+        // writes within the logits buffer, whose length is the vocabulary size.
+        // Callers keep `self` alive until decoding with `params` has finished.
+        unsafe {
+            params.set_filter_logits_callback(Some(apply_logit_bias));
+            params.set_filter_logits_callback_user_data(self as *const Self as *mut c_void);
+        }
+    }
+}
+
+unsafe extern "C" fn apply_logit_bias(
+    _ctx: *mut WhisperSysContext,
+    _state: *mut WhisperSysState,
+    tokens: *const WhisperTokenData,
+    n_tokens: c_int,
+    logits: *mut f32,
+    user_data: *mut c_void,
+) {
+    if logits.is_null() || user_data.is_null() {
+        return;
+    }
+
+    let bias = &*(user_data as *const LogitBias);
+    let logits = std::slice::from_raw_parts_mut(logits, bias.n_vocab);
+    for &(token, value) in &bias.biases {
+        logits[token as usize] += value;
+    }
+    for &token in &bias.suppressed {
+        logits[token as usize] = f32::NEG_INFINITY;
+    }
+
+    if let Some((trie, eot)) = &bias.grammar {
+        let decoded: Vec<WhisperToken> = if tokens.is_null() || n_tokens <= 0 {
+            Vec::new()
+        } else {
+            std::slice::from_raw_parts(tokens, n_tokens as usize)
+                .iter()
+                .map(|data| data.id)
+                .filter(|id| id < eot)
+                .collect()
+        };
+
+        let (allowed, may_end) = trie.allowed(&decoded);
+        let mut kept: Vec<(usize, f32)> = allowed
+            .iter()
+            .chain(may_end.then_some(eot))
+            .filter(|&&token| token >= 0 && (token as usize) < logits.len())
+            .map(|&token| (token as usize, logits[token as usize]))
+            .collect();
+        if kept.is_empty() {
+            kept.push((*eot as usize, 0.0));
+        }
+        logits.fill(f32::NEG_INFINITY);
+        for (token, logit) in kept {
+            logits[token] = logit;
+        }
+    }
+}
+
+/// The tokens among `tokens`, with their text, whose whole text matches `pattern`
+pub(crate) fn matching_tokens<'a, I>(
+    pattern: &str,
+    tokens: I,
+) -> anyhow::Result<Vec<WhisperToken>>
+where
+    I: IntoIterator<Item = (WhisperToken, &'a str)>,
+{
+    let regex = Regex::new(&format!("^(?:{})$", pattern))
+        .map_err(|e| anyhow::anyhow!("Invalid suppress regex {:?}: {}", pattern, e))?;
+    Ok(tokens
+        .into_iter()
+        .filter(|(_, text)| regex.is_match(text))
+        .map(|(token, _)| token)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matching_tokens() {
+        let tokens = [(0, " www"), (1, " http"), (2, "s"), (3, " Subscribe"), (4, " wwwx")];
+
+        let matching = matching_tokens(r"(?i)\s*(www|https?)", tokens).unwrap();
+        // The whole token has to match
+        assert_eq!(matching, vec![0, 1]);
+        assert!(matching_tokens("(", tokens).is_err());
+    }
+}
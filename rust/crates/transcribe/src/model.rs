@@ -0,0 +1,594 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    env,
+    fs::File,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+use whisper_rs::DtwModelPreset;
+
+/// Repository the ggml conversions of the Whisper models are published in
+pub const MODEL_REPO_URL: &str = "https://huggingface.co/ggerganov/whisper.cpp";
+
+/// Environment variable that overrides the model cache directory
+pub const CACHE_DIR_ENV: &str = "VOICEKB_MODEL_DIR";
+
+/// Whisper weights are released by OpenAI under the MIT license
+#[cfg(feature = "download")]
+const MODEL_LICENSE: &str = "MIT";
+
+const REGISTRY_NAME: &str = "models.json";
+
+/// Models published in the whisper.cpp repository
+pub const KNOWN_MODELS: &[&str] = &[
+    "tiny",
+    "tiny.en",
+    "base",
+    "base.en",
+    "small",
+    "small.en",
+    "small.en-tdrz",
+    "medium",
+    "medium.en",
+    "large-v1",
+    "large-v2",
+    "large-v3",
+    "large-v3-turbo",
+    "tiny-q5_1",
+    "tiny.en-q5_1",
+    "tiny-q8_0",
+    "base-q5_1",
+    "base.en-q5_1",
+    "base-q8_0",
+    "small-q5_1",
+    "small.en-q5_1",
+    "small-q8_0",
+    "medium-q5_0",
+    "medium.en-q5_0",
+    "medium-q8_0",
+    "large-v2-q5_0",
+    "large-v2-q8_0",
+    "large-v3-q5_0",
+    "large-v3-turbo-q5_0",
+    "large-v3-turbo-q8_0",
+];
+
+/// The DTW alignment heads for a model name or file name, e.g. "base.en" or
+/// "ggml-small-q5_1.bin", for word timestamps through
+/// [`DtwMode::ModelPreset`](whisper_rs::DtwMode::ModelPreset)
+///
+/// Quantized models share the heads of their full-precision model; fine-tuned
+/// ones such as tinydiarize have none.
+pub fn dtw_preset(model: &str) -> Option<DtwModelPreset> {
+    let name = model.strip_prefix("ggml-").unwrap_or(model);
+    let name = name.strip_suffix(".bin").unwrap_or(name);
+    let name = name.split("-q").next().unwrap_or(name);
+    Some(match name {
+        "tiny.en" => DtwModelPreset::TinyEn,
+        "tiny" => DtwModelPreset::Tiny,
+        "base.en" => DtwModelPreset::BaseEn,
+        "base" => DtwModelPreset::Base,
+        "small.en" => DtwModelPreset::SmallEn,
+        "small" => DtwModelPreset::Small,
+        "medium.en" => DtwModelPreset::MediumEn,
+        "medium" => DtwModelPreset::Medium,
+        "large-v1" => DtwModelPreset::LargeV1,
+        "large-v2" => DtwModelPreset::LargeV2,
+        "large-v3" => DtwModelPreset::LargeV3,
+        "large-v3-turbo" => DtwModelPreset::LargeV3Turbo,
+        _ => return None,
+    })
+}
+
+/// Magic number at the start of a ggml model file ("ggml")
+const GGML_MAGIC: u32 = 0x6767_6d6c;
+/// The quantization format version is stored in the thousands of the file type
+const QNT_VERSION_FACTOR: i32 = 1000;
+
+/// Metadata recorded for every downloaded model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelRecord {
+    pub name: String,
+    /// File name inside the cache directory
+    pub file: String,
+    /// URL the model was downloaded from
+    pub source: String,
+    pub license: String,
+    /// Hex encoded SHA256 of the file
+    pub sha256: String,
+    pub size: u64,
+}
+
+/// Resolves model names like `base.en` to files in a local cache, downloading them on demand
+pub struct ModelManager {
+    cache_dir: PathBuf,
+    #[cfg(feature = "download")]
+    agent: ureq::Agent,
+    /// Stops at the hub's redirect to the CDN, whose response lacks the checksum
+    #[cfg(feature = "download")]
+    checksum_agent: ureq::Agent,
+}
+
+impl ModelManager {
+    /// Create a manager that keeps models in `cache_dir`
+    pub fn new<P: Into<PathBuf>>(cache_dir: P) -> Self {
+        Self {
+            cache_dir: cache_dir.into(),
+            // No overall timeout; the larger models take a while to download
+            #[cfg(feature = "download")]
+            agent: ureq::AgentBuilder::new()
+                .timeout_connect(std::time::Duration::from_secs(30))
+                .timeout_read(std::time::Duration::from_secs(60))
+                .build(),
+            #[cfg(feature = "download")]
+            checksum_agent: ureq::AgentBuilder::new()
+                .timeout(std::time::Duration::from_secs(30))
+                .redirects(0)
+                .build(),
+        }
+    }
+
+    /// Create a manager for the default cache directory
+    pub fn with_default_dir() -> Result<Self> {
+        Ok(Self::new(default_cache_dir()?))
+    }
+
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
+    /// Path a model is (or would be) stored at
+    pub fn path(&self, name: &str) -> Result<PathBuf> {
+        Ok(self.cache_dir.join(file_name(name)?))
+    }
+
+    /// Path of a model if it has been downloaded
+    pub fn resolve(&self, name: &str) -> Result<Option<PathBuf>> {
+        let path = self.path(name)?;
+        Ok(path.exists().then_some(path))
+    }
+
+    /// Metadata of all downloaded models
+    pub fn installed(&self) -> Result<Vec<ModelRecord>> {
+        let path = self.cache_dir.join(REGISTRY_NAME);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let contents = std::fs::read_to_string(&path)?;
+        serde_json::from_str(&contents)
+            .map_err(|e| anyhow::anyhow!("Invalid model registry {:?}: {}", path, e))
+    }
+
+    /// Return the path of a model, downloading it first if needed
+    #[cfg(feature = "download")]
+    pub fn ensure<F>(&self, name: &str, progress: F) -> Result<PathBuf>
+    where
+        F: FnMut(u64, Option<u64>),
+    {
+        match self.resolve(name)? {
+            Some(path) => Ok(path),
+            None => self.pull(name, progress),
+        }
+    }
+
+    /// Download a model, verifying its SHA256 before moving it into the cache
+    ///
+    /// `progress` is called with the number of bytes downloaded so far and the
+    /// total size, when the server reports it.
+    #[cfg(feature = "download")]
+    pub fn pull<F>(&self, name: &str, progress: F) -> Result<PathBuf>
+    where
+        F: FnMut(u64, Option<u64>),
+    {
+        let file = file_name(name)?;
+        let (part_path, url, sha256, size) = self.download(name, &file, progress)?;
+
+        let path = self.cache_dir.join(&file);
+        std::fs::rename(&part_path, &path)?;
+
+        self.record(ModelRecord {
+            name: name.to_string(),
+            file,
+            source: url,
+            license: MODEL_LICENSE.to_string(),
+            sha256,
+            size,
+        })?;
+
+        Ok(path)
+    }
+
+    /// Download the Core ML encoder of a model and unpack it next to the model
+    ///
+    /// whisper.cpp runs the encoder on the Neural Engine when it finds it, in builds
+    /// with the `coreml` feature. Quantized models share the encoder of their
+    /// full-precision model.
+    #[cfg(feature = "download")]
+    pub fn pull_coreml_encoder<F>(&self, name: &str, progress: F) -> Result<PathBuf>
+    where
+        F: FnMut(u64, Option<u64>),
+    {
+        if !cfg!(target_vendor = "apple") {
+            return Err(anyhow::anyhow!("Core ML encoders only run on Apple hardware"));
+        }
+        let encoder = coreml_encoder_path(&self.path(name)?);
+        let dir_name = encoder
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| anyhow::anyhow!("Invalid model name: {:?}", name))?;
+        let file = format!("{}.zip", dir_name);
+        let (part_path, _, _, _) = self.download(name, &file, progress)?;
+
+        // `ditto` ships with macOS and keeps the bundle intact
+        let status = std::process::Command::new("ditto")
+            .args(["-x", "-k"])
+            .arg(&part_path)
+            .arg(&self.cache_dir)
+            .status();
+        let _ = std::fs::remove_file(&part_path);
+        match status {
+            Ok(status) if status.success() && encoder.exists() => Ok(encoder),
+            Ok(_) => Err(anyhow::anyhow!("Failed to unpack the Core ML encoder of {}", name)),
+            Err(e) => Err(anyhow::anyhow!("Failed to run ditto: {}", e)),
+        }
+    }
+
+    /// Download `file` from the model repository into a `.part` file, verifying its
+    /// SHA256, and return its path, URL, SHA256 and size
+    #[cfg(feature = "download")]
+    fn download<F>(
+        &self,
+        name: &str,
+        file: &str,
+        mut progress: F,
+    ) -> Result<(PathBuf, String, String, u64)>
+    where
+        F: FnMut(u64, Option<u64>),
+    {
+        let url = format!("{}/resolve/main/{}", MODEL_REPO_URL, file);
+        std::fs::create_dir_all(&self.cache_dir)?;
+
+        let expected = self.expected_sha256(&url);
+        let response = self
+            .agent
+            .get(&url)
+            .call()
+            .map_err(|e| anyhow::anyhow!("Failed to download model {}: {}", name, e))?;
+        let expected = expected.or_else(|| sha256_header(&response));
+        let total = response
+            .header("content-length")
+            .and_then(|length| length.parse().ok());
+
+        let part_path = self.cache_dir.join(format!("{}.part", file));
+        let (sha256, size) = {
+            let mut writer = std::io::BufWriter::new(File::create(&part_path)?);
+            let mut reader = response.into_reader();
+            let result = copy_hashed(&mut reader, &mut writer, |n| progress(n, total));
+            writer.flush()?;
+            result?
+        };
+
+        if let Some(expected) = expected {
+            if expected != sha256 {
+                let _ = std::fs::remove_file(&part_path);
+                return Err(anyhow::anyhow!(
+                    "Checksum mismatch for model {}: expected {}, got {}",
+                    name,
+                    expected,
+                    sha256
+                ));
+            }
+        } else {
+            let _ = std::fs::remove_file(&part_path);
+            return Err(anyhow::anyhow!(
+                "Server did not report a checksum for model {}, refusing to install it",
+                name
+            ));
+        }
+
+        Ok((part_path, url, sha256, size))
+    }
+
+    /// SHA256 the hub reports for the file at `url`
+    ///
+    /// For LFS files the hub redirects to a CDN and reports the SHA256 of the content
+    /// as the linked ETag of the redirect only, so this asks without following it.
+    #[cfg(feature = "download")]
+    fn expected_sha256(&self, url: &str) -> Option<String> {
+        let response = self.checksum_agent.head(url).call().ok()?;
+        sha256_header(&response)
+    }
+
+    /// Check a downloaded model against the checksum recorded when it was pulled
+    pub fn verify(&self, name: &str) -> Result<bool> {
+        let record = self
+            .installed()?
+            .into_iter()
+            .find(|record| record.name == name)
+            .ok_or_else(|| anyhow::anyhow!("Model {} is not installed", name))?;
+
+        let mut file = File::open(self.cache_dir.join(&record.file))?;
+        let (sha256, _) = copy_hashed(&mut file, &mut std::io::sink(), |_| {})?;
+        Ok(sha256 == record.sha256)
+    }
+
+    #[cfg(feature = "download")]
+    fn record(&self, record: ModelRecord) -> Result<()> {
+        let mut records = self.installed()?;
+        records.retain(|existing| existing.name != record.name);
+        records.push(record);
+        records.sort_by(|a, b| a.name.cmp(&b.name));
+
+        std::fs::write(
+            self.cache_dir.join(REGISTRY_NAME),
+            serde_json::to_string_pretty(&records)?,
+        )?;
+        Ok(())
+    }
+}
+
+/// Hyperparameters read from the header of a ggml model file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelInfo {
+    pub n_vocab: i32,
+    pub n_audio_layer: i32,
+    pub n_text_layer: i32,
+    pub n_mels: i32,
+    /// ggml file type, i.e. the tensor format of the weights
+    pub ftype: i32,
+    /// Version of the quantization format, 0 for unquantized models
+    pub quantization_version: i32,
+}
+
+impl ModelInfo {
+    /// Read the header of a ggml model without loading the weights
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut header = [0u8; 48];
+        File::open(path)?
+            .read_exact(&mut header)
+            .map_err(|e| anyhow::anyhow!("Failed to read model header {:?}: {}", path, e))?;
+        Self::parse(&header).map_err(|e| anyhow::anyhow!("{:?}: {}", path, e))
+    }
+
+    fn parse(header: &[u8; 48]) -> Result<Self> {
+        let field = |index: usize| {
+            let bytes = &header[index * 4..index * 4 + 4];
+            i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+        };
+
+        if field(0) as u32 != GGML_MAGIC {
+            return Err(anyhow::anyhow!(
+                "Not a ggml model; convert it with whisper.cpp first"
+            ));
+        }
+
+        // magic, n_vocab, n_audio_ctx, n_audio_state, n_audio_head, n_audio_layer,
+        // n_text_ctx, n_text_state, n_text_head, n_text_layer, n_mels, ftype
+        let ftype = field(11);
+        let info = Self {
+            n_vocab: field(1),
+            n_audio_layer: field(5),
+            n_text_layer: field(9),
+            n_mels: field(10),
+            ftype: ftype % QNT_VERSION_FACTOR,
+            quantization_version: ftype / QNT_VERSION_FACTOR,
+        };
+
+        if info.quantization() == "unknown" {
+            return Err(anyhow::anyhow!("Unsupported model file type {}", info.ftype));
+        }
+        Ok(info)
+    }
+
+    /// Name of the weight format, e.g. "f16", "q5_1" or "q4_K"
+    pub fn quantization(&self) -> &'static str {
+        match self.ftype {
+            0 => "f32",
+            1 => "f16",
+            2 => "q4_0",
+            3 => "q4_1",
+            7 => "q8_0",
+            8 => "q5_0",
+            9 => "q5_1",
+            10 => "q2_K",
+            11 => "q3_K",
+            12 => "q4_K",
+            13 => "q5_K",
+            14 => "q6_K",
+            24 => "bf16",
+            _ => "unknown",
+        }
+    }
+
+    /// Whether the weights are quantized
+    pub fn is_quantized(&self) -> bool {
+        !matches!(self.ftype, 0 | 1 | 24)
+    }
+
+    /// Whether the model understands languages other than English
+    pub fn is_multilingual(&self) -> bool {
+        self.n_vocab >= 51865
+    }
+
+    /// Model size, derived from the number of encoder layers
+    pub fn size(&self) -> &'static str {
+        match self.n_audio_layer {
+            4 => "tiny",
+            6 => "base",
+            12 => "small",
+            24 => "medium",
+            32 => "large",
+            _ => "unknown",
+        }
+    }
+}
+
+/// Where whisper.cpp looks for the Core ML encoder of a model file, e.g.
+/// `ggml-base.en-encoder.mlmodelc` for `ggml-base.en-q5_1.bin`
+pub fn coreml_encoder_path(model: &Path) -> PathBuf {
+    let stem = model.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+    // Quantized models share the encoder of their full-precision model
+    let stem = match stem.rsplit_once('-') {
+        Some((base, quantization))
+            if quantization.len() == 4
+                && quantization.starts_with('q')
+                && quantization.as_bytes()[2] == b'_' =>
+        {
+            base
+        }
+        _ => stem,
+    };
+    model.with_file_name(format!("{}-encoder.mlmodelc", stem))
+}
+
+/// Platform cache directory for models, unless overridden by `VOICEKB_MODEL_DIR`
+pub fn default_cache_dir() -> Result<PathBuf> {
+    if let Some(dir) = env::var_os(CACHE_DIR_ENV) {
+        return Ok(PathBuf::from(dir));
+    }
+
+    app_cache_dir()
+        .map(|base| base.join("models"))
+        .ok_or_else(|| anyhow::anyhow!("Could not determine a cache directory for models"))
+}
+
+/// Platform cache directory of the application, e.g. `~/.cache/voicekb`
+pub(crate) fn app_cache_dir() -> Option<PathBuf> {
+    let base = if cfg!(target_os = "windows") {
+        env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library").join("Caches"))
+    } else {
+        env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+    };
+    base.map(|base| base.join("voicekb"))
+}
+
+/// File name of a model in the whisper.cpp repository, e.g. `ggml-base.en.bin`
+fn file_name(name: &str) -> Result<String> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'));
+    if !valid {
+        return Err(anyhow::anyhow!("Invalid model name: {:?}", name));
+    }
+    Ok(format!("ggml-{}.bin", name))
+}
+
+/// SHA256 in the linked ETag of a hub response, or its ETag for files stored as is
+#[cfg(feature = "download")]
+fn sha256_header(response: &ureq::Response) -> Option<String> {
+    response
+        .header("x-linked-etag")
+        .or_else(|| response.header("etag"))
+        .map(|etag| etag.trim_start_matches("W/").trim_matches('"').to_lowercase())
+        .filter(|etag| is_sha256(etag))
+}
+
+#[cfg(feature = "download")]
+fn is_sha256(value: &str) -> bool {
+    value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Copy `reader` into `writer`, returning the hex SHA256 and length of the data
+fn copy_hashed<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    mut progress: impl FnMut(u64),
+) -> Result<(String, u64)> {
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut total = 0u64;
+
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+        writer.write_all(&buffer[..n])?;
+        total += n as u64;
+        progress(total);
+    }
+
+    let digest = hasher.finalize();
+    let hex = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    Ok((hex, total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name("base.en").unwrap(), "ggml-base.en.bin");
+        assert!(file_name("../etc/passwd").is_err());
+        assert!(file_name("").is_err());
+    }
+
+    #[test]
+    fn test_coreml_encoder_path() {
+        assert_eq!(
+            coreml_encoder_path(Path::new("models/ggml-base.en.bin")),
+            Path::new("models/ggml-base.en-encoder.mlmodelc")
+        );
+        assert_eq!(
+            coreml_encoder_path(Path::new("ggml-large-v3-turbo-q5_0.bin")),
+            Path::new("ggml-large-v3-turbo-encoder.mlmodelc")
+        );
+    }
+
+    #[test]
+    fn test_dtw_preset() {
+        assert!(matches!(dtw_preset("base.en"), Some(DtwModelPreset::BaseEn)));
+        assert!(matches!(dtw_preset("ggml-small-q5_1.bin"), Some(DtwModelPreset::Small)));
+        assert!(matches!(
+            dtw_preset("large-v3-turbo-q8_0"),
+            Some(DtwModelPreset::LargeV3Turbo)
+        ));
+        assert!(dtw_preset("small.en-tdrz").is_none());
+    }
+
+    #[test]
+    fn test_model_info() {
+        // base.en quantized to q5_K with quantization format version 2
+        let fields: [i32; 12] = [
+            GGML_MAGIC as i32, 51864, 1500, 512, 8, 6, 448, 512, 8, 6, 80, 2013,
+        ];
+        let mut header = [0u8; 48];
+        for (chunk, field) in header.chunks_exact_mut(4).zip(fields) {
+            chunk.copy_from_slice(&field.to_le_bytes());
+        }
+
+        let info = ModelInfo::parse(&header).unwrap();
+        assert_eq!(info.quantization(), "q5_K");
+        assert_eq!(info.quantization_version, 2);
+        assert_eq!(info.size(), "base");
+        assert!(info.is_quantized());
+        assert!(!info.is_multilingual());
+
+        header[0] = 0;
+        assert!(ModelInfo::parse(&header).is_err());
+    }
+
+    #[test]
+    fn test_copy_hashed() {
+        let mut output = Vec::new();
+        let (sha256, size) = copy_hashed(&mut &b"abc"[..], &mut output, |_| {}).unwrap();
+        assert_eq!(
+            sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(size, 3);
+        assert_eq!(output, b"abc");
+    }
+}
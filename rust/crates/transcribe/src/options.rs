@@ -0,0 +1,434 @@
+use crate::{
+    abort::AbortHandle, grammar::Grammar, hints::Hints, outcome::strip_annotations,
+    progress::ProgressCallback, scheduler::Priority, segment_callback::SegmentCallback,
+    postprocess::Postprocess, squelch::Squelch, vad::Vad, vocabulary::Vocabulary, NewSegment,
+};
+use whisper_rs::SamplingStrategy;
+
+/// Decoding strategy
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sampling {
+    /// Pick the most likely token at each step, keeping the best of `best_of` candidates
+    /// when sampling with a non-zero temperature
+    Greedy { best_of: i32 },
+    /// Keep `beam_size` hypotheses alive; slower but more accurate.
+    /// `patience` is a multiplier on the beam size used for early stopping (-1.0 for default)
+    BeamSearch { beam_size: i32, patience: f32 },
+}
+
+impl Default for Sampling {
+    fn default() -> Self {
+        Self::Greedy { best_of: 0 }
+    }
+}
+
+impl From<Sampling> for SamplingStrategy {
+    fn from(sampling: Sampling) -> Self {
+        match sampling {
+            Sampling::Greedy { best_of } => SamplingStrategy::Greedy { best_of },
+            Sampling::BeamSearch {
+                beam_size,
+                patience,
+            } => SamplingStrategy::BeamSearch {
+                beam_size,
+                patience,
+            },
+        }
+    }
+}
+
+/// Per-call decoding options
+///
+/// Start from [`TranscribeOptions::default`] (English, greedy decoding, one thread
+/// per CPU, nothing printed) and adjust with the builder methods.
+#[derive(Debug, Clone)]
+pub struct TranscribeOptions {
+    /// Text fed to the decoder as previous context, used to bias recognition
+    /// toward domain vocabulary (e.g. "Kubernetes, kubectl, etcd")
+    pub initial_prompt: Option<String>,
+    /// Words to favor during decoding and correct the output toward
+    pub vocabulary: Option<Vocabulary>,
+    /// Phonetic spellings to replace with the intended word
+    pub hints: Option<Hints>,
+    /// Control phrases removed from the output
+    pub squelch: Option<Squelch>,
+    /// Phrases the output is limited to, e.g. for voice commands
+    pub grammar: Option<Grammar>,
+    /// Tokens whose whole text matches this regex are never decoded
+    pub suppress_regex: Option<String>,
+    /// Texts whose first token gets a bias added to its logit, favoring them when
+    /// positive and avoiding them when negative
+    pub token_bias: Vec<(String, f32)>,
+    /// Filters that rewrite the corrected output, e.g. number words to digits
+    pub postprocess: Option<Postprocess>,
+    /// Spoken language code, e.g. "nl"; `None` detects the language
+    pub language: Option<String>,
+    /// Languages to choose from when detecting the language, e.g. `["fr", "en"]`;
+    /// empty allows any
+    pub languages: Vec<String>,
+    /// Translate the speech into English instead of transcribing it
+    pub translate: bool,
+    /// Decoding strategy
+    pub sampling: Sampling,
+    /// Initial sampling temperature; `None` uses the whisper default (0.0)
+    pub temperature: Option<f32>,
+    /// Temperature added when a decode fails the thresholds below and is retried;
+    /// `None` uses the whisper default (0.2), 0.0 disables the fallback
+    pub temperature_increment: Option<f32>,
+    /// Decodes whose token entropy is below this are treated as repetitive and
+    /// retried at a higher temperature; `None` uses the whisper default (2.4)
+    pub entropy_threshold: Option<f32>,
+    /// Decodes whose average log probability is below this are retried at a higher
+    /// temperature; `None` uses the whisper default (-1.0)
+    pub logprob_threshold: Option<f32>,
+    /// Segments whose no-speech probability is above this, and whose average log
+    /// probability is below `logprob_threshold`, are dropped as silence; `None` uses
+    /// the whisper default (0.6)
+    pub no_speech_threshold: Option<f32>,
+    /// Trim silence and split long pauses before decoding
+    pub vad: Option<Vad>,
+    /// Detect speaker turns; needs a tinydiarize model such as `small.en-tdrz`
+    pub tinydiarize: bool,
+    /// Keep noise annotations such as `[laughter]` or `♪` out of the output
+    pub suppress_non_speech: bool,
+    /// Cancels the transcription when aborted
+    pub abort: Option<AbortHandle>,
+    /// Called with each segment as soon as it is decoded
+    pub on_segment: Option<SegmentCallback>,
+    /// Called with the share of the audio decoded so far, in percent
+    pub on_progress: Option<ProgressCallback>,
+    /// Whether the decode yields to interactive transcriptions on the same transcriber
+    pub priority: Priority,
+    /// Number of threads to decode with
+    pub threads: i32,
+    /// Number of speech chunks (see [`TranscribeOptions::vad`]) decoded at the same
+    /// time, sharing `threads` between them
+    pub parallel_chunks: usize,
+    /// Compute per-token timestamps; always on for segment transcription
+    pub token_timestamps: bool,
+    /// Print special tokens ([_BEG_], timestamps, ...) to stdout
+    pub print_special: bool,
+    /// Print progress to stdout
+    pub print_progress: bool,
+    /// Print results to stdout as they are decoded
+    pub print_realtime: bool,
+    /// Print segment timestamps to stdout
+    pub print_timestamps: bool,
+}
+
+impl Default for TranscribeOptions {
+    fn default() -> Self {
+        Self {
+            initial_prompt: None,
+            vocabulary: None,
+            hints: None,
+            squelch: None,
+            grammar: None,
+            suppress_regex: None,
+            token_bias: Vec::new(),
+            postprocess: None,
+            language: Some("en".to_string()),
+            languages: Vec::new(),
+            translate: false,
+            sampling: Sampling::default(),
+            temperature: None,
+            temperature_increment: None,
+            entropy_threshold: None,
+            logprob_threshold: None,
+            no_speech_threshold: None,
+            vad: None,
+            tinydiarize: false,
+            suppress_non_speech: false,
+            abort: None,
+            on_segment: None,
+            on_progress: None,
+            priority: Priority::default(),
+            threads: num_cpus::get() as i32,
+            // whisper.cpp scales well up to about four threads per decode
+            parallel_chunks: (num_cpus::get() / 4).max(1),
+            token_timestamps: false,
+            print_special: false,
+            print_progress: false,
+            print_realtime: false,
+            print_timestamps: false,
+        }
+    }
+}
+
+impl TranscribeOptions {
+    /// Set the initial prompt
+    pub fn initial_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.initial_prompt = Some(prompt.into());
+        self
+    }
+
+    /// Set the custom vocabulary
+    pub fn vocabulary(mut self, vocabulary: Vocabulary) -> Self {
+        self.vocabulary = Some(vocabulary);
+        self
+    }
+
+    /// Set the pronunciation hints
+    pub fn hints(mut self, hints: Hints) -> Self {
+        self.hints = Some(hints);
+        self
+    }
+
+    /// Set the spoken language
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    /// Let whisper detect the spoken language
+    pub fn detect_language(mut self) -> Self {
+        self.language = None;
+        self
+    }
+
+    /// Only detect one of `languages`, for speakers who switch between a few
+    ///
+    /// Has no effect unless the language is detected.
+    pub fn languages<I, S>(mut self, languages: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.languages = languages.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Translate the speech into English
+    pub fn translate(mut self, translate: bool) -> Self {
+        self.translate = translate;
+        self
+    }
+
+    /// Set the control phrases to remove from the output
+    pub fn squelch(mut self, squelch: Squelch) -> Self {
+        self.squelch = Some(squelch);
+        self
+    }
+
+    /// Set the filters applied to the output after all other corrections
+    pub fn postprocess(mut self, postprocess: Postprocess) -> Self {
+        self.postprocess = Some(postprocess);
+        self
+    }
+
+    /// Only recognize the phrases of `grammar`
+    ///
+    /// Turns off timestamps and decodes each speech chunk as a single segment.
+    pub fn grammar(mut self, grammar: Grammar) -> Self {
+        self.grammar = Some(grammar);
+        self
+    }
+
+    /// Never decode a token whose whole text matches `pattern`, like whisper.cpp's
+    /// `--suppress-regex`
+    ///
+    /// Keeps output Whisper keeps hallucinating out at decode time, e.g. `(?i)\s*www`
+    /// for URLs. Tokens are word pieces, usually with a leading space. An invalid
+    /// pattern fails the transcription.
+    pub fn suppress_regex(mut self, pattern: impl Into<String>) -> Self {
+        self.suppress_regex = Some(pattern.into());
+        self
+    }
+
+    /// Add `bias` to the logit of the first token of `text` at every decoding step
+    ///
+    /// Around 2 to 5 favors a word noticeably, as with [`TranscribeOptions::vocabulary`]
+    /// but at a chosen strength, and a negative bias avoids it. Applies with and
+    /// without a leading space.
+    pub fn token_bias(mut self, text: impl Into<String>, bias: f32) -> Self {
+        self.token_bias.push((text.into(), bias));
+        self
+    }
+
+    /// Use greedy decoding with `best_of` candidates
+    pub fn greedy(mut self, best_of: i32) -> Self {
+        self.sampling = Sampling::Greedy { best_of };
+        self
+    }
+
+    /// Use beam search decoding
+    pub fn beam_search(mut self, beam_size: i32, patience: f32) -> Self {
+        self.sampling = Sampling::BeamSearch {
+            beam_size,
+            patience,
+        };
+        self
+    }
+
+    /// Set the initial sampling temperature
+    pub fn temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// Set how much the temperature rises on each retry of a failed decode
+    ///
+    /// A decode fails when it looks repetitive ([`TranscribeOptions::entropy_threshold`])
+    /// or unlikely ([`TranscribeOptions::logprob_threshold`]); 0.0 keeps the first result.
+    pub fn temperature_increment(mut self, increment: f32) -> Self {
+        self.temperature_increment = Some(increment.max(0.0));
+        self
+    }
+
+    /// Set the entropy below which a decode counts as repetitive and is retried
+    ///
+    /// Raise it when noisy input makes whisper repeat a phrase over and over.
+    pub fn entropy_threshold(mut self, threshold: f32) -> Self {
+        self.entropy_threshold = Some(threshold);
+        self
+    }
+
+    /// Set the average token log probability below which a decode is retried
+    pub fn logprob_threshold(mut self, threshold: f32) -> Self {
+        self.logprob_threshold = Some(threshold);
+        self
+    }
+
+    /// Set the no-speech probability above which an unlikely segment is dropped as silence
+    pub fn no_speech_threshold(mut self, threshold: f32) -> Self {
+        self.no_speech_threshold = Some(threshold.clamp(0.0, 1.0));
+        self
+    }
+
+    /// Skip silence with a voice activity detector
+    pub fn vad(mut self, vad: Vad) -> Self {
+        self.vad = Some(vad);
+        self
+    }
+
+    /// Make the transcription cancellable through `handle`
+    pub fn abort_handle(mut self, handle: AbortHandle) -> Self {
+        self.abort = Some(handle);
+        self
+    }
+
+    /// Receive each segment as soon as it is decoded, before the whole recording is done
+    ///
+    /// Speech chunks are decoded one after the other when this is set, so
+    /// segments arrive in order.
+    pub fn on_segment<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(NewSegment) + Send + Sync + 'static,
+    {
+        self.on_segment = Some(SegmentCallback::new(callback));
+        self
+    }
+
+    /// Receive the share of the audio decoded so far, in percent, e.g. to show a
+    /// progress bar while transcribing a long file
+    pub fn on_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(u8) + Send + Sync + 'static,
+    {
+        self.on_progress = Some(ProgressCallback::new(callback));
+        self
+    }
+
+    /// Set whether the transcription waits for interactive ones
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Label segments with speakers using a tinydiarize model's speaker-turn tokens
+    pub fn tinydiarize(mut self, tinydiarize: bool) -> Self {
+        self.tinydiarize = tinydiarize;
+        self
+    }
+
+    /// Keep annotations of non-speech sounds out of the output
+    ///
+    /// Whisper is kept from decoding symbol tokens such as `♪`, `[` and `(`,
+    /// and any bracketed or parenthesized annotation that still gets through
+    /// is removed from the text.
+    pub fn suppress_non_speech(mut self, suppress: bool) -> Self {
+        self.suppress_non_speech = suppress;
+        self
+    }
+
+    /// Set the number of decoding threads
+    pub fn threads(mut self, threads: i32) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Set how many speech chunks are decoded at the same time
+    ///
+    /// Long recordings split by the VAD decode up to this many times faster on
+    /// machines with many cores; each concurrent decode needs its own model state.
+    pub fn parallel_chunks(mut self, parallel_chunks: usize) -> Self {
+        self.parallel_chunks = parallel_chunks.max(1);
+        self
+    }
+
+    /// Compute per-token timestamps
+    pub fn token_timestamps(mut self, token_timestamps: bool) -> Self {
+        self.token_timestamps = token_timestamps;
+        self
+    }
+
+    /// Print special tokens to stdout
+    pub fn print_special(mut self, print_special: bool) -> Self {
+        self.print_special = print_special;
+        self
+    }
+
+    /// Print progress to stdout
+    pub fn print_progress(mut self, print_progress: bool) -> Self {
+        self.print_progress = print_progress;
+        self
+    }
+
+    /// Print results to stdout as they are decoded
+    pub fn print_realtime(mut self, print_realtime: bool) -> Self {
+        self.print_realtime = print_realtime;
+        self
+    }
+
+    /// Print segment timestamps to stdout
+    pub fn print_timestamps(mut self, print_timestamps: bool) -> Self {
+        self.print_timestamps = print_timestamps;
+        self
+    }
+
+    /// The prompt passed to whisper: the initial prompt followed by the vocabulary glossary
+    pub(crate) fn prompt(&self) -> Option<String> {
+        let glossary = self.vocabulary.as_ref().and_then(Vocabulary::prompt);
+        match (&self.initial_prompt, glossary) {
+            (Some(prompt), Some(glossary)) => Some(format!("{} {}", prompt, glossary)),
+            (Some(prompt), None) => Some(prompt.clone()),
+            (None, glossary) => glossary,
+        }
+    }
+
+    /// Apply output post-correction
+    pub(crate) fn correct(&self, text: &str) -> String {
+        let text = if self.suppress_non_speech {
+            strip_annotations(text)
+        } else {
+            text.to_string()
+        };
+        // Strip control phrases first so corrections can't turn them into other words
+        let text = match &self.squelch {
+            Some(squelch) => squelch.strip(&text),
+            None => text,
+        };
+        let text = match &self.hints {
+            Some(hints) => hints.apply(&text),
+            None => text,
+        };
+        let text = match &self.vocabulary {
+            Some(vocabulary) => vocabulary.correct(&text),
+            None => text,
+        };
+        match &self.postprocess {
+            Some(postprocess) => postprocess.apply(&text),
+            None => text,
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::segment::Segment;
+
+/// What a recording turned out to contain
+#[derive(Debug, Clone, PartialEq)]
+pub enum TranscriptionOutcome {
+    /// Recognized speech, without non-speech markers
+    Speech(Vec<Segment>),
+    /// Nothing was said: whisper dropped the audio as silence (see
+    /// [`TranscribeOptions::no_speech_threshold`](crate::TranscribeOptions::no_speech_threshold))
+    /// or only wrote markers such as `[BLANK_AUDIO]`
+    NoSpeech,
+}
+
+impl TranscriptionOutcome {
+    /// Sort decoded segments into speech or silence, dropping non-speech markers
+    pub fn from_segments(mut segments: Vec<Segment>) -> Self {
+        segments.retain(|segment| !is_non_speech(&segment.text));
+        if segments.is_empty() {
+            Self::NoSpeech
+        } else {
+            Self::Speech(segments)
+        }
+    }
+
+    /// Whether anything was said
+    pub fn is_speech(&self) -> bool {
+        matches!(self, Self::Speech(_))
+    }
+
+    /// The speech segments; empty without speech
+    pub fn segments(&self) -> &[Segment] {
+        match self {
+            Self::Speech(segments) => segments,
+            Self::NoSpeech => &[],
+        }
+    }
+}
+
+/// Whether `text` holds no speech: empty, or a marker whisper writes for
+/// silence, music or noise, such as `[BLANK_AUDIO]` or `(upbeat music)`
+pub fn is_non_speech(text: &str) -> bool {
+    let text = text.trim();
+    text.is_empty()
+        || (text.starts_with('[') && text.ends_with(']'))
+        || (text.starts_with('(') && text.ends_with(')'))
+}
+
+/// Remove annotations of non-speech sounds from `text`: `[...]` and `(...)`
+/// spans and music notes
+pub(crate) fn strip_annotations(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut closing = None;
+    for c in text.chars() {
+        match (closing, c) {
+            (Some(end), _) if c == end => closing = None,
+            (Some(_), _) => {}
+            (None, '[' | '(' | '♪' | '♫' | '♬') => {
+                // Don't leave a space before punctuation that follows
+                stripped.truncate(stripped.trim_end().len());
+                closing = match c {
+                    '[' => Some(']'),
+                    '(' => Some(')'),
+                    _ => None,
+                };
+            }
+            (None, _) => stripped.push(c),
+        }
+    }
+    stripped.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(text: &str) -> Segment {
+        Segment::new(text, 0, 0)
+    }
+
+    #[test]
+    fn test_outcome() {
+        assert!(is_non_speech(" [BLANK_AUDIO]"));
+        assert!(is_non_speech("(upbeat music)"));
+        assert!(!is_non_speech("[1] is the first item"));
+
+        let outcome = TranscriptionOutcome::from_segments(vec![segment("[BLANK_AUDIO]")]);
+        assert_eq!(outcome, TranscriptionOutcome::NoSpeech);
+        assert_eq!(TranscriptionOutcome::from_segments(Vec::new()), TranscriptionOutcome::NoSpeech);
+
+        let outcome =
+            TranscriptionOutcome::from_segments(vec![segment("[MUSIC]"), segment("Hello.")]);
+        assert_eq!(outcome.segments(), [segment("Hello.")]);
+    }
+
+    #[test]
+    fn test_strip_annotations() {
+        assert_eq!(strip_annotations("So [laughter] that's it (coughs)."), "So that's it.");
+        assert_eq!(strip_annotations("♪ la la ♪"), "la la");
+        assert_eq!(strip_annotations("[BLANK_AUDIO]"), "");
+    }
+}
@@ -0,0 +1,102 @@
+use anyhow::Result;
+use rubato::{
+    Resampler, SincFixedIn, SincInterpolationParameters, SincInterpolationType, WindowFunction,
+};
+use std::{io::Read, path::Path};
+
+/// Sample rate Whisper models expect
+pub const WHISPER_SAMPLE_RATE: u32 = 16000;
+
+/// Convert interleaved 16-bit PCM to the 16kHz mono f32 audio Whisper expects
+///
+/// Channels are averaged into one before resampling, so only a single channel
+/// goes through the (comparatively slow) sinc resampler.
+pub fn pcm_to_whisper(samples: &[i16], sample_rate: u32, channels: u16) -> Result<Vec<f32>> {
+    // Beyond these rates a damaged header would blow the audio up or down absurdly
+    if channels == 0 || !(1000..=384_000).contains(&sample_rate) {
+        return Err(anyhow::anyhow!(
+            "Invalid PCM format: {} Hz, {} channels",
+            sample_rate,
+            channels
+        ));
+    }
+
+    let mono: Vec<f32> = samples
+        .chunks_exact(channels as usize)
+        .map(|frame| frame.iter().map(|&s| s as f32 / 32768.0).sum::<f32>() / channels as f32)
+        .collect();
+    resample(&mono, sample_rate)
+}
+
+/// Load a 16-bit WAV file of any sample rate and channel count as 16kHz mono audio
+pub fn load_wav_resampled<P: AsRef<Path>>(path: P) -> Result<Vec<f32>> {
+    let reader = hound::WavReader::open(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to open WAV file: {}", e))?;
+    wav_resampled(reader)
+}
+
+/// Read 16-bit WAV data of any sample rate and channel count, e.g. from memory, as
+/// 16kHz mono audio
+pub fn read_wav_resampled<R: Read>(reader: R) -> Result<Vec<f32>> {
+    let reader = hound::WavReader::new(reader)
+        .map_err(|e| anyhow::anyhow!("Failed to read WAV data: {}", e))?;
+    wav_resampled(reader)
+}
+
+fn wav_resampled<R: Read>(reader: hound::WavReader<R>) -> Result<Vec<f32>> {
+    let spec = reader.spec();
+    if spec.bits_per_sample != 16 || spec.sample_format != hound::SampleFormat::Int {
+        return Err(anyhow::anyhow!(
+            "Unsupported WAV format: {}-bit {:?}. Only 16-bit integer PCM is supported.",
+            spec.bits_per_sample,
+            spec.sample_format
+        ));
+    }
+
+    let samples = reader
+        .into_samples::<i16>()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow::anyhow!("Failed to read i16 samples: {}", e))?;
+    pcm_to_whisper(&samples, spec.sample_rate, spec.channels)
+}
+
+fn resample(input: &[f32], sample_rate: u32) -> Result<Vec<f32>> {
+    if sample_rate == WHISPER_SAMPLE_RATE || input.is_empty() {
+        return Ok(input.to_vec());
+    }
+
+    let params = SincInterpolationParameters {
+        sinc_len: 256,
+        f_cutoff: 0.95,
+        interpolation: SincInterpolationType::Linear,
+        oversampling_factor: 256,
+        window: WindowFunction::BlackmanHarris2,
+    };
+    let ratio = WHISPER_SAMPLE_RATE as f64 / sample_rate as f64;
+    let mut resampler = SincFixedIn::<f32>::new(ratio, 1.0, params, input.len(), 1)
+        .map_err(|e| anyhow::anyhow!("Failed to create resampler: {}", e))?;
+    let mut output = resampler
+        .process(&[input], None)
+        .map_err(|e| anyhow::anyhow!("Failed to resample audio: {}", e))?;
+    Ok(output.remove(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pcm_to_whisper() {
+        // Stereo at 16kHz is only downmixed
+        let audio = pcm_to_whisper(&[16384, -16384, 16384, 16384], 16000, 2).unwrap();
+        assert_eq!(audio, vec![0.0, 0.5]);
+
+        // One second at 48kHz becomes one second at 16kHz, give or take the filter delay
+        let audio = pcm_to_whisper(&vec![1000; 48000], 48000, 1).unwrap();
+        assert!((audio.len() as i64 - 16000).abs() < 160, "{}", audio.len());
+
+        assert!(pcm_to_whisper(&[0; 4], 16000, 0).is_err());
+        // A damaged header's rate, which would make billions of samples out of a few
+        assert!(pcm_to_whisper(&[0; 4], 1, 1).is_err());
+    }
+}
@@ -0,0 +1,476 @@
+use std::{fmt, sync::Arc};
+
+use crate::fuzzy::{normalize, split_punctuation};
+
+/// A rewrite applied to transcribed text, e.g. to turn spoken forms into written ones
+///
+/// Filters must implement `Debug` because options are part of transcript cache keys;
+/// include any settings that change the output.
+pub trait TextFilter: fmt::Debug + Send + Sync {
+    fn apply(&self, text: &str) -> String;
+}
+
+/// Text filters applied in order to the output, after all other corrections
+#[derive(Debug, Clone, Default)]
+pub struct Postprocess {
+    filters: Vec<Arc<dyn TextFilter>>,
+}
+
+impl Postprocess {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a filter after the ones already in the pipeline
+    pub fn filter<F: TextFilter + 'static>(mut self, filter: F) -> Self {
+        self.filters.push(Arc::new(filter));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    /// Run `text` through every filter
+    pub fn apply(&self, text: &str) -> String {
+        self.filters
+            .iter()
+            .fold(text.to_string(), |text, filter| filter.apply(&text))
+    }
+}
+
+/// Writes numbers of ten and up, and numbers of several words, as digits
+///
+/// "twenty five" becomes "25" and "two hundred and one" becomes "201", while "one"
+/// or "five" stay words. Words spoken one digit at a time ("one two three") are not
+/// combined.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NumberWords;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Unit,
+    Teen,
+    Ten,
+    Hundred,
+    Scale,
+}
+
+fn number_word(word: &str) -> Option<(u64, Kind)> {
+    const UNITS: [&str; 10] = [
+        "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+    ];
+    const TEENS: [&str; 10] = [
+        "ten", "eleven", "twelve", "thirteen", "fourteen", "fifteen", "sixteen", "seventeen",
+        "eighteen", "nineteen",
+    ];
+    const TENS: [&str; 8] = [
+        "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+    ];
+    const SCALES: [(&str, u64); 3] = [
+        ("thousand", 1_000),
+        ("million", 1_000_000),
+        ("billion", 1_000_000_000),
+    ];
+
+    if let Some(i) = UNITS.iter().position(|&unit| unit == word) {
+        Some((i as u64, Kind::Unit))
+    } else if let Some(i) = TEENS.iter().position(|&teen| teen == word) {
+        Some((10 + i as u64, Kind::Teen))
+    } else if let Some(i) = TENS.iter().position(|&ten| ten == word) {
+        Some((20 + 10 * i as u64, Kind::Ten))
+    } else if word == "hundred" {
+        Some((100, Kind::Hundred))
+    } else {
+        SCALES
+            .iter()
+            .find(|(scale, _)| *scale == word)
+            .map(|&(_, value)| (value, Kind::Scale))
+    }
+}
+
+/// Accumulates the value of a run of number words
+#[derive(Clone, Copy, Default)]
+struct Number {
+    total: u64,
+    /// Value below the last scale word
+    current: u64,
+    last: Option<Kind>,
+    scale: Option<u64>,
+}
+
+impl Number {
+    /// Add the next word, or return false if it can't continue this number
+    fn push(&mut self, value: u64, kind: Kind) -> bool {
+        use Kind::*;
+        let fits = match (self.last, kind) {
+            (None, Unit | Teen | Ten) => true,
+            _ if value == 0 => false,
+            (Some(Ten), Unit) | (Some(Hundred | Scale), Unit | Teen | Ten) => true,
+            (Some(Unit | Teen | Ten), Hundred) => self.current < 100,
+            (Some(Unit | Teen | Ten | Hundred), Scale) => self.scale.is_none_or(|s| value < s),
+            _ => false,
+        };
+        if !fits {
+            return false;
+        }
+
+        match kind {
+            Unit | Teen | Ten => self.current += value,
+            Hundred => self.current *= value,
+            Scale => {
+                self.total += self.current * value;
+                self.current = 0;
+                self.scale = Some(value);
+            }
+        }
+        self.last = Some(kind);
+        true
+    }
+
+    fn value(&self) -> u64 {
+        self.total + self.current
+    }
+}
+
+impl NumberWords {
+    /// The number at the start of `words`: how many words it spans, its value and
+    /// how many number words it has
+    fn take(words: &[&str]) -> Option<(usize, u64, usize)> {
+        let mut number = Number::default();
+        let mut taken = 0;
+        let mut count = 0;
+
+        for (i, word) in words.iter().enumerate() {
+            let (leading, core, trailing) = split_punctuation(word);
+            if i > 0 && !leading.is_empty() {
+                break;
+            }
+            let core = core.to_lowercase();
+            // "and" only counts if a number follows, which leaves `taken` behind it
+            let joins = matches!(number.last, Some(Kind::Hundred | Kind::Scale));
+            if core == "and" && joins && trailing.is_empty() {
+                continue;
+            }
+
+            // "twenty-five" is taken whole or not at all
+            let Some(parts) = core.split('-').map(number_word).collect::<Option<Vec<_>>>() else {
+                break;
+            };
+            let mut next = number;
+            if !parts.iter().all(|&(value, kind)| next.push(value, kind)) {
+                break;
+            }
+            number = next;
+            taken = i + 1;
+            count += parts.len();
+
+            if !trailing.is_empty() {
+                break;
+            }
+        }
+
+        (taken > 0).then(|| (taken, number.value(), count))
+    }
+}
+
+impl TextFilter for NumberWords {
+    fn apply(&self, text: &str) -> String {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let mut output: Vec<String> = Vec::with_capacity(words.len());
+
+        let mut i = 0;
+        while i < words.len() {
+            match Self::take(&words[i..]) {
+                Some((taken, value, count)) if count > 1 || value >= 10 => {
+                    let (leading, _, _) = split_punctuation(words[i]);
+                    let (_, _, trailing) = split_punctuation(words[i + taken - 1]);
+                    output.push(format!("{}{}{}", leading, value, trailing));
+                    i += taken;
+                }
+                _ => {
+                    output.push(words[i].to_string());
+                    i += 1;
+                }
+            }
+        }
+
+        output.join(" ")
+    }
+}
+
+/// Which neighbouring words a spoken symbol is written against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attach {
+    /// Spaced from both, e.g. "&"
+    Neither,
+    /// Against the word before, e.g. "50%"
+    Previous,
+    /// Against the word after, e.g. "$20"
+    Next,
+    /// Against both, e.g. "name@example.com"
+    Both,
+}
+
+/// Replaces spoken symbol names such as "at sign" with the symbol
+///
+/// The default table covers @, &, %, #, $ and _; add more with
+/// [`SpokenSymbols::symbol`] or start from [`SpokenSymbols::empty`].
+#[derive(Debug, Clone)]
+pub struct SpokenSymbols {
+    symbols: Vec<(Vec<String>, String, Attach)>,
+}
+
+impl Default for SpokenSymbols {
+    fn default() -> Self {
+        Self::empty()
+            .symbol("at sign", "@", Attach::Both)
+            .symbol("at symbol", "@", Attach::Both)
+            .symbol("ampersand", "&", Attach::Neither)
+            .symbol("percent sign", "%", Attach::Previous)
+            .symbol("hash sign", "#", Attach::Next)
+            .symbol("dollar sign", "$", Attach::Next)
+            .symbol("underscore", "_", Attach::Both)
+    }
+}
+
+impl SpokenSymbols {
+    /// A table without any symbols
+    pub fn empty() -> Self {
+        Self {
+            symbols: Vec::new(),
+        }
+    }
+
+    /// Write `symbol` for the spoken `phrase`, ignoring case and punctuation
+    pub fn symbol(mut self, phrase: &str, symbol: &str, attach: Attach) -> Self {
+        let words: Vec<String> = phrase
+            .split_whitespace()
+            .map(normalize)
+            .filter(|word| !word.is_empty())
+            .collect();
+        if !words.is_empty() {
+            self.symbols.push((words, symbol.to_string(), attach));
+            // Try longer phrases first
+            self.symbols.sort_by_key(|(words, _, _)| std::cmp::Reverse(words.len()));
+        }
+        self
+    }
+}
+
+impl TextFilter for SpokenSymbols {
+    fn apply(&self, text: &str) -> String {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let normalized: Vec<String> = words.iter().map(|word| normalize(word)).collect();
+        let mut output = String::with_capacity(text.len());
+        let mut glue_next = true;
+
+        let mut i = 0;
+        while i < words.len() {
+            let matched = self
+                .symbols
+                .iter()
+                .find(|(phrase, _, _)| normalized[i..].starts_with(phrase.as_slice()));
+
+            let (word, attach) = match matched {
+                Some((phrase, symbol, attach)) => {
+                    let (leading, _, _) = split_punctuation(words[i]);
+                    let (_, _, trailing) = split_punctuation(words[i + phrase.len() - 1]);
+                    i += phrase.len();
+                    (format!("{}{}{}", leading, symbol, trailing), *attach)
+                }
+                None => {
+                    i += 1;
+                    (words[i - 1].to_string(), Attach::Neither)
+                }
+            };
+
+            if !glue_next && !matches!(attach, Attach::Previous | Attach::Both) {
+                output.push(' ');
+            }
+            output.push_str(&word);
+            glue_next = matches!(attach, Attach::Next | Attach::Both);
+        }
+
+        output
+    }
+}
+
+/// Writes "..." as an ellipsis and spaced or doubled hyphens as an em dash
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Typography;
+
+impl TextFilter for Typography {
+    fn apply(&self, text: &str) -> String {
+        let mut output = String::with_capacity(text.len());
+        let mut dots = 0;
+        for c in text.chars().chain(std::iter::once('\0')) {
+            if c == '.' {
+                dots += 1;
+                continue;
+            }
+            match dots {
+                0 => {}
+                1 | 2 => output.extend(std::iter::repeat_n('.', dots)),
+                _ => output.push('…'),
+            }
+            dots = 0;
+            if c != '\0' {
+                output.push(c);
+            }
+        }
+
+        output
+            .split_whitespace()
+            .map(|word| match word {
+                "-" | "--" => "—".to_string(),
+                word => word.replace("--", "—"),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Words and phrases that fill a hesitation, lowercase
+pub const FILLERS: &[&str] = &[
+    "um", "umm", "uh", "uhh", "uhm", "er", "erm", "ah", "hmm", "you know", "i mean",
+];
+
+/// Removes fillers such as "um", "uh" and "you know" from the text
+///
+/// Phrases such as "you know" only count as fillers when punctuation follows, as in
+/// "you know, it works" but not in "you know the answer". A sentence that started with
+/// a filler starts with a capital again, and one that ended with it keeps its full stop.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Fillers;
+
+impl TextFilter for Fillers {
+    fn apply(&self, text: &str) -> String {
+        let tokens: Vec<&str> = text.split_whitespace().collect();
+        let mut kept: Vec<String> = Vec::with_capacity(tokens.len());
+        let mut capitalize = false;
+        let mut i = 0;
+        while i < tokens.len() {
+            let Some((_, len)) = filler_at(&tokens[i..]) else {
+                let mut word = tokens[i].to_string();
+                if std::mem::take(&mut capitalize) {
+                    word = capitalized(&word);
+                }
+                kept.push(word);
+                i += 1;
+                continue;
+            };
+
+            let sentence_start = kept.last().is_none_or(|word| word.ends_with(['.', '!', '?']));
+            capitalize |= sentence_start && tokens[i].starts_with(char::is_uppercase);
+            let last = tokens[i + len - 1];
+            let end = last.chars().last().filter(|c| matches!(c, '.' | '!' | '?'));
+            if let (Some(end), Some(word)) = (end, kept.last_mut()) {
+                // In place of the punctuation after the word before, such as a comma
+                let after = split_punctuation(word).2.len();
+                word.truncate(word.len() - after);
+                word.push(end);
+            }
+            i += len;
+        }
+        kept.join(" ")
+    }
+}
+
+/// The fillers said in `text`, in order, lowercase
+pub fn find_fillers(text: &str) -> Vec<String> {
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    let mut fillers = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        match filler_at(&tokens[i..]) {
+            Some((filler, len)) => {
+                fillers.push(filler);
+                i += len;
+            }
+            None => i += 1,
+        }
+    }
+    fillers
+}
+
+/// The filler `tokens` start with and how many tokens it takes up, if any
+fn filler_at(tokens: &[&str]) -> Option<(String, usize)> {
+    let word = normalize(split_punctuation(tokens.first()?).1);
+    if let Some(&next) = tokens.get(1) {
+        let (_, next_word, after) = split_punctuation(next);
+        let phrase = format!("{} {}", word, normalize(next_word));
+        let joined = split_punctuation(tokens[0]).2.is_empty();
+        let set_off = !after.is_empty() || tokens.len() == 2;
+        if joined && set_off && FILLERS.contains(&phrase.as_str()) {
+            return Some((phrase, 2));
+        }
+    }
+    FILLERS.contains(&word.as_str()).then_some((word, 1))
+}
+
+/// `word` with its first letter in upper case
+fn capitalized(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number_words() {
+        let numbers = NumberWords;
+
+        assert_eq!(numbers.apply("I need twenty five copies."), "I need 25 copies.");
+        assert_eq!(
+            numbers.apply("Two hundred and one, three thousand four hundred."),
+            "201, 3400."
+        );
+        assert_eq!(numbers.apply("Give me one or two-thirds"), "Give me one or two-thirds");
+        assert_eq!(numbers.apply("dial one two three"), "dial one two three");
+        assert_eq!(numbers.apply("Twenty-three and five"), "23 and five");
+    }
+
+    #[test]
+    fn test_spoken_symbols() {
+        let symbols = SpokenSymbols::default().symbol("dot com", ".com", Attach::Both);
+
+        assert_eq!(
+            symbols.apply("Mail jane at sign example dot com."),
+            "Mail jane@example.com."
+        );
+        assert_eq!(symbols.apply("Up fifty percent sign, ampersand more"), "Up fifty%, & more");
+        assert_eq!(symbols.apply("Meet at six"), "Meet at six");
+    }
+
+    #[test]
+    fn test_fillers() {
+        assert_eq!(Fillers.apply("So, um, I think it works."), "So, I think it works.");
+        assert_eq!(Fillers.apply("Uh, you know, it works, uh."), "It works.");
+        let literal = "Do you know the answer? I mean it.";
+        assert_eq!(Fillers.apply(literal), literal);
+        assert_eq!(
+            find_fillers("Um, I mean, you know the answer, hmm?"),
+            ["um", "i mean", "hmm"]
+        );
+    }
+
+    #[test]
+    fn test_pipeline() {
+        let postprocess = Postprocess::new()
+            .filter(NumberWords)
+            .filter(SpokenSymbols::default())
+            .filter(Typography);
+
+        assert_eq!(
+            postprocess.apply("Wait... it's up forty two percent sign - really"),
+            "Wait… it's up 42% — really"
+        );
+        assert_eq!(postprocess.apply("Well--no."), "Well—no.");
+    }
+}
@@ -0,0 +1,139 @@
+use std::{
+    collections::HashMap,
+    ffi::c_void,
+    fmt,
+    os::raw::c_int,
+    sync::{Arc, Mutex},
+};
+use whisper_rs::{FullParams, WhisperSysContext, WhisperSysState};
+
+/// Receives how much of a transcription is done, in percent
+///
+/// Progress only increases and ends at 100 when the transcription succeeds.
+/// The callback runs on a decoding thread, so it should return quickly.
+#[derive(Clone)]
+pub struct ProgressCallback(Arc<dyn Fn(u8) + Send + Sync>);
+
+impl ProgressCallback {
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: Fn(u8) + Send + Sync + 'static,
+    {
+        Self(Arc::new(callback))
+    }
+}
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+/// Combines the progress of the speech chunks of one transcription, which may be
+/// decoded at the same time, into the progress of the whole
+pub(crate) struct ProgressTracker<'a> {
+    callback: &'a ProgressCallback,
+    /// Samples in all chunks
+    total: usize,
+    /// Decoded samples by chunk offset, and the last percentage reported
+    done: Mutex<(HashMap<usize, usize>, u8)>,
+}
+
+impl<'a> ProgressTracker<'a> {
+    /// Track the chunks given by offset and length in samples
+    pub fn new<I>(callback: &'a ProgressCallback, chunks: I) -> Self
+    where
+        I: IntoIterator<Item = (usize, usize)>,
+    {
+        Self {
+            callback,
+            total: chunks.into_iter().map(|(_, len)| len).sum(),
+            done: Mutex::new((HashMap::new(), 0)),
+        }
+    }
+
+    /// The progress of the chunk at `offset`, `len` samples long
+    pub fn chunk(&self, offset: usize, len: usize) -> ChunkProgress<'_> {
+        ChunkProgress {
+            tracker: self,
+            offset,
+            len,
+        }
+    }
+
+    fn update(&self, offset: usize, decoded: usize) {
+        let mut done = self.done.lock().unwrap_or_else(|e| e.into_inner());
+        done.0.insert(offset, decoded);
+        let decoded: usize = done.0.values().sum();
+        let percent = (decoded * 100 / self.total.max(1)).min(100) as u8;
+        if percent > done.1 {
+            done.1 = percent;
+            (self.callback.0)(percent);
+        }
+    }
+}
+
+/// The progress of one speech chunk
+pub(crate) struct ChunkProgress<'a> {
+    tracker: &'a ProgressTracker<'a>,
+    offset: usize,
+    len: usize,
+}
+
+impl ChunkProgress<'_> {
+    /// Install the chunk as the progress callback of `params`
+    ///
+    /// The chunk must outlive every use of `params`.
+    pub fn install(&self, params: &mut FullParams) {
+        // SAFETY: the callback only reads `self` through the user data pointer.
+        // Callers keep `self` alive until decoding with `params` has finished.
+        unsafe {
+            params.set_progress_callback(Some(report_progress));
+            params.set_progress_callback_user_data(self as *const Self as *mut c_void);
+        }
+    }
+
+    /// Count the whole chunk as decoded; whisper reports progress in steps and
+    /// may stop short of 100%
+    pub fn finish(&self) {
+        self.tracker.update(self.offset, self.len);
+    }
+}
+
+unsafe extern "C" fn report_progress(
+    _ctx: *mut WhisperSysContext,
+    _state: *mut WhisperSysState,
+    progress: c_int,
+    user_data: *mut c_void,
+) {
+    if user_data.is_null() {
+        return;
+    }
+    let chunk = &*(user_data as *const ChunkProgress);
+    let progress = progress.clamp(0, 100) as usize;
+    chunk.tracker.update(chunk.offset, chunk.len * progress / 100);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_tracker() {
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let callback = {
+            let reported = reported.clone();
+            ProgressCallback::new(move |percent| reported.lock().unwrap().push(percent))
+        };
+        let tracker = ProgressTracker::new(&callback, [(0, 300), (500, 100)]);
+        let (first, second) = (tracker.chunk(0, 300), tracker.chunk(500, 100));
+
+        tracker.update(0, 150);
+        second.finish();
+        // A lower report for a chunk never moves the total back
+        tracker.update(0, 30);
+        tracker.update(0, 150);
+        first.finish();
+        assert_eq!(*reported.lock().unwrap(), vec![37, 62, 100]);
+    }
+}
@@ -0,0 +1,147 @@
+use std::sync::{Condvar, Mutex};
+
+/// How urgently a transcription is needed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Priority {
+    /// Someone is waiting for the text, e.g. push-to-talk dictation
+    #[default]
+    Interactive,
+    /// Batch and API jobs that yield to interactive work
+    Background,
+}
+
+/// Orders decodes on a transcriber so background work never delays dictation
+///
+/// Interactive decodes start right away. Background decodes wait until no
+/// interactive decode is running, so a long job gives way between chunks. A
+/// chunk that is already decoding is not interrupted. With a limit on concurrent
+/// decodes, decodes beyond it wait for a running one to finish, interactive ones
+/// first.
+#[derive(Debug)]
+pub(crate) struct Scheduler {
+    decodes: Mutex<Decodes>,
+    idle: Condvar,
+}
+
+#[derive(Debug)]
+struct Decodes {
+    /// Interactive decodes running or waiting for a free slot
+    interactive: usize,
+    running: usize,
+    limit: usize,
+}
+
+/// Permission to decode, held for the duration of one whisper run
+pub(crate) struct Slot<'a> {
+    scheduler: &'a Scheduler,
+    priority: Priority,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self {
+            decodes: Mutex::new(Decodes {
+                interactive: 0,
+                running: 0,
+                limit: usize::MAX,
+            }),
+            idle: Condvar::new(),
+        }
+    }
+}
+
+impl Scheduler {
+    /// Wait until a decode with `priority` may run
+    pub fn acquire(&self, priority: Priority) -> Slot<'_> {
+        let mut decodes = self.lock();
+        if priority == Priority::Interactive {
+            decodes.interactive += 1;
+        }
+        loop {
+            let blocked = match priority {
+                Priority::Interactive => false,
+                Priority::Background => decodes.interactive > 0,
+            };
+            if !blocked && decodes.running < decodes.limit {
+                break;
+            }
+            decodes = self.idle.wait(decodes).unwrap_or_else(|e| e.into_inner());
+        }
+        decodes.running += 1;
+        Slot {
+            scheduler: self,
+            priority,
+        }
+    }
+
+    /// Run at most `limit` decodes at the same time
+    pub fn set_limit(&self, limit: usize) {
+        self.lock().limit = limit.max(1);
+        self.idle.notify_all();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Decodes> {
+        self.decodes.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        let mut decodes = self.scheduler.lock();
+        decodes.running -= 1;
+        if self.priority == Priority::Interactive {
+            decodes.interactive -= 1;
+        }
+        self.scheduler.idle.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::atomic::{AtomicBool, Ordering},
+        time::Duration,
+    };
+
+    #[test]
+    fn test_background_waits_for_interactive() {
+        let scheduler = Scheduler::default();
+        let started = AtomicBool::new(false);
+
+        let interactive = scheduler.acquire(Priority::Interactive);
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                let _slot = scheduler.acquire(Priority::Background);
+                started.store(true, Ordering::SeqCst);
+            });
+
+            // Interactive work is never held up, background work is
+            drop(scheduler.acquire(Priority::Interactive));
+            std::thread::sleep(Duration::from_millis(50));
+            assert!(!started.load(Ordering::SeqCst));
+            drop(interactive);
+        });
+        assert!(started.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_limit() {
+        let scheduler = Scheduler::default();
+        scheduler.set_limit(1);
+        let started = AtomicBool::new(false);
+
+        let first = scheduler.acquire(Priority::Interactive);
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                let _slot = scheduler.acquire(Priority::Interactive);
+                started.store(true, Ordering::SeqCst);
+            });
+
+            std::thread::sleep(Duration::from_millis(50));
+            assert!(!started.load(Ordering::SeqCst));
+            drop(first);
+        });
+        assert!(started.load(Ordering::SeqCst));
+    }
+}
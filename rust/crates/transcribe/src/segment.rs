@@ -0,0 +1,259 @@
+use serde::{Deserialize, Serialize};
+
+/// A single word with its position in the audio
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Word {
+    pub text: String,
+    pub start_ms: i64,
+    pub end_ms: i64,
+    /// Mean probability of the tokens that make up the word
+    pub probability: f32,
+}
+
+/// A transcribed segment with its position in the audio
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Segment {
+    pub text: String,
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub words: Vec<Word>,
+    /// Mean probability of the text tokens in the segment (0.0 - 1.0)
+    pub probability: f32,
+    /// Mean log probability of the text tokens in the segment
+    pub avg_logprob: f32,
+    /// The next segment is spoken by someone else (tinydiarize models only)
+    pub speaker_turn: bool,
+    /// Speaker label, alternating between 0 and 1 at every speaker turn
+    pub speaker: usize,
+}
+
+impl Segment {
+    /// A confident segment of `text` from `start_ms` to `end_ms`, without word timings
+    /// or a speaker turn
+    pub fn new(text: impl Into<String>, start_ms: i64, end_ms: i64) -> Self {
+        Self {
+            text: text.into(),
+            start_ms,
+            end_ms,
+            words: Vec::new(),
+            probability: 1.0,
+            avg_logprob: 0.0,
+            speaker_turn: false,
+            speaker: 0,
+        }
+    }
+
+    /// Whether the segment's mean token probability reaches `threshold`
+    pub fn is_confident(&self, threshold: f32) -> bool {
+        self.probability >= threshold
+    }
+}
+
+/// Format segments as a dialogue, one `Speaker N: ...` line per speaker turn
+pub fn format_dialogue(segments: &[Segment]) -> String {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    for segment in segments.iter().filter(|segment| !segment.text.is_empty()) {
+        match lines.last_mut() {
+            Some((speaker, text)) if *speaker == segment.speaker => {
+                text.push(' ');
+                text.push_str(&segment.text);
+            }
+            _ => lines.push((segment.speaker, segment.text.clone())),
+        }
+    }
+
+    lines
+        .iter()
+        .map(|(speaker, text)| format!("Speaker {}: {}", speaker + 1, text))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Assign speakers from the speaker-turn markers, assuming two people take turns
+pub(crate) fn label_speakers(segments: &mut [Segment]) {
+    let mut speaker = 0;
+    for segment in segments {
+        segment.speaker = speaker;
+        if segment.speaker_turn {
+            speaker = (speaker + 1) % 2;
+        }
+    }
+}
+
+/// A decoded text token as reported by whisper
+#[derive(Debug, Clone)]
+pub(crate) struct Token {
+    pub bytes: Vec<u8>,
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub probability: f32,
+    pub logprob: f32,
+}
+
+/// Convert a whisper timestamp (10 ms units) to milliseconds
+pub(crate) fn to_ms(timestamp: i64) -> i64 {
+    timestamp * 10
+}
+
+/// Mean probability and mean log probability of a token sequence
+pub(crate) fn token_confidence(tokens: &[Token]) -> (f32, f32) {
+    if tokens.is_empty() {
+        return (0.0, f32::NEG_INFINITY);
+    }
+
+    let count = tokens.len() as f32;
+    let probability = tokens.iter().map(|t| t.probability).sum::<f32>() / count;
+    let logprob = tokens.iter().map(|t| t.logprob).sum::<f32>() / count;
+    (probability, logprob)
+}
+
+/// Time tokens by where DTW aligned them (`dtw_ms`, negative when unaligned) instead
+/// of by whisper's timestamp tokens
+///
+/// DTW gives one point per token, so each token is taken to last until the next
+/// one starts, and the last until `end_ms`. Unaligned tokens start with the one
+/// before them.
+pub(crate) fn align_tokens(tokens: &mut [Token], dtw_ms: &[i64], end_ms: i64) {
+    let mut previous_start = None;
+    for (token, &start_ms) in tokens.iter_mut().zip(dtw_ms) {
+        if start_ms >= 0 {
+            token.start_ms = start_ms;
+        } else if let Some(previous_start) = previous_start {
+            token.start_ms = previous_start;
+        }
+        previous_start = Some(token.start_ms);
+    }
+    let mut next_start = end_ms;
+    for token in tokens.iter_mut().rev() {
+        token.start_ms = token.start_ms.min(next_start);
+        token.end_ms = next_start;
+        next_start = token.start_ms;
+    }
+}
+
+/// Group sub-word tokens into words
+///
+/// Whisper tokens that start a new word carry a leading space, everything else
+/// (word pieces, punctuation) is appended to the current word. Token bytes are
+/// joined before UTF-8 decoding because multi-byte characters may be split
+/// across tokens.
+pub(crate) fn group_words(tokens: &[Token]) -> Vec<Word> {
+    let mut words = Vec::new();
+    let mut current: Vec<&Token> = Vec::new();
+
+    for token in tokens {
+        if token.bytes.first() == Some(&b' ') && !current.is_empty() {
+            words.push(merge_tokens(&current));
+            current.clear();
+        }
+        current.push(token);
+    }
+
+    if !current.is_empty() {
+        words.push(merge_tokens(&current));
+    }
+
+    words.retain(|word| !word.text.is_empty());
+    words
+}
+
+fn merge_tokens(tokens: &[&Token]) -> Word {
+    let bytes: Vec<u8> = tokens.iter().flat_map(|t| t.bytes.iter().copied()).collect();
+    let probability =
+        tokens.iter().map(|t| t.probability).sum::<f32>() / tokens.len() as f32;
+
+    Word {
+        text: String::from_utf8_lossy(&bytes).trim().to_string(),
+        start_ms: tokens[0].start_ms,
+        end_ms: tokens[tokens.len() - 1].end_ms,
+        probability,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(text: &[u8], start_ms: i64, end_ms: i64) -> Token {
+        Token {
+            bytes: text.to_vec(),
+            start_ms,
+            end_ms,
+            probability: 0.5,
+            logprob: -0.7,
+        }
+    }
+
+    #[test]
+    fn test_group_words() {
+        let tokens = vec![
+            token(b" Hello", 0, 300),
+            token(b",", 300, 320),
+            token(b" kub", 400, 500),
+            token(b"ectl", 500, 700),
+            token(b".", 700, 710),
+        ];
+
+        let words = group_words(&tokens);
+        assert_eq!(words.len(), 2);
+        assert_eq!(words[0].text, "Hello,");
+        assert_eq!((words[0].start_ms, words[0].end_ms), (0, 320));
+        assert_eq!(words[1].text, "kubectl.");
+        assert_eq!((words[1].start_ms, words[1].end_ms), (400, 710));
+    }
+
+    #[test]
+    fn test_align_tokens() {
+        let mut tokens = vec![
+            token(b" Hello", 0, 500),
+            token(b" there", 0, 500),
+            token(b".", 0, 500),
+        ];
+        align_tokens(&mut tokens, &[120, 480, -1], 900);
+        let times: Vec<_> = tokens.iter().map(|t| (t.start_ms, t.end_ms)).collect();
+        assert_eq!(times, vec![(120, 480), (480, 480), (480, 900)]);
+        assert_eq!(group_words(&tokens)[1].text, "there.");
+    }
+
+    #[test]
+    fn test_group_words_split_utf8() {
+        // "é" is 0xC3 0xA9, split across two tokens
+        let tokens = vec![token(b" caf\xC3", 0, 100), token(b"\xA9", 100, 200)];
+
+        let words = group_words(&tokens);
+        assert_eq!(words.len(), 1);
+        assert_eq!(words[0].text, "café");
+    }
+
+    #[test]
+    fn test_format_dialogue() {
+        let segment = |text: &str, speaker_turn| Segment {
+            speaker_turn,
+            ..Segment::new(text, 0, 0)
+        };
+        let mut segments = vec![
+            segment("Hi, how are you?", true),
+            segment("Fine, thanks.", false),
+            segment("And you?", true),
+            segment("Good.", false),
+        ];
+
+        label_speakers(&mut segments);
+        assert_eq!(
+            format_dialogue(&segments),
+            "Speaker 1: Hi, how are you?\nSpeaker 2: Fine, thanks. And you?\nSpeaker 1: Good."
+        );
+    }
+
+    #[test]
+    fn test_token_confidence() {
+        let mut tokens = vec![token(b" a", 0, 10), token(b" b", 10, 20)];
+        tokens[1].probability = 1.0;
+        tokens[1].logprob = 0.0;
+
+        let (probability, logprob) = token_confidence(&tokens);
+        assert_eq!(probability, 0.75);
+        assert_eq!(logprob, -0.35);
+        assert_eq!(token_confidence(&[]).0, 0.0);
+    }
+}
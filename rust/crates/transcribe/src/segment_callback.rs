@@ -0,0 +1,155 @@
+use std::{
+    ffi::{c_void, CStr},
+    fmt,
+    os::raw::c_int,
+    sync::Arc,
+};
+use whisper_rs::{whisper_rs_sys, FullParams, WhisperSysContext, WhisperSysState};
+
+use crate::{outcome::is_non_speech, segment::to_ms, TranscribeOptions};
+
+/// Identifies a segment within a transcription
+///
+/// Segments are numbered within the chunk of speech they were decoded in, so the ID
+/// of a segment doesn't depend on how many segments earlier chunks had, or on chunks
+/// being decoded again. IDs sort in the order of the recording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SegmentId {
+    /// Start of the chunk in the recording
+    pub chunk_start_ms: i64,
+    /// Position of the segment in the chunk, counting from 0
+    pub index: usize,
+}
+
+impl fmt::Display for SegmentId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.chunk_start_ms, self.index)
+    }
+}
+
+/// A segment reported while the rest of the audio is still being decoded
+///
+/// Segments without speech, such as `[BLANK_AUDIO]`, are not reported, but still
+/// count toward the index of their [`SegmentId`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewSegment {
+    pub id: SegmentId,
+    /// Text after control phrases, hints and vocabulary corrections
+    pub text: String,
+    pub start_ms: i64,
+    pub end_ms: i64,
+    /// End of the chunk the segment was decoded in; later segments start after it
+    pub chunk_end_ms: i64,
+    /// Mean probability of the text tokens in the segment (0.0 - 1.0)
+    pub probability: f32,
+}
+
+/// Receives each segment as soon as whisper has decoded it
+///
+/// The callback runs on the decoding thread, so it should hand the segment off
+/// (e.g. through a channel) rather than do slow work itself.
+#[derive(Clone)]
+pub struct SegmentCallback(Arc<dyn Fn(NewSegment) + Send + Sync>);
+
+impl SegmentCallback {
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: Fn(NewSegment) + Send + Sync + 'static,
+    {
+        Self(Arc::new(callback))
+    }
+}
+
+impl fmt::Debug for SegmentCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SegmentCallback")
+    }
+}
+
+/// A segment callback with what it needs to report one run's segments
+pub(crate) struct SegmentSink<'a> {
+    callback: &'a SegmentCallback,
+    options: &'a TranscribeOptions,
+    /// Position of the decoded chunk in the full recording
+    offset_ms: i64,
+    end_ms: i64,
+}
+
+impl<'a> SegmentSink<'a> {
+    pub fn new(
+        callback: &'a SegmentCallback,
+        options: &'a TranscribeOptions,
+        offset_ms: i64,
+        end_ms: i64,
+    ) -> Self {
+        Self {
+            callback,
+            options,
+            offset_ms,
+            end_ms,
+        }
+    }
+
+    /// Install the sink as the new segment callback of `params`
+    ///
+    /// The sink must outlive every use of `params`.
+    pub fn install(&self, params: &mut FullParams) {
+        // SAFETY: the callback only reads `self` through the user data pointer.
+        // Callers keep `self` alive until decoding with `params` has finished.
+        unsafe {
+            params.set_new_segment_callback(Some(report_segments));
+            params.set_new_segment_callback_user_data(self as *const Self as *mut c_void);
+        }
+    }
+}
+
+unsafe extern "C" fn report_segments(
+    ctx: *mut WhisperSysContext,
+    state: *mut WhisperSysState,
+    n_new: c_int,
+    user_data: *mut c_void,
+) {
+    if ctx.is_null() || state.is_null() || user_data.is_null() {
+        return;
+    }
+
+    let sink = &*(user_data as *const SegmentSink);
+    let eot = whisper_rs_sys::whisper_token_eot(ctx);
+    let n_segments = whisper_rs_sys::whisper_full_n_segments_from_state(state);
+    for i in (n_segments - n_new).max(0)..n_segments {
+        let text = whisper_rs_sys::whisper_full_get_segment_text_from_state(state, i);
+        if text.is_null() {
+            continue;
+        }
+        let text = sink
+            .options
+            .correct(CStr::from_ptr(text).to_string_lossy().trim());
+        if is_non_speech(&text) {
+            continue;
+        }
+
+        // Mean probability of the text tokens, as in `Segment::probability`
+        let (mut sum, mut count) = (0.0, 0);
+        for j in 0..whisper_rs_sys::whisper_full_n_tokens_from_state(state, i) {
+            let data = whisper_rs_sys::whisper_full_get_token_data_from_state(state, i, j);
+            if data.id < eot {
+                sum += data.p;
+                count += 1;
+            }
+        }
+
+        let t0 = whisper_rs_sys::whisper_full_get_segment_t0_from_state(state, i);
+        let t1 = whisper_rs_sys::whisper_full_get_segment_t1_from_state(state, i);
+        (sink.callback.0)(NewSegment {
+            id: SegmentId {
+                chunk_start_ms: sink.offset_ms,
+                index: i as usize,
+            },
+            text,
+            start_ms: to_ms(t0) + sink.offset_ms,
+            end_ms: to_ms(t1) + sink.offset_ms,
+            chunk_end_ms: sink.end_ms,
+            probability: if count > 0 { sum / count as f32 } else { 0.0 },
+        });
+    }
+}
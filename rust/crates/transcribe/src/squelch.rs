@@ -0,0 +1,96 @@
+use crate::fuzzy::{normalize, split_punctuation};
+
+/// Control phrases (e.g. "stop dictation" or a wake word) that must never be typed
+///
+/// Phrases are matched anywhere in the text, ignoring case and punctuation,
+/// and removed together with the words they span.
+#[derive(Debug, Clone, Default)]
+pub struct Squelch {
+    phrases: Vec<Vec<String>>,
+}
+
+impl Squelch {
+    /// Create a squelch list from control phrases
+    pub fn new<I, S>(phrases: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut phrases: Vec<Vec<String>> = phrases
+            .into_iter()
+            .map(|phrase| {
+                phrase
+                    .as_ref()
+                    .split_whitespace()
+                    .map(normalize)
+                    .filter(|word| !word.is_empty())
+                    .collect()
+            })
+            .filter(|words: &Vec<String>| !words.is_empty())
+            .collect();
+        // Try longer phrases first so "hey keyboard stop" wins over "hey keyboard"
+        phrases.sort_by_key(|words| std::cmp::Reverse(words.len()));
+
+        Self { phrases }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.phrases.is_empty()
+    }
+
+    /// Remove all control phrases from `text`
+    pub fn strip(&self, text: &str) -> String {
+        if self.phrases.is_empty() {
+            return text.to_string();
+        }
+
+        let input: Vec<&str> = text.split_whitespace().collect();
+        let normalized: Vec<String> = input.iter().map(|word| normalize(word)).collect();
+        let mut output: Vec<String> = Vec::with_capacity(input.len());
+
+        let mut i = 0;
+        while i < input.len() {
+            let matched = self
+                .phrases
+                .iter()
+                .find(|phrase| normalized[i..].starts_with(phrase.as_slice()));
+
+            match matched {
+                Some(phrase) => {
+                    // Keep sentence punctuation that followed the phrase
+                    let (_, _, trailing) = split_punctuation(input[i + phrase.len() - 1]);
+                    if let Some(previous) = output.last_mut() {
+                        let (_, _, previous_trailing) = split_punctuation(previous);
+                        if previous_trailing.is_empty() {
+                            previous.push_str(trailing);
+                        }
+                    }
+                    i += phrase.len();
+                }
+                None => {
+                    output.push(input[i].to_string());
+                    i += 1;
+                }
+            }
+        }
+
+        output.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip() {
+        let squelch = Squelch::new(["stop dictation", "Hey Keyboard"]);
+
+        assert_eq!(
+            squelch.strip("Hey keyboard, send the report. Stop dictation."),
+            "send the report."
+        );
+        assert_eq!(squelch.strip("send it stop dictation."), "send it.");
+        assert_eq!(squelch.strip("Stop the dictation"), "Stop the dictation");
+    }
+}
@@ -0,0 +1,85 @@
+use anyhow::Result;
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Mutex,
+};
+use whisper_rs::{WhisperContext, WhisperState};
+
+/// Warm whisper states kept for reuse between transcriptions
+///
+/// Creating a state allocates the KV caches and compute buffers (and uploads
+/// them to the GPU), which is a noticeable part of a short transcription.
+/// `WhisperState::full` clears the results and caches of the previous run, so
+/// a returned state can be handed out again as is. The pool grows to the
+/// highest number of concurrent transcriptions.
+#[derive(Default)]
+pub(crate) struct StatePool {
+    idle: Mutex<Vec<WhisperState>>,
+}
+
+/// A state checked out of a [`StatePool`], returned to it when dropped
+pub(crate) struct PooledState<'a> {
+    state: Option<WhisperState>,
+    pool: &'a StatePool,
+}
+
+impl StatePool {
+    /// Create a pool holding one warm state for `context`
+    pub fn warm(context: &WhisperContext) -> Result<Self> {
+        let pool = Self::default();
+        let state = create_state(context)?;
+        pool.lock().push(state);
+        Ok(pool)
+    }
+
+    /// Take an idle state, or create a new one if all are in use
+    pub fn checkout(&self, context: &WhisperContext) -> Result<PooledState<'_>> {
+        let state = match self.lock().pop() {
+            Some(state) => state,
+            None => create_state(context)?,
+        };
+        Ok(PooledState {
+            state: Some(state),
+            pool: self,
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<WhisperState>> {
+        self.idle.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl PooledState<'_> {
+    /// Drop the state instead of returning it, e.g. after a failed decode
+    pub fn discard(mut self) {
+        self.state = None;
+    }
+}
+
+impl Deref for PooledState<'_> {
+    type Target = WhisperState;
+
+    fn deref(&self) -> &WhisperState {
+        self.state.as_ref().expect("state is only taken on drop")
+    }
+}
+
+impl DerefMut for PooledState<'_> {
+    fn deref_mut(&mut self) -> &mut WhisperState {
+        self.state.as_mut().expect("state is only taken on drop")
+    }
+}
+
+impl Drop for PooledState<'_> {
+    fn drop(&mut self) {
+        if let Some(state) = self.state.take() {
+            self.pool.lock().push(state);
+        }
+    }
+}
+
+fn create_state(context: &WhisperContext) -> Result<WhisperState> {
+    context
+        .create_state()
+        .map_err(|e| anyhow::anyhow!("Failed to create model state: {}", e))
+}
@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+use crate::pcm::WHISPER_SAMPLE_RATE;
+
+/// How long a transcription took relative to the audio it decoded
+///
+/// Logging the real-time factor over time shows slowdowns, and comparing it with
+/// and without `use_gpu` shows whether GPU offload is actually engaged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscriptionStats {
+    /// Length of the audio passed in, including any silence a VAD skipped
+    pub audio_seconds: f32,
+    /// Time from the start of the call until decoding finished, including time spent
+    /// waiting for other decodes to finish
+    pub decode_ms: u64,
+    /// Decoding time divided by audio length; below 1.0 is faster than real time
+    pub rtf: f32,
+    /// The model file, or the SHA-256 of a model loaded from memory
+    pub model: String,
+    /// Decoding threads, shared between speech chunks decoded at the same time
+    pub threads: i32,
+}
+
+impl TranscriptionStats {
+    /// Stats for `samples` of 16kHz audio decoded in `elapsed`
+    pub(crate) fn new(samples: usize, elapsed: Duration, model: String, threads: i32) -> Self {
+        let audio_seconds = samples as f32 / WHISPER_SAMPLE_RATE as f32;
+        Self {
+            audio_seconds,
+            decode_ms: elapsed.as_millis() as u64,
+            rtf: elapsed.as_secs_f32() / audio_seconds.max(f32::EPSILON),
+            model,
+            threads,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_real_time_factor() {
+        let stats = TranscriptionStats::new(48000, Duration::from_millis(750), "m".into(), 4);
+        assert_eq!(stats.audio_seconds, 3.0);
+        assert_eq!(stats.decode_ms, 750);
+        assert_eq!(stats.rtf, 0.25);
+    }
+}
@@ -0,0 +1,159 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::{fmt::Write, str::FromStr};
+
+use crate::Segment;
+
+/// Output formats for a timestamped transcription
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Plain text, segments joined by spaces
+    Text,
+    /// SubRip subtitles
+    Srt,
+    /// WebVTT subtitles
+    Vtt,
+    /// The full text and all segments with their words and timings
+    Json,
+}
+
+/// Contents of the JSON format
+#[derive(Serialize)]
+struct Transcript<'a> {
+    text: &'a str,
+    segments: &'a [Segment],
+}
+
+impl Format {
+    /// Render `segments` in this format
+    pub fn render(self, segments: &[Segment]) -> Result<String> {
+        Ok(match self {
+            Format::Text => to_text(segments),
+            Format::Srt => to_srt(segments),
+            Format::Vtt => to_vtt(segments),
+            Format::Json => to_json(segments)?,
+        })
+    }
+
+    /// File extension commonly used for the format
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Text => "txt",
+            Format::Srt => "srt",
+            Format::Vtt => "vtt",
+            Format::Json => "json",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "text" | "txt" => Ok(Format::Text),
+            "srt" => Ok(Format::Srt),
+            "vtt" | "webvtt" => Ok(Format::Vtt),
+            "json" => Ok(Format::Json),
+            _ => Err(anyhow::anyhow!("Unknown output format: {}", s)),
+        }
+    }
+}
+
+/// Join the text of all segments
+pub fn to_text(segments: &[Segment]) -> String {
+    cues(segments)
+        .map(|segment| segment.text.trim())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Format segments as SubRip (.srt) subtitles
+pub fn to_srt(segments: &[Segment]) -> String {
+    let mut srt = String::new();
+    for (index, segment) in cues(segments).enumerate() {
+        let _ = writeln!(
+            srt,
+            "{}\n{} --> {}\n{}\n",
+            index + 1,
+            timestamp(segment.start_ms, ','),
+            timestamp(segment.end_ms, ','),
+            segment.text.trim()
+        );
+    }
+    srt
+}
+
+/// Format segments as WebVTT (.vtt) subtitles
+pub fn to_vtt(segments: &[Segment]) -> String {
+    let mut vtt = String::from("WEBVTT\n\n");
+    for segment in cues(segments) {
+        let _ = writeln!(
+            vtt,
+            "{} --> {}\n{}\n",
+            timestamp(segment.start_ms, '.'),
+            timestamp(segment.end_ms, '.'),
+            segment.text.trim()
+        );
+    }
+    vtt
+}
+
+/// Format the text and segments as pretty-printed JSON
+pub fn to_json(segments: &[Segment]) -> Result<String> {
+    let transcript = Transcript {
+        text: &to_text(segments),
+        segments,
+    };
+    serde_json::to_string_pretty(&transcript)
+        .map_err(|e| anyhow::anyhow!("Failed to serialize transcript: {}", e))
+}
+
+/// Segments that have text to show
+fn cues(segments: &[Segment]) -> impl Iterator<Item = &Segment> {
+    segments
+        .iter()
+        .filter(|segment| !segment.text.trim().is_empty())
+}
+
+/// `HH:MM:SS<separator>mmm`; SRT separates milliseconds with a comma, WebVTT with a dot
+fn timestamp(ms: i64, separator: char) -> String {
+    let ms = ms.max(0);
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        separator,
+        ms % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subtitles() {
+        let segments = vec![
+            Segment::new(" Hello there.", 0, 1500),
+            Segment::new("", 1500, 1600),
+            Segment::new(" General Kenobi!", 1600, 3_725_040),
+        ];
+
+        assert_eq!(to_text(&segments), "Hello there. General Kenobi!");
+        assert_eq!(
+            to_srt(&segments),
+            "1\n00:00:00,000 --> 00:00:01,500\nHello there.\n\n\
+             2\n00:00:01,600 --> 01:02:05,040\nGeneral Kenobi!\n\n"
+        );
+        assert_eq!(
+            to_vtt(&segments),
+            "WEBVTT\n\n\
+             00:00:00.000 --> 00:00:01.500\nHello there.\n\n\
+             00:00:01.600 --> 01:02:05.040\nGeneral Kenobi!\n\n"
+        );
+        assert_eq!("webvtt".parse::<Format>().unwrap(), Format::Vtt);
+        assert!("doc".parse::<Format>().is_err());
+    }
+}
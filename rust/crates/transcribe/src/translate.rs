@@ -0,0 +1,11 @@
+use anyhow::Result;
+
+#[cfg(feature = "translation")]
+pub use crate::libretranslate::LibreTranslate;
+
+/// Translates transcribed text into another language
+pub trait Translator: Send + Sync {
+    /// Translate `text` into the `target` language (ISO 639-1 code, e.g. "nl").
+    /// `source` is the spoken language, or `None` to let the backend detect it.
+    fn translate(&self, text: &str, source: Option<&str>, target: &str) -> Result<String>;
+}
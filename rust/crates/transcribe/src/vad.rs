@@ -0,0 +1,163 @@
+use std::ops::Range;
+
+/// Whisper expects 16kHz audio
+const SAMPLE_RATE: usize = 16000;
+
+/// Energy-based voice activity detector
+///
+/// The recording is cut into short frames and each frame's level is compared
+/// against a threshold derived from the noise floor of the recording itself.
+/// Whisper tends to hallucinate text on silence, so trimming it away and
+/// splitting on long pauses gives cleaner output.
+#[derive(Debug, Clone)]
+pub struct Vad {
+    frame_ms: usize,
+    /// Frames must be this far above the noise floor to count as speech
+    noise_margin_db: f32,
+    /// Frames below this level are never speech
+    min_level_db: f32,
+    /// Silence kept around each speech region
+    padding_ms: usize,
+    /// Pauses longer than this split the recording
+    max_pause_ms: usize,
+    /// Speech regions shorter than this are dropped as clicks and pops
+    min_speech_ms: usize,
+}
+
+impl Default for Vad {
+    fn default() -> Self {
+        Self {
+            frame_ms: 30,
+            noise_margin_db: 12.0,
+            min_level_db: -50.0,
+            padding_ms: 200,
+            max_pause_ms: 1000,
+            min_speech_ms: 90,
+        }
+    }
+}
+
+impl Vad {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how far above the noise floor (in dB) a frame must be to count as speech
+    pub fn noise_margin_db(mut self, noise_margin_db: f32) -> Self {
+        self.noise_margin_db = noise_margin_db;
+        self
+    }
+
+    /// Set the level (in dBFS) below which audio is always treated as silence
+    pub fn min_level_db(mut self, min_level_db: f32) -> Self {
+        self.min_level_db = min_level_db;
+        self
+    }
+
+    /// Set the amount of silence kept before and after speech
+    pub fn padding_ms(mut self, padding_ms: usize) -> Self {
+        self.padding_ms = padding_ms;
+        self
+    }
+
+    /// Set the longest pause that does not split the recording
+    pub fn max_pause_ms(mut self, max_pause_ms: usize) -> Self {
+        self.max_pause_ms = max_pause_ms;
+        self
+    }
+
+    /// Sample ranges of `audio` (f32, 16kHz) that contain speech, padded and in order
+    pub fn speech_ranges(&self, audio: &[f32]) -> Vec<Range<usize>> {
+        let frame_len = (SAMPLE_RATE * self.frame_ms / 1000).max(1);
+        let levels: Vec<f32> = audio.chunks(frame_len).map(level_db).collect();
+        if levels.is_empty() {
+            return Vec::new();
+        }
+
+        let threshold = self.threshold(&levels);
+        let frames = |ms: usize| ms.div_ceil(self.frame_ms);
+
+        // Runs of speech frames, joined across short pauses
+        let mut regions: Vec<Range<usize>> = Vec::new();
+        for (i, level) in levels.iter().enumerate() {
+            if *level < threshold {
+                continue;
+            }
+            match regions.last_mut() {
+                Some(last) if i - last.end <= frames(self.max_pause_ms) => last.end = i + 1,
+                _ => regions.push(i..i + 1),
+            }
+        }
+        regions.retain(|region| region.len() >= frames(self.min_speech_ms));
+
+        // Pad, merge regions that now overlap and convert to samples
+        let padding = frames(self.padding_ms);
+        let mut ranges: Vec<Range<usize>> = Vec::with_capacity(regions.len());
+        for region in regions {
+            let start = region.start.saturating_sub(padding) * frame_len;
+            let end = ((region.end + padding) * frame_len).min(audio.len());
+            match ranges.last_mut() {
+                Some(last) if start <= last.end => last.end = end,
+                _ => ranges.push(start..end),
+            }
+        }
+        ranges
+    }
+
+    /// Level a frame must reach to count as speech
+    fn threshold(&self, levels: &[f32]) -> f32 {
+        let mut sorted = levels.to_vec();
+        sorted.sort_by(f32::total_cmp);
+        let noise_floor = sorted[sorted.len() / 10];
+        let peak = sorted[sorted.len() - 1];
+
+        // Without real silence in the recording the noise floor is quiet speech,
+        // so never require more than a level well below the loudest frame
+        (noise_floor + self.noise_margin_db)
+            .min(peak - 2.0 * self.noise_margin_db)
+            .max(self.min_level_db)
+    }
+}
+
+/// RMS level of a frame in dBFS
+fn level_db(frame: &[f32]) -> f32 {
+    let power = frame.iter().map(|sample| sample * sample).sum::<f32>() / frame.len() as f32;
+    10.0 * power.max(1e-10).log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(ms: usize, amplitude: f32) -> Vec<f32> {
+        (0..SAMPLE_RATE * ms / 1000)
+            .map(|i| amplitude * (i as f32 * 0.1).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_speech_ranges() {
+        let vad = Vad::new().padding_ms(0).max_pause_ms(500);
+
+        // 1s silence, 1s speech, 2s silence, 0.6s speech, 0.3s silence, 0.3s speech, 1s silence
+        let mut audio = tone(1000, 0.001);
+        audio.extend(tone(1000, 0.5));
+        audio.extend(tone(2000, 0.001));
+        audio.extend(tone(600, 0.5));
+        audio.extend(tone(300, 0.001));
+        audio.extend(tone(300, 0.5));
+        audio.extend(tone(1000, 0.001));
+
+        let ranges = vad.speech_ranges(&audio);
+        let seconds: Vec<(f32, f32)> = ranges
+            .iter()
+            .map(|r| (r.start as f32 / 16000.0, r.end as f32 / 16000.0))
+            .collect();
+
+        assert_eq!(ranges.len(), 2, "{:?}", seconds);
+        assert!((seconds[0].0 - 1.0).abs() < 0.05 && (seconds[0].1 - 2.0).abs() < 0.05);
+        assert!((seconds[1].0 - 4.0).abs() < 0.05 && (seconds[1].1 - 5.2).abs() < 0.05);
+
+        assert!(vad.speech_ranges(&tone(2000, 0.0)).is_empty());
+    }
+}
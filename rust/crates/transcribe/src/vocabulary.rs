@@ -0,0 +1,112 @@
+use crate::fuzzy::{normalize, replace_similar};
+
+/// Default logit boost applied to the first token of each vocabulary word
+const DEFAULT_BOOST: f32 = 2.0;
+/// Default minimum similarity for fuzzy post-correction
+const DEFAULT_MIN_SIMILARITY: f32 = 0.8;
+/// Longest run of transcribed words that may be merged into one vocabulary word
+const MAX_WORDS_PER_MATCH: usize = 3;
+
+/// A list of words (names, product codenames, jargon) the transcriber should favor
+///
+/// The vocabulary is applied in three places:
+/// - it is appended to the initial prompt so the decoder sees the spelling,
+/// - the first token of each word gets a logit boost during decoding,
+/// - the output is fuzzily corrected toward the vocabulary afterwards.
+#[derive(Debug, Clone)]
+pub struct Vocabulary {
+    words: Vec<String>,
+    boost: f32,
+    min_similarity: f32,
+}
+
+impl Vocabulary {
+    /// Create a vocabulary from a word list
+    pub fn new<I, S>(words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            words: words
+                .into_iter()
+                .map(Into::into)
+                .map(|word: String| word.trim().to_string())
+                .filter(|word| !word.is_empty())
+                .collect(),
+            boost: DEFAULT_BOOST,
+            min_similarity: DEFAULT_MIN_SIMILARITY,
+        }
+    }
+
+    /// Set the logit boost applied to vocabulary tokens (0.0 disables boosting)
+    pub fn boost(mut self, boost: f32) -> Self {
+        self.boost = boost;
+        self
+    }
+
+    /// Set the minimum similarity (0.0 - 1.0) required for fuzzy correction
+    pub fn min_similarity(mut self, min_similarity: f32) -> Self {
+        self.min_similarity = min_similarity.clamp(0.0, 1.0);
+        self
+    }
+
+    /// The words in this vocabulary
+    pub fn words(&self) -> &[String] {
+        &self.words
+    }
+
+    /// The logit boost applied to vocabulary tokens
+    pub fn token_boost(&self) -> f32 {
+        self.boost
+    }
+
+    /// Prompt text that primes the decoder with the vocabulary spelling
+    pub fn prompt(&self) -> Option<String> {
+        if self.words.is_empty() {
+            return None;
+        }
+        Some(format!("Glossary: {}.", self.words.join(", ")))
+    }
+
+    /// Replace words in `text` that closely resemble a vocabulary word
+    ///
+    /// Runs of up to three transcribed words are compared against each
+    /// vocabulary word, so "cube control" can become "kubectl".
+    pub fn correct(&self, text: &str) -> String {
+        let targets: Vec<(String, &str)> = self
+            .words
+            .iter()
+            .map(|word| (normalize(word), word.as_str()))
+            .collect();
+        replace_similar(text, &targets, MAX_WORDS_PER_MATCH, self.min_similarity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt() {
+        let vocabulary = Vocabulary::new(["Kubernetes", " kubectl ", ""]);
+        assert_eq!(vocabulary.words(), ["Kubernetes", "kubectl"]);
+        assert_eq!(
+            vocabulary.prompt().as_deref(),
+            Some("Glossary: Kubernetes, kubectl.")
+        );
+        assert_eq!(Vocabulary::new(Vec::<String>::new()).prompt(), None);
+    }
+
+    #[test]
+    fn test_correct() {
+        let vocabulary = Vocabulary::new(["Kubernetes", "Siobhan", "etcd"]);
+
+        assert_eq!(
+            vocabulary.correct("Deploy it on Kubernetis, then ask Siobhán."),
+            "Deploy it on Kubernetes, then ask Siobhan."
+        );
+        assert_eq!(vocabulary.correct("Check etc d first"), "Check etcd first");
+        assert_eq!(vocabulary.correct("nothing to see here"), "nothing to see here");
+    }
+}
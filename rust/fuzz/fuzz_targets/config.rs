@@ -0,0 +1,25 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// The CLI is a binary, so its config module is compiled into the target as is
+#[allow(dead_code)]
+#[path = "../../crates/cli/src/config.rs"]
+mod config;
+
+// A bad config file or hotkey name must be an error, not a panic of the daemon that
+// grabs the keyboard
+fuzz_target!(|data: &[u8]| {
+    let Ok(json) = std::str::from_utf8(data) else {
+        return;
+    };
+    if let Ok(config) = serde_json::from_str::<config::Config>(json) {
+        let stages = config.modes.iter().map(|mode| mode.stages.over(&config.stages));
+        for stages in stages.chain([config.stages.clone()]) {
+            if let Some(vad) = &stages.vad {
+                let _ = vad.vad();
+            }
+        }
+    }
+    let _ = serde_json::from_str::<keyctl::Key>(json);
+});
@@ -0,0 +1,18 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// The sample rate and channel count come from the WAV header or the device, the samples
+// follow them
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 6 {
+        return;
+    }
+    let sample_rate = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+    let channels = u16::from_le_bytes([data[4], data[5]]);
+    let samples: Vec<i16> = data[6..]
+        .chunks_exact(2)
+        .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    let _ = transcribe::pcm_to_whisper(&samples, sample_rate, channels);
+});
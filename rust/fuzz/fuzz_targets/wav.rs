@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+// Recordings left behind by a crash and files given to `voicekb batch` may be damaged
+fuzz_target!(|data: &[u8]| {
+    let _ = transcribe::read_wav_as_float(Cursor::new(data));
+    let _ = transcribe::read_wav_resampled(Cursor::new(data));
+});